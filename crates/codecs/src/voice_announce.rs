@@ -3,6 +3,7 @@
 //! Provides functionality to play pre-recorded voice announcements
 //! for station identification and mode announcements.
 
+use openham_core::resample::Resampler;
 use std::path::Path;
 
 /// Voice announcement player for pre-recorded audio files
@@ -30,36 +31,18 @@ impl VoiceAnnouncer {
         pcm_data.to_vec()
     }
     
-    /// Resample audio if needed (simple linear interpolation)
-    pub fn resample_if_needed(&self, audio_data: &[f32], source_sample_rate: f64) -> Vec<f32> {
+    /// Resample audio if needed (windowed-sinc polyphase, see
+    /// [`openham_core::resample::Resampler`])
+    pub fn resample_if_needed(&self, audio_data: &[f32], source_sample_rate: f64) -> crate::Result<Vec<f32>> {
         if (source_sample_rate - self.sample_rate).abs() < 1.0 {
             // Sample rates are close enough, no resampling needed
-            return audio_data.to_vec();
+            return Ok(audio_data.to_vec());
         }
         
-        let ratio = self.sample_rate / source_sample_rate;
-        let new_length = (audio_data.len() as f64 * ratio) as usize;
-        let mut resampled = Vec::with_capacity(new_length);
-        
-        for i in 0..new_length {
-            let source_index = i as f64 / ratio;
-            let index_floor = source_index.floor() as usize;
-            let index_ceil = (index_floor + 1).min(audio_data.len() - 1);
-            let fraction = source_index - index_floor as f64;
-            
-            if index_floor < audio_data.len() {
-                let sample = if index_floor == index_ceil {
-                    audio_data[index_floor]
-                } else {
-                    // Linear interpolation
-                    audio_data[index_floor] * (1.0 - fraction) as f32 + 
-                    audio_data[index_ceil] * fraction as f32
-                };
-                resampled.push(sample);
-            }
-        }
-        
-        resampled
+        let mut resampler = Resampler::new(source_sample_rate, self.sample_rate)?;
+        let input: Vec<f64> = audio_data.iter().map(|&s| s as f64).collect();
+        let output = resampler.process_all(&input)?;
+        Ok(output.into_iter().map(|s| s as f32).collect())
     }
     
     /// Simple WAV file reader (basic implementation)
@@ -116,7 +99,7 @@ impl VoiceAnnouncer {
         }
         
         // Resample if needed
-        Ok(self.resample_if_needed(&samples, sample_rate as f64))
+        Ok(self.resample_if_needed(&samples, sample_rate as f64)?)
     }
 }
 
@@ -205,12 +188,15 @@ mod tests {
         let test_data = vec![0.0, 1.0, 0.0, -1.0]; // 4 samples
         
         // Test upsampling (4kHz to 8kHz should double the length)
-        let resampled = announcer.resample_if_needed(&test_data, 4000.0);
+        let resampled = announcer.resample_if_needed(&test_data, 4000.0).unwrap();
         assert!(resampled.len() > test_data.len());
         
         // Test no resampling needed
-        let no_resample = announcer.resample_if_needed(&test_data, 8000.0);
+        let no_resample = announcer.resample_if_needed(&test_data, 8000.0).unwrap();
         assert_eq!(no_resample.len(), test_data.len());
+        
+        // A bogus source rate (e.g. from a corrupt WAV header) is an error
+        assert!(announcer.resample_if_needed(&test_data, 0.0).is_err());
     }
 
     #[test]
@@ -0,0 +1,285 @@
+//! Automatic gain control
+//!
+//! [`Agc`] tracks the envelope of a real or complex stream and scales it
+//! towards a target level, so downstream thresholds do not depend on the
+//! input level. The envelope rises with the attack time constant, is held
+//! for the hang time after a peak, and then falls with the decay time
+//! constant. In gated mode the gain is frozen while the envelope sits
+//! below a squelch threshold, so the noise between transmissions is not
+//! pumped up to full scale.
+
+use crate::buffer::Sample;
+use crate::{CoreError, Result};
+
+/// AGC settings; times are in seconds, gains are linear
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgcConfig {
+    /// Envelope (peak magnitude) the output is driven towards
+    pub target_level: f64,
+    /// Time constant for a rising envelope
+    pub attack_time: f64,
+    /// Time constant for a falling envelope, once the hang time has run out
+    pub decay_time: f64,
+    /// How long the envelope is held after a peak before it starts decaying
+    pub hang_time: f64,
+    /// Lower gain limit
+    pub min_gain: f64,
+    /// Upper gain limit
+    pub max_gain: f64,
+    /// Squelch threshold on the input envelope; below it the gain is frozen
+    pub gate_threshold: Option<f64>,
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        Self {
+            target_level: 0.5,
+            attack_time: 0.002,
+            decay_time: 0.5,
+            hang_time: 0.1,
+            min_gain: 0.01,
+            max_gain: 1000.0,
+            gate_threshold: None,
+        }
+    }
+}
+
+impl AgcConfig {
+    /// Freeze the gain while the input envelope is below `threshold`
+    pub fn with_gate(mut self, threshold: f64) -> Self {
+        self.gate_threshold = Some(threshold);
+        self
+    }
+}
+
+/// Envelope-tracking automatic gain control
+#[derive(Debug, Clone)]
+pub struct Agc {
+    config: AgcConfig,
+    attack: f64,
+    decay: f64,
+    hang_samples: usize,
+    hang_counter: usize,
+    envelope: f64,
+    gain: f64,
+}
+
+impl Agc {
+    /// Create an AGC for a stream at `sample_rate` Hz
+    pub fn new(config: AgcConfig, sample_rate: f64) -> Result<Self> {
+        if sample_rate <= 0.0 {
+            return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+        }
+        if config.target_level <= 0.0 {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: "AGC target level must be positive".to_string(),
+            });
+        }
+        if config.attack_time < 0.0 || config.decay_time < 0.0 || config.hang_time < 0.0 {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: "AGC time constants must not be negative".to_string(),
+            });
+        }
+        if config.min_gain <= 0.0 || config.min_gain > config.max_gain {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: format!(
+                    "Invalid AGC gain limits: {}..{}",
+                    config.min_gain, config.max_gain
+                ),
+            });
+        }
+
+        // One-pole smoothing coefficient for a time constant; zero means
+        // follow the input immediately.
+        let coefficient = |time: f64| {
+            if time == 0.0 {
+                1.0
+            } else {
+                1.0 - (-1.0 / (time * sample_rate)).exp()
+            }
+        };
+
+        Ok(Self {
+            attack: coefficient(config.attack_time),
+            decay: coefficient(config.decay_time),
+            hang_samples: (config.hang_time * sample_rate).round() as usize,
+            hang_counter: 0,
+            envelope: 0.0,
+            gain: 1.0f64.clamp(config.min_gain, config.max_gain),
+            config,
+        })
+    }
+
+    /// Current gain
+    pub fn gain(&self) -> f64 {
+        self.gain
+    }
+
+    /// Current gain in dB
+    pub fn gain_db(&self) -> f64 {
+        20.0 * self.gain.log10()
+    }
+
+    /// Current input envelope estimate
+    pub fn envelope(&self) -> f64 {
+        self.envelope
+    }
+
+    /// Whether the gate is closed (the envelope is below the squelch
+    /// threshold); always false when no gate is configured
+    pub fn is_gated(&self) -> bool {
+        self.config
+            .gate_threshold
+            .is_some_and(|threshold| self.envelope < threshold)
+    }
+
+    /// Settings in use
+    pub fn config(&self) -> &AgcConfig {
+        &self.config
+    }
+
+    /// Scale one sample and update the envelope and gain
+    pub fn process_sample<T: Sample<Real = f64>>(&mut self, input: T) -> T {
+        let level = input.power().sqrt();
+        if level > self.envelope {
+            self.envelope += self.attack * (level - self.envelope);
+            self.hang_counter = self.hang_samples;
+        } else if self.hang_counter > 0 {
+            self.hang_counter -= 1;
+        } else {
+            self.envelope += self.decay * (level - self.envelope);
+        }
+
+        if !self.is_gated() && self.envelope > 0.0 {
+            self.gain = (self.config.target_level / self.envelope)
+                .clamp(self.config.min_gain, self.config.max_gain);
+        }
+        input * self.gain
+    }
+
+    /// Scale a block
+    pub fn process<T: Sample<Real = f64>>(&mut self, input: &[T]) -> Vec<T> {
+        input.iter().map(|&x| self.process_sample(x)).collect()
+    }
+
+    /// Scale a block in place
+    pub fn process_in_place<T: Sample<Real = f64>>(&mut self, samples: &mut [T]) {
+        for x in samples.iter_mut() {
+            *x = self.process_sample(*x);
+        }
+    }
+
+    /// Forget the envelope and return to unity gain (clamped to the limits)
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+        self.hang_counter = 0;
+        self.gain = 1.0f64.clamp(self.config.min_gain, self.config.max_gain);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Complex;
+    use std::f64::consts::PI;
+
+    fn tone(amplitude: f64, len: usize) -> Vec<f64> {
+        (0..len)
+            .map(|n| amplitude * (2.0 * PI * 1000.0 * n as f64 / 8000.0).sin())
+            .collect()
+    }
+
+    fn peak(samples: &[f64]) -> f64 {
+        samples.iter().fold(0.0, |m, x| m.max(x.abs()))
+    }
+
+    #[test]
+    fn test_agc_levels_real_input() {
+        let config = AgcConfig::default();
+        for amplitude in [0.01, 0.2, 3.0] {
+            let mut agc = Agc::new(config.clone(), 8000.0).unwrap();
+            let out = agc.process(&tone(amplitude, 8000));
+            assert!(
+                (peak(&out[4000..]) - config.target_level).abs() < 0.02,
+                "amplitude {}: peak {}",
+                amplitude,
+                peak(&out[4000..])
+            );
+        }
+    }
+
+    #[test]
+    fn test_agc_complex_input_and_gain_limits() {
+        let config = AgcConfig {
+            max_gain: 10.0,
+            ..AgcConfig::default()
+        };
+        let mut agc = Agc::new(config, 8000.0).unwrap();
+        let input: Vec<Complex> = (0..4000)
+            .map(|n| Complex::from_phase(0.3 * n as f64) * 0.2)
+            .collect();
+        let out = agc.process(&input);
+        assert!((out[3999].magnitude() - 0.5).abs() < 1e-3);
+
+        // A very weak signal hits the gain ceiling.
+        agc.reset();
+        agc.process(&vec![Complex::new(1e-4, 0.0); 8000]);
+        assert!((agc.gain() - 10.0).abs() < 1e-12);
+        assert!((agc.gain_db() - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_agc_attack_hang_and_decay() {
+        let config = AgcConfig {
+            attack_time: 0.001,
+            decay_time: 0.05,
+            hang_time: 0.1,
+            ..AgcConfig::default()
+        };
+        let mut agc = Agc::new(config, 8000.0).unwrap();
+
+        // A loud burst is brought down within a few attack time constants.
+        let out = agc.process(&tone(2.0, 400));
+        assert!(peak(&out[200..]) < 0.6);
+
+        // During the hang time the envelope holds after the signal drops.
+        let held = agc.envelope();
+        agc.process(&tone(0.1, 400));
+        assert!((agc.envelope() - held).abs() < 1e-12);
+
+        // Once the hang time runs out the envelope decays to the new level.
+        agc.process(&tone(0.1, 8000));
+        assert!((agc.envelope() - 0.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_agc_gate_freezes_gain_on_squelch() {
+        let config = AgcConfig {
+            decay_time: 0.01,
+            hang_time: 0.0,
+            ..AgcConfig::default()
+        }
+        .with_gate(0.05);
+        let mut agc = Agc::new(config, 8000.0).unwrap();
+
+        agc.process(&tone(0.2, 4000));
+        assert!(!agc.is_gated());
+
+        // Noise-level input closes the gate; the gain stops rising at the
+        // threshold instead of climbing to the ceiling.
+        agc.process(&tone(0.001, 4000));
+        assert!(agc.is_gated());
+        let gain = agc.gain();
+        assert!(gain <= 0.5 / 0.05 + 1e-9);
+        agc.process(&tone(0.001, 4000));
+        assert_eq!(agc.gain(), gain);
+
+        assert!(Agc::new(AgcConfig::default(), 0.0).is_err());
+        let bad = AgcConfig {
+            min_gain: 2.0,
+            max_gain: 1.0,
+            ..AgcConfig::default()
+        };
+        assert!(Agc::new(bad, 8000.0).is_err());
+    }
+}
@@ -1,7 +1,12 @@
 //! Sample buffer management and operations
 
 use crate::{CoreError, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::UnsafeCell;
 use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime};
 
 /// Generic sample buffer for audio data
 #[derive(Debug, Clone)]
@@ -85,76 +90,616 @@ impl<T> IndexMut<usize> for SampleBuffer<T> {
     }
 }
 
+/// Floating-point precision the DSP types are generic over
+///
+/// `f64` is the default everywhere; `f32` halves memory and cache traffic,
+/// which matters on small boards and in multi-channel receivers, at about
+/// 7 significant digits. Every `Float` is also a real [`Sample`] of its own
+/// precision.
+pub trait Float:
+    rustfft::FftNum + Sample<Real = Self> + Default + PartialOrd + std::ops::AddAssign + std::iter::Sum
+{
+    /// Convert from `f64`, rounding if the precision is lower
+    fn narrow(value: f64) -> Self;
+
+    /// Convert to `f64` exactly
+    fn widen(self) -> f64;
+
+    fn sqrt(self) -> Self;
+
+    fn sin(self) -> Self;
+
+    fn cos(self) -> Self;
+
+    fn atan2(self, other: Self) -> Self;
+}
+
+impl Float for f64 {
+    fn narrow(value: f64) -> Self {
+        value
+    }
+
+    fn widen(self) -> f64 {
+        self
+    }
+
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+
+    fn sin(self) -> Self {
+        f64::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        f64::cos(self)
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        f64::atan2(self, other)
+    }
+}
+
+impl Float for f32 {
+    fn narrow(value: f64) -> Self {
+        value as f32
+    }
+
+    fn widen(self) -> f64 {
+        self as f64
+    }
+
+    fn sqrt(self) -> Self {
+        f32::sqrt(self)
+    }
+
+    fn sin(self) -> Self {
+        f32::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        f32::cos(self)
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        f32::atan2(self, other)
+    }
+}
+
 /// Complex number representation for IQ data
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
-pub struct Complex {
-    pub real: f64,
-    pub imag: f64,
+///
+/// `Complex` on its own is double precision; `Complex<f32>` (alias
+/// [`Complex32`]) is the single-precision form.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Complex<F: Float = f64> {
+    pub real: F,
+    pub imag: F,
 }
 
+/// Single-precision complex sample
+pub type Complex32 = Complex<f32>;
+
 impl Complex {
     pub fn new(real: f64, imag: f64) -> Self {
         Self { real, imag }
     }
+}
+
+impl<F: Float> Complex<F> {
+    /// Construct at any precision; [`Complex::new`] is the `f64` shorthand
+    pub fn from_parts(real: F, imag: F) -> Self {
+        Self { real, imag }
+    }
     
-    pub fn magnitude(&self) -> f64 {
+    pub fn magnitude(&self) -> F {
         (self.real * self.real + self.imag * self.imag).sqrt()
     }
     
-    pub fn norm(&self) -> f64 {
+    pub fn norm(&self) -> F {
         self.magnitude()
     }
     
-    pub fn norm_sqr(&self) -> f64 {
+    pub fn norm_sqr(&self) -> F {
         self.real * self.real + self.imag * self.imag
     }
     
-    pub fn phase(&self) -> f64 {
+    pub fn phase(&self) -> F {
         self.imag.atan2(self.real)
     }
+    
+    pub fn conj(&self) -> Self {
+        Self::from_parts(self.real, -self.imag)
+    }
+    
+    /// Unit-magnitude phasor `e^{j * radians}`
+    pub fn from_phase(radians: F) -> Self {
+        Self::from_parts(radians.cos(), radians.sin())
+    }
+
+    /// Convert to another precision
+    pub fn cast<G: Float>(self) -> Complex<G> {
+        Complex::from_parts(G::narrow(self.real.widen()), G::narrow(self.imag.widen()))
+    }
 }
 
-impl std::ops::Mul<f64> for Complex {
-    type Output = Complex;
+impl<F: Float> std::ops::Mul<F> for Complex<F> {
+    type Output = Complex<F>;
 
-    fn mul(self, rhs: f64) -> Self::Output {
-        Complex::new(self.real * rhs, self.imag * rhs)
+    fn mul(self, rhs: F) -> Self::Output {
+        Complex::from_parts(self.real * rhs, self.imag * rhs)
     }
 }
 
-impl std::ops::Add for Complex {
-    type Output = Complex;
+impl<F: Float> std::ops::Add for Complex<F> {
+    type Output = Complex<F>;
 
-    fn add(self, rhs: Complex) -> Self::Output {
-        Complex::new(self.real + rhs.real, self.imag + rhs.imag)
+    fn add(self, rhs: Complex<F>) -> Self::Output {
+        Complex::from_parts(self.real + rhs.real, self.imag + rhs.imag)
     }
 }
 
-impl std::ops::Sub for Complex {
-    type Output = Complex;
+impl<F: Float> std::ops::Sub for Complex<F> {
+    type Output = Complex<F>;
 
-    fn sub(self, rhs: Complex) -> Self::Output {
-        Complex::new(self.real - rhs.real, self.imag - rhs.imag)
+    fn sub(self, rhs: Complex<F>) -> Self::Output {
+        Complex::from_parts(self.real - rhs.real, self.imag - rhs.imag)
     }
 }
 
-impl std::ops::Mul for Complex {
-    type Output = Complex;
+impl<F: Float> std::ops::Mul for Complex<F> {
+    type Output = Complex<F>;
 
-    fn mul(self, rhs: Complex) -> Self::Output {
-        Complex::new(
+    fn mul(self, rhs: Complex<F>) -> Self::Output {
+        Complex::from_parts(
             self.real * rhs.real - self.imag * rhs.imag,
             self.real * rhs.imag + self.imag * rhs.real,
         )
     }
 }
 
+impl<F: Float> std::ops::Div for Complex<F> {
+    type Output = Complex<F>;
+
+    fn div(self, rhs: Complex<F>) -> Self::Output {
+        let d = rhs.norm_sqr();
+        Complex::from_parts(
+            (self.real * rhs.real + self.imag * rhs.imag) / d,
+            (self.imag * rhs.real - self.real * rhs.imag) / d,
+        )
+    }
+}
+
+impl<F: Float> std::ops::Neg for Complex<F> {
+    type Output = Complex<F>;
+
+    fn neg(self) -> Self::Output {
+        Complex::from_parts(-self.real, -self.imag)
+    }
+}
+
+/// Sample types the generic DSP blocks operate on: real and complex
+/// ([`Complex`]) values at either [`Float`] precision
+pub trait Sample:
+    Copy
+    + Default
+    + std::fmt::Debug
+    + PartialEq
+    + std::ops::Add<Output = Self>
+    + std::ops::Sub<Output = Self>
+    + std::ops::Mul<Output = Self>
+    + std::ops::Div<Output = Self>
+    + std::ops::Mul<<Self as Sample>::Real, Output = Self>
+    + Send
+    + Sync
+    + 'static
+{
+    /// Precision of the sample's components
+    type Real: Float;
+
+    /// Whether the sample has a quadrature component, so its spectrum is
+    /// not symmetric about DC
+    const COMPLEX: bool;
+
+    /// Widen to a complex value (real samples get a zero imaginary part)
+    fn to_complex(self) -> Complex<Self::Real>;
+    
+    /// Squared magnitude
+    fn power(self) -> Self::Real;
+}
+
+impl Sample for f64 {
+    type Real = f64;
+    const COMPLEX: bool = false;
+
+    fn to_complex(self) -> Complex {
+        Complex::new(self, 0.0)
+    }
+    
+    fn power(self) -> f64 {
+        self * self
+    }
+}
+
+impl Sample for f32 {
+    type Real = f32;
+    const COMPLEX: bool = false;
+
+    fn to_complex(self) -> Complex32 {
+        Complex::from_parts(self, 0.0)
+    }
+
+    fn power(self) -> f32 {
+        self * self
+    }
+}
+
+impl<F: Float> Sample for Complex<F> {
+    type Real = F;
+    const COMPLEX: bool = true;
+
+    fn to_complex(self) -> Complex<F> {
+        self
+    }
+    
+    fn power(self) -> F {
+        self.norm_sqr()
+    }
+}
+
 /// Type alias for complex sample buffers (IQ data)
 pub type ComplexBuffer = SampleBuffer<Complex>;
 
 /// Type alias for real sample buffers (audio data)
 pub type AudioBuffer = SampleBuffer<f64>;
 
+/// Single-precision complex sample buffer
+pub type ComplexBuffer32 = SampleBuffer<Complex32>;
+
+/// Single-precision real sample buffer, the format sound cards deliver
+pub type AudioBuffer32 = SampleBuffer<f32>;
+
+/// Shared state of a [`ring_buffer`]. `head` and `tail` count the samples
+/// ever written and released: everything before `tail` has been consumed,
+/// `tail..head` is readable and `head..tail + capacity` is free for the
+/// producer. Each slot also holds how far the stream index has run ahead of
+/// the slot count when the sample was written, i.e. how many samples were
+/// lost before it.
+struct RingShared<T> {
+    slots: Box<[UnsafeCell<(T, u64)>]>,
+    head: AtomicU64,
+    tail: AtomicU64,
+    dropped: AtomicU64,
+    /// Stream samples lost so far, dropped or missing upstream
+    skipped: AtomicU64,
+    sample_rate: f64,
+    /// Wall-clock time of sample index 0, fixed by the first push
+    epoch: OnceLock<SystemTime>,
+}
+
+// SAFETY: a slot is written only by the producer while it lies in
+// `head..tail + capacity` and read only by the consumer while it lies in
+// `tail..head`. The two ranges never overlap, and each side publishes its
+// index with release ordering after touching the slots, so no slot is ever
+// accessed from both threads at once.
+unsafe impl<T: Send> Sync for RingShared<T> {}
+
+impl<T> RingShared<T> {
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn time_at(&self, index: u64) -> Option<SystemTime> {
+        self.epoch
+            .get()
+            .map(|epoch| *epoch + Duration::from_secs_f64(index as f64 / self.sample_rate))
+    }
+
+    fn slot(&self, position: u64) -> &UnsafeCell<(T, u64)> {
+        &self.slots[(position % self.capacity() as u64) as usize]
+    }
+}
+
+/// Create a lock-free single-producer/single-consumer ring buffer holding up
+/// to `capacity` samples at `sample_rate`
+///
+/// Every sample gets an absolute index counted from the start of the stream,
+/// and a wall-clock timestamp derived from the sample clock: the first push
+/// fixes the time of index 0, and later samples are `index / sample_rate`
+/// after it. The producer never blocks; when the consumer falls behind, the
+/// samples that don't fit are dropped and counted, and the stream index
+/// skips over them, so later indices and timestamps stay true to the
+/// capture. A read never spans such a gap.
+pub fn ring_buffer<T: Copy + Default + Send>(
+    capacity: usize,
+    sample_rate: f64,
+) -> Result<(RingProducer<T>, RingConsumer<T>)> {
+    if sample_rate <= 0.0 {
+        return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+    }
+    if capacity == 0 {
+        return Err(CoreError::BufferSizeMismatch {
+            expected: 1,
+            actual: 0,
+        });
+    }
+    let shared = Arc::new(RingShared {
+        slots: (0..capacity).map(|_| UnsafeCell::new((T::default(), 0))).collect(),
+        head: AtomicU64::new(0),
+        tail: AtomicU64::new(0),
+        dropped: AtomicU64::new(0),
+        skipped: AtomicU64::new(0),
+        sample_rate,
+        epoch: OnceLock::new(),
+    });
+    Ok((
+        RingProducer {
+            shared: shared.clone(),
+        },
+        RingConsumer { shared },
+    ))
+}
+
+/// Writing half of a [`ring_buffer`], typically owned by a capture callback
+pub struct RingProducer<T> {
+    shared: Arc<RingShared<T>>,
+}
+
+impl<T: Copy> RingProducer<T> {
+    /// Append samples whose last one was captured just now; returns how many
+    /// fit. Only the first push takes its time from the clock; after that
+    /// the sample clock alone moves the timeline on.
+    pub fn push(&mut self, samples: &[T]) -> usize {
+        if self.shared.epoch.get().is_none() {
+            let span = Duration::from_secs_f64(samples.len() as f64 / self.shared.sample_rate);
+            let now = SystemTime::now();
+            self.anchor(now.checked_sub(span).unwrap_or(now));
+        }
+        self.write(samples)
+    }
+
+    /// Append samples, the first of which was captured at `captured_at`;
+    /// returns how many fit. The first push anchors the stream's timeline.
+    /// A later push stamped more than a sample after the time the sample
+    /// clock predicts for it means samples went missing upstream: the
+    /// stream index skips ahead to `captured_at`, leaving a gap.
+    pub fn push_stamped(&mut self, samples: &[T], captured_at: SystemTime) -> usize {
+        let shared = &*self.shared;
+        match shared.epoch.get() {
+            None => self.anchor(captured_at),
+            Some(epoch) => {
+                let expected = self.position();
+                let stamped = captured_at
+                    .duration_since(*epoch)
+                    .map_or(0.0, |elapsed| elapsed.as_secs_f64() * shared.sample_rate);
+                if stamped - expected as f64 > 1.0 {
+                    let missing = (stamped - expected as f64).round() as u64;
+                    shared.skipped.fetch_add(missing, Ordering::Relaxed);
+                }
+            }
+        }
+        self.write(samples)
+    }
+
+    /// Fix the wall-clock time of stream index 0 from that of the next sample
+    fn anchor(&self, captured_at: SystemTime) {
+        let offset = Duration::from_secs_f64(self.position() as f64 / self.shared.sample_rate);
+        self.shared
+            .epoch
+            .get_or_init(|| captured_at.checked_sub(offset).unwrap_or(captured_at));
+    }
+
+    fn write(&mut self, samples: &[T]) -> usize {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        let skipped = shared.skipped.load(Ordering::Relaxed);
+        let tail = shared.tail.load(Ordering::Acquire);
+        let free = shared.capacity() - (head - tail) as usize;
+        let count = samples.len().min(free);
+        for (i, &sample) in samples[..count].iter().enumerate() {
+            // SAFETY: the slot lies in the free region, which only the
+            // producer touches.
+            unsafe { *shared.slot(head + i as u64).get() = (sample, skipped) };
+        }
+        shared.head.store(head + count as u64, Ordering::Release);
+        if count < samples.len() {
+            let lost = (samples.len() - count) as u64;
+            shared.dropped.fetch_add(lost, Ordering::Relaxed);
+            shared.skipped.fetch_add(lost, Ordering::Relaxed);
+        }
+        count
+    }
+
+    /// Number of samples that can be pushed without dropping any
+    pub fn free(&self) -> usize {
+        let head = self.shared.head.load(Ordering::Relaxed);
+        let tail = self.shared.tail.load(Ordering::Acquire);
+        self.shared.capacity() - (head - tail) as usize
+    }
+
+    /// Absolute index the next pushed sample will get, unless it is
+    /// stamped after a gap
+    pub fn position(&self) -> u64 {
+        self.shared.head.load(Ordering::Relaxed) + self.shared.skipped.load(Ordering::Relaxed)
+    }
+}
+
+/// A block of samples read from a [`ring_buffer`], with its place in the
+/// stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamWindow<T> {
+    /// Absolute index of the first sample
+    pub start_index: u64,
+    /// Wall-clock time of the first sample
+    pub timestamp: SystemTime,
+    /// Sample rate of the stream
+    pub sample_rate: f64,
+    /// The samples
+    pub samples: Vec<T>,
+}
+
+impl<T> StreamWindow<T> {
+    /// Absolute index of the sample `offset` samples into the window
+    pub fn index_at(&self, offset: usize) -> u64 {
+        self.start_index + offset as u64
+    }
+
+    /// Wall-clock time of the sample `offset` samples into the window
+    pub fn time_at(&self, offset: usize) -> SystemTime {
+        self.timestamp + Duration::from_secs_f64(offset as f64 / self.sample_rate)
+    }
+}
+
+/// Reading half of a [`ring_buffer`], typically owned by a decoder thread
+pub struct RingConsumer<T> {
+    shared: Arc<RingShared<T>>,
+}
+
+impl<T: Copy> RingConsumer<T> {
+    /// Number of samples ready to read
+    pub fn available(&self) -> usize {
+        let tail = self.shared.tail.load(Ordering::Relaxed);
+        (self.shared.head.load(Ordering::Acquire) - tail) as usize
+    }
+
+    /// Buffer capacity in samples
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity()
+    }
+
+    /// Sample rate of the stream
+    pub fn sample_rate(&self) -> f64 {
+        self.shared.sample_rate
+    }
+
+    /// Absolute index of the next sample to read
+    pub fn position(&self) -> u64 {
+        let tail = self.shared.tail.load(Ordering::Relaxed);
+        if self.available() > 0 {
+            tail + self.skipped_at(tail)
+        } else {
+            tail + self.shared.skipped.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Whether the producer has been dropped, so nothing more will arrive
+    /// beyond what is [`available`](RingConsumer::available)
+    pub fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+
+    /// Samples the producer has had to drop because the buffer was full.
+    /// Stream indices skip over them.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Wall-clock time of an absolute sample index, once the first push has
+    /// anchored the timeline
+    pub fn time_at(&self, index: u64) -> Option<SystemTime> {
+        self.shared.time_at(index)
+    }
+
+    /// Copy the next `len` samples and release the first `advance` of them,
+    /// so the next window starts `advance` samples later and overlaps this
+    /// one by `len - advance`. Returns `None` until `len` samples are ready.
+    ///
+    /// A window never spans a gap in the stream: when one would, the
+    /// samples before the gap are released unread and the window starts
+    /// after it.
+    pub fn read_window(&mut self, len: usize, advance: usize) -> Result<Option<StreamWindow<T>>> {
+        if len > self.capacity() {
+            return Err(CoreError::BufferSizeMismatch {
+                expected: self.capacity(),
+                actual: len,
+            });
+        }
+        if advance > len {
+            return Err(CoreError::BufferSizeMismatch {
+                expected: len,
+                actual: advance,
+            });
+        }
+        loop {
+            if self.available() < len {
+                return Ok(None);
+            }
+            let run = self.contiguous(len);
+            if run == len {
+                return Ok(Some(self.read(len, advance)));
+            }
+            self.skip(run);
+        }
+    }
+
+    /// Read and release everything that is ready, up to the next gap in the
+    /// stream
+    pub fn take(&mut self) -> StreamWindow<T> {
+        let len = self.contiguous(self.available());
+        self.read(len, len)
+    }
+
+    /// Release up to `count` samples without reading them; returns how many
+    /// were skipped
+    pub fn skip(&mut self, count: usize) -> usize {
+        let count = count.min(self.available());
+        let tail = self.shared.tail.load(Ordering::Relaxed);
+        self.shared.tail.store(tail + count as u64, Ordering::Release);
+        count
+    }
+
+    /// Stream samples lost before the one in slot `position`, which must be
+    /// readable
+    fn skipped_at(&self, position: u64) -> u64 {
+        // SAFETY: the caller checked that the slot lies in `tail..head`,
+        // which only the consumer touches.
+        unsafe { (*self.shared.slot(position).get()).1 }
+    }
+
+    /// How many of the first `len` readable samples run on without a gap
+    fn contiguous(&self, len: usize) -> usize {
+        let tail = self.shared.tail.load(Ordering::Relaxed);
+        if len == 0 {
+            return 0;
+        }
+        let first = self.skipped_at(tail);
+        // The skipped count never falls, so bisect for where it first grows.
+        let (mut low, mut high) = (1, len);
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.skipped_at(tail + mid as u64) == first {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    fn read(&mut self, len: usize, advance: usize) -> StreamWindow<T> {
+        let shared = &*self.shared;
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let samples = (tail..tail + len as u64)
+            // SAFETY: the caller checked that `len` samples are available,
+            // so the slot lies in `tail..head`, which only the consumer
+            // touches.
+            .map(|position| unsafe { (*shared.slot(position).get()).0 })
+            .collect();
+        let start_index = if len > 0 {
+            tail + self.skipped_at(tail)
+        } else {
+            tail + shared.skipped.load(Ordering::Relaxed)
+        };
+        shared.tail.store(tail + advance as u64, Ordering::Release);
+        StreamWindow {
+            start_index,
+            // Nothing can be read before the first push sets the epoch.
+            timestamp: shared.time_at(start_index).unwrap_or(SystemTime::UNIX_EPOCH),
+            sample_rate: shared.sample_rate,
+            samples,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,5 +722,108 @@ mod tests {
         let c = Complex::new(3.0, 4.0);
         assert_eq!(c.magnitude(), 5.0);
         assert!((c.phase() - 0.9272952180016122).abs() < 1e-10);
+        
+        let q = c / Complex::new(0.0, 1.0);
+        assert!((q.real - 4.0).abs() < 1e-12 && (q.imag + 3.0).abs() < 1e-12);
+        assert_eq!(c.conj(), Complex::new(3.0, -4.0));
+        assert_eq!(-c, Complex::new(-3.0, -4.0));
+    }
+
+    #[test]
+    fn test_ring_buffer_overlapping_windows() {
+        let (mut producer, mut consumer) = ring_buffer::<f64>(8, 1000.0).unwrap();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        assert!(consumer.read_window(4, 2).unwrap().is_none());
+
+        let input: Vec<f64> = (0..6).map(f64::from).collect();
+        assert_eq!(producer.push_stamped(&input, start), 6);
+        let first = consumer.read_window(4, 2).unwrap().unwrap();
+        assert_eq!(first.samples, vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(first.start_index, 0);
+        assert_eq!(first.timestamp, start);
+        let second = consumer.read_window(4, 2).unwrap().unwrap();
+        assert_eq!(second.samples, vec![2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(second.start_index, 2);
+        assert_eq!(second.time_at(1), start + Duration::from_millis(3));
+        assert!(consumer.read_window(4, 2).unwrap().is_none());
+
+        // Wraps around; the unreleased overlap still counts against the
+        // capacity, so two of these are dropped.
+        assert_eq!(producer.free(), 6);
+        let more: Vec<f64> = (6..14).map(f64::from).collect();
+        assert_eq!(producer.push(&more), 6);
+        assert_eq!(consumer.dropped(), 2);
+        let rest = consumer.take();
+        assert_eq!(rest.start_index, 4);
+        assert_eq!(rest.samples, (4..12).map(f64::from).collect::<Vec<_>>());
+        assert_eq!(consumer.position(), producer.position());
+        assert_eq!(consumer.time_at(12), Some(start + Duration::from_millis(12)));
+
+        assert!(consumer.read_window(9, 1).is_err());
+        assert!(consumer.read_window(4, 5).is_err());
+        assert!(ring_buffer::<f64>(0, 1000.0).is_err());
+        assert!(ring_buffer::<f64>(8, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_ring_buffer_gaps_keep_the_timeline() {
+        let (mut producer, mut consumer) = ring_buffer::<f64>(8, 1000.0).unwrap();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let input: Vec<f64> = (0..10).map(f64::from).collect();
+        assert_eq!(producer.push_stamped(&input, start), 8);
+        assert_eq!(consumer.dropped(), 2);
+        assert_eq!(producer.position(), 10);
+
+        // Samples 8 and 9 were dropped, so the next push starts at index 10,
+        // whatever the ring's slot count.
+        assert_eq!(consumer.take().samples, input[..8]);
+        assert_eq!(producer.push(&[10.0, 11.0, 12.0]), 3);
+        let window = consumer.read_window(3, 3).unwrap().unwrap();
+        assert_eq!(window.start_index, 10);
+        assert_eq!(window.timestamp, start + Duration::from_millis(10));
+        assert_eq!(window.samples, vec![10.0, 11.0, 12.0]);
+
+        // A push stamped 5 ms after the sample clock expects it was preceded
+        // by 5 lost samples; a window that would span the gap starts after it.
+        producer.push_stamped(&[13.0, 14.0], start + Duration::from_millis(13));
+        producer.push_stamped(&[20.0, 21.0, 22.0], start + Duration::from_millis(20));
+        assert_eq!(consumer.position(), 13);
+        let window = consumer.read_window(3, 1).unwrap().unwrap();
+        assert_eq!(window.start_index, 20);
+        assert_eq!(window.time_at(2), start + Duration::from_millis(22));
+        assert_eq!(window.samples, vec![20.0, 21.0, 22.0]);
+        assert_eq!(consumer.position(), 21);
+        assert_eq!(consumer.dropped(), 2);
+    }
+
+    #[test]
+    fn test_ring_buffer_across_threads() {
+        let (mut producer, mut consumer) = ring_buffer::<Complex>(64, 8000.0).unwrap();
+        let total = 20_000u64;
+        let writer = std::thread::spawn(move || {
+            let mut next = 0u64;
+            while next < total {
+                let block: Vec<Complex> = (next..(next + 7).min(total))
+                    .map(|i| Complex::new(i as f64, -(i as f64)))
+                    .collect();
+                next += producer.push(&block) as u64;
+                // Only what fitted was pushed; retry the rest.
+                std::thread::yield_now();
+            }
+        });
+
+        let mut expected = 0u64;
+        while expected < total {
+            let window = consumer.take();
+            if window.samples.is_empty() {
+                std::thread::yield_now();
+            }
+            assert_eq!(window.start_index, expected);
+            for sample in window.samples {
+                assert_eq!(sample, Complex::new(expected as f64, -(expected as f64)));
+                expected += 1;
+            }
+        }
+        writer.join().unwrap();
     }
 }
\ No newline at end of file
@@ -10,9 +10,13 @@
 //! then AWGN.
 
 use crate::buffer::Complex;
+use crate::resample::{kaiser_sinc, Resampler};
+use crate::{CoreError, Result};
 use rand::rngs::StdRng;
+use rand::Rng;
 use rand::SeedableRng;
-use rand_distr::{Distribution, StandardNormal};
+use rand_distr::{Distribution, Poisson, StandardNormal};
+use serde::{Deserialize, Serialize};
 use std::f64::consts::PI;
 
 /// Average power of a complex signal: the mean of `|x|^2`.
@@ -47,7 +51,11 @@ pub fn add_awgn_snr(samples: &mut [Complex], snr_db: f64, rng: &mut StdRng) {
         return;
     }
     let snr_lin = 10f64.powf(snr_db / 10.0);
-    let noise_power = sig / snr_lin;
+    add_awgn_power(samples, sig / snr_lin, rng);
+}
+
+/// Add complex AWGN of total power `noise_power`.
+fn add_awgn_power(samples: &mut [Complex], noise_power: f64, rng: &mut StdRng) {
     // Half the noise power goes to each quadrature, so the per-component
     // standard deviation is sqrt(N/2).
     let sigma = (noise_power / 2.0).sqrt();
@@ -138,7 +146,7 @@ pub fn apply_phase_offset(samples: &mut [Complex], radians: f64) {
 }
 
 /// A multipath tap: a delay (in samples) and a complex gain.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct MultipathTap {
     pub delay: usize,
     pub gain: Complex,
@@ -158,16 +166,80 @@ pub fn apply_multipath(samples: &[Complex], taps: &[MultipathTap]) -> Vec<Comple
     out
 }
 
+/// Kernel half-width, in samples, of the fractional-delay interpolator.
+const TIMING_HALF_TAPS: usize = 16;
+
+/// Delay a signal by `delay` samples, which may be fractional or negative,
+/// using a Kaiser-windowed sinc interpolator. Output length matches the input;
+/// samples shifted in from outside the buffer are zero. Models the arbitrary
+/// sampling phase of a receiver relative to the transmitted symbols.
+pub fn apply_timing_offset(samples: &[Complex], delay: f64) -> Vec<Complex> {
+    let (shift, taps) = timing_taps(delay);
+    let len = samples.len() as isize;
+    (0..len)
+        .map(|n| {
+            let mut y = Complex::default();
+            for &(j, h) in &taps {
+                let k = n - shift - j;
+                if (0..len).contains(&k) {
+                    y = y + samples[k as usize] * h;
+                }
+            }
+            y
+        })
+        .collect()
+}
+
+/// Whole-sample shift and interpolator taps for a delay of `delay` samples:
+/// `y[n] = sum_j x[n - shift - j] * h(j + frac)` over the kernel support.
+fn timing_taps(delay: f64) -> (isize, Vec<(isize, f64)>) {
+    let shift = delay.floor();
+    let frac = delay - shift;
+    let half = TIMING_HALF_TAPS as isize;
+    let taps = (1 - half..=half)
+        .map(|j| (j, kaiser_sinc(j as f64 - frac, TIMING_HALF_TAPS, 1.0)))
+        .collect();
+    (shift as isize, taps)
+}
+
 /// A channel impairment that transforms a signal in place. Implementors may
 /// reallocate the buffer (e.g. multipath), hence `&mut Vec`.
+///
+/// Successive calls continue one signal: stages keep their phase, delay
+/// lines and level measurements across calls, so a stream can be fed
+/// through in chunks.
 pub trait Channel {
     fn apply(&mut self, samples: &mut Vec<Complex>);
 }
 
-/// AWGN impairment at a fixed target SNR with its own seeded RNG.
+/// Average power of everything a stage has been fed so far. Stages that set
+/// a level against the signal use this rather than [`signal_power`] of the
+/// current buffer, so a chunked signal is measured as one stream; a single
+/// call sees exactly [`signal_power`].
+#[derive(Debug, Clone, Default, PartialEq)]
+struct RunningPower {
+    energy: f64,
+    count: usize,
+}
+
+impl RunningPower {
+    /// Fold in `samples` and return the average power so far.
+    fn update(&mut self, samples: &[Complex]) -> f64 {
+        self.energy += samples.iter().map(|s| s.norm_sqr()).sum::<f64>();
+        self.count += samples.len();
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.energy / self.count as f64
+    }
+}
+
+/// AWGN impairment at a fixed target SNR with its own seeded RNG. The SNR
+/// is measured against the signal power over all calls so far.
 pub struct Awgn {
     snr_db: f64,
     rng: StdRng,
+    power: RunningPower,
 }
 
 impl Awgn {
@@ -175,29 +247,54 @@ impl Awgn {
         Self {
             snr_db,
             rng: StdRng::seed_from_u64(seed),
+            power: RunningPower::default(),
         }
     }
 }
 
 impl Channel for Awgn {
     fn apply(&mut self, samples: &mut Vec<Complex>) {
-        add_awgn_snr(samples, self.snr_db, &mut self.rng);
+        let sig = self.power.update(samples);
+        if sig == 0.0 {
+            return;
+        }
+        let snr_lin = 10f64.powf(self.snr_db / 10.0);
+        add_awgn_power(samples, sig / snr_lin, &mut self.rng);
     }
 }
 
-/// Constant carrier frequency offset impairment.
+/// Constant carrier frequency offset impairment. The phase runs on across
+/// calls.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FreqOffset {
     pub freq_hz: f64,
     pub sample_rate: f64,
+    #[serde(skip)]
+    phase: f64,
+}
+
+impl FreqOffset {
+    pub fn new(freq_hz: f64, sample_rate: f64) -> Self {
+        Self {
+            freq_hz,
+            sample_rate,
+            phase: 0.0,
+        }
+    }
 }
 
 impl Channel for FreqOffset {
     fn apply(&mut self, samples: &mut Vec<Complex>) {
-        apply_freq_offset(samples, self.freq_hz, self.sample_rate);
+        let step = 2.0 * PI * self.freq_hz / self.sample_rate;
+        for s in samples.iter_mut() {
+            *s = *s * Complex::new(self.phase.cos(), self.phase.sin());
+            self.phase = (self.phase + step) % (2.0 * PI);
+        }
     }
 }
 
 /// Constant phase rotation impairment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PhaseOffset {
     pub radians: f64,
 }
@@ -208,14 +305,812 @@ impl Channel for PhaseOffset {
     }
 }
 
-/// Static multipath impairment.
+/// Static multipath impairment. The delay line carries across calls, so
+/// echoes of one buffer land in the next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Multipath {
     pub taps: Vec<MultipathTap>,
+    #[serde(skip)]
+    history: Vec<Complex>,
+}
+
+impl Multipath {
+    pub fn new(taps: Vec<MultipathTap>) -> Self {
+        Self {
+            taps,
+            history: Vec::new(),
+        }
+    }
 }
 
 impl Channel for Multipath {
     fn apply(&mut self, samples: &mut Vec<Complex>) {
-        *samples = apply_multipath(samples, &self.taps);
+        let max_delay = self.taps.iter().map(|t| t.delay).max().unwrap_or(0);
+        // The last `max_delay` inputs, zero before the first call
+        let kept = self.history.len().min(max_delay);
+        let mut input = vec![Complex::default(); max_delay - kept];
+        input.extend_from_slice(&self.history[self.history.len() - kept..]);
+        input.extend_from_slice(samples);
+        *samples = apply_multipath(&input, &self.taps).split_off(max_delay);
+        self.history = input.split_off(input.len() - max_delay);
+    }
+}
+
+/// Fixed timing offset of `delay_samples` (fractional, may be negative).
+///
+/// State carries across calls. The interpolator needs a few samples of
+/// look-ahead, so the tail of each buffer is held back and comes out at the
+/// start of the next call; a signal applied in one go loses those last few
+/// samples, as [`SampleRateOffset`] does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimingOffset {
+    pub delay_samples: f64,
+    /// Input the interpolator may still read, starting at stream index `base`
+    #[serde(skip)]
+    pending: Vec<Complex>,
+    #[serde(skip)]
+    base: usize,
+    #[serde(skip)]
+    emitted: usize,
+}
+
+impl TimingOffset {
+    pub fn new(delay_samples: f64) -> Self {
+        Self {
+            delay_samples,
+            pending: Vec::new(),
+            base: 0,
+            emitted: 0,
+        }
+    }
+}
+
+impl Channel for TimingOffset {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        let (shift, taps) = timing_taps(self.delay_samples);
+        let half = TIMING_HALF_TAPS as isize;
+        self.pending.extend_from_slice(samples);
+        let received = (self.base + self.pending.len()) as isize;
+        // Output n reads input up to n - shift + half - 1
+        let lookahead = (half - 1 - shift).max(0);
+        let ready = (received - lookahead).max(self.emitted as isize);
+        let base = self.base as isize;
+        samples.clear();
+        for n in self.emitted as isize..ready {
+            let mut y = Complex::default();
+            for &(j, h) in &taps {
+                let k = n - shift - j;
+                if (base..received).contains(&k) {
+                    y = y + self.pending[(k - base) as usize] * h;
+                }
+            }
+            samples.push(y);
+        }
+        self.emitted = ready as usize;
+        // Drop input that falls behind the next output's kernel
+        let oldest = (ready - shift - half).max(0) as usize;
+        let done = oldest.saturating_sub(self.base).min(self.pending.len());
+        self.pending.drain(..done);
+        self.base += done;
+    }
+}
+
+/// Sample-clock mismatch: the receiver's clock runs `ppm` parts per million
+/// fast (negative: slow) relative to the transmitter's, so the signal is
+/// continuously resampled and the output is correspondingly longer or
+/// shorter than the input.
+///
+/// State carries across calls. The interpolator needs a few samples of
+/// look-ahead, so the tail of each buffer is held back and comes out at the
+/// start of the next call; a signal applied in one go loses those last few
+/// samples, as if the capture stopped just before them.
+pub struct SampleRateOffset {
+    resampler: Resampler<Complex>,
+}
+
+impl SampleRateOffset {
+    /// Fails if `ppm` is -1e6 or below.
+    pub fn new(ppm: f64) -> Result<Self> {
+        if ppm <= -1e6 {
+            return Err(CoreError::ResampleError {
+                msg: format!("sample clock offset {} ppm must be above -1e6", ppm),
+            });
+        }
+        let resampler = Resampler::new(1.0, 1.0 + ppm * 1e-6)?;
+        Ok(Self { resampler })
+    }
+
+    /// Input samples per output sample.
+    pub fn ratio(&self) -> f64 {
+        self.resampler.ratio()
+    }
+}
+
+impl Channel for SampleRateOffset {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        let mut out = Vec::with_capacity(self.resampler.output_length(samples.len()));
+        // `process` only fails on invalid configuration, ruled out in `new`.
+        if self.resampler.process(samples, &mut out).is_ok() {
+            *samples = out;
+        }
+    }
+}
+
+/// Carrier frequency offset that starts at `initial_offset_hz` and drifts
+/// linearly at `rate_hz_per_s`, e.g. a transmitter warming up. Phase and
+/// elapsed time carry across calls.
+pub struct FrequencyDrift {
+    pub initial_offset_hz: f64,
+    pub rate_hz_per_s: f64,
+    pub sample_rate: f64,
+    elapsed: u64,
+    phase: f64,
+}
+
+impl FrequencyDrift {
+    pub fn new(initial_offset_hz: f64, rate_hz_per_s: f64, sample_rate: f64) -> Self {
+        Self {
+            initial_offset_hz,
+            rate_hz_per_s,
+            sample_rate,
+            elapsed: 0,
+            phase: 0.0,
+        }
+    }
+
+    /// Instantaneous frequency offset for the next sample, in Hz.
+    pub fn current_offset_hz(&self) -> f64 {
+        self.initial_offset_hz + self.rate_hz_per_s * self.elapsed as f64 / self.sample_rate
+    }
+}
+
+impl Channel for FrequencyDrift {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        for s in samples.iter_mut() {
+            *s = *s * Complex::new(self.phase.cos(), self.phase.sin());
+            let step = 2.0 * PI * self.current_offset_hz() / self.sample_rate;
+            self.phase = (self.phase + step) % (2.0 * PI);
+            self.elapsed += 1;
+        }
+    }
+}
+
+/// Clipping characteristic for [`Clipper`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Clipping {
+    /// Envelope limited to the clip level, phase untouched.
+    Hard,
+    /// `level * tanh(|x| / level)`: linear for small signals, rounding
+    /// smoothly into the clip level.
+    Soft,
+}
+
+/// Envelope clipping, as in an overdriven soundcard or an ALC that can't
+/// keep up. The clip level sits `clip_ratio_db` above the signal's RMS
+/// (measured over all calls so far, like [`Awgn`]), so lowering it models
+/// more drive. For real audio (zero imaginary part) this is ordinary waveform
+/// clipping.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Clipper {
+    pub clipping: Clipping,
+    pub clip_ratio_db: f64,
+    #[serde(skip)]
+    power: RunningPower,
+}
+
+impl Clipper {
+    pub fn new(clipping: Clipping, clip_ratio_db: f64) -> Self {
+        Self {
+            clipping,
+            clip_ratio_db,
+            power: RunningPower::default(),
+        }
+    }
+}
+
+impl Channel for Clipper {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        let level = self.power.update(samples).sqrt() * 10f64.powf(self.clip_ratio_db / 20.0);
+        if level == 0.0 {
+            return;
+        }
+        for s in samples.iter_mut() {
+            let r = s.magnitude();
+            if r == 0.0 {
+                continue;
+            }
+            let clipped = match self.clipping {
+                Clipping::Hard => r.min(level),
+                Clipping::Soft => level * (r / level).tanh(),
+            };
+            *s = *s * (clipped / r);
+        }
+    }
+}
+
+/// AM-AM / AM-PM characteristic of a power amplifier, on an input envelope
+/// normalized so that 1.0 is the saturation point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AmplifierModel {
+    /// Rapp solid-state amplifier: `r / (1 + r^(2p))^(1/(2p))`, no AM-PM.
+    /// Larger `smoothness` (p) approaches a hard limiter.
+    Rapp { smoothness: f64 },
+    /// Saleh travelling-wave tube: `A(r) = a_a r / (1 + b_a r^2)` and
+    /// `Φ(r) = a_p r^2 / (1 + b_p r^2)`. The input is scaled so that
+    /// saturation (`r = 1 / sqrt(b_a)`) lands on 1.0; `alpha_a` only sets
+    /// the absolute gain, which the stage normalizes out.
+    Saleh {
+        alpha_a: f64,
+        beta_a: f64,
+        alpha_phi: f64,
+        beta_phi: f64,
+    },
+}
+
+impl AmplifierModel {
+    /// Saleh's published TWT fit.
+    pub const SALEH_TWT: AmplifierModel = AmplifierModel::Saleh {
+        alpha_a: 2.1587,
+        beta_a: 1.1517,
+        alpha_phi: 4.0033,
+        beta_phi: 9.1040,
+    };
+
+    /// Output gain (complex) for an input envelope `r`, with small-signal
+    /// gain normalized to 1.
+    fn gain(&self, r: f64) -> Complex {
+        match *self {
+            AmplifierModel::Rapp { smoothness } => {
+                let p2 = 2.0 * smoothness;
+                Complex::new(1.0 / (1.0 + r.powf(p2)).powf(1.0 / p2), 0.0)
+            }
+            AmplifierModel::Saleh {
+                beta_a,
+                alpha_phi,
+                beta_phi,
+                ..
+            } => {
+                let x = r / beta_a.sqrt();
+                let amplitude = 1.0 / (1.0 + beta_a * x * x);
+                let phase = alpha_phi * x * x / (1.0 + beta_phi * x * x);
+                Complex::new(amplitude * phase.cos(), amplitude * phase.sin())
+            }
+        }
+    }
+}
+
+/// Power amplifier non-linearity driven `input_backoff_db` below
+/// saturation, with backoff measured from the signal's RMS over all calls.
+/// The small-signal gain is normalized to unity, so with plenty of backoff
+/// the stage is transparent and as the backoff shrinks the peaks compress
+/// (and, for Saleh, rotate).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Amplifier {
+    pub model: AmplifierModel,
+    pub input_backoff_db: f64,
+    #[serde(skip)]
+    power: RunningPower,
+}
+
+impl Amplifier {
+    pub fn new(model: AmplifierModel, input_backoff_db: f64) -> Self {
+        Self {
+            model,
+            input_backoff_db,
+            power: RunningPower::default(),
+        }
+    }
+
+    /// Rapp amplifier with smoothness `p` (2-3 is typical of SSPAs).
+    pub fn rapp(smoothness: f64, input_backoff_db: f64) -> Self {
+        Self::new(AmplifierModel::Rapp { smoothness }, input_backoff_db)
+    }
+
+    /// Saleh TWT amplifier with the standard coefficients.
+    pub fn saleh(input_backoff_db: f64) -> Self {
+        Self::new(AmplifierModel::SALEH_TWT, input_backoff_db)
+    }
+}
+
+impl Channel for Amplifier {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        let rms = self.power.update(samples).sqrt();
+        if rms == 0.0 {
+            return;
+        }
+        // Input envelope normalized so 1.0 is saturation
+        let drive = 10f64.powf(-self.input_backoff_db / 20.0) / rms;
+        for s in samples.iter_mut() {
+            *s = *s * self.model.gain(s.magnitude() * drive);
+        }
+    }
+}
+
+/// Constant DC offset, e.g. from an AC-coupling fault or a direct-conversion
+/// receiver's LO leakage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DcOffset {
+    pub offset: Complex,
+}
+
+impl Channel for DcOffset {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        for s in samples.iter_mut() {
+            *s = *s + self.offset;
+        }
+    }
+}
+
+/// Quadrature (IQ) imbalance: the Q branch has `gain_db` more gain than I
+/// and is `phase_deg` away from 90 degrees. The I branch is untouched, so the
+/// result is `mu * x + nu * conj(x)` with an image
+/// [`image_rejection_db`](IqImbalance::image_rejection_db) below the signal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IqImbalance {
+    pub gain_db: f64,
+    pub phase_deg: f64,
+}
+
+impl IqImbalance {
+    /// Power ratio of the wanted signal to its mirror image, in dB.
+    pub fn image_rejection_db(&self) -> f64 {
+        let g = 10f64.powf(self.gain_db / 20.0);
+        let c = self.phase_deg.to_radians().cos();
+        10.0 * ((1.0 + 2.0 * g * c + g * g) / (1.0 - 2.0 * g * c + g * g)).log10()
+    }
+}
+
+impl Channel for IqImbalance {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        let g = 10f64.powf(self.gain_db / 20.0);
+        let (sin, cos) = self.phase_deg.to_radians().sin_cos();
+        for s in samples.iter_mut() {
+            s.imag = g * (s.imag * cos - s.real * sin);
+        }
+    }
+}
+
+/// One propagation path of a Watterson HF channel.
+///
+/// The path's complex gain is a Rayleigh-fading process whose Doppler power
+/// spectrum is Gaussian. Following ITU-R F.1487, `doppler_spread_hz` is the
+/// two-sigma width of that spectrum and `doppler_shift_hz` offsets its centre.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WattersonPath {
+    /// Propagation delay relative to the input, in seconds.
+    pub delay_s: f64,
+    /// Mean power of the path (linear). Presets split unit power evenly.
+    pub power: f64,
+    /// Two-sigma frequency spread of the Gaussian Doppler spectrum (Hz). Zero
+    /// gives a static, non-fading path.
+    pub doppler_spread_hz: f64,
+    /// Frequency shift of the Doppler spectrum centre (Hz).
+    pub doppler_shift_hz: f64,
+}
+
+/// Standard two-path HF conditions from ITU-R F.1487 / CCIR Rec. 520.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WattersonPreset {
+    /// 0.5 ms differential delay, 0.1 Hz spread.
+    CcirGood,
+    /// 1 ms differential delay, 0.5 Hz spread.
+    CcirModerate,
+    /// 2 ms differential delay, 1 Hz spread.
+    CcirPoor,
+    /// 0.5 ms differential delay, 10 Hz spread (high-latitude flutter).
+    Flutter,
+}
+
+impl WattersonPreset {
+    /// The two equal-power paths that make up this condition.
+    pub fn paths(self) -> Vec<WattersonPath> {
+        let (delay_s, spread_hz) = match self {
+            WattersonPreset::CcirGood => (0.5e-3, 0.1),
+            WattersonPreset::CcirModerate => (1.0e-3, 0.5),
+            WattersonPreset::CcirPoor => (2.0e-3, 1.0),
+            WattersonPreset::Flutter => (0.5e-3, 10.0),
+        };
+        let path = |delay_s| WattersonPath {
+            delay_s,
+            power: 0.5,
+            doppler_spread_hz: spread_hz,
+            doppler_shift_hz: 0.0,
+        };
+        vec![path(0.0), path(delay_s)]
+    }
+}
+
+/// Tap-gain updates per Hz of Doppler spread. The Gaussian shaping filter
+/// runs at this low rate and is linearly interpolated up to the sample rate.
+const FADING_OVERSAMPLE: f64 = 32.0;
+
+/// Time-varying complex gain of a single Watterson path.
+struct FadingTap {
+    amplitude: f64,
+    /// Gaussian shaping filter, normalized to unit output power. Empty for a
+    /// static path.
+    shaping: Vec<f64>,
+    noise: Vec<Complex>,
+    noise_index: usize,
+    /// Tap-rate samples advanced per input sample.
+    step: f64,
+    frac: f64,
+    prev: Complex,
+    next: Complex,
+    shift_step: f64,
+    shift_phase: f64,
+}
+
+impl FadingTap {
+    fn new(path: &WattersonPath, sample_rate: f64, rng: &mut StdRng) -> Self {
+        let mut tap = Self {
+            amplitude: path.power.max(0.0).sqrt(),
+            shaping: Vec::new(),
+            noise: Vec::new(),
+            noise_index: 0,
+            step: 0.0,
+            frac: 0.0,
+            prev: Complex::new(1.0, 0.0),
+            next: Complex::new(1.0, 0.0),
+            shift_step: 2.0 * PI * path.doppler_shift_hz / sample_rate,
+            shift_phase: 0.0,
+        };
+        if path.doppler_spread_hz > 0.0 {
+            // A Gaussian power spectrum with sigma_f = spread / 2 is produced by
+            // white noise through a filter whose impulse response is
+            // exp(-4 pi^2 sigma_f^2 t^2); truncate it at four time-sigmas.
+            let tap_rate = FADING_OVERSAMPLE * path.doppler_spread_hz;
+            let sigma_f = path.doppler_spread_hz / 2.0;
+            let sigma_t = 1.0 / (2.0 * 2f64.sqrt() * PI * sigma_f);
+            let half = (4.0 * sigma_t * tap_rate).ceil() as isize;
+            let mut shaping: Vec<f64> = (-half..=half)
+                .map(|k| {
+                    let t = k as f64 / tap_rate;
+                    (-4.0 * PI * PI * sigma_f * sigma_f * t * t).exp()
+                })
+                .collect();
+            let energy: f64 = shaping.iter().map(|h| h * h).sum();
+            for h in &mut shaping {
+                *h /= energy.sqrt();
+            }
+            tap.noise = (0..shaping.len()).map(|_| complex_gaussian(rng)).collect();
+            tap.shaping = shaping;
+            tap.step = tap_rate / sample_rate;
+            // Start in steady state rather than ramping up from zero.
+            tap.prev = tap.generate(rng);
+            tap.next = tap.generate(rng);
+        }
+        tap
+    }
+
+    /// Push one unit-power complex Gaussian sample through the shaping
+    /// filter and return the next tap-rate gain.
+    fn generate(&mut self, rng: &mut StdRng) -> Complex {
+        let len = self.noise.len();
+        self.noise[self.noise_index] = complex_gaussian(rng);
+        self.noise_index = (self.noise_index + 1) % len;
+        let mut out = Complex::default();
+        for (i, &h) in self.shaping.iter().enumerate() {
+            out = out + self.noise[(self.noise_index + i) % len] * h;
+        }
+        out
+    }
+
+    /// Gain to apply to the current input sample; advances the process.
+    fn gain(&mut self, rng: &mut StdRng) -> Complex {
+        let g = if self.shaping.is_empty() {
+            Complex::new(1.0, 0.0)
+        } else {
+            let g = self.prev * (1.0 - self.frac) + self.next * self.frac;
+            self.frac += self.step;
+            while self.frac >= 1.0 {
+                self.frac -= 1.0;
+                self.prev = self.next;
+                self.next = self.generate(rng);
+            }
+            g
+        };
+        if self.shift_step == 0.0 {
+            return g * self.amplitude;
+        }
+        let shift = Complex::new(self.shift_phase.cos(), self.shift_phase.sin());
+        self.shift_phase = (self.shift_phase + self.shift_step) % (2.0 * PI);
+        g * shift * self.amplitude
+    }
+}
+
+/// Unit-power complex Gaussian sample (variance 1/2 per quadrature).
+fn complex_gaussian(rng: &mut StdRng) -> Complex {
+    let zi: f64 = StandardNormal.sample(rng);
+    let zq: f64 = StandardNormal.sample(rng);
+    Complex::new(zi, zq) * std::f64::consts::FRAC_1_SQRT_2
+}
+
+/// Watterson (ITU-R F.1487) time-varying HF channel: a tapped delay line
+/// whose taps fade independently with Gaussian Doppler spectra.
+///
+/// State carries across [`Channel::apply`] calls, so feeding a signal in
+/// chunks gives the same output as applying it in one go.
+pub struct Watterson {
+    paths: Vec<(usize, FadingTap)>,
+    history: Vec<Complex>,
+    rng: StdRng,
+}
+
+impl Watterson {
+    /// Build a channel from explicit paths. Delays are rounded to whole
+    /// samples at `sample_rate`.
+    pub fn new(paths: &[WattersonPath], sample_rate: f64, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let paths: Vec<(usize, FadingTap)> = paths
+            .iter()
+            .map(|p| {
+                let delay = (p.delay_s.max(0.0) * sample_rate).round() as usize;
+                (delay, FadingTap::new(p, sample_rate, &mut rng))
+            })
+            .collect();
+        let max_delay = paths.iter().map(|(d, _)| *d).max().unwrap_or(0);
+        Self {
+            paths,
+            history: vec![Complex::default(); max_delay],
+            rng,
+        }
+    }
+
+    /// Build one of the standard CCIR/ITU-R conditions.
+    pub fn preset(preset: WattersonPreset, sample_rate: f64, seed: u64) -> Self {
+        Self::new(&preset.paths(), sample_rate, seed)
+    }
+}
+
+impl Channel for Watterson {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        let hist = self.history.len();
+        let mut input = std::mem::take(&mut self.history);
+        input.extend_from_slice(samples);
+        for (n, out) in samples.iter_mut().enumerate() {
+            let mut y = Complex::default();
+            for (delay, tap) in self.paths.iter_mut() {
+                y = y + input[hist + n - *delay] * tap.gain(&mut self.rng);
+            }
+            *out = y;
+        }
+        self.history = input.split_off(input.len() - hist);
+    }
+}
+
+/// Statistics of an [`ImpulseNoise`] process.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImpulseModel {
+    /// Gaussian background plus, with probability `probability` per sample,
+    /// a Gaussian impulse `impulse_ratio_db` stronger than the background.
+    /// Models static crashes and ignition noise.
+    BernoulliGaussian {
+        probability: f64,
+        impulse_ratio_db: f64,
+    },
+    /// Middleton class A: the number of overlapping impulses on a sample is
+    /// Poisson with mean `impulsive_index` (A), and `gaussian_ratio` (Γ) is
+    /// the background-to-impulsive power ratio. Small A is very impulsive;
+    /// large A tends to Gaussian.
+    MiddletonClassA {
+        impulsive_index: f64,
+        gaussian_ratio: f64,
+    },
+}
+
+impl ImpulseModel {
+    /// Fails unless the probability is in [0, 1], A is positive and Γ is
+    /// non-negative, all finite.
+    fn validate(&self) -> Result<()> {
+        let msg = match *self {
+            ImpulseModel::BernoulliGaussian {
+                probability,
+                impulse_ratio_db,
+            } => {
+                if !(0.0..=1.0).contains(&probability) {
+                    format!("impulse probability {} must be in [0, 1]", probability)
+                } else if !impulse_ratio_db.is_finite() {
+                    format!("impulse ratio {} dB must be finite", impulse_ratio_db)
+                } else {
+                    return Ok(());
+                }
+            }
+            ImpulseModel::MiddletonClassA {
+                impulsive_index,
+                gaussian_ratio,
+            } => {
+                if !(impulsive_index > 0.0 && impulsive_index.is_finite()) {
+                    format!("impulsive index A = {} must be positive", impulsive_index)
+                } else if !(gaussian_ratio >= 0.0 && gaussian_ratio.is_finite()) {
+                    format!("Gaussian ratio Γ = {} must be non-negative", gaussian_ratio)
+                } else {
+                    return Ok(());
+                }
+            }
+        };
+        Err(CoreError::InvalidChannelParameters { msg })
+    }
+}
+
+/// Impulsive noise at a fixed SNR against the *total* average noise power,
+/// so it slots into the same sweeps as [`Awgn`]. Each sample draws a noise
+/// variance from the model (normalized to unit mean) and then a complex
+/// Gaussian sample at that variance.
+pub struct ImpulseNoise {
+    model: ImpulseModel,
+    snr_db: f64,
+    rng: StdRng,
+    power: RunningPower,
+}
+
+impl ImpulseNoise {
+    /// Fails if the model parameters are out of range, see
+    /// [`ImpulseModel`].
+    pub fn new(model: ImpulseModel, snr_db: f64, seed: u64) -> Result<Self> {
+        model.validate()?;
+        Ok(Self {
+            model,
+            snr_db,
+            rng: StdRng::seed_from_u64(seed),
+            power: RunningPower::default(),
+        })
+    }
+
+    /// Bernoulli-Gaussian impulsive noise, see [`ImpulseModel::BernoulliGaussian`].
+    pub fn bernoulli_gaussian(
+        snr_db: f64,
+        probability: f64,
+        impulse_ratio_db: f64,
+        seed: u64,
+    ) -> Result<Self> {
+        let model = ImpulseModel::BernoulliGaussian {
+            probability,
+            impulse_ratio_db,
+        };
+        Self::new(model, snr_db, seed)
+    }
+
+    /// Middleton class-A impulsive noise, see [`ImpulseModel::MiddletonClassA`].
+    pub fn middleton_class_a(
+        snr_db: f64,
+        impulsive_index: f64,
+        gaussian_ratio: f64,
+        seed: u64,
+    ) -> Result<Self> {
+        let model = ImpulseModel::MiddletonClassA {
+            impulsive_index,
+            gaussian_ratio,
+        };
+        Self::new(model, snr_db, seed)
+    }
+
+    /// Unit-mean variance multiplier for the next sample.
+    fn variance(&mut self) -> f64 {
+        match self.model {
+            ImpulseModel::BernoulliGaussian {
+                probability,
+                impulse_ratio_db,
+            } => {
+                let ratio = 10f64.powf(impulse_ratio_db / 10.0);
+                let hit = self.rng.gen_bool(probability);
+                (1.0 + if hit { ratio } else { 0.0 }) / (1.0 + probability * ratio)
+            }
+            ImpulseModel::MiddletonClassA {
+                impulsive_index,
+                gaussian_ratio,
+            } => {
+                let m = if impulsive_index > 0.0 {
+                    Poisson::new(impulsive_index)
+                        .map(|p| p.sample(&mut self.rng))
+                        .unwrap_or(0.0)
+                } else {
+                    0.0
+                };
+                (m / impulsive_index.max(f64::MIN_POSITIVE) + gaussian_ratio)
+                    / (1.0 + gaussian_ratio)
+            }
+        }
+    }
+}
+
+impl Channel for ImpulseNoise {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        let sig = self.power.update(samples);
+        if sig == 0.0 {
+            return;
+        }
+        let noise_power = sig / 10f64.powf(self.snr_db / 10.0);
+        for s in samples.iter_mut() {
+            let sigma = (noise_power * self.variance()).sqrt();
+            *s = *s + complex_gaussian(&mut self.rng) * sigma;
+        }
+    }
+}
+
+/// Unmodulated carrier at `offset_hz` from the signal, `sir_db` below the
+/// signal power (negative values put the carrier above the signal). The
+/// carrier phase runs on across calls.
+pub struct CwInterferer {
+    pub offset_hz: f64,
+    pub sir_db: f64,
+    pub sample_rate: f64,
+    phase: f64,
+    power: RunningPower,
+}
+
+impl CwInterferer {
+    pub fn new(offset_hz: f64, sir_db: f64, sample_rate: f64) -> Self {
+        Self {
+            offset_hz,
+            sir_db,
+            sample_rate,
+            phase: 0.0,
+            power: RunningPower::default(),
+        }
+    }
+}
+
+impl Channel for CwInterferer {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        let sig = self.power.update(samples);
+        let amplitude = (sig / 10f64.powf(self.sir_db / 10.0)).sqrt();
+        let step = 2.0 * PI * self.offset_hz / self.sample_rate;
+        for s in samples.iter_mut() {
+            *s = *s + Complex::new(self.phase.cos(), self.phase.sin()) * amplitude;
+            self.phase = (self.phase + step) % (2.0 * PI);
+        }
+    }
+}
+
+/// A second signal, taken from another sample buffer, shifted by
+/// `offset_hz` and added `sir_db` below the wanted signal's power. The
+/// interferer buffer is looped if it is shorter than the signal, and its
+/// position and frequency-shift phase run on across calls.
+pub struct AdjacentChannel {
+    interferer: Vec<Complex>,
+    interferer_power: f64,
+    position: usize,
+    pub offset_hz: f64,
+    pub sir_db: f64,
+    pub sample_rate: f64,
+    phase: f64,
+    power: RunningPower,
+}
+
+impl AdjacentChannel {
+    pub fn new(interferer: Vec<Complex>, offset_hz: f64, sir_db: f64, sample_rate: f64) -> Self {
+        Self {
+            interferer_power: signal_power(&interferer),
+            interferer,
+            position: 0,
+            offset_hz,
+            sir_db,
+            sample_rate,
+            phase: 0.0,
+            power: RunningPower::default(),
+        }
+    }
+}
+
+impl Channel for AdjacentChannel {
+    fn apply(&mut self, samples: &mut Vec<Complex>) {
+        if self.interferer_power == 0.0 {
+            return;
+        }
+        let sig = self.power.update(samples);
+        let gain = (sig / 10f64.powf(self.sir_db / 10.0) / self.interferer_power).sqrt();
+        let step = 2.0 * PI * self.offset_hz / self.sample_rate;
+        for s in samples.iter_mut() {
+            let shift = Complex::new(self.phase.cos(), self.phase.sin());
+            *s = *s + self.interferer[self.position] * shift * gain;
+            self.position = (self.position + 1) % self.interferer.len();
+            self.phase = (self.phase + step) % (2.0 * PI);
+        }
     }
 }
 
@@ -223,7 +1118,7 @@ impl Channel for Multipath {
 /// AWGN models the receiver seeing distortion plus front-end noise.
 #[derive(Default)]
 pub struct ChannelChain {
-    stages: Vec<Box<dyn Channel>>,
+    stages: Vec<Box<dyn Channel + Send>>,
 }
 
 impl ChannelChain {
@@ -231,7 +1126,7 @@ impl ChannelChain {
         Self { stages: Vec::new() }
     }
 
-    pub fn then(mut self, stage: impl Channel + 'static) -> Self {
+    pub fn then(mut self, stage: impl Channel + Send + 'static) -> Self {
         self.stages.push(Box::new(stage));
         self
     }
@@ -251,7 +1146,6 @@ mod tests {
 
     /// A reproducible BPSK-ish test signal: random ±1 on the real axis.
     fn bpsk_signal(n: usize, seed: u64) -> Vec<Complex> {
-        use rand::Rng;
         let mut rng = StdRng::seed_from_u64(seed);
         (0..n)
             .map(|_| Complex::new(if rng.gen::<bool>() { 1.0 } else { -1.0 }, 0.0))
@@ -412,4 +1306,355 @@ mod tests {
             "expected ~10 dB after chain, got {snr_vs_rotated}"
         );
     }
+
+    #[test]
+    fn watterson_static_path_is_pure_delay() {
+        let x = bpsk_signal(50, 21);
+        let path = WattersonPath {
+            delay_s: 3.0 / 8000.0,
+            power: 1.0,
+            doppler_spread_hz: 0.0,
+            doppler_shift_hz: 0.0,
+        };
+        let mut ch = Watterson::new(&[path], 8000.0, 1);
+        let mut y = x.clone();
+        ch.apply(&mut y);
+        assert!(y[..3].iter().all(|s| s.norm_sqr() == 0.0));
+        for n in 3..x.len() {
+            assert!((y[n] - x[n - 3]).norm_sqr() < 1e-24);
+        }
+    }
+
+    #[test]
+    fn watterson_is_deterministic_and_chunk_invariant() {
+        let x = bpsk_signal(4000, 23);
+        let mut whole = x.clone();
+        Watterson::preset(WattersonPreset::CcirPoor, 8000.0, 7).apply(&mut whole);
+
+        let mut ch = Watterson::preset(WattersonPreset::CcirPoor, 8000.0, 7);
+        let mut chunked = Vec::new();
+        for chunk in x.chunks(333) {
+            let mut c = chunk.to_vec();
+            ch.apply(&mut c);
+            chunked.extend(c);
+        }
+        assert_eq!(whole, chunked);
+
+        let mut other = x.clone();
+        Watterson::preset(WattersonPreset::CcirPoor, 8000.0, 8).apply(&mut other);
+        assert_ne!(whole, other);
+    }
+
+    #[test]
+    fn watterson_fades_with_unit_mean_power() {
+        // A constant carrier through a single 10 Hz-spread path: the envelope
+        // must show deep Rayleigh fades while the average power stays ~1.
+        let fs = 2000.0;
+        let path = WattersonPath {
+            delay_s: 0.0,
+            power: 1.0,
+            doppler_spread_hz: 10.0,
+            doppler_shift_hz: 0.0,
+        };
+        let mut ch = Watterson::new(&[path], fs, 3);
+        let mut y = vec![Complex::new(1.0, 0.0); (60.0 * fs) as usize];
+        ch.apply(&mut y);
+        let mean = signal_power(&y);
+        assert!((mean - 1.0).abs() < 0.2, "mean fading power {mean}");
+        let min = y.iter().map(|s| s.norm_sqr()).fold(f64::INFINITY, f64::min);
+        let max = y.iter().map(|s| s.norm_sqr()).fold(0.0, f64::max);
+        assert!(10.0 * (max / min).log10() > 20.0, "fade depth too shallow");
+    }
+
+    #[test]
+    fn watterson_spectrum_width_tracks_spread() {
+        // The tap process decorrelates faster for a wider Doppler spread.
+        let fs = 2000.0;
+        let lag = (0.05 * fs) as usize;
+        let correlation = |spread: f64| {
+            let path = WattersonPath {
+                delay_s: 0.0,
+                power: 1.0,
+                doppler_spread_hz: spread,
+                doppler_shift_hz: 0.0,
+            };
+            let mut ch = Watterson::new(&[path], fs, 11);
+            let mut y = vec![Complex::new(1.0, 0.0); (40.0 * fs) as usize];
+            ch.apply(&mut y);
+            let mut acc = Complex::default();
+            for n in lag..y.len() {
+                let b = y[n - lag];
+                acc = acc + y[n] * Complex::new(b.real, -b.imag);
+            }
+            acc.magnitude() / signal_power(&y) / (y.len() - lag) as f64
+        };
+        assert!(correlation(0.5) > 0.9);
+        assert!(correlation(10.0) < 0.5);
+    }
+
+    /// Fraction of noise samples more than 10 dB above the mean noise power.
+    fn peak_fraction(noise: &[Complex]) -> f64 {
+        let mean = signal_power(noise);
+        noise.iter().filter(|n| n.norm_sqr() > 10.0 * mean).count() as f64 / noise.len() as f64
+    }
+
+    #[test]
+    fn impulse_noise_hits_target_snr_and_is_impulsive() {
+        let clean = bpsk_signal(200_000, 21);
+        let gaussian = awgn_at_snr(&clean, 6.0, 1);
+        let gaussian: Vec<Complex> = gaussian.iter().zip(&clean).map(|(y, x)| *y - *x).collect();
+
+        let stages = [
+            ImpulseNoise::bernoulli_gaussian(6.0, 0.01, 20.0, 3).unwrap(),
+            ImpulseNoise::middleton_class_a(6.0, 0.1, 0.01, 3).unwrap(),
+        ];
+        for mut stage in stages {
+            let mut noisy = clean.clone();
+            stage.apply(&mut noisy);
+            let measured = measure_snr_db(&clean, &noisy);
+            assert!(
+                (measured - 6.0).abs() < 0.5,
+                "{:?}: {measured} dB",
+                stage.model
+            );
+            // Far heavier tails than Gaussian noise at the same power
+            let noise: Vec<Complex> = noisy.iter().zip(&clean).map(|(y, x)| *y - *x).collect();
+            assert!(
+                peak_fraction(&noise) > 10.0 * peak_fraction(&gaussian),
+                "{:?} not impulsive",
+                stage.model
+            );
+        }
+
+        assert!(ImpulseNoise::bernoulli_gaussian(6.0, 1.5, 20.0, 3).is_err());
+        assert!(ImpulseNoise::bernoulli_gaussian(6.0, f64::NAN, 20.0, 3).is_err());
+        assert!(ImpulseNoise::middleton_class_a(6.0, -0.1, 0.01, 3).is_err());
+        assert!(ImpulseNoise::middleton_class_a(6.0, 0.1, -0.01, 3).is_err());
+    }
+
+    #[test]
+    fn cw_interferer_adds_carrier_at_offset_and_level() {
+        let fs = 8000.0;
+        let clean = vec![Complex::new(1.0, 0.0); 8000];
+        let mut y = clean.clone();
+        let mut ch = CwInterferer::new(1000.0, 10.0, fs);
+        ch.apply(&mut y);
+        let interference: Vec<Complex> = y.iter().zip(&clean).map(|(y, x)| *y - *x).collect();
+        assert!((signal_power(&interference) - 0.1).abs() < 1e-9);
+        let step = 2.0 * PI * 1000.0 / fs;
+        for (n, i) in interference.iter().enumerate().take(100) {
+            let expected =
+                Complex::new((step * n as f64).cos(), (step * n as f64).sin()) * 0.1f64.sqrt();
+            assert!((*i - expected).magnitude() < 1e-9);
+        }
+
+        // Phase carries across calls.
+        let mut chunked = CwInterferer::new(1000.0, 10.0, fs);
+        let mut a = clean[..3001].to_vec();
+        let mut b = clean[3001..].to_vec();
+        chunked.apply(&mut a);
+        chunked.apply(&mut b);
+        a.extend(b);
+        for (x, z) in a.iter().zip(&y) {
+            assert!((*x - *z).magnitude() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn adjacent_channel_loops_interferer_and_chains() {
+        let fs = 8000.0;
+        let clean = bpsk_signal(10_000, 8);
+        let other = bpsk_signal(3000, 9);
+        let mut y = clean.clone();
+        let mut chain = ChannelChain::new()
+            .then(AdjacentChannel::new(other.clone(), 2000.0, 3.0, fs))
+            .then(CwInterferer::new(-500.0, 30.0, fs));
+        chain.apply(&mut y);
+        assert_eq!(y.len(), clean.len());
+
+        let mut acs = clean.clone();
+        AdjacentChannel::new(other.clone(), 0.0, 3.0, fs).apply(&mut acs);
+        let interference: Vec<Complex> = acs.iter().zip(&clean).map(|(y, x)| *y - *x).collect();
+        let gain = 10f64.powf(-3.0 / 20.0);
+        for (n, i) in interference.iter().enumerate() {
+            assert!((*i - other[n % other.len()] * gain).magnitude() < 1e-9);
+        }
+    }
+
+    fn tone(n: usize, cycles_per_sample: f64) -> Vec<Complex> {
+        (0..n)
+            .map(|k| {
+                let phase = 2.0 * PI * cycles_per_sample * k as f64;
+                Complex::new(phase.cos(), phase.sin())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn timing_offset_delays_by_fractional_samples() {
+        let x = tone(1000, 0.05);
+        // Whole-sample delay is an exact shift.
+        let y = apply_timing_offset(&x, 3.0);
+        assert!(y[..3].iter().all(|s| s.magnitude() < 1e-12));
+        for n in 3..1000 {
+            assert!((y[n] - x[n - 3]).magnitude() < 1e-12);
+        }
+        // Fractional delays (either sign) match the analytic delayed tone.
+        for delay in [0.37, -2.5] {
+            let y = apply_timing_offset(&x, delay);
+            let mut stage = TimingOffset::new(delay);
+            let mut z = x.clone();
+            stage.apply(&mut z);
+            for n in 50..950 {
+                let phase = 2.0 * PI * 0.05 * (n as f64 - delay);
+                let expected = Complex::new(phase.cos(), phase.sin());
+                assert!((y[n] - expected).magnitude() < 1e-3, "delay {delay} n {n}");
+                assert_eq!(y[n], z[n]);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_rate_offset_stretches_time_continuously() {
+        // A receiver clock 1000 ppm fast samples the tone at 1/1.001 of the
+        // transmitter's sample spacing.
+        let x = tone(20_000, 0.02);
+        let mut ch = SampleRateOffset::new(1000.0).unwrap();
+        let mut y = x.clone();
+        ch.apply(&mut y);
+        assert!((y.len() as f64 - 20_000.0 * 1.001).abs() < 2.0 * TIMING_HALF_TAPS as f64);
+        for (m, s) in y.iter().enumerate().skip(100) {
+            let phase = 2.0 * PI * 0.02 * m as f64 * ch.ratio();
+            assert!((*s - Complex::new(phase.cos(), phase.sin())).magnitude() < 1e-3);
+        }
+
+        // Chunked application gives the same stream.
+        let mut chunked = SampleRateOffset::new(1000.0).unwrap();
+        let mut out = Vec::new();
+        for chunk in x.chunks(777) {
+            let mut c = chunk.to_vec();
+            chunked.apply(&mut c);
+            out.extend(c);
+        }
+        assert_eq!(out.len(), y.len());
+        for (a, b) in out.iter().zip(&y) {
+            assert!((*a - *b).magnitude() < 1e-12);
+        }
+
+        assert!(SampleRateOffset::new(-1e6).is_err());
+    }
+
+    #[test]
+    fn frequency_drift_ramps_offset_across_calls() {
+        let fs = 8000.0;
+        let mut ch = FrequencyDrift::new(10.0, 5.0, fs);
+        let mut a = vec![Complex::new(1.0, 0.0); 4000];
+        let mut b = vec![Complex::new(1.0, 0.0); 8001];
+        ch.apply(&mut a);
+        ch.apply(&mut b);
+        a.extend(b);
+        let freq = |n: usize| {
+            let d = a[n + 1] * Complex::new(a[n].real, -a[n].imag);
+            d.imag.atan2(d.real) * fs / (2.0 * PI)
+        };
+        assert!((freq(0) - 10.0).abs() < 1e-6);
+        assert!((freq(3999) - (10.0 + 5.0 * 3999.0 / fs)).abs() < 1e-6);
+        assert!((freq(11_999) - (10.0 + 5.0 * 11_999.0 / fs)).abs() < 1e-6);
+        assert!((ch.current_offset_hz() - (10.0 + 5.0 * 12_001.0 / fs)).abs() < 1e-9);
+    }
+
+    /// Complex Gaussian samples: a signal with a large peak-to-average ratio,
+    /// like OFDM.
+    fn gaussian_signal(n: usize, seed: u64) -> Vec<Complex> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n).map(|_| complex_gaussian(&mut rng)).collect()
+    }
+
+    #[test]
+    fn clipper_limits_envelope_and_keeps_phase() {
+        let x = gaussian_signal(50_000, 12);
+        let level = signal_power(&x).sqrt() * 10f64.powf(3.0 / 20.0);
+        for clipping in [Clipping::Hard, Clipping::Soft] {
+            let mut y = x.clone();
+            Clipper::new(clipping, 3.0).apply(&mut y);
+            for (a, b) in x.iter().zip(&y) {
+                assert!(b.magnitude() <= level + 1e-12);
+                assert!((a.phase() - b.phase()).abs() < 1e-9);
+                if clipping == Clipping::Hard && a.magnitude() < level {
+                    assert_eq!(a, b);
+                }
+            }
+            // More drive (a lower clip ratio) means more distortion.
+            let mut z = x.clone();
+            Clipper::new(clipping, 0.0).apply(&mut z);
+            assert!(measure_snr_db(&x, &z) < measure_snr_db(&x, &y));
+        }
+    }
+
+    #[test]
+    fn amplifier_compresses_with_drive() {
+        let x = gaussian_signal(50_000, 13);
+        let distortion = |mut amp: Amplifier| {
+            let mut y = x.clone();
+            amp.apply(&mut y);
+            measure_snr_db(&x, &y)
+        };
+        // Transparent with plenty of backoff, increasingly distorted with drive.
+        assert!(distortion(Amplifier::rapp(2.0, 30.0)) > 40.0);
+        assert!(distortion(Amplifier::saleh(30.0)) > 25.0);
+        for amp in [|b| Amplifier::rapp(2.0, b), |b| Amplifier::saleh(b)] {
+            let snr: Vec<f64> = [12.0, 6.0, 0.0]
+                .iter()
+                .map(|&b| distortion(amp(b)))
+                .collect();
+            assert!(snr[0] > snr[1] && snr[1] > snr[2], "{snr:?}");
+        }
+
+        // A very smooth-edged Rapp is close to a hard limiter at saturation.
+        let mut y = x.clone();
+        Amplifier::rapp(50.0, 0.0).apply(&mut y);
+        assert!(y.iter().all(|s| s.magnitude() <= 1.0 + 1e-9));
+
+        // Saleh rotates strong samples (AM-PM); Rapp doesn't.
+        let mut y = x.clone();
+        Amplifier::saleh(0.0).apply(&mut y);
+        let peak = (0..x.len())
+            .max_by(|&a, &b| x[a].magnitude().total_cmp(&x[b].magnitude()))
+            .unwrap();
+        assert!((y[peak].phase() - x[peak].phase()).abs() > 0.1);
+    }
+
+    #[test]
+    fn dc_offset_and_iq_imbalance() {
+        let x = tone(8000, 0.1);
+        let mut y = x.clone();
+        DcOffset {
+            offset: Complex::new(0.1, -0.2),
+        }
+        .apply(&mut y);
+        for (a, b) in x.iter().zip(&y) {
+            assert!((*b - *a - Complex::new(0.1, -0.2)).magnitude() < 1e-12);
+        }
+
+        // A complex tone picks up a mirror image at -f; measure its level by
+        // correlating against both tones.
+        let mut iq = IqImbalance {
+            gain_db: 1.0,
+            phase_deg: 5.0,
+        };
+        let mut y = x.clone();
+        iq.apply(&mut y);
+        assert!(x.iter().zip(&y).all(|(a, b)| a.real == b.real));
+        let correlate = |reference: &[Complex]| {
+            let mut acc = Complex::default();
+            for (r, s) in reference.iter().zip(&y) {
+                acc = acc + *s * Complex::new(r.real, -r.imag);
+            }
+            acc.norm_sqr()
+        };
+        let image = tone(8000, -0.1);
+        let measured = 10.0 * (correlate(&x) / correlate(&image)).log10();
+        assert!((measured - iq.image_rejection_db()).abs() < 0.01);
+        assert!((iq.image_rejection_db() - 22.8).abs() < 0.1);
+    }
 }
@@ -0,0 +1,1358 @@
+//! Filter design
+//!
+//! FIR taps by the window method or by Parks-McClellan equiripple
+//! optimisation, and Butterworth / Chebyshev / elliptic IIR filters realised
+//! as cascaded second-order sections. Frequencies are in Hz alongside the
+//! sample rate, as in [`FirFilter::lowpass`](crate::filter::FirFilter::lowpass).
+//! FIR designers return a tap vector for [`FirFilter::new`](crate::filter::FirFilter::new)
+//! or [`FastFirFilter::new`](crate::filter::FastFirFilter::new); IIR designers
+//! return a [`SosFilter`].
+
+use crate::buffer::{Complex, Sample};
+use crate::filter::{Biquad, SosFilter};
+use crate::{CoreError, Result};
+use std::f64::consts::PI;
+
+/// Band layout of a frequency-selective filter, edges in Hz
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BandType {
+    Lowpass(f64),
+    Highpass(f64),
+    Bandpass(f64, f64),
+    Bandstop(f64, f64),
+}
+
+impl BandType {
+    fn validate(&self, sample_rate: f64) -> Result<()> {
+        if sample_rate <= 0.0 {
+            return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+        }
+        let nyquist = sample_rate / 2.0;
+        let in_range = |f: f64| f > 0.0 && f < nyquist;
+        let ok = match *self {
+            BandType::Lowpass(f) | BandType::Highpass(f) => in_range(f),
+            BandType::Bandpass(lo, hi) | BandType::Bandstop(lo, hi) => {
+                in_range(lo) && in_range(hi) && lo < hi
+            }
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(invalid(format!(
+                "Invalid band edges {:?} for sample rate {}",
+                self, sample_rate
+            )))
+        }
+    }
+}
+
+/// Window applied to a windowed-sinc design
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    Rectangular,
+    Hamming,
+    Hann,
+    Blackman,
+    /// Kaiser window with shape parameter beta, see [`kaiser_parameters`]
+    Kaiser(f64),
+}
+
+impl Window {
+    /// Window samples for a filter of `len` taps
+    pub fn coefficients(self, len: usize) -> Vec<f64> {
+        if len == 1 {
+            return vec![1.0];
+        }
+        let m = (len - 1) as f64;
+        (0..len)
+            .map(|i| {
+                let x = 2.0 * PI * i as f64 / m;
+                match self {
+                    Window::Rectangular => 1.0,
+                    Window::Hamming => 0.54 - 0.46 * x.cos(),
+                    Window::Hann => 0.5 - 0.5 * x.cos(),
+                    Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                    Window::Kaiser(beta) => {
+                        let r = 2.0 * i as f64 / m - 1.0;
+                        bessel_i0(beta * (1.0 - r * r).max(0.0).sqrt()) / bessel_i0(beta)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+fn invalid(msg: String) -> CoreError {
+    CoreError::InvalidFilterParameters { msg }
+}
+
+/// Modified Bessel function of the first kind, order zero
+pub(crate) fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    for k in 1..200 {
+        term *= (half / k as f64).powi(2);
+        sum += term;
+        if term < sum * 1e-17 {
+            break;
+        }
+    }
+    sum
+}
+
+/// Kaiser window length and beta for a given stopband attenuation (dB) and
+/// transition width (Hz). The length is rounded up to an odd number so the
+/// result suits every [`BandType`].
+pub fn kaiser_parameters(
+    atten_db: f64,
+    transition_width: f64,
+    sample_rate: f64,
+) -> Result<(usize, f64)> {
+    if transition_width <= 0.0 || transition_width >= sample_rate / 2.0 {
+        return Err(invalid(format!(
+            "Invalid transition width: {}",
+            transition_width
+        )));
+    }
+    let beta = if atten_db > 50.0 {
+        0.1102 * (atten_db - 8.7)
+    } else if atten_db >= 21.0 {
+        0.5842 * (atten_db - 21.0).powf(0.4) + 0.07886 * (atten_db - 21.0)
+    } else {
+        0.0
+    };
+    let dw = 2.0 * PI * transition_width / sample_rate;
+    let n = (((atten_db - 7.95) / (2.285 * dw)).ceil().max(1.0) as usize + 1) | 1;
+    Ok((n, beta))
+}
+
+/// Ideal low-pass impulse response with cutoff `fc` (cycles/sample) at offset `m`
+fn sinc_lowpass(fc: f64, m: f64) -> f64 {
+    if m == 0.0 {
+        2.0 * fc
+    } else {
+        (2.0 * PI * fc * m).sin() / (PI * m)
+    }
+}
+
+/// Windowed-sinc FIR design
+///
+/// Gain is normalised to unity at DC (low-pass, band-stop), Nyquist
+/// (high-pass) or the band centre (band-pass). High-pass and band-stop
+/// filters need an odd tap count.
+pub fn fir_window(
+    band: BandType,
+    sample_rate: f64,
+    num_taps: usize,
+    window: Window,
+) -> Result<Vec<f64>> {
+    band.validate(sample_rate)?;
+    if num_taps == 0 {
+        return Err(invalid("Number of taps must be greater than 0".to_string()));
+    }
+    if num_taps.is_multiple_of(2) && matches!(band, BandType::Highpass(_) | BandType::Bandstop(..))
+    {
+        return Err(invalid(format!(
+            "{:?} needs an odd number of taps, got {}",
+            band, num_taps
+        )));
+    }
+
+    let center = (num_taps - 1) as f64 / 2.0;
+    let norm = |f: f64| f / sample_rate;
+    let ideal = |m: f64| -> f64 {
+        let delta = if m == 0.0 { 1.0 } else { 0.0 };
+        match band {
+            BandType::Lowpass(f) => sinc_lowpass(norm(f), m),
+            BandType::Highpass(f) => delta - sinc_lowpass(norm(f), m),
+            BandType::Bandpass(lo, hi) => sinc_lowpass(norm(hi), m) - sinc_lowpass(norm(lo), m),
+            BandType::Bandstop(lo, hi) => {
+                delta - sinc_lowpass(norm(hi), m) + sinc_lowpass(norm(lo), m)
+            }
+        }
+    };
+
+    let mut taps: Vec<f64> = window
+        .coefficients(num_taps)
+        .iter()
+        .enumerate()
+        .map(|(i, w)| ideal(i as f64 - center) * w)
+        .collect();
+
+    let reference = match band {
+        BandType::Lowpass(_) | BandType::Bandstop(..) => 0.0,
+        BandType::Highpass(_) => sample_rate / 2.0,
+        BandType::Bandpass(lo, hi) => (lo + hi) / 2.0,
+    };
+    let gain = freq_response(&taps, &[1.0], reference, sample_rate).magnitude();
+    if gain > 0.0 {
+        for t in &mut taps {
+            *t /= gain;
+        }
+    }
+    Ok(taps)
+}
+
+/// Windowed Hilbert transformer
+///
+/// Odd lengths give a type III filter (zero response at DC and Nyquist);
+/// even lengths give a type IV filter (zero response at DC only). The
+/// output is delayed by `(num_taps - 1) / 2` samples.
+pub fn fir_hilbert(num_taps: usize, window: Window) -> Result<Vec<f64>> {
+    if num_taps < 2 {
+        return Err(invalid(
+            "Hilbert transformer needs at least 2 taps".to_string(),
+        ));
+    }
+    let center = (num_taps - 1) as f64 / 2.0;
+    Ok(window
+        .coefficients(num_taps)
+        .iter()
+        .enumerate()
+        .map(|(i, w)| {
+            let m = i as f64 - center;
+            if m == 0.0 {
+                0.0
+            } else {
+                w * (1.0 - (PI * m).cos()) / (PI * m)
+            }
+        })
+        .collect())
+}
+
+const REMEZ_GRID_DENSITY: usize = 16;
+const REMEZ_MAX_ITERATIONS: usize = 40;
+
+/// Parks-McClellan equiripple FIR design
+///
+/// `bands` holds `(start, end)` edges in Hz, ascending and within
+/// `[0, sample_rate / 2]`; `desired` and `weights` give the target
+/// amplitude and error weight per band. `antisymmetric` selects odd
+/// symmetry (type III/IV), used for Hilbert transformers and
+/// differentiators, in which case the amplitude is that of `-j * H`.
+pub fn remez(
+    num_taps: usize,
+    bands: &[(f64, f64)],
+    desired: &[f64],
+    weights: &[f64],
+    sample_rate: f64,
+    antisymmetric: bool,
+) -> Result<Vec<f64>> {
+    if sample_rate <= 0.0 {
+        return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+    }
+    if num_taps < 3 {
+        return Err(invalid(
+            "Equiripple design needs at least 3 taps".to_string(),
+        ));
+    }
+    if bands.is_empty() || desired.len() != bands.len() || weights.len() != bands.len() {
+        return Err(invalid(
+            "bands, desired and weights must be non-empty and of equal length".to_string(),
+        ));
+    }
+    let nyquist = sample_rate / 2.0;
+    let mut previous = 0.0;
+    for &(lo, hi) in bands {
+        if lo < previous || hi <= lo || hi > nyquist {
+            return Err(invalid(format!("Invalid band edges ({}, {})", lo, hi)));
+        }
+        previous = hi;
+    }
+    if weights.iter().any(|&w| w <= 0.0) {
+        return Err(invalid("Band weights must be positive".to_string()));
+    }
+
+    // A(f) = Q(f) * P(cos 2 pi f), P a polynomial with r coefficients.
+    let odd = num_taps % 2 == 1;
+    let (r, q): (usize, fn(f64) -> f64) = match (antisymmetric, odd) {
+        (false, true) => (num_taps.div_ceil(2), |_| 1.0),
+        (false, false) => (num_taps / 2, |f| (PI * f).cos()),
+        (true, true) => ((num_taps - 1) / 2, |f| (2.0 * PI * f).sin()),
+        (true, false) => (num_taps / 2, |f| (PI * f).sin()),
+    };
+
+    // Dense grid, skipping the points where Q vanishes.
+    let step = 0.5 / (REMEZ_GRID_DENSITY * r) as f64;
+    let mut grid_x = Vec::new();
+    let mut grid_d = Vec::new();
+    let mut grid_w = Vec::new();
+    let mut grid_band = Vec::new();
+    for (b, &(lo, hi)) in bands.iter().enumerate() {
+        let mut lo = lo / sample_rate;
+        let mut hi = hi / sample_rate;
+        if q(lo).abs() < 1e-9 {
+            lo += step;
+        }
+        if q(hi).abs() < 1e-9 {
+            hi -= step;
+        }
+        if hi < lo {
+            continue;
+        }
+        let points = ((hi - lo) / step).ceil().max(1.0) as usize;
+        for i in 0..=points {
+            let f = lo + (hi - lo) * i as f64 / points as f64;
+            let qf = q(f);
+            grid_x.push((2.0 * PI * f).cos());
+            grid_d.push(desired[b] / qf);
+            grid_w.push(weights[b] * qf);
+            grid_band.push(b);
+        }
+    }
+    let grid_len = grid_x.len();
+    if grid_len <= r {
+        return Err(invalid(
+            "Bands too narrow for the requested number of taps".to_string(),
+        ));
+    }
+
+    let mut extremals: Vec<usize> = (0..=r).map(|i| i * (grid_len - 1) / r).collect();
+    let mut interp = RemezInterpolant::default();
+    for _ in 0..REMEZ_MAX_ITERATIONS {
+        interp = RemezInterpolant::fit(&extremals, &grid_x, &grid_d, &grid_w);
+        let delta = interp.delta.abs();
+
+        let error: Vec<f64> = (0..grid_len)
+            .map(|j| grid_w[j] * (grid_d[j] - interp.eval(grid_x[j])))
+            .collect();
+        let next = match remez_extrema(&error, &grid_band, delta, r + 1) {
+            Some(next) => next,
+            None => break,
+        };
+        let max_error = next.iter().map(|&j| error[j].abs()).fold(0.0, f64::max);
+        let converged = next == extremals || max_error - delta <= 1e-6 * delta;
+        extremals = next;
+        if converged {
+            break;
+        }
+    }
+
+    // Sample the amplitude response on the DFT grid and invert.
+    let half_delay = (num_taps - 1) as f64 / 2.0;
+    let spectrum: Vec<Complex> = (0..num_taps)
+        .map(|k| {
+            let f = k as f64 / num_taps as f64;
+            let amplitude = q(f) * interp.eval((2.0 * PI * f).cos());
+            let linear_phase = Complex::from_phase(-2.0 * PI * f * half_delay);
+            if antisymmetric {
+                Complex::new(0.0, amplitude) * linear_phase
+            } else {
+                linear_phase * amplitude
+            }
+        })
+        .collect();
+    Ok((0..num_taps)
+        .map(|n| {
+            spectrum
+                .iter()
+                .enumerate()
+                .map(|(k, h)| {
+                    let twiddle =
+                        Complex::from_phase(2.0 * PI * (k * n % num_taps) as f64 / num_taps as f64);
+                    (*h * twiddle).real
+                })
+                .sum::<f64>()
+                / num_taps as f64
+        })
+        .collect())
+}
+
+/// Barycentric form of the Remez polynomial through the current extremal set
+#[derive(Debug, Default)]
+struct RemezInterpolant {
+    delta: f64,
+    x: Vec<f64>,
+    c: Vec<f64>,
+    weights: Vec<f64>,
+}
+
+impl RemezInterpolant {
+    fn fit(extremals: &[usize], grid_x: &[f64], grid_d: &[f64], grid_w: &[f64]) -> Self {
+        let x: Vec<f64> = extremals.iter().map(|&j| grid_x[j]).collect();
+        let gamma = barycentric_weights(&x);
+        let sign = |i: usize| if i.is_multiple_of(2) { 1.0 } else { -1.0 };
+
+        let mut num = 0.0;
+        let mut den = 0.0;
+        for (i, &j) in extremals.iter().enumerate() {
+            num += gamma[i] * grid_d[j];
+            den += gamma[i] * sign(i) / grid_w[j];
+        }
+        let delta = num / den;
+
+        // Interpolate through all but the last extremal; the last one is
+        // satisfied by construction of delta.
+        let r = extremals.len() - 1;
+        let c: Vec<f64> = extremals[..r]
+            .iter()
+            .enumerate()
+            .map(|(i, &j)| grid_d[j] - sign(i) * delta / grid_w[j])
+            .collect();
+        let x = x[..r].to_vec();
+        let weights = barycentric_weights(&x);
+        Self {
+            delta,
+            x,
+            c,
+            weights,
+        }
+    }
+
+    fn eval(&self, x: f64) -> f64 {
+        let mut num = 0.0;
+        let mut den = 0.0;
+        for ((&xi, &ci), &wi) in self.x.iter().zip(&self.c).zip(&self.weights) {
+            let diff = x - xi;
+            if diff.abs() < 1e-14 {
+                return ci;
+            }
+            num += wi * ci / diff;
+            den += wi / diff;
+        }
+        num / den
+    }
+}
+
+/// Barycentric Lagrange weights, scaled by 2 per factor to avoid overflow
+fn barycentric_weights(x: &[f64]) -> Vec<f64> {
+    (0..x.len())
+        .map(|i| {
+            let product: f64 = (0..x.len())
+                .filter(|&j| j != i)
+                .map(|j| 2.0 * (x[i] - x[j]))
+                .product();
+            1.0 / product
+        })
+        .collect()
+}
+
+/// Pick `count` alternating local extrema of the weighted error
+fn remez_extrema(error: &[f64], band: &[usize], delta: f64, count: usize) -> Option<Vec<usize>> {
+    let threshold = delta * (1.0 - 1e-6);
+    let mut candidates: Vec<usize> = Vec::new();
+    for j in 0..error.len() {
+        let e = error[j];
+        if e.abs() < threshold {
+            continue;
+        }
+        let not_exceeded = |k: usize| {
+            band[k] != band[j]
+                || if e > 0.0 {
+                    e >= error[k]
+                } else {
+                    e <= error[k]
+                }
+        };
+        let left = j == 0 || not_exceeded(j - 1);
+        let right = j + 1 == error.len() || not_exceeded(j + 1);
+        if !(left && right) {
+            continue;
+        }
+        match candidates.last() {
+            Some(&last) if error[last].signum() == e.signum() => {
+                if e.abs() > error[last].abs() {
+                    *candidates.last_mut().unwrap() = j;
+                }
+            }
+            _ => candidates.push(j),
+        }
+    }
+    if candidates.len() < count {
+        return None;
+    }
+    while candidates.len() > count {
+        if error[candidates[0]].abs() < error[*candidates.last().unwrap()].abs() {
+            candidates.remove(0);
+        } else {
+            candidates.pop();
+        }
+    }
+    Some(candidates)
+}
+
+/// Equiripple FIR design for a standard band layout
+///
+/// Each edge of `band` is the centre of a transition band `transition_width`
+/// Hz wide. Pass and stop bands are weighted equally; use [`remez`] for
+/// other trade-offs.
+pub fn fir_equiripple(
+    band: BandType,
+    transition_width: f64,
+    sample_rate: f64,
+    num_taps: usize,
+) -> Result<Vec<f64>> {
+    band.validate(sample_rate)?;
+    let nyquist = sample_rate / 2.0;
+    let half = transition_width / 2.0;
+    let (edges, desired): (Vec<f64>, Vec<f64>) = match band {
+        BandType::Lowpass(f) => (vec![f - half, f + half], vec![1.0, 0.0]),
+        BandType::Highpass(f) => (vec![f - half, f + half], vec![0.0, 1.0]),
+        BandType::Bandpass(lo, hi) => (
+            vec![lo - half, lo + half, hi - half, hi + half],
+            vec![0.0, 1.0, 0.0],
+        ),
+        BandType::Bandstop(lo, hi) => (
+            vec![lo - half, lo + half, hi - half, hi + half],
+            vec![1.0, 0.0, 1.0],
+        ),
+    };
+    if transition_width <= 0.0
+        || edges[0] <= 0.0
+        || edges[edges.len() - 1] >= nyquist
+        || edges.windows(2).any(|w| w[1] <= w[0])
+    {
+        return Err(invalid(format!(
+            "Transition width {} Hz does not fit band layout {:?}",
+            transition_width, band
+        )));
+    }
+    if num_taps.is_multiple_of(2) && desired[desired.len() - 1] != 0.0 {
+        return Err(invalid(format!(
+            "{:?} needs an odd number of taps, got {}",
+            band, num_taps
+        )));
+    }
+
+    let mut bands = Vec::with_capacity(desired.len());
+    let mut start = 0.0;
+    for pair in edges.chunks(2) {
+        bands.push((start, pair[0]));
+        start = pair[1];
+    }
+    bands.push((start, nyquist));
+    remez(
+        num_taps,
+        &bands,
+        &desired,
+        &vec![1.0; desired.len()],
+        sample_rate,
+        false,
+    )
+}
+
+/// Equiripple Hilbert transformer with unity gain over `low..high` Hz
+pub fn fir_hilbert_equiripple(
+    num_taps: usize,
+    low: f64,
+    high: f64,
+    sample_rate: f64,
+) -> Result<Vec<f64>> {
+    BandType::Bandpass(low, high).validate(sample_rate)?;
+    // remez designs the amplitude of -j * H, so flip the sign to get the
+    // conventional +90 degree shift of negative frequencies.
+    let taps = remez(num_taps, &[(low, high)], &[1.0], &[1.0], sample_rate, true)?;
+    Ok(taps.into_iter().map(|t| -t).collect())
+}
+
+/// Frequency response of the FIR filter `taps` at `freq` Hz
+pub fn fir_response<S: Sample>(taps: &[S], freq: f64, sample_rate: f64) -> Complex {
+    let w = 2.0 * PI * freq / sample_rate;
+    taps.iter()
+        .enumerate()
+        .fold(Complex::new(0.0, 0.0), |acc, (k, &c)| {
+            acc + Complex::from_phase(-w * k as f64) * c.to_complex().cast()
+        })
+}
+
+/// Frequency response of `b(z) / a(z)` at `freq` Hz
+pub fn freq_response<S: Sample>(b: &[S], a: &[S], freq: f64, sample_rate: f64) -> Complex {
+    fir_response(b, freq, sample_rate) / fir_response(a, freq, sample_rate)
+}
+
+/// Shift a real prototype's response by `shift` Hz, turning a low-pass into
+/// a complex band-pass centred on `shift`. The modulation is referenced to
+/// the centre tap so the shifted filter keeps the prototype's delay.
+pub fn frequency_shift(taps: &[f64], shift: f64, sample_rate: f64) -> Vec<Complex> {
+    let center = (taps.len() as f64 - 1.0) / 2.0;
+    taps.iter()
+        .enumerate()
+        .map(|(i, &t)| {
+            Complex::from_phase(2.0 * PI * shift * (i as f64 - center) / sample_rate) * t
+        })
+        .collect()
+}
+
+/// Windowed-sinc complex band-pass passing `low..high` Hz
+///
+/// Edges lie in `(-sample_rate / 2, sample_rate / 2)` and need not be
+/// symmetric about zero, so `300.0..3000.0` selects the upper sideband of a
+/// baseband signal and `-3000.0..-300.0` the lower one. Gain is unity at
+/// the band centre.
+pub fn fir_complex_bandpass(
+    low: f64,
+    high: f64,
+    sample_rate: f64,
+    num_taps: usize,
+    window: Window,
+) -> Result<Vec<Complex>> {
+    let nyquist = sample_rate / 2.0;
+    if sample_rate <= 0.0 {
+        return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+    }
+    if low <= -nyquist || high >= nyquist || low >= high {
+        return Err(invalid(format!(
+            "Invalid complex band edges ({}, {}) for sample rate {}",
+            low, high, sample_rate
+        )));
+    }
+    let half_width = (high - low) / 2.0;
+    let prototype = fir_window(BandType::Lowpass(half_width), sample_rate, num_taps, window)?;
+    Ok(frequency_shift(&prototype, (low + high) / 2.0, sample_rate))
+}
+
+/// Analog prototype family for [`iir_design`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IirPrototype {
+    /// Maximally flat; band edges are the -3 dB points
+    Butterworth,
+    /// Equiripple passband; band edges are where the response leaves the ripple band
+    Chebyshev1 { ripple_db: f64 },
+    /// Equiripple stopband; band edges are where the attenuation first reaches `atten_db`
+    Chebyshev2 { atten_db: f64 },
+    /// Equiripple in both bands; band edges as for [`IirPrototype::Chebyshev1`]
+    Elliptic { ripple_db: f64, atten_db: f64 },
+}
+
+/// Zeros, poles and gain of a transfer function
+#[derive(Debug, Clone)]
+struct Zpk {
+    zeros: Vec<Complex>,
+    poles: Vec<Complex>,
+    gain: f64,
+}
+
+/// IIR design by bilinear transform of an analog prototype
+///
+/// `order` is the prototype order; band-pass and band-stop designs come
+/// out at twice that order. The result is normalised to unity gain at DC
+/// (low-pass, band-stop), Nyquist (high-pass) or the geometric band centre
+/// (band-pass), except for Chebyshev I and elliptic filters of even order
+/// whose passband peaks at 0 dB and dips by the ripple at those points.
+pub fn iir_design(
+    prototype: IirPrototype,
+    order: usize,
+    band: BandType,
+    sample_rate: f64,
+) -> Result<SosFilter> {
+    band.validate(sample_rate)?;
+    if order == 0 {
+        return Err(invalid("Filter order must be greater than 0".to_string()));
+    }
+    let analog = match prototype {
+        IirPrototype::Butterworth => butterworth_prototype(order),
+        IirPrototype::Chebyshev1 { ripple_db } => {
+            if ripple_db <= 0.0 {
+                return Err(invalid(format!(
+                    "Invalid passband ripple: {} dB",
+                    ripple_db
+                )));
+            }
+            chebyshev1_prototype(order, ripple_db)
+        }
+        IirPrototype::Chebyshev2 { atten_db } => {
+            if atten_db <= 0.0 {
+                return Err(invalid(format!(
+                    "Invalid stopband attenuation: {} dB",
+                    atten_db
+                )));
+            }
+            chebyshev2_prototype(order, atten_db)
+        }
+        IirPrototype::Elliptic {
+            ripple_db,
+            atten_db,
+        } => {
+            if ripple_db <= 0.0 || atten_db <= ripple_db {
+                return Err(invalid(format!(
+                    "Invalid elliptic ripple/attenuation: {} / {} dB",
+                    ripple_db, atten_db
+                )));
+            }
+            elliptic_prototype(order, ripple_db, atten_db)
+        }
+    };
+
+    let warp = |f: f64| 2.0 * sample_rate * (PI * f / sample_rate).tan();
+    let analog = match band {
+        BandType::Lowpass(f) => lp_to_lp(analog, warp(f)),
+        BandType::Highpass(f) => lp_to_hp(analog, warp(f)),
+        BandType::Bandpass(lo, hi) => {
+            let (w1, w2) = (warp(lo), warp(hi));
+            lp_to_bp(analog, (w1 * w2).sqrt(), w2 - w1)
+        }
+        BandType::Bandstop(lo, hi) => {
+            let (w1, w2) = (warp(lo), warp(hi));
+            lp_to_bs(analog, (w1 * w2).sqrt(), w2 - w1)
+        }
+    };
+    zpk_to_sos(bilinear(analog, sample_rate))
+}
+
+fn prod(values: &[Complex]) -> Complex {
+    values
+        .iter()
+        .fold(Complex::new(1.0, 0.0), |acc, &v| acc * v)
+}
+
+fn csqrt(z: Complex) -> Complex {
+    let r = z.magnitude().sqrt();
+    let theta = z.phase() / 2.0;
+    Complex::new(r * theta.cos(), r * theta.sin())
+}
+
+fn butterworth_prototype(order: usize) -> Zpk {
+    let n = order as f64;
+    let poles = (0..order)
+        .map(|k| {
+            let m = 2.0 * k as f64 - n + 1.0;
+            -Complex::from_phase(PI * m / (2.0 * n))
+        })
+        .collect();
+    Zpk {
+        zeros: Vec::new(),
+        poles,
+        gain: 1.0,
+    }
+}
+
+fn chebyshev1_prototype(order: usize, ripple_db: f64) -> Zpk {
+    let n = order as f64;
+    let eps = (10f64.powf(ripple_db / 10.0) - 1.0).sqrt();
+    let mu = (1.0 / eps).asinh() / n;
+    let poles: Vec<Complex> = (0..order)
+        .map(|k| {
+            let theta = PI * (2.0 * k as f64 - n + 1.0) / (2.0 * n);
+            Complex::new(-mu.sinh() * theta.cos(), -mu.cosh() * theta.sin())
+        })
+        .collect();
+    let mut gain = prod(&poles.iter().map(|&p| -p).collect::<Vec<_>>()).real;
+    if order.is_multiple_of(2) {
+        gain /= (1.0 + eps * eps).sqrt();
+    }
+    Zpk {
+        zeros: Vec::new(),
+        poles,
+        gain,
+    }
+}
+
+fn chebyshev2_prototype(order: usize, atten_db: f64) -> Zpk {
+    let n = order as f64;
+    let de = 1.0 / (10f64.powf(atten_db / 10.0) - 1.0).sqrt();
+    let mu = (1.0 / de).asinh() / n;
+
+    let zeros: Vec<Complex> = (0..order)
+        .filter_map(|k| {
+            let m = 2.0 * k as f64 - n + 1.0;
+            if m == 0.0 {
+                None
+            } else {
+                Some(Complex::new(0.0, -1.0 / (PI * m / (2.0 * n)).sin()))
+            }
+        })
+        .collect();
+    let poles: Vec<Complex> = (0..order)
+        .map(|k| {
+            let theta = PI * (2.0 * k as f64 - n + 1.0) / (2.0 * n);
+            let p = Complex::new(-mu.sinh() * theta.cos(), -mu.cosh() * theta.sin());
+            Complex::new(1.0, 0.0) / p
+        })
+        .collect();
+    let neg = |v: &[Complex]| v.iter().map(|&x| -x).collect::<Vec<_>>();
+    let gain = (prod(&neg(&poles)) / prod(&neg(&zeros))).real;
+    Zpk { zeros, poles, gain }
+}
+
+/// Arithmetic-geometric mean
+fn agm(mut a: f64, mut b: f64) -> f64 {
+    while (a - b).abs() > 1e-15 * a {
+        let next = ((a + b) / 2.0, (a * b).sqrt());
+        a = next.0;
+        b = next.1;
+    }
+    a
+}
+
+/// Complete elliptic integral of the first kind, from the complementary
+/// parameter `1 - m` to keep precision when `m` is close to 1
+fn ellipk_complement(m_complement: f64) -> f64 {
+    PI / (2.0 * agm(1.0, m_complement.sqrt()))
+}
+
+/// Jacobi elliptic functions `(sn, cn, dn)` of `u` with parameter `m`,
+/// by the descending Landen transformation
+fn ellipj(u: f64, m: f64) -> (f64, f64, f64) {
+    if m < 1e-12 {
+        return (u.sin(), u.cos(), 1.0);
+    }
+    let mut a = vec![1.0];
+    let mut c = vec![m.sqrt()];
+    let mut b = (1.0 - m).sqrt();
+    while c[c.len() - 1].abs() > 1e-15 && a.len() < 32 {
+        let an = a[a.len() - 1];
+        a.push((an + b) / 2.0);
+        c.push((an - b) / 2.0);
+        b = (an * b).sqrt();
+    }
+    let n = a.len() - 1;
+    let mut phi = 2f64.powi(n as i32) * a[n] * u;
+    for k in (1..=n).rev() {
+        phi = (phi + (c[k] / a[k] * phi.sin()).asin()) / 2.0;
+    }
+    let sn = phi.sin();
+    (sn, phi.cos(), (1.0 - m * sn * sn).sqrt())
+}
+
+/// Carlson's symmetric elliptic integral R_F
+fn carlson_rf(mut x: f64, mut y: f64, mut z: f64) -> f64 {
+    loop {
+        let mu = (x + y + z) / 3.0;
+        let (dx, dy, dz) = (1.0 - x / mu, 1.0 - y / mu, 1.0 - z / mu);
+        if dx.abs().max(dy.abs()).max(dz.abs()) < 1e-4 {
+            let e2 = dx * dy - dz * dz;
+            let e3 = dx * dy * dz;
+            return (1.0 + (e2 / 24.0 - 0.1 - 3.0 * e3 / 44.0) * e2 + e3 / 14.0) / mu.sqrt();
+        }
+        let (sx, sy, sz) = (x.sqrt(), y.sqrt(), z.sqrt());
+        let lambda = sx * sy + sy * sz + sz * sx;
+        x = (x + lambda) / 4.0;
+        y = (y + lambda) / 4.0;
+        z = (z + lambda) / 4.0;
+    }
+}
+
+/// Incomplete elliptic integral of the first kind F(phi | m), with `m`
+/// given as its complement
+fn ellipf_complement(phi: f64, m_complement: f64) -> f64 {
+    let (s, c) = phi.sin_cos();
+    s * carlson_rf(c * c, c * c + m_complement * s * s, 1.0)
+}
+
+/// Solve `K(m) / K(1 - m) = ratio` for `(m, 1 - m)` via the nome
+fn elliptic_parameter_from_ratio(ratio: f64) -> (f64, f64) {
+    let from_nome = |q: f64| {
+        // k = theta2^2 / theta3^2, so m = (theta2 / theta3)^4
+        let mut theta2 = 0.0;
+        let mut theta3 = 1.0;
+        for n in 0..40 {
+            let nf = n as f64;
+            theta2 += 2.0 * q.powf((nf + 0.5) * (nf + 0.5));
+            if n > 0 {
+                theta3 += 2.0 * q.powf(nf * nf);
+            }
+        }
+        (theta2 / theta3).powi(4)
+    };
+    if ratio <= 1.0 {
+        let m = from_nome((-PI / ratio).exp());
+        (m, 1.0 - m)
+    } else {
+        let mc = from_nome((-PI * ratio).exp());
+        (1.0 - mc, mc)
+    }
+}
+
+fn elliptic_prototype(order: usize, ripple_db: f64, atten_db: f64) -> Zpk {
+    let n = order as f64;
+    let eps_sq = 10f64.powf(ripple_db / 10.0) - 1.0;
+    let eps = eps_sq.sqrt();
+    let ck1_sq = eps_sq / (10f64.powf(atten_db / 10.0) - 1.0);
+    let ck1p_sq = 1.0 - ck1_sq;
+
+    // Degree equation: N K(m) / K(1 - m) = K(k1^2) / K(1 - k1^2)
+    let ratio = n * ellipk_complement(ck1p_sq) / ellipk_complement(ck1_sq);
+    let (m, m_complement) = elliptic_parameter_from_ratio(ratio);
+    let capk = ellipk_complement(m_complement);
+
+    let j: Vec<f64> = ((1 - order % 2)..order)
+        .step_by(2)
+        .map(|v| v as f64)
+        .collect();
+    let sn_cn_dn: Vec<(f64, f64, f64)> = j.iter().map(|&jj| ellipj(jj * capk / n, m)).collect();
+
+    let mut zeros = Vec::new();
+    for &(s, _, _) in &sn_cn_dn {
+        if s.abs() > 1e-12 {
+            let z = Complex::new(0.0, 1.0 / (m.sqrt() * s));
+            zeros.push(z);
+            zeros.push(z.conj());
+        }
+    }
+
+    // sc^-1(1 / eps) with the complementary modulus k1'
+    let r = ellipf_complement((1.0 / eps).atan(), ck1_sq);
+    let v0 = capk * r / (n * ellipk_complement(ck1p_sq));
+    let (sv, cv, dv) = ellipj(v0, m_complement);
+
+    let mut poles = Vec::new();
+    for &(s, c, d) in &sn_cn_dn {
+        let den = 1.0 - (d * sv).powi(2);
+        let p = Complex::new(-c * d * sv * cv / den, -s * dv / den);
+        if p.imag.abs() < 1e-12 * p.magnitude().max(1.0) {
+            poles.push(Complex::new(p.real, 0.0));
+        } else {
+            poles.push(p);
+            poles.push(p.conj());
+        }
+    }
+
+    let neg = |v: &[Complex]| v.iter().map(|&x| -x).collect::<Vec<_>>();
+    let mut gain = (prod(&neg(&poles)) / prod(&neg(&zeros))).real;
+    if order.is_multiple_of(2) {
+        gain /= (1.0 + eps_sq).sqrt();
+    }
+    Zpk { zeros, poles, gain }
+}
+
+fn lp_to_lp(zpk: Zpk, wo: f64) -> Zpk {
+    let degree = zpk.poles.len() as i32 - zpk.zeros.len() as i32;
+    Zpk {
+        zeros: zpk.zeros.iter().map(|&z| z * wo).collect(),
+        poles: zpk.poles.iter().map(|&p| p * wo).collect(),
+        gain: zpk.gain * wo.powi(degree),
+    }
+}
+
+fn lp_to_hp(zpk: Zpk, wo: f64) -> Zpk {
+    let degree = zpk.poles.len() - zpk.zeros.len();
+    let wo_c = Complex::new(wo, 0.0);
+    let mut zeros: Vec<Complex> = zpk.zeros.iter().map(|&z| wo_c / z).collect();
+    zeros.extend(std::iter::repeat_n(Complex::new(0.0, 0.0), degree));
+    let neg = |v: &[Complex]| v.iter().map(|&x| -x).collect::<Vec<_>>();
+    let gain = zpk.gain * (prod(&neg(&zpk.zeros)) / prod(&neg(&zpk.poles))).real;
+    Zpk {
+        zeros,
+        poles: zpk.poles.iter().map(|&p| wo_c / p).collect(),
+        gain,
+    }
+}
+
+/// Roots `x * bw / 2 +/- sqrt((x * bw / 2)^2 - wo^2)` for the band transforms
+fn split_roots(values: &[Complex], wo: f64) -> Vec<Complex> {
+    let mut out = Vec::with_capacity(values.len() * 2);
+    for &x in values {
+        let disc = csqrt(x * x - Complex::new(wo * wo, 0.0));
+        out.push(x + disc);
+        out.push(x - disc);
+    }
+    out
+}
+
+fn lp_to_bp(zpk: Zpk, wo: f64, bw: f64) -> Zpk {
+    let degree = zpk.poles.len() - zpk.zeros.len();
+    let scale = |v: &[Complex]| v.iter().map(|&x| x * (bw / 2.0)).collect::<Vec<_>>();
+    let mut zeros = split_roots(&scale(&zpk.zeros), wo);
+    zeros.extend(std::iter::repeat_n(Complex::new(0.0, 0.0), degree));
+    Zpk {
+        zeros,
+        poles: split_roots(&scale(&zpk.poles), wo),
+        gain: zpk.gain * bw.powi(degree as i32),
+    }
+}
+
+fn lp_to_bs(zpk: Zpk, wo: f64, bw: f64) -> Zpk {
+    let degree = zpk.poles.len() - zpk.zeros.len();
+    let invert = |v: &[Complex]| {
+        v.iter()
+            .map(|&x| Complex::new(bw / 2.0, 0.0) / x)
+            .collect::<Vec<_>>()
+    };
+    let mut zeros = split_roots(&invert(&zpk.zeros), wo);
+    for _ in 0..degree {
+        zeros.push(Complex::new(0.0, wo));
+        zeros.push(Complex::new(0.0, -wo));
+    }
+    let neg = |v: &[Complex]| v.iter().map(|&x| -x).collect::<Vec<_>>();
+    let gain = zpk.gain * (prod(&neg(&zpk.zeros)) / prod(&neg(&zpk.poles))).real;
+    Zpk {
+        zeros,
+        poles: split_roots(&invert(&zpk.poles), wo),
+        gain,
+    }
+}
+
+fn bilinear(zpk: Zpk, sample_rate: f64) -> Zpk {
+    let fs2 = Complex::new(2.0 * sample_rate, 0.0);
+    let map = |s: Complex| (fs2 + s) / (fs2 - s);
+    let degree = zpk.poles.len() - zpk.zeros.len();
+    let mut zeros: Vec<Complex> = zpk.zeros.iter().map(|&z| map(z)).collect();
+    zeros.extend(std::iter::repeat_n(Complex::new(-1.0, 0.0), degree));
+    let gain = zpk.gain
+        * (prod(&zpk.zeros.iter().map(|&z| fs2 - z).collect::<Vec<_>>())
+            / prod(&zpk.poles.iter().map(|&p| fs2 - p).collect::<Vec<_>>()))
+        .real;
+    Zpk {
+        zeros,
+        poles: zpk.poles.iter().map(|&p| map(p)).collect(),
+        gain,
+    }
+}
+
+/// Split roots into real values and one representative per conjugate pair
+fn split_conjugates(roots: &[Complex]) -> (Vec<f64>, Vec<Complex>) {
+    let mut real = Vec::new();
+    let mut complex = Vec::new();
+    for &r in roots {
+        if r.imag.abs() <= 1e-10 * r.magnitude().max(1.0) {
+            real.push(r.real);
+        } else if r.imag > 0.0 {
+            complex.push(r);
+        }
+    }
+    (real, complex)
+}
+
+/// Remove the zeros nearest to `target` and return them as quadratic
+/// coefficients `[1, c1, c2]`: one zero for a single real pole, otherwise
+/// a conjugate pair or two real zeros, whichever is closer
+fn take_nearest(
+    real: &mut Vec<f64>,
+    complex: &mut Vec<Complex>,
+    target: Complex,
+    single: bool,
+) -> [f64; 3] {
+    let distance = |z: Complex| (z - target).magnitude();
+    let nearest_real = |real: &Vec<f64>| {
+        (0..real.len()).min_by(|&a, &b| {
+            distance(Complex::new(real[a], 0.0)).total_cmp(&distance(Complex::new(real[b], 0.0)))
+        })
+    };
+    let nearest_complex =
+        (0..complex.len()).min_by(|&a, &b| distance(complex[a]).total_cmp(&distance(complex[b])));
+
+    let use_complex = match (nearest_complex, nearest_real(real)) {
+        (Some(_), None) => true,
+        (Some(c), Some(r)) => {
+            !single
+                && (real.len() < 2 || distance(complex[c]) < distance(Complex::new(real[r], 0.0)))
+        }
+        (None, _) => false,
+    };
+    if use_complex {
+        let z = complex.remove(nearest_complex.unwrap());
+        return [1.0, -2.0 * z.real, z.norm_sqr()];
+    }
+    match nearest_real(real) {
+        Some(i) => {
+            let z1 = real.remove(i);
+            match nearest_real(real) {
+                Some(j) if !single => {
+                    let z2 = real.remove(j);
+                    [1.0, -(z1 + z2), z1 * z2]
+                }
+                _ => [1.0, -z1, 0.0],
+            }
+        }
+        None => [1.0, 0.0, 0.0],
+    }
+}
+
+/// Group poles and zeros into second-order sections, pairing each pole
+/// pair with its nearest zeros. Sections are ordered so that the poles
+/// closest to the unit circle come last.
+fn zpk_to_sos(zpk: Zpk) -> Result<SosFilter> {
+    let (mut real_poles, mut complex_poles) = split_conjugates(&zpk.poles);
+    let (mut real_zeros, mut complex_zeros) = split_conjugates(&zpk.zeros);
+
+    let mut sections: Vec<([f64; 3], [f64; 3])> = Vec::new();
+    complex_poles.sort_by(|a, b| b.magnitude().total_cmp(&a.magnitude()));
+    for p in complex_poles.drain(..) {
+        let b = take_nearest(&mut real_zeros, &mut complex_zeros, p, false);
+        sections.push((b, [1.0, -2.0 * p.real, p.norm_sqr()]));
+    }
+    real_poles.sort_by(|a, b| b.abs().total_cmp(&a.abs()));
+    for pair in real_poles.chunks(2) {
+        let target = Complex::new(pair[0], 0.0);
+        let single = pair.len() == 1;
+        let b = take_nearest(&mut real_zeros, &mut complex_zeros, target, single);
+        let a = if single {
+            [1.0, -pair[0], 0.0]
+        } else {
+            [1.0, -(pair[0] + pair[1]), pair[0] * pair[1]]
+        };
+        sections.push((b, a));
+    }
+    if sections.is_empty() {
+        return Err(invalid("Design produced no poles".to_string()));
+    }
+
+    sections.reverse();
+    sections[0].0.iter_mut().for_each(|c| *c *= zpk.gain);
+    SosFilter::new(
+        sections
+            .into_iter()
+            .map(|(b, a)| Biquad::new(b, a))
+            .collect::<Result<Vec<_>>>()?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Filter;
+
+    const FS: f64 = 8000.0;
+
+    fn fir_db(taps: &[f64], freq: f64) -> f64 {
+        20.0 * freq_response(taps, &[1.0], freq, FS).magnitude().log10()
+    }
+
+    #[test]
+    fn test_window_designs() {
+        let lp = fir_window(BandType::Lowpass(1000.0), FS, 101, Window::Hamming).unwrap();
+        assert!(fir_db(&lp, 0.0).abs() < 1e-9);
+        assert!((fir_db(&lp, 1000.0) + 6.0).abs() < 0.5);
+        assert!(fir_db(&lp, 1500.0) < -45.0);
+
+        let hp = fir_window(BandType::Highpass(1000.0), FS, 101, Window::Blackman).unwrap();
+        assert!(fir_db(&hp, 4000.0).abs() < 1e-9);
+        assert!(fir_db(&hp, 500.0) < -60.0);
+
+        let bp = fir_window(
+            BandType::Bandpass(1000.0, 2000.0),
+            FS,
+            129,
+            Window::Kaiser(6.0),
+        )
+        .unwrap();
+        assert!(fir_db(&bp, 1500.0).abs() < 1e-9);
+        assert!(fir_db(&bp, 400.0) < -50.0 && fir_db(&bp, 2600.0) < -50.0);
+
+        let bs = fir_window(BandType::Bandstop(1000.0, 2000.0), FS, 129, Window::Hann).unwrap();
+        assert!(fir_db(&bs, 0.0).abs() < 1e-9);
+        assert!(fir_db(&bs, 1500.0) < -40.0);
+
+        assert!(fir_window(BandType::Highpass(1000.0), FS, 100, Window::Hamming).is_err());
+        assert!(fir_window(BandType::Bandpass(2000.0, 1000.0), FS, 101, Window::Hamming).is_err());
+    }
+
+    #[test]
+    fn test_kaiser_meets_spec() {
+        let (n, beta) = kaiser_parameters(60.0, 400.0, FS).unwrap();
+        assert_eq!(n % 2, 1);
+        let taps = fir_window(BandType::Lowpass(1000.0), FS, n, Window::Kaiser(beta)).unwrap();
+        for f in [1200.0, 1500.0, 2500.0, 3900.0] {
+            assert!(
+                fir_db(&taps, f) < -59.0,
+                "{} Hz: {} dB",
+                f,
+                fir_db(&taps, f)
+            );
+        }
+        assert!(fir_db(&taps, 800.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_equiripple_lowpass() {
+        let taps = fir_equiripple(BandType::Lowpass(1000.0), 400.0, FS, 61).unwrap();
+        // Symmetric (linear phase)
+        for i in 0..taps.len() / 2 {
+            assert!((taps[i] - taps[taps.len() - 1 - i]).abs() < 1e-9);
+        }
+        let passband_err = (0..=80)
+            .map(|i| {
+                (freq_response(&taps, &[1.0], 800.0 * i as f64 / 80.0, FS).magnitude() - 1.0).abs()
+            })
+            .fold(0.0, f64::max);
+        let stopband_err = (0..=280)
+            .map(|i| {
+                freq_response(&taps, &[1.0], 1200.0 + 2800.0 * i as f64 / 280.0, FS).magnitude()
+            })
+            .fold(0.0, f64::max);
+        // Equal weights: equal ripple in both bands, and far better than a
+        // same-length rectangular window.
+        assert!((passband_err - stopband_err).abs() < 0.05 * stopband_err);
+        assert!(stopband_err < 0.01, "stopband ripple {}", stopband_err);
+    }
+
+    #[test]
+    fn test_equiripple_band_layouts() {
+        let bp = fir_equiripple(BandType::Bandpass(1000.0, 2000.0), 300.0, FS, 80).unwrap();
+        assert!(fir_db(&bp, 1500.0).abs() < 0.2);
+        assert!(fir_db(&bp, 500.0) < -35.0 && fir_db(&bp, 2500.0) < -35.0);
+
+        let hp = fir_equiripple(BandType::Highpass(2000.0), 400.0, FS, 51).unwrap();
+        assert!(fir_db(&hp, 3500.0).abs() < 0.2);
+        assert!(fir_db(&hp, 1000.0) < -35.0);
+        assert!(fir_equiripple(BandType::Highpass(2000.0), 400.0, FS, 50).is_err());
+    }
+
+    #[test]
+    fn test_hilbert_designs() {
+        let windowed = fir_hilbert(101, Window::Blackman).unwrap();
+        let equiripple = fir_hilbert_equiripple(63, 300.0, 3700.0, FS).unwrap();
+        for taps in [&windowed, &equiripple] {
+            let delay = (taps.len() - 1) as f64 / 2.0;
+            for f in [500.0, 1500.0, 3000.0] {
+                // Remove the linear phase; what is left is -90 degrees.
+                let h = freq_response(taps, &[1.0], f, FS)
+                    * Complex::from_phase(2.0 * PI * f / FS * delay);
+                assert!(
+                    (h.magnitude() - 1.0).abs() < 0.01,
+                    "{} Hz: |H| = {}",
+                    f,
+                    h.magnitude()
+                );
+                assert!((h.phase() + PI / 2.0).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_complex_bandpass_selects_one_sideband() {
+        let usb = fir_complex_bandpass(300.0, 3000.0, FS, 129, Window::Blackman).unwrap();
+        let db = |f: f64| 20.0 * fir_response(&usb, f, FS).magnitude().log10();
+        assert!(db(1650.0).abs() < 1e-9);
+        assert!(db(1000.0).abs() < 0.01 && db(2500.0).abs() < 0.01);
+        for f in [-2500.0, -1650.0, -1000.0, 3600.0] {
+            assert!(db(f) < -60.0, "{} Hz: {} dB", f, db(f));
+        }
+
+        let lsb = fir_complex_bandpass(-3000.0, -300.0, FS, 129, Window::Blackman).unwrap();
+        assert!(fir_response(&lsb, -1650.0, FS).magnitude() > 0.999);
+        assert!(fir_response(&lsb, 1650.0, FS).magnitude() < 1e-3);
+        assert!(fir_complex_bandpass(300.0, 4000.0, FS, 129, Window::Blackman).is_err());
+    }
+
+    #[test]
+    fn test_butterworth() {
+        let lp = iir_design(IirPrototype::Butterworth, 4, BandType::Lowpass(1000.0), FS).unwrap();
+        assert_eq!(lp.sections().len(), 2);
+        assert!(lp.magnitude_db(0.0, FS).abs() < 1e-9);
+        assert!((lp.magnitude_db(1000.0, FS) + 3.0103).abs() < 1e-6);
+        let mut previous = 1.0;
+        for i in 1..40 {
+            let mag = lp.response(100.0 * i as f64, FS).magnitude();
+            assert!(mag < previous);
+            previous = mag;
+        }
+
+        let hp = iir_design(IirPrototype::Butterworth, 3, BandType::Highpass(1000.0), FS).unwrap();
+        assert!(hp.magnitude_db(4000.0, FS).abs() < 1e-9);
+        assert!((hp.magnitude_db(1000.0, FS) + 3.0103).abs() < 1e-6);
+
+        let bp = iir_design(
+            IirPrototype::Butterworth,
+            3,
+            BandType::Bandpass(1000.0, 2000.0),
+            FS,
+        )
+        .unwrap();
+        assert_eq!(bp.sections().len(), 3);
+        assert!((bp.magnitude_db(1000.0, FS) + 3.0103).abs() < 1e-6);
+        assert!((bp.magnitude_db(2000.0, FS) + 3.0103).abs() < 1e-6);
+
+        let bs = iir_design(
+            IirPrototype::Butterworth,
+            2,
+            BandType::Bandstop(1000.0, 2000.0),
+            FS,
+        )
+        .unwrap();
+        assert!(bs.magnitude_db(0.0, FS).abs() < 1e-9);
+        assert!((bs.magnitude_db(1000.0, FS) + 3.0103).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_chebyshev() {
+        let c1 = iir_design(
+            IirPrototype::Chebyshev1 { ripple_db: 1.0 },
+            5,
+            BandType::Lowpass(1000.0),
+            FS,
+        )
+        .unwrap();
+        for i in 0..=100 {
+            let db = c1.magnitude_db(10.0 * i as f64, FS);
+            assert!((-1.0 - 1e-6..=1e-9).contains(&db), "{} dB", db);
+        }
+        assert!((c1.magnitude_db(1000.0, FS) + 1.0).abs() < 1e-6);
+        assert!(c1.magnitude_db(2000.0, FS) < -40.0);
+
+        let c2 = iir_design(
+            IirPrototype::Chebyshev2 { atten_db: 50.0 },
+            6,
+            BandType::Lowpass(1500.0),
+            FS,
+        )
+        .unwrap();
+        assert!(c2.magnitude_db(0.0, FS).abs() < 1e-9);
+        for i in 0..=100 {
+            let f = 1500.0 + 25.0 * i as f64;
+            assert!(c2.magnitude_db(f, FS) <= -50.0 + 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_elliptic() {
+        let el = iir_design(
+            IirPrototype::Elliptic {
+                ripple_db: 0.5,
+                atten_db: 60.0,
+            },
+            6,
+            BandType::Lowpass(1000.0),
+            FS,
+        )
+        .unwrap();
+        for i in 0..=100 {
+            let db = el.magnitude_db(10.0 * i as f64, FS);
+            assert!((-0.5 - 1e-6..=1e-6).contains(&db), "{} dB", db);
+        }
+        // Stopband edge from the degree equation, about 1339 Hz here
+        for i in 0..=100 {
+            let f = 1340.0 + 26.0 * i as f64;
+            assert!(
+                el.magnitude_db(f, FS) <= -60.0 + 1e-3,
+                "{} Hz: {} dB",
+                f,
+                el.magnitude_db(f, FS)
+            );
+        }
+
+        let odd = iir_design(
+            IirPrototype::Elliptic {
+                ripple_db: 1.0,
+                atten_db: 40.0,
+            },
+            5,
+            BandType::Bandpass(1000.0, 2000.0),
+            FS,
+        )
+        .unwrap();
+        assert_eq!(odd.sections().len(), 5);
+        assert!(odd.magnitude_db(1414.0, FS).abs() < 1.0 + 1e-6);
+        assert!(odd.magnitude_db(200.0, FS) < -40.0 + 1e-6);
+    }
+
+    #[test]
+    fn test_sos_filter_matches_response() {
+        let mut filter = iir_design(
+            IirPrototype::Chebyshev1 { ripple_db: 0.5 },
+            4,
+            BandType::Bandpass(1200.0, 2200.0),
+            FS,
+        )
+        .unwrap();
+        for f in [700.0, 1500.0, 3000.0] {
+            Filter::<f64>::reset(&mut filter);
+            let input: Vec<f64> = (0..8000)
+                .map(|n| (2.0 * PI * f * n as f64 / FS).sin())
+                .collect();
+            let mut output = vec![0.0; input.len()];
+            filter.process_buffer(&input, &mut output).unwrap();
+            let tail = &output[4000..];
+            let rms = (tail.iter().map(|x| x * x).sum::<f64>() / tail.len() as f64).sqrt();
+            let expected = filter.response(f, FS).magnitude();
+            assert!(
+                (rms * 2f64.sqrt() - expected).abs() < 1e-3,
+                "{} Hz: {} vs {}",
+                f,
+                rms,
+                expected
+            );
+        }
+    }
+}
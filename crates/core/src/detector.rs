@@ -0,0 +1,619 @@
+//! Signal presence detection
+//!
+//! [`SignalDetector`] runs a short-time FFT over the input, tracks the noise
+//! floor of every bin by minimum statistics, and flags bins whose lightly
+//! smoothed power stands a fixed ratio above their floor (a constant false-alarm rate
+//! test, independent of the absolute noise level). Occupied bins are grouped
+//! into runs, runs are linked from one frame to the next when they overlap
+//! in frequency, and each track is reported as a [`SignalSegment`] with its
+//! sample range, centre frequency and bandwidth once it ends.
+//!
+//! Real input is analysed one-sided (`0..fs/2`); complex input keeps the
+//! full two-sided spectrum, so a detector over IQ samples reports signed
+//! frequencies in `-fs/2..fs/2`.
+
+use crate::buffer::Sample;
+use crate::fft::{Spectrogram, SpectrogramConfig};
+use crate::{CoreError, Result};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// Sub-windows the minimum-statistics window is split into; the floor can
+/// follow a rising noise level after one sub-window instead of a full window.
+const MIN_STAT_SUBWINDOWS: usize = 8;
+
+/// Recursive smoothing of the power the noise floor is tracked on; heavier
+/// than the detection smoothing, as the minimum of a noisy estimate is
+/// itself noisy.
+const FLOOR_SMOOTHING: f64 = 0.8;
+
+/// Bins either side averaged into each bin's floor, as the reference cells
+/// of a cell-averaging CFAR; this steadies the noisy per-bin minimum.
+const FLOOR_SPAN_BINS: usize = 4;
+
+/// Integration steps for the minimum-statistics bias.
+const BIAS_STEPS: usize = 4000;
+
+/// Detector settings; times are in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorConfig {
+    /// FFT length (power of two); frames hop by half of it
+    pub fft_size: usize,
+    /// Recursive smoothing of the per-bin power tested against the floor, in
+    /// `[0, 1)`; heavier smoothing lowers the false-alarm rate but makes
+    /// segments end later
+    pub smoothing: f64,
+    /// Length of the minimum-statistics window; should be longer than the
+    /// longest transmission, or the floor creeps up underneath it
+    pub noise_window: f64,
+    /// Power over the noise floor at which a bin counts as occupied, in dB
+    pub threshold_db: f64,
+    /// Occupied runs separated by at most this many free bins are merged
+    pub bin_gap: usize,
+    /// Segments occupied for less than this are discarded
+    pub min_duration: f64,
+    /// How long a segment stays open after its bins fall silent
+    pub hang_time: f64,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            fft_size: 512,
+            smoothing: 0.5,
+            noise_window: 5.0,
+            threshold_db: 8.0,
+            bin_gap: 2,
+            min_duration: 0.1,
+            hang_time: 0.1,
+        }
+    }
+}
+
+impl DetectorConfig {
+    /// Set the detection threshold in dB over the noise floor
+    pub fn with_threshold_db(mut self, threshold_db: f64) -> Self {
+        self.threshold_db = threshold_db;
+        self
+    }
+
+    /// Set the minimum-statistics window length
+    pub fn with_noise_window(mut self, noise_window: f64) -> Self {
+        self.noise_window = noise_window;
+        self
+    }
+}
+
+/// A detected transmission
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalSegment {
+    /// Index of the first sample of the first occupied frame
+    pub start: usize,
+    /// Index one past the last sample of the last occupied frame
+    pub end: usize,
+    /// Centre of the occupied band in Hz
+    pub center_frequency: f64,
+    /// Width of the occupied band in Hz
+    pub bandwidth: f64,
+    /// Average in-band power over the noise floor, in dB
+    pub snr_db: f64,
+}
+
+impl SignalSegment {
+    /// Length in samples
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether the segment covers no samples
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+
+    /// Lower edge of the occupied band in Hz
+    pub fn low_frequency(&self) -> f64 {
+        self.center_frequency - self.bandwidth / 2.0
+    }
+
+    /// Upper edge of the occupied band in Hz
+    pub fn high_frequency(&self) -> f64 {
+        self.center_frequency + self.bandwidth / 2.0
+    }
+}
+
+/// A segment that is still being tracked
+#[derive(Debug, Clone)]
+struct Track {
+    start: usize,
+    end: usize,
+    low_bin: usize,
+    high_bin: usize,
+    signal_power: f64,
+    noise_power: f64,
+    occupied_frames: usize,
+    idle_frames: usize,
+}
+
+/// Streaming CFAR signal detector with minimum-statistics noise tracking,
+/// over real or complex samples of either precision
+pub struct SignalDetector<T: Sample = f64> {
+    config: DetectorConfig,
+    spectrogram: Spectrogram<T::Real>,
+    sample_rate: f64,
+    hop: usize,
+    bin_width: f64,
+    /// Frequency of bin 0 in Hz: 0 for real input, `-fs/2` for complex
+    start_frequency: f64,
+    smoothed: Vec<f64>,
+    floor_smoothed: Vec<f64>,
+    subwindow_min: Vec<f64>,
+    subwindow_frames: usize,
+    subwindow_len: usize,
+    past_minima: VecDeque<Vec<f64>>,
+    minimum: Vec<f64>,
+    floor: Vec<f64>,
+    bias: f64,
+    threshold: f64,
+    /// Frames to let the smoothing settle before tracking minima
+    settle_frames: usize,
+    frames: usize,
+    hang_frames: usize,
+    min_frames: usize,
+    /// Index of the first sample of the next frame
+    position: usize,
+    tracks: Vec<Track>,
+    _sample: PhantomData<T>,
+}
+
+impl<T: Sample> SignalDetector<T> {
+    /// Create a detector for a stream at `sample_rate` Hz
+    pub fn new(config: DetectorConfig, sample_rate: f64) -> Result<Self> {
+        if !(0.0..1.0).contains(&config.smoothing) {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: format!("Smoothing must be in [0, 1), got {}", config.smoothing),
+            });
+        }
+        if config.noise_window <= 0.0 || config.min_duration < 0.0 || config.hang_time < 0.0 {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: "Detector times must be positive".to_string(),
+            });
+        }
+        let mut spectrogram_config = SpectrogramConfig::new(config.fft_size, sample_rate)?;
+        let (bins, start_frequency) = if T::COMPLEX {
+            spectrogram_config = spectrogram_config.two_sided();
+            (config.fft_size, -sample_rate / 2.0)
+        } else {
+            (config.fft_size / 2 + 1, 0.0)
+        };
+        let hop = spectrogram_config.hop();
+        let bin_width = spectrogram_config.fft.frequency_resolution();
+
+        let frame_rate = sample_rate / hop as f64;
+        let window_frames = ((config.noise_window * frame_rate).round() as usize).max(1);
+        let subwindow_len = window_frames.div_ceil(MIN_STAT_SUBWINDOWS);
+
+        Ok(Self {
+            spectrogram: Spectrogram::new(spectrogram_config)?,
+            sample_rate,
+            hop,
+            bin_width,
+            start_frequency,
+            smoothed: Vec::new(),
+            floor_smoothed: Vec::new(),
+            subwindow_min: vec![f64::INFINITY; bins],
+            subwindow_frames: 0,
+            subwindow_len,
+            past_minima: VecDeque::with_capacity(MIN_STAT_SUBWINDOWS),
+            minimum: vec![0.0; bins],
+            floor: vec![0.0; bins],
+            bias: minimum_bias(window_frames, FLOOR_SMOOTHING),
+            threshold: 10f64.powf(config.threshold_db / 10.0),
+            settle_frames: (3.0 / (1.0 - FLOOR_SMOOTHING)).ceil() as usize,
+            frames: 0,
+            hang_frames: (config.hang_time * frame_rate).round() as usize,
+            min_frames: (config.min_duration * frame_rate).round() as usize,
+            position: 0,
+            tracks: Vec::new(),
+            config,
+            _sample: PhantomData,
+        })
+    }
+
+    /// Settings in use
+    pub fn config(&self) -> &DetectorConfig {
+        &self.config
+    }
+
+    /// Bin spacing in Hz
+    pub fn bin_width(&self) -> f64 {
+        self.bin_width
+    }
+
+    /// Frequency of the first noise-floor bin in Hz: 0 for real input,
+    /// `-fs/2` for complex
+    pub fn start_frequency(&self) -> f64 {
+        self.start_frequency
+    }
+
+    /// Current noise floor per bin (bin `k` is at
+    /// `start_frequency + k * bin_width` Hz), in full-scale power per Hz;
+    /// zero until the first frames have settled
+    pub fn noise_floor(&self) -> &[f64] {
+        &self.floor
+    }
+
+    /// Whether any segment is open, i.e. a signal was seen within the hang
+    /// time
+    pub fn is_active(&self) -> bool {
+        !self.tracks.is_empty()
+    }
+
+    /// Segments that are still open, reported up to the latest frame
+    pub fn active_segments(&self) -> Vec<SignalSegment> {
+        self.tracks.iter().map(|t| self.segment(t)).collect()
+    }
+
+    /// Feed samples and collect every segment that ended within them
+    pub fn process(&mut self, input: &[T]) -> Result<Vec<SignalSegment>> {
+        let mut finished = Vec::new();
+        for spectrum in self.spectrogram.process(input)? {
+            if self.update_floor(&spectrum.psd) {
+                let runs = self.occupied_runs();
+                self.update_tracks(&runs, &mut finished);
+            }
+            self.position += self.hop;
+        }
+        Ok(finished)
+    }
+
+    /// Close every open segment and return those long enough to report
+    pub fn flush(&mut self) -> Vec<SignalSegment> {
+        let tracks = std::mem::take(&mut self.tracks);
+        tracks
+            .iter()
+            .filter(|t| t.occupied_frames >= self.min_frames)
+            .map(|t| self.segment(t))
+            .collect()
+    }
+
+    /// Forget the noise floor, open segments and stream position
+    pub fn reset(&mut self) {
+        self.spectrogram.reset();
+        self.smoothed.clear();
+        self.floor_smoothed.clear();
+        self.subwindow_min.fill(f64::INFINITY);
+        self.subwindow_frames = 0;
+        self.past_minima.clear();
+        self.floor.fill(0.0);
+        self.frames = 0;
+        self.position = 0;
+        self.tracks.clear();
+    }
+
+    /// Smooth the new frame and update the minimum-statistics floor;
+    /// returns false while the smoothing is still settling
+    fn update_floor(&mut self, psd: &[f64]) -> bool {
+        smooth(&mut self.smoothed, psd, self.config.smoothing);
+        smooth(&mut self.floor_smoothed, psd, FLOOR_SMOOTHING);
+        // The first frames carry too few averages; a low outlier among them
+        // would pin the minimum for a whole window.
+        if self.frames < self.settle_frames {
+            self.frames += 1;
+            return false;
+        }
+
+        for (m, &s) in self.subwindow_min.iter_mut().zip(&self.floor_smoothed) {
+            *m = m.min(s);
+        }
+        self.subwindow_frames += 1;
+
+        for (k, minimum) in self.minimum.iter_mut().enumerate() {
+            *minimum = self
+                .past_minima
+                .iter()
+                .fold(self.subwindow_min[k], |m, past| m.min(past[k]));
+        }
+        let last = self.minimum.len() - 1;
+        for (k, floor) in self.floor.iter_mut().enumerate() {
+            let cells =
+                &self.minimum[k.saturating_sub(FLOOR_SPAN_BINS)..=(k + FLOOR_SPAN_BINS).min(last)];
+            *floor = self.bias * cells.iter().sum::<f64>() / cells.len() as f64;
+        }
+
+        if self.subwindow_frames == self.subwindow_len {
+            if self.past_minima.len() == MIN_STAT_SUBWINDOWS - 1 {
+                self.past_minima.pop_front();
+            }
+            self.past_minima.push_back(self.subwindow_min.clone());
+            self.subwindow_min.fill(f64::INFINITY);
+            self.subwindow_frames = 0;
+        }
+        true
+    }
+
+    /// Runs of occupied bins in the latest frame, as inclusive bin ranges;
+    /// runs closer than `bin_gap` free bins are merged. For real input DC
+    /// and Nyquist are skipped: they have half the degrees of freedom of the
+    /// other bins, and so a far higher false-alarm rate.
+    fn occupied_runs(&self) -> Vec<(usize, usize)> {
+        let last = self.smoothed.len() - 1;
+        let edge = |k: usize| !T::COMPLEX && (k == 0 || k == last);
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for (k, (&s, &floor)) in self.smoothed.iter().zip(&self.floor).enumerate() {
+            if edge(k) || s <= floor * self.threshold {
+                continue;
+            }
+            match runs.last_mut() {
+                Some((_, high)) if k - *high <= self.config.bin_gap + 1 => *high = k,
+                _ => runs.push((k, k)),
+            }
+        }
+        runs
+    }
+
+    /// Extend the tracks that overlap a run, open new ones for the rest,
+    /// and close tracks that have been idle past the hang time
+    fn update_tracks(&mut self, runs: &[(usize, usize)], finished: &mut Vec<SignalSegment>) {
+        let frame_end = self.position + self.config.fft_size;
+        for track in &mut self.tracks {
+            track.idle_frames += 1;
+        }
+
+        for &(low, high) in runs {
+            let (signal, noise) = (low..=high).fold((0.0, 0.0), |(s, n), k| {
+                (s + self.smoothed[k], n + self.floor[k])
+            });
+            let overlapping = self.tracks.iter_mut().find(|t| {
+                low <= t.high_bin + self.config.bin_gap + 1
+                    && t.low_bin <= high + self.config.bin_gap + 1
+            });
+            match overlapping {
+                Some(track) => {
+                    track.end = frame_end;
+                    track.low_bin = track.low_bin.min(low);
+                    track.high_bin = track.high_bin.max(high);
+                    track.signal_power += signal;
+                    track.noise_power += noise;
+                    // Count the frame once even if several runs land here.
+                    if track.idle_frames > 0 {
+                        track.occupied_frames += 1;
+                    }
+                    track.idle_frames = 0;
+                }
+                None => self.tracks.push(Track {
+                    start: self.position,
+                    end: frame_end,
+                    low_bin: low,
+                    high_bin: high,
+                    signal_power: signal,
+                    noise_power: noise,
+                    occupied_frames: 1,
+                    idle_frames: 0,
+                }),
+            }
+        }
+
+        let mut index = 0;
+        while index < self.tracks.len() {
+            if self.tracks[index].idle_frames > self.hang_frames {
+                let track = self.tracks.remove(index);
+                if track.occupied_frames >= self.min_frames {
+                    finished.push(self.segment(&track));
+                }
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    fn segment(&self, track: &Track) -> SignalSegment {
+        // Each bin covers half a bin either side of its centre.
+        let low = (self.start_frequency + (track.low_bin as f64 - 0.5) * self.bin_width)
+            .max(self.start_frequency);
+        let high = (self.start_frequency + (track.high_bin as f64 + 0.5) * self.bin_width)
+            .min(self.sample_rate / 2.0);
+        let excess = (track.signal_power - track.noise_power).max(0.0);
+        SignalSegment {
+            start: track.start,
+            end: track.end,
+            center_frequency: (low + high) / 2.0,
+            bandwidth: high - low,
+            snr_db: 10.0 * (excess / track.noise_power.max(1e-30)).max(1e-30).log10(),
+        }
+    }
+}
+
+/// One step of recursive smoothing; an empty state takes the input as is
+fn smooth(state: &mut Vec<f64>, input: &[f64], alpha: f64) {
+    if state.is_empty() {
+        state.extend_from_slice(input);
+    } else {
+        for (s, &x) in state.iter_mut().zip(input) {
+            *s = alpha * *s + (1.0 - alpha) * x;
+        }
+    }
+}
+
+/// Factor by which the windowed minimum of the smoothed periodogram falls
+/// short of the mean noise power
+///
+/// A Hann periodogram bin is chi-squared with 2 degrees of freedom;
+/// smoothing with `alpha` raises that to about `2 (1 + alpha) / (1 - alpha)`
+/// and leaves about `frames (1 - alpha) / (1 + alpha)` independent values in
+/// the window. The bias is the reciprocal of the expected minimum of that
+/// many unit-mean gamma variables, rounding the shape to an integer so the
+/// survival function has a closed form.
+fn minimum_bias(frames: usize, alpha: f64) -> f64 {
+    let ratio = (1.0 + alpha) / (1.0 - alpha);
+    let shape = ratio.round().max(1.0) as usize;
+    let count = (frames as f64 / ratio).max(1.0);
+
+    // Survival function of a gamma(shape, 1 / shape) variable
+    let survival = |x: f64| {
+        let z = shape as f64 * x;
+        let mut term = 1.0;
+        let mut sum = 1.0;
+        for i in 1..shape {
+            term *= z / i as f64;
+            sum += term;
+        }
+        (-z).exp() * sum
+    };
+
+    // E[min] = integral of survival^count over [0, inf); the integrand is
+    // negligible well before 4.
+    let step = 4.0 / BIAS_STEPS as f64;
+    let expected_min: f64 = (0..BIAS_STEPS)
+        .map(|i| survival((i as f64 + 0.5) * step).powf(count) * step)
+        .sum();
+    1.0 / expected_min
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Complex;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rand_distr::{Distribution, Normal};
+    use std::f64::consts::PI;
+
+    const FS: f64 = 8000.0;
+
+    fn noise(rng: &mut StdRng, sigma: f64, len: usize) -> Vec<f64> {
+        let normal = Normal::new(0.0, sigma).unwrap();
+        (0..len).map(|_| normal.sample(rng)).collect()
+    }
+
+    fn add_tone(samples: &mut [f64], freq: f64, amplitude: f64, range: std::ops::Range<usize>) {
+        for n in range {
+            samples[n] += amplitude * (2.0 * PI * freq * n as f64 / FS).sin();
+        }
+    }
+
+    #[test]
+    fn test_noise_floor_tracks_white_noise() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for sigma in [0.001, 0.1] {
+            let mut detector = SignalDetector::new(DetectorConfig::default(), FS).unwrap();
+            let input = noise(&mut rng, sigma, 8 * FS as usize);
+            detector.process(&input).unwrap();
+
+            // One-sided PSD of white noise: 2 sigma^2 / fs.
+            let expected = 2.0 * sigma * sigma / FS;
+            let floor = detector.noise_floor();
+            let interior = &floor[4..floor.len() - 4];
+            let mean = interior.iter().sum::<f64>() / interior.len() as f64;
+            let error_db = 10.0 * (mean / expected).log10();
+            assert!(error_db.abs() < 1.5, "sigma {}: {:.2} dB", sigma, error_db);
+        }
+    }
+
+    #[test]
+    fn test_detects_bursts_with_frequency_and_extent() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let len = 10 * FS as usize;
+        let mut input = noise(&mut rng, 0.01, len);
+        // Two tones at different times and one overlapping in time on
+        // another frequency.
+        add_tone(&mut input, 1000.0, 0.02, 40_000..48_000);
+        add_tone(&mut input, 2500.0, 0.02, 44_000..56_000);
+        add_tone(&mut input, 1000.0, 0.02, 64_000..70_000);
+
+        let mut detector = SignalDetector::new(DetectorConfig::default(), FS).unwrap();
+        let mut segments = Vec::new();
+        for chunk in input.chunks(1000) {
+            segments.extend(detector.process(chunk).unwrap());
+        }
+        segments.extend(detector.flush());
+        segments.sort_by_key(|s| s.start);
+
+        assert_eq!(segments.len(), 3, "{:?}", segments);
+        let expected = [
+            (40_000, 48_000, 1000.0),
+            (44_000, 56_000, 2500.0),
+            (64_000, 70_000, 1000.0),
+        ];
+        for (segment, &(start, end, freq)) in segments.iter().zip(&expected) {
+            // Frames are one FFT long; the end also trails by the time the
+            // smoothed power takes to decay to the threshold.
+            let fft_size = DetectorConfig::default().fft_size;
+            assert!(segment.start.abs_diff(start) <= fft_size, "{:?}", segment);
+            assert!(segment.end.abs_diff(end) <= 4 * fft_size, "{:?}", segment);
+            assert!((segment.center_frequency - freq).abs() < 2.0 * detector.bin_width());
+            assert!(
+                segment.bandwidth < 8.0 * detector.bin_width(),
+                "{:?}",
+                segment
+            );
+            assert!(segment.low_frequency() < freq && freq < segment.high_frequency());
+            // Tone power 0.02^2 / 2 over a few bins of 0.01^2-variance noise.
+            assert!(segment.snr_db > 10.0, "{:?}", segment);
+        }
+        assert!(!detector.is_active());
+    }
+
+    #[test]
+    fn test_threshold_follows_noise_level() {
+        // The same burst-to-noise ratio is found whatever the absolute level,
+        // and a change in band noise alone raises no detection once the floor
+        // has caught up.
+        for scale in [0.001, 1.0] {
+            let mut input = vec![0.0; 12 * FS as usize];
+            let mut rng = StdRng::seed_from_u64(9);
+            let quiet = noise(&mut rng, 0.01 * scale, 4 * FS as usize);
+            let loud = noise(&mut rng, 0.04 * scale, 8 * FS as usize);
+            input[..quiet.len()].copy_from_slice(&quiet);
+            input[quiet.len()..].copy_from_slice(&loud);
+            add_tone(&mut input, 1500.0, 0.2 * scale, 80_000..88_000);
+
+            let config = DetectorConfig::default().with_noise_window(1.5);
+            let mut detector = SignalDetector::new(config, FS).unwrap();
+            let mut segments = detector.process(&input).unwrap();
+            segments.extend(detector.flush());
+
+            let late: Vec<_> = segments.iter().filter(|s| s.start > 50_000).collect();
+            assert_eq!(late.len(), 1, "scale {}: {:?}", scale, segments);
+            assert!((late[0].center_frequency - 1500.0).abs() < 2.0 * detector.bin_width());
+        }
+    }
+
+    #[test]
+    fn test_complex_input_and_rejects_bad_config() {
+        let mut detector = SignalDetector::<Complex>::new(DetectorConfig::default(), FS).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        let (re, im) = (noise(&mut rng, 0.05, 40_000), noise(&mut rng, 0.05, 40_000));
+        let mut input: Vec<Complex> = re
+            .iter()
+            .zip(&im)
+            .map(|(&r, &i)| Complex::new(r, i))
+            .collect();
+        // Negative frequencies are kept apart from positive ones, not folded.
+        for (freq, start) in [(-1500.0, 20_000), (700.0, 30_000)] {
+            for (n, s) in input.iter_mut().enumerate().skip(start).take(5_000) {
+                *s = *s + Complex::from_phase(2.0 * PI * freq * n as f64 / FS) * 0.5;
+            }
+        }
+        let mut segments = detector.process(&input).unwrap();
+        segments.extend(detector.flush());
+        assert_eq!(segments.len(), 2, "{:?}", segments);
+        assert_eq!(detector.start_frequency(), -FS / 2.0);
+        assert!((segments[0].center_frequency + 1500.0).abs() < 2.0 * detector.bin_width());
+        assert!((segments[1].center_frequency - 700.0).abs() < 2.0 * detector.bin_width());
+
+        detector.reset();
+        assert!(!detector.is_active());
+        assert!(detector.noise_floor().iter().all(|&f| f == 0.0));
+
+        let bad = DetectorConfig {
+            smoothing: 1.0,
+            ..DetectorConfig::default()
+        };
+        assert!(SignalDetector::<f64>::new(bad, FS).is_err());
+        let bad = DetectorConfig {
+            fft_size: 500,
+            ..DetectorConfig::default()
+        };
+        assert!(SignalDetector::<f64>::new(bad, FS).is_err());
+    }
+}
@@ -20,6 +20,18 @@ pub enum CoreError {
     #[error("Resampling error: {msg}")]
     ResampleError { msg: String },
     
+    #[error("Invalid channel parameters: {msg}")]
+    InvalidChannelParameters { msg: String },
+    
+    #[error("Invalid channel scenario: {msg}")]
+    InvalidScenario { msg: String },
+    
+    #[error("Flowgraph error: {msg}")]
+    FlowgraphError { msg: String },
+    
+    #[error("WAV error: {0}")]
+    Wav(#[from] hound::Error),
+    
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 }
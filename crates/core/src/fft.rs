@@ -1,7 +1,11 @@
-//! FFT processing wrapper
+//! FFT processing wrapper and short-time spectral estimation
 
-use crate::{buffer::Complex, CoreError, Result};
-use rustfft::{FftPlanner, num_complex::Complex64};
+use crate::{
+    buffer::{Complex, Float, Sample},
+    design::Window,
+    CoreError, Result,
+};
+use rustfft::{FftPlanner, num_complex};
 use std::sync::Arc;
 
 /// FFT configuration
@@ -43,20 +47,23 @@ impl FftConfig {
 }
 
 /// FFT processor for signal analysis and processing
-pub struct FftProcessor {
+///
+/// Runs in double precision by default; `FftProcessor<f32>` transforms
+/// [`Complex32`](crate::buffer::Complex32) data.
+pub struct FftProcessor<F: Float = f64> {
     config: FftConfig,
-    fft: Arc<dyn rustfft::Fft<f64>>,
-    ifft: Arc<dyn rustfft::Fft<f64>>,
-    scratch: Vec<Complex64>,
+    fft: Arc<dyn rustfft::Fft<F>>,
+    ifft: Arc<dyn rustfft::Fft<F>>,
+    scratch: Vec<num_complex::Complex<F>>,
 }
 
-impl FftProcessor {
+impl<F: Float> FftProcessor<F> {
     /// Create a new FFT processor
     pub fn new(config: FftConfig) -> Result<Self> {
         let mut planner = FftPlanner::new();
         let fft = planner.plan_fft_forward(config.size);
         let ifft = planner.plan_fft_inverse(config.size);
-        let scratch = vec![Complex64::new(0.0, 0.0); config.size];
+        let scratch = vec![num_complex::Complex::new(F::default(), F::default()); config.size];
         
         Ok(Self {
             config,
@@ -72,7 +79,7 @@ impl FftProcessor {
     }
     
     /// Perform forward FFT
-    pub fn fft(&mut self, input: &[Complex], output: &mut [Complex]) -> Result<()> {
+    pub fn fft(&mut self, input: &[Complex<F>], output: &mut [Complex<F>]) -> Result<()> {
         if input.len() != self.config.size || output.len() != self.config.size {
             return Err(CoreError::BufferSizeMismatch {
                 expected: self.config.size,
@@ -80,24 +87,24 @@ impl FftProcessor {
             });
         }
         
-        // Convert to rustfft format
-        let mut buffer: Vec<Complex64> = input.iter()
-            .map(|c| Complex64::new(c.real, c.imag))
-            .collect();
+        // Convert to rustfft format in the reusable scratch buffer
+        for (dst, c) in self.scratch.iter_mut().zip(input) {
+            *dst = num_complex::Complex::new(c.real, c.imag);
+        }
         
         // Perform FFT
-        self.fft.process(&mut buffer);
+        self.fft.process(&mut self.scratch);
         
         // Convert back to our format
-        for (i, c) in buffer.iter().enumerate() {
-            output[i] = Complex::new(c.re, c.im);
+        for (i, c) in self.scratch.iter().enumerate() {
+            output[i] = Complex::from_parts(c.re, c.im);
         }
         
         Ok(())
     }
     
     /// Perform inverse FFT
-    pub fn ifft(&mut self, input: &[Complex], output: &mut [Complex]) -> Result<()> {
+    pub fn ifft(&mut self, input: &[Complex<F>], output: &mut [Complex<F>]) -> Result<()> {
         if input.len() != self.config.size || output.len() != self.config.size {
             return Err(CoreError::BufferSizeMismatch {
                 expected: self.config.size,
@@ -105,25 +112,25 @@ impl FftProcessor {
             });
         }
         
-        // Convert to rustfft format
-        let mut buffer: Vec<Complex64> = input.iter()
-            .map(|c| Complex64::new(c.real, c.imag))
-            .collect();
+        // Convert to rustfft format in the reusable scratch buffer
+        for (dst, c) in self.scratch.iter_mut().zip(input) {
+            *dst = num_complex::Complex::new(c.real, c.imag);
+        }
         
         // Perform IFFT
-        self.ifft.process(&mut buffer);
+        self.ifft.process(&mut self.scratch);
         
         // Convert back to our format and normalize
-        let scale = 1.0 / self.config.size as f64;
-        for (i, c) in buffer.iter().enumerate() {
-            output[i] = Complex::new(c.re * scale, c.im * scale);
+        let scale = F::narrow(1.0 / self.config.size as f64);
+        for (i, c) in self.scratch.iter().enumerate() {
+            output[i] = Complex::from_parts(c.re * scale, c.im * scale);
         }
         
         Ok(())
     }
     
     /// Compute power spectral density
-    pub fn power_spectrum(&mut self, input: &[Complex], output: &mut [f64]) -> Result<()> {
+    pub fn power_spectrum(&mut self, input: &[Complex<F>], output: &mut [F]) -> Result<()> {
         if input.len() != self.config.size {
             return Err(CoreError::BufferSizeMismatch {
                 expected: self.config.size,
@@ -148,7 +155,7 @@ impl FftProcessor {
             
             // Scale appropriately (double for non-DC and non-Nyquist bins)
             if i > 0 && i < self.config.size / 2 {
-                output[i] *= 2.0;
+                output[i] = output[i] + output[i];
             }
         }
         
@@ -156,6 +163,294 @@ impl FftProcessor {
     }
 }
 
+/// Short-time FFT settings for [`Spectrogram`] and [`welch`]
+#[derive(Debug, Clone)]
+pub struct SpectrogramConfig {
+    pub fft: FftConfig,
+    pub window: Window,
+    /// Fraction of each segment shared with the next, in `[0, 1)`
+    pub overlap: f64,
+    /// Segments averaged into each emitted row
+    pub averages: usize,
+    /// Fold negative frequencies onto positive ones (for real input);
+    /// otherwise rows span `-fs/2..fs/2`
+    pub onesided: bool,
+}
+
+impl SpectrogramConfig {
+    /// Hann window, 50% overlap, no averaging, one-sided
+    pub fn new(size: usize, sample_rate: f64) -> Result<Self> {
+        Ok(Self {
+            fft: FftConfig::new(size, sample_rate)?,
+            window: Window::Hann,
+            overlap: 0.5,
+            averages: 1,
+            onesided: true,
+        })
+    }
+
+    /// Set the analysis window
+    pub fn with_window(mut self, window: Window) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set the segment overlap fraction
+    pub fn with_overlap(mut self, overlap: f64) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Average `averages` segments per row (Welch's method)
+    pub fn with_averages(mut self, averages: usize) -> Self {
+        self.averages = averages;
+        self
+    }
+
+    /// Keep the full two-sided spectrum, for complex input
+    pub fn two_sided(mut self) -> Self {
+        self.onesided = false;
+        self
+    }
+
+    /// Samples between successive segments
+    pub fn hop(&self) -> usize {
+        ((self.fft.size as f64 * (1.0 - self.overlap)).round() as usize).max(1)
+    }
+}
+
+/// One power spectral density estimate
+#[derive(Debug, Clone)]
+pub struct Spectrum {
+    /// Power spectral density per bin, in full-scale power per Hz
+    /// (0 dBFS is a mean square of 1.0)
+    pub psd: Vec<f64>,
+    /// Frequency of bin 0 in Hz
+    pub start_frequency: f64,
+    /// Bin spacing in Hz
+    pub bin_width: f64,
+    /// Time of the first sample of the first segment, in seconds
+    pub start_time: f64,
+    /// Number of segments averaged into this estimate
+    pub averages: usize,
+}
+
+impl Spectrum {
+    /// Centre frequency of `bin` in Hz
+    pub fn frequency(&self, bin: usize) -> f64 {
+        self.start_frequency + bin as f64 * self.bin_width
+    }
+
+    /// Bin nearest to `frequency` Hz
+    pub fn bin(&self, frequency: f64) -> usize {
+        let bin = ((frequency - self.start_frequency) / self.bin_width).round();
+        (bin.max(0.0) as usize).min(self.psd.len() - 1)
+    }
+
+    /// PSD in dBFS/Hz
+    pub fn psd_db(&self) -> Vec<f64> {
+        self.psd.iter().map(|&p| power_to_db(p)).collect()
+    }
+
+    /// Power between `low` and `high` Hz (inclusive bins, edges in either
+    /// order), in full-scale units
+    pub fn band_power(&self, low: f64, high: f64) -> f64 {
+        let (start, end) = (self.bin(low), self.bin(high));
+        let (start, end) = (start.min(end), start.max(end));
+        self.psd[start..=end].iter().sum::<f64>() * self.bin_width
+    }
+
+    /// Total power across all bins
+    pub fn total_power(&self) -> f64 {
+        self.psd.iter().sum::<f64>() * self.bin_width
+    }
+
+    /// Noise floor estimate in full-scale power per Hz
+    ///
+    /// Uses the median bin, which narrow carriers barely move, corrected
+    /// for the median-to-mean ratio of an averaged periodogram (Wilson-Hilferty
+    /// approximation for a chi-squared with `2 * averages` degrees of freedom).
+    pub fn noise_floor(&self) -> f64 {
+        let mut sorted = self.psd.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = sorted[sorted.len() / 2];
+        let bias = (1.0 - 1.0 / (9.0 * self.averages as f64)).powi(3);
+        median / bias
+    }
+
+    /// Noise floor estimate in dBFS/Hz
+    pub fn noise_floor_db(&self) -> f64 {
+        power_to_db(self.noise_floor())
+    }
+}
+
+fn power_to_db(power: f64) -> f64 {
+    10.0 * power.max(1e-30).log10()
+}
+
+/// Streaming short-time FFT: windowed, overlapped and optionally averaged
+/// segments, emitted as successive [`Spectrum`] rows
+///
+/// Segments are windowed and transformed at the precision `F` of the input;
+/// the periodograms are averaged in `f64`.
+pub struct Spectrogram<F: Float = f64> {
+    config: SpectrogramConfig,
+    processor: FftProcessor<F>,
+    window: Vec<F>,
+    window_power: f64,
+    hop: usize,
+    pending: Vec<Complex<F>>,
+    segment: Vec<Complex<F>>,
+    spectrum: Vec<Complex<F>>,
+    accumulator: Vec<f64>,
+    count: usize,
+    /// Absolute index of `pending[0]`
+    position: usize,
+    /// Absolute index of the first segment in the current average
+    row_start: usize,
+}
+
+impl<F: Float> Spectrogram<F> {
+    /// Create a spectrogram engine
+    pub fn new(config: SpectrogramConfig) -> Result<Self> {
+        if !(0.0..1.0).contains(&config.overlap) {
+            return Err(CoreError::FftError {
+                msg: format!("Overlap must be in [0, 1), got {}", config.overlap),
+            });
+        }
+        if config.averages == 0 {
+            return Err(CoreError::FftError {
+                msg: "At least one segment must be averaged".to_string(),
+            });
+        }
+
+        let size = config.fft.size;
+        // Periodic (DFT-even) window: one sample longer, last one dropped
+        let mut window = config.window.coefficients(size + 1);
+        window.truncate(size);
+        let window_power = window.iter().map(|w| w * w).sum();
+        let window = window.into_iter().map(F::narrow).collect();
+        let bins = if config.onesided { size / 2 + 1 } else { size };
+
+        Ok(Self {
+            processor: FftProcessor::new(config.fft.clone())?,
+            hop: config.hop(),
+            window,
+            window_power,
+            pending: Vec::new(),
+            segment: vec![Complex::default(); size],
+            spectrum: vec![Complex::default(); size],
+            accumulator: vec![0.0; bins],
+            count: 0,
+            position: 0,
+            row_start: 0,
+            config,
+        })
+    }
+
+    /// Settings in use
+    pub fn config(&self) -> &SpectrogramConfig {
+        &self.config
+    }
+
+    /// Feed samples and collect every row completed by them
+    pub fn process<T: Sample<Real = F>>(&mut self, input: &[T]) -> Result<Vec<Spectrum>> {
+        let size = self.config.fft.size;
+        self.pending.extend(input.iter().map(|x| x.to_complex()));
+
+        let mut rows = Vec::new();
+        let mut offset = 0;
+        while self.pending.len() - offset >= size {
+            if self.count == 0 {
+                self.row_start = self.position + offset;
+            }
+            self.accumulate(offset)?;
+            offset += self.hop;
+            if self.count == self.config.averages {
+                rows.push(self.emit());
+            }
+        }
+        self.pending.drain(..offset);
+        self.position += offset;
+        Ok(rows)
+    }
+
+    /// Drop buffered samples and any partial average
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.accumulator.fill(0.0);
+        self.count = 0;
+        self.position = 0;
+        self.row_start = 0;
+    }
+
+    /// Add the periodogram of the segment starting at `pending[offset]`
+    fn accumulate(&mut self, offset: usize) -> Result<()> {
+        let size = self.config.fft.size;
+        for ((dst, &x), &w) in self
+            .segment
+            .iter_mut()
+            .zip(&self.pending[offset..offset + size])
+            .zip(&self.window)
+        {
+            *dst = x * w;
+        }
+        self.processor.fft(&self.segment, &mut self.spectrum)?;
+
+        let scale = 1.0 / (self.config.fft.sample_rate * self.window_power);
+        let power = |k: usize| self.spectrum[k].norm_sqr().widen() * scale;
+        if self.config.onesided {
+            // Fold bin k and its mirror N-k; DC and Nyquist have no mirror.
+            for (k, acc) in self.accumulator.iter_mut().enumerate() {
+                let mirror = (size - k) % size;
+                *acc += if mirror == k { power(k) } else { power(k) + power(mirror) };
+            }
+        } else {
+            // Reorder so bin 0 is -fs/2.
+            for (k, acc) in self.accumulator.iter_mut().enumerate() {
+                *acc += power((k + size / 2) % size);
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn emit(&mut self) -> Spectrum {
+        let fft = &self.config.fft;
+        let averages = self.count;
+        let psd = self.accumulator.iter().map(|&p| p / averages as f64).collect();
+        self.accumulator.fill(0.0);
+        self.count = 0;
+        Spectrum {
+            psd,
+            start_frequency: if self.config.onesided {
+                0.0
+            } else {
+                -fft.sample_rate / 2.0
+            },
+            bin_width: fft.frequency_resolution(),
+            start_time: self.row_start as f64 / fft.sample_rate,
+            averages,
+        }
+    }
+}
+
+/// Welch PSD of a whole buffer: every full segment is averaged into one
+/// estimate, ignoring `config.averages`
+pub fn welch<T: Sample>(input: &[T], config: SpectrogramConfig) -> Result<Spectrum> {
+    let size = config.fft.size;
+    if input.len() < size {
+        return Err(CoreError::BufferSizeMismatch {
+            expected: size,
+            actual: input.len(),
+        });
+    }
+    let segments = (input.len() - size) / config.hop() + 1;
+    let mut spectrogram = Spectrogram::<T::Real>::new(config.with_averages(segments))?;
+    let mut rows = spectrogram.process(input)?;
+    Ok(rows.remove(0))
+}
+
 /// Windowing functions for FFT processing
 pub mod window {
     /// Apply Hamming window to signal
@@ -194,6 +489,7 @@ pub mod window {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::f64::consts::PI;
 
     #[test]
     fn test_fft_config_creation() {
@@ -212,7 +508,7 @@ mod tests {
     #[test]
     fn test_fft_processor_creation() {
         let config = FftConfig::new(64, 1000.0).unwrap();
-        let processor = FftProcessor::new(config).unwrap();
+        let processor = FftProcessor::<f64>::new(config).unwrap();
         assert_eq!(processor.config().size, 64);
     }
 
@@ -222,7 +518,7 @@ mod tests {
         let mut processor = FftProcessor::new(config).unwrap();
         
         // Create a simple test signal
-        let input = vec![
+        let input: Vec<Complex> = vec![
             Complex::new(1.0, 0.0),
             Complex::new(0.0, 0.0),
             Complex::new(0.0, 0.0),
@@ -245,4 +541,116 @@ mod tests {
             assert!((original.imag - recovered.imag).abs() < 1e-10);
         }
     }
+
+    #[test]
+    fn test_single_precision_fft() {
+        let config = FftConfig::new(256, 8000.0).unwrap();
+        let mut wide = FftProcessor::<f64>::new(config.clone()).unwrap();
+        let mut narrow = FftProcessor::<f32>::new(config).unwrap();
+        let input: Vec<Complex> = (0..256)
+            .map(|n| Complex::from_phase(2.0 * PI * 0.11 * n as f64) * (1.0 + 0.3 * (n as f64 * 0.05).sin()))
+            .collect();
+        let input32: Vec<_> = input.iter().map(|c| c.cast::<f32>()).collect();
+
+        let mut expected = vec![Complex::default(); 256];
+        let mut got = vec![Default::default(); 256];
+        wide.fft(&input, &mut expected).unwrap();
+        narrow.fft(&input32, &mut got).unwrap();
+        for (g, e) in got.iter().zip(&expected) {
+            assert!((g.cast::<f64>() - *e).magnitude() < 1e-3);
+        }
+
+        let mut back = vec![Default::default(); 256];
+        narrow.ifft(&got, &mut back).unwrap();
+        for (b, x) in back.iter().zip(&input32) {
+            assert!((*b - *x).magnitude() < 1e-5);
+        }
+
+        let mut power = vec![0.0f32; 129];
+        narrow.power_spectrum(&input32, &mut power).unwrap();
+        let peak = (0..129).max_by(|&a, &b| power[a].total_cmp(&power[b])).unwrap();
+        assert_eq!(peak, 28);
+    }
+
+    #[test]
+    fn test_welch_calibration() {
+        use rand::{rngs::StdRng, SeedableRng};
+        use rand_distr::{Distribution, StandardNormal};
+
+        // Complex white noise of unit power: flat at 1/fs per Hz, two-sided.
+        let fs = 1000.0;
+        let mut rng = StdRng::seed_from_u64(7);
+        let noise: Vec<Complex> = (0..65536)
+            .map(|_| {
+                let re: f64 = StandardNormal.sample(&mut rng);
+                let im: f64 = StandardNormal.sample(&mut rng);
+                Complex::new(re, im) * std::f64::consts::FRAC_1_SQRT_2
+            })
+            .collect();
+        let config = SpectrogramConfig::new(256, fs).unwrap().two_sided();
+        let psd = welch(&noise, config).unwrap();
+        assert_eq!(psd.psd.len(), 256);
+        assert_eq!(psd.frequency(0), -500.0);
+        assert!((psd.noise_floor_db() + 30.0).abs() < 0.3, "{}", psd.noise_floor_db());
+        assert!((psd.total_power() - 1.0).abs() < 0.05);
+
+        // Real tone of amplitude 1 (mean square 0.5) on a weak noise floor.
+        let fs = 8000.0;
+        let input: Vec<f64> = noise
+            .iter()
+            .enumerate()
+            .map(|(n, w)| (2.0 * PI * 1000.0 * n as f64 / fs).cos() + 0.01 * w.real)
+            .collect();
+        let config = SpectrogramConfig::new(512, fs).unwrap().with_window(Window::Blackman);
+        let psd = welch(&input, config).unwrap();
+        assert_eq!(psd.psd.len(), 257);
+        let peak = (0..psd.psd.len()).max_by(|&a, &b| psd.psd[a].total_cmp(&psd.psd[b])).unwrap();
+        assert_eq!(psd.frequency(peak), 1000.0);
+        assert!((psd.band_power(900.0, 1100.0) - 0.5).abs() < 0.01);
+        assert_eq!(psd.band_power(1100.0, 900.0), psd.band_power(900.0, 1100.0));
+        assert_eq!(psd.band_power(5000.0, -100.0), psd.total_power());
+        // Noise power 0.5e-4 spread over 4 kHz
+        let expected_floor = 10.0 * (0.5e-4 / 4000.0f64).log10();
+        assert!((psd.noise_floor_db() - expected_floor).abs() < 0.5);
+
+        // Single-precision input is transformed in f32 and lands on the same
+        // estimate to within its rounding.
+        let narrow: Vec<f32> = input.iter().map(|&x| x as f32).collect();
+        let config = SpectrogramConfig::new(512, fs).unwrap().with_window(Window::Blackman);
+        let psd32 = welch(&narrow, config).unwrap();
+        assert!((psd32.band_power(900.0, 1100.0) - psd.band_power(900.0, 1100.0)).abs() < 1e-5);
+        assert!((psd32.noise_floor_db() - psd.noise_floor_db()).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_spectrogram_streaming_rows() {
+        let fs = 8000.0;
+        let input: Vec<f64> = (0..1000).map(|n| (0.3 * n as f64).sin()).collect();
+        let config = SpectrogramConfig::new(256, fs).unwrap().with_averages(2);
+        assert_eq!(config.hop(), 128);
+
+        let mut streaming = Spectrogram::new(config.clone()).unwrap();
+        let mut rows = Vec::new();
+        for chunk in input.chunks(77) {
+            rows.extend(streaming.process(chunk).unwrap());
+        }
+        // (1000 - 256) / 128 + 1 = 6 segments, two per row
+        assert_eq!(rows.len(), 3);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.averages, 2);
+            assert!((row.start_time - (i * 256) as f64 / fs).abs() < 1e-12);
+        }
+
+        let mut oneshot = Spectrogram::new(config).unwrap();
+        let direct = oneshot.process(&input).unwrap();
+        for (a, b) in rows.iter().zip(&direct) {
+            for (x, y) in a.psd.iter().zip(&b.psd) {
+                assert!((x - y).abs() < 1e-12);
+            }
+        }
+
+        assert!(Spectrogram::<f64>::new(SpectrogramConfig::new(256, fs).unwrap().with_overlap(1.0)).is_err());
+        assert!(Spectrogram::<f64>::new(SpectrogramConfig::new(256, fs).unwrap().with_averages(0)).is_err());
+        assert!(welch(&input[..100], SpectrogramConfig::new(256, fs).unwrap()).is_err());
+    }
 }
\ No newline at end of file
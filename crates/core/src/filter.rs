@@ -1,6 +1,10 @@
 //! Digital filter implementations
 
+use crate::buffer::{Complex, Float, Sample};
+use crate::design::{self, BandType, Window};
+use crate::fft::{FftConfig, FftProcessor};
 use crate::{CoreError, Result};
+use std::ops::Mul;
 
 /// Generic filter trait
 pub trait Filter<T: Copy> {
@@ -28,23 +32,30 @@ pub trait Filter<T: Copy> {
 }
 
 /// Finite Impulse Response (FIR) filter
+///
+/// Taps `C` are real (`f64`, the default) or complex, and the delay line
+/// holds samples of type `T`, which defaults to the tap type. So
+/// `FirFilter` filters real data, `FirFilter<f64, Complex>` (see
+/// [`FirFilter::into_complex`]) runs real taps over I and Q, and
+/// `FirFilter<Complex>` runs complex taps over IQ data. Samples have the
+/// taps' precision: `FirFilter<f32>` filters `f32`.
 #[derive(Debug, Clone)]
-pub struct FirFilter {
-    coefficients: Vec<f64>,
-    delay_line: Vec<f64>,
+pub struct FirFilter<C: Sample = f64, T: Sample<Real = C::Real> = C> {
+    coefficients: Vec<C>,
+    delay_line: Vec<T>,
     index: usize,
 }
 
-impl FirFilter {
+impl<C: Sample> FirFilter<C> {
     /// Create a new FIR filter with given coefficients
-    pub fn new(coefficients: Vec<f64>) -> Result<Self> {
+    pub fn new(coefficients: Vec<C>) -> Result<Self> {
         if coefficients.is_empty() {
             return Err(CoreError::InvalidFilterParameters {
                 msg: "FIR filter must have at least one coefficient".to_string(),
             });
         }
         
-        let delay_line = vec![0.0; coefficients.len()];
+        let delay_line = vec![C::default(); coefficients.len()];
         
         Ok(Self {
             coefficients,
@@ -52,7 +63,32 @@ impl FirFilter {
             index: 0,
         })
     }
+}
+
+impl<C: Sample, T: Sample<Real = C::Real>> FirFilter<C, T> {
+    /// The same taps over complex samples, with a cleared delay line; real
+    /// taps then filter I and Q independently
+    pub fn into_complex(self) -> FirFilter<C, Complex<C::Real>> {
+        FirFilter {
+            delay_line: vec![Complex::default(); self.coefficients.len()],
+            coefficients: self.coefficients,
+            index: 0,
+        }
+    }
+    
+    /// Get the filter coefficients
+    pub fn coefficients(&self) -> &[C] {
+        &self.coefficients
+    }
     
+    /// Complex frequency response at `freq` Hz (negative frequencies are
+    /// meaningful for complex taps)
+    pub fn response(&self, freq: f64, sample_rate: f64) -> Complex {
+        design::fir_response(&self.coefficients, freq, sample_rate)
+    }
+}
+
+impl FirFilter<f64> {
     /// Create a simple low-pass FIR filter
     pub fn lowpass(cutoff_freq: f64, sample_rate: f64, num_taps: usize) -> Result<Self> {
         if cutoff_freq <= 0.0 || cutoff_freq >= sample_rate / 2.0 {
@@ -87,58 +123,99 @@ impl FirFilter {
         
         Self::new(coefficients)
     }
+    
+    /// Create a Hamming-windowed high-pass FIR filter (`num_taps` must be odd)
+    pub fn highpass(cutoff_freq: f64, sample_rate: f64, num_taps: usize) -> Result<Self> {
+        Self::design(BandType::Highpass(cutoff_freq), sample_rate, num_taps)
+    }
+    
+    /// Create a Hamming-windowed band-pass FIR filter
+    pub fn bandpass(low_freq: f64, high_freq: f64, sample_rate: f64, num_taps: usize) -> Result<Self> {
+        Self::design(BandType::Bandpass(low_freq, high_freq), sample_rate, num_taps)
+    }
+    
+    /// Create a Hamming-windowed band-stop FIR filter (`num_taps` must be odd)
+    pub fn bandstop(low_freq: f64, high_freq: f64, sample_rate: f64, num_taps: usize) -> Result<Self> {
+        Self::design(BandType::Bandstop(low_freq, high_freq), sample_rate, num_taps)
+    }
+    
+    fn design(band: BandType, sample_rate: f64, num_taps: usize) -> Result<Self> {
+        Self::new(design::fir_window(band, sample_rate, num_taps, Window::Hamming)?)
+    }
 }
 
-impl Filter<f64> for FirFilter {
-    fn process_sample(&mut self, input: f64) -> f64 {
+impl FirFilter<Complex> {
+    /// Create a Hamming-windowed complex band-pass FIR filter passing
+    /// `low_freq..high_freq`, which may be negative or straddle zero
+    /// (e.g. `300.0..3000.0` keeps only the upper sideband)
+    pub fn complex_bandpass(low_freq: f64, high_freq: f64, sample_rate: f64, num_taps: usize) -> Result<Self> {
+        Self::new(design::fir_complex_bandpass(
+            low_freq,
+            high_freq,
+            sample_rate,
+            num_taps,
+            Window::Hamming,
+        )?)
+    }
+}
+
+impl<C: Sample, T: Sample<Real = C::Real> + Mul<C, Output = T>> Filter<T> for FirFilter<C, T> {
+    fn process_sample(&mut self, input: T) -> T {
         // Store input in delay line
+        let len = self.delay_line.len();
         self.delay_line[self.index] = input;
         
         // Compute convolution
-        let mut output = 0.0;
+        let mut output = T::default();
         for (i, &coeff) in self.coefficients.iter().enumerate() {
-            let delay_index = (self.index + self.delay_line.len() - i) % self.delay_line.len();
-            output += coeff * self.delay_line[delay_index];
+            output = output + self.delay_line[(self.index + len - i) % len] * coeff;
         }
         
         // Update delay line index
-        self.index = (self.index + 1) % self.delay_line.len();
+        self.index = (self.index + 1) % len;
         
         output
     }
     
     fn reset(&mut self) {
-        self.delay_line.fill(0.0);
+        self.delay_line.fill(T::default());
         self.index = 0;
     }
 }
 
 /// Infinite Impulse Response (IIR) filter
+///
+/// Like [`FirFilter`], coefficients `C` may be real or complex and the
+/// history holds samples of type `T`. Coefficients are scaled at
+/// construction so that `a[0] == 1`.
 #[derive(Debug, Clone)]
-pub struct IirFilter {
-    b_coeffs: Vec<f64>, // Feedforward coefficients
-    a_coeffs: Vec<f64>, // Feedback coefficients
-    x_history: Vec<f64>, // Input history
-    y_history: Vec<f64>, // Output history
+pub struct IirFilter<C: Sample = f64, T: Sample<Real = C::Real> = C> {
+    b_coeffs: Vec<C>, // Feedforward coefficients
+    a_coeffs: Vec<C>, // Feedback coefficients
+    x_history: Vec<T>, // Input history
+    y_history: Vec<T>, // Output history
 }
 
-impl IirFilter {
+impl<C: Sample> IirFilter<C> {
     /// Create a new IIR filter with given coefficients
-    pub fn new(b_coeffs: Vec<f64>, a_coeffs: Vec<f64>) -> Result<Self> {
+    pub fn new(b_coeffs: Vec<C>, a_coeffs: Vec<C>) -> Result<Self> {
         if b_coeffs.is_empty() || a_coeffs.is_empty() {
             return Err(CoreError::InvalidFilterParameters {
                 msg: "IIR filter must have at least one coefficient in each array".to_string(),
             });
         }
         
-        if a_coeffs[0] == 0.0 {
+        let a0 = a_coeffs[0];
+        if a0.power() == C::Real::default() {
             return Err(CoreError::InvalidFilterParameters {
                 msg: "First feedback coefficient (a[0]) cannot be zero".to_string(),
             });
         }
         
-        let x_history = vec![0.0; b_coeffs.len()];
-        let y_history = vec![0.0; a_coeffs.len()];
+        let b_coeffs: Vec<C> = b_coeffs.into_iter().map(|b| b / a0).collect();
+        let a_coeffs: Vec<C> = a_coeffs.into_iter().map(|a| a / a0).collect();
+        let x_history = vec![C::default(); b_coeffs.len()];
+        let y_history = vec![C::default(); a_coeffs.len()];
         
         Ok(Self {
             b_coeffs,
@@ -147,7 +224,26 @@ impl IirFilter {
             y_history,
         })
     }
+}
+
+impl<C: Sample, T: Sample<Real = C::Real>> IirFilter<C, T> {
+    /// The same coefficients over complex samples, with cleared history
+    pub fn into_complex(self) -> IirFilter<C, Complex<C::Real>> {
+        IirFilter {
+            x_history: vec![Complex::default(); self.b_coeffs.len()],
+            y_history: vec![Complex::default(); self.a_coeffs.len()],
+            b_coeffs: self.b_coeffs,
+            a_coeffs: self.a_coeffs,
+        }
+    }
     
+    /// Complex frequency response at `freq` Hz
+    pub fn response(&self, freq: f64, sample_rate: f64) -> Complex {
+        design::freq_response(&self.b_coeffs, &self.a_coeffs, freq, sample_rate)
+    }
+}
+
+impl IirFilter<f64> {
     /// Create a simple first-order low-pass IIR filter
     pub fn lowpass_1st_order(cutoff_freq: f64, sample_rate: f64) -> Result<Self> {
         if cutoff_freq <= 0.0 || cutoff_freq >= sample_rate / 2.0 {
@@ -167,52 +263,439 @@ impl IirFilter {
     }
 }
 
-impl Filter<f64> for IirFilter {
-    fn process_sample(&mut self, input: f64) -> f64 {
+impl<C: Sample, T: Sample<Real = C::Real> + Mul<C, Output = T>> Filter<T> for IirFilter<C, T> {
+    fn process_sample(&mut self, input: T) -> T {
         // Shift input history
-        for i in (1..self.x_history.len()).rev() {
-            self.x_history[i] = self.x_history[i - 1];
-        }
+        self.x_history.rotate_right(1);
         self.x_history[0] = input;
         
-        // Compute output
-        let mut output = 0.0;
-        
         // Feedforward terms
-        for (i, &coeff) in self.b_coeffs.iter().enumerate() {
-            if i < self.x_history.len() {
-                output += coeff * self.x_history[i];
-            }
+        let mut output = T::default();
+        for (&x, &coeff) in self.x_history.iter().zip(&self.b_coeffs) {
+            output = output + x * coeff;
         }
         
-        // Feedback terms (skip a[0])
-        for (i, &coeff) in self.a_coeffs.iter().skip(1).enumerate() {
-            if i < self.y_history.len() - 1 {
-                output -= coeff * self.y_history[i];
-            }
+        // Feedback terms (a[0] is 1)
+        for (&y, &coeff) in self.y_history.iter().zip(&self.a_coeffs[1..]) {
+            output = output - y * coeff;
         }
         
-        // Normalize by a[0]
-        output /= self.a_coeffs[0];
-        
         // Shift output history
-        for i in (1..self.y_history.len()).rev() {
-            self.y_history[i] = self.y_history[i - 1];
-        }
+        self.y_history.rotate_right(1);
         self.y_history[0] = output;
         
         output
     }
     
     fn reset(&mut self) {
-        self.x_history.fill(0.0);
-        self.y_history.fill(0.0);
+        self.x_history.fill(T::default());
+        self.y_history.fill(T::default());
+    }
+}
+
+/// Second-order IIR section, normalized so that `a0 == 1`
+///
+/// Runs in transposed direct form II, which behaves well numerically for
+/// the high-Q sections produced by [`design::iir_design`]. Coefficients are
+/// real and the state holds samples of type `T`, so `Biquad` filters real
+/// data and `Biquad<Complex>` IQ data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad<T: Sample = f64> {
+    b: [T::Real; 3],
+    a: [T::Real; 3],
+    s1: T,
+    s2: T,
+}
+
+impl<T: Sample> Biquad<T> {
+    /// Create a section from numerator `b` and denominator `a` coefficients
+    pub fn new(b: [T::Real; 3], a: [T::Real; 3]) -> Result<Self> {
+        if a[0] == T::Real::default() {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: "First feedback coefficient (a[0]) cannot be zero".to_string(),
+            });
+        }
+        
+        let b = [b[0] / a[0], b[1] / a[0], b[2] / a[0]];
+        let a = [T::Real::narrow(1.0), a[1] / a[0], a[2] / a[0]];
+        Ok(Self {
+            b,
+            a,
+            s1: T::default(),
+            s2: T::default(),
+        })
+    }
+    
+    /// Normalized `(b, a)` coefficients
+    pub fn coefficients(&self) -> ([T::Real; 3], [T::Real; 3]) {
+        (self.b, self.a)
+    }
+    
+    /// Complex frequency response at `freq` Hz
+    pub fn response(&self, freq: f64, sample_rate: f64) -> Complex {
+        let b = self.b.map(Float::widen);
+        let a = self.a.map(Float::widen);
+        design::freq_response(&b, &a, freq, sample_rate)
+    }
+    
+    /// The same section for another sample type, with cleared state
+    pub fn cast<U: Sample>(&self) -> Biquad<U> {
+        let narrow = |c: T::Real| U::Real::narrow(c.widen());
+        Biquad {
+            b: self.b.map(narrow),
+            a: self.a.map(narrow),
+            s1: U::default(),
+            s2: U::default(),
+        }
+    }
+    
+    fn step(&mut self, input: T) -> T {
+        let output = input * self.b[0] + self.s1;
+        self.s1 = input * self.b[1] - output * self.a[1] + self.s2;
+        self.s2 = input * self.b[2] - output * self.a[2];
+        output
+    }
+}
+
+impl<T: Sample> Filter<T> for Biquad<T> {
+    fn process_sample(&mut self, input: T) -> T {
+        self.step(input)
+    }
+    
+    fn reset(&mut self) {
+        self.s1 = T::default();
+        self.s2 = T::default();
+    }
+}
+
+/// Cascade of second-order sections
+///
+/// [`design::iir_design`] returns a real-sample cascade; use
+/// [`SosFilter::cast`] to run it on IQ data or in single precision.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SosFilter<T: Sample = f64> {
+    sections: Vec<Biquad<T>>,
+}
+
+impl<T: Sample> SosFilter<T> {
+    /// Create a cascade from one or more sections
+    pub fn new(sections: Vec<Biquad<T>>) -> Result<Self> {
+        if sections.is_empty() {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: "SOS filter must have at least one section".to_string(),
+            });
+        }
+        
+        Ok(Self { sections })
+    }
+    
+    /// Get the sections, in processing order
+    pub fn sections(&self) -> &[Biquad<T>] {
+        &self.sections
+    }
+    
+    /// Complex frequency response at `freq` Hz
+    pub fn response(&self, freq: f64, sample_rate: f64) -> Complex {
+        self.sections
+            .iter()
+            .fold(Complex::new(1.0, 0.0), |acc, s| acc * s.response(freq, sample_rate))
+    }
+    
+    /// Magnitude response at `freq` Hz in dB
+    pub fn magnitude_db(&self, freq: f64, sample_rate: f64) -> f64 {
+        20.0 * self.response(freq, sample_rate).magnitude().max(1e-300).log10()
+    }
+    
+    /// The same cascade for another sample type, e.g. `cast::<Complex>()`
+    /// for IQ data or `cast::<f32>()` for single precision, with cleared state
+    pub fn cast<U: Sample>(&self) -> SosFilter<U> {
+        SosFilter {
+            sections: self.sections.iter().map(Biquad::cast).collect(),
+        }
+    }
+    
+    /// Filter a whole block forward and then backward, giving zero phase
+    /// shift and the squared magnitude response. Leaves `self` untouched.
+    pub fn filter_zero_phase(&self, input: &[T]) -> Vec<T> {
+        let mut filter = self.clone();
+        filter.reset();
+        let mut output: Vec<T> = input.iter().map(|&x| filter.process_sample(x)).collect();
+        filter.reset();
+        for y in output.iter_mut().rev() {
+            *y = filter.process_sample(*y);
+        }
+        output
+    }
+}
+
+impl<T: Sample> Filter<T> for SosFilter<T> {
+    fn process_sample(&mut self, input: T) -> T {
+        self.sections
+            .iter_mut()
+            .fold(input, |x, s| s.step(x))
+    }
+    
+    fn reset(&mut self) {
+        for section in &mut self.sections {
+            section.reset();
+        }
+    }
+}
+
+/// Tap count from which [`FastFirFilter`] switches from direct-form to
+/// FFT (overlap-save) convolution. Below this the FFT overhead does not pay off.
+pub const FFT_CONVOLUTION_MIN_TAPS: usize = 64;
+
+/// FIR filter for long impulse responses, with block processing.
+///
+/// Taps are real and have the samples' precision: `FastFirFilter<f64>` (the
+/// default) filters real data, `FastFirFilter<Complex>` IQ data.
+///
+/// Short filters run a direct-form convolution; filters with at least
+/// [`FFT_CONVOLUTION_MIN_TAPS`] taps use overlap-save fast convolution on top
+/// of [`FftProcessor`], which turns the per-sample cost from `O(taps)` into
+/// `O(log N)`. Real input is packed two blocks per complex FFT, one in the
+/// real and one in the imaginary part, which the real taps keep apart.
+/// Either way the output is the exact linear convolution with no added
+/// latency, and [`Filter::process_sample`] and [`Filter::process_buffer`] can
+/// be freely interleaved on one stream. Use `process_buffer` with large
+/// blocks to get the FFT speed-up.
+pub struct FastFirFilter<T: Sample = f64> {
+    coefficients: Vec<T::Real>,
+    /// The last `taps - 1` inputs, oldest first, are
+    /// `history[pos..pos + taps - 1]`. Each input is stored twice, `taps - 1`
+    /// apart, so that window stays contiguous without shifting.
+    history: Vec<T>,
+    pos: usize,
+    /// History followed by the current block, reused between calls.
+    extended: Vec<T>,
+    overlap_save: Option<OverlapSave<T::Real>>,
+}
+
+/// Overlap-save state: an `N`-point FFT producing `N - taps + 1` outputs per
+/// block.
+struct OverlapSave<F: Float> {
+    fft: FftProcessor<F>,
+    spectrum: Vec<Complex<F>>,
+    block: Vec<Complex<F>>,
+    freq: Vec<Complex<F>>,
+    time: Vec<Complex<F>>,
+}
+
+impl<F: Float> OverlapSave<F> {
+    fn new(coefficients: &[F]) -> Result<Self> {
+        // Four times the filter length keeps the useful fraction of each block
+        // at 75% or better.
+        let size = (4 * coefficients.len()).next_power_of_two();
+        let mut fft = FftProcessor::new(FftConfig::new(size, 1.0)?)?;
+        let mut padded = vec![Complex::default(); size];
+        for (p, &c) in padded.iter_mut().zip(coefficients) {
+            *p = Complex::from_parts(c, F::default());
+        }
+        let mut spectrum = vec![Complex::default(); size];
+        fft.fft(&padded, &mut spectrum)?;
+        Ok(Self {
+            fft,
+            spectrum,
+            block: padded,
+            freq: vec![Complex::default(); size],
+            time: vec![Complex::default(); size],
+        })
+    }
+    
+    /// Circularly convolve `block` with the taps into `time`
+    fn convolve(&mut self) -> Result<()> {
+        self.fft.fft(&self.block, &mut self.freq)?;
+        for (f, &h) in self.freq.iter_mut().zip(&self.spectrum) {
+            *f = *f * h;
+        }
+        self.fft.ifft(&self.freq, &mut self.time)
+    }
+}
+
+impl<T: Sample> FastFirFilter<T> {
+    /// Create a filter with given coefficients, choosing the convolution
+    /// engine by tap count
+    pub fn new(coefficients: Vec<T::Real>) -> Result<Self> {
+        if coefficients.is_empty() {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: "FIR filter must have at least one coefficient".to_string(),
+            });
+        }
+        
+        let overlap_save = if coefficients.len() >= FFT_CONVOLUTION_MIN_TAPS {
+            Some(OverlapSave::new(&coefficients)?)
+        } else {
+            None
+        };
+        
+        Ok(Self {
+            history: vec![T::default(); 2 * (coefficients.len() - 1)],
+            pos: 0,
+            extended: Vec::new(),
+            coefficients,
+            overlap_save,
+        })
+    }
+    
+    /// Get the filter coefficients
+    pub fn coefficients(&self) -> &[T::Real] {
+        &self.coefficients
+    }
+    
+    /// Whether this filter runs FFT (overlap-save) convolution
+    pub fn uses_fft(&self) -> bool {
+        self.overlap_save.is_some()
+    }
+    
+    fn filter_sample(&mut self, input: T) -> T {
+        let memory = self.coefficients.len() - 1;
+        let past = &self.history[self.pos..self.pos + memory];
+        let mut output = input * self.coefficients[0];
+        for (&x, &c) in past.iter().rev().zip(&self.coefficients[1..]) {
+            output = output + x * c;
+        }
+        if memory > 0 {
+            self.history[self.pos] = input;
+            self.history[self.pos + memory] = input;
+            self.pos = (self.pos + 1) % memory;
+        }
+        output
+    }
+    
+    /// Check the buffer sizes and lay out history followed by `input` in
+    /// `extended`
+    fn begin_block(&mut self, input: &[T], output: &[T]) -> Result<()> {
+        if input.len() != output.len() {
+            return Err(CoreError::BufferSizeMismatch {
+                expected: input.len(),
+                actual: output.len(),
+            });
+        }
+        let memory = self.coefficients.len() - 1;
+        self.extended.clear();
+        self.extended
+            .extend_from_slice(&self.history[self.pos..self.pos + memory]);
+        self.extended.extend_from_slice(input);
+        Ok(())
+    }
+    
+    /// Keep the last `taps - 1` samples of `extended` as the new history
+    fn end_block(&mut self) {
+        let memory = self.coefficients.len() - 1;
+        let tail = &self.extended[self.extended.len() - memory..];
+        self.history[..memory].copy_from_slice(tail);
+        self.history[memory..].copy_from_slice(tail);
+        self.pos = 0;
+    }
+    
+    fn direct_block(&self, output: &mut [T]) {
+        let taps = self.coefficients.len();
+        for (n, out) in output.iter_mut().enumerate() {
+            let mut acc = T::default();
+            for (k, &c) in self.coefficients.iter().enumerate() {
+                acc = acc + self.extended[n + taps - 1 - k] * c;
+            }
+            *out = acc;
+        }
+    }
+    
+    fn clear(&mut self) {
+        self.history.fill(T::default());
+        self.pos = 0;
+    }
+}
+
+impl<T: Sample<Real = f64>> FastFirFilter<T> {
+    /// Create a windowed-sinc low-pass filter (same design as
+    /// [`FirFilter::lowpass`])
+    pub fn lowpass(cutoff_freq: f64, sample_rate: f64, num_taps: usize) -> Result<Self> {
+        Self::new(FirFilter::lowpass(cutoff_freq, sample_rate, num_taps)?.coefficients)
+    }
+}
+
+impl<F: Float> Filter<F> for FastFirFilter<F> {
+    fn process_sample(&mut self, input: F) -> F {
+        self.filter_sample(input)
+    }
+    
+    fn process_buffer(&mut self, input: &[F], output: &mut [F]) -> Result<()> {
+        self.begin_block(input, output)?;
+        match self.overlap_save.as_mut() {
+            None => self.direct_block(output),
+            Some(os) => {
+                let taps = self.coefficients.len();
+                let size = os.block.len();
+                let step = size - taps + 1;
+                let sample = |i: usize| self.extended.get(i).copied().unwrap_or_default();
+                // Two blocks per FFT: `start` in the real part and
+                // `start + step` in the imaginary part.
+                for start in (0..input.len()).step_by(2 * step) {
+                    for (i, b) in os.block.iter_mut().enumerate() {
+                        *b = Complex::from_parts(sample(start + i), sample(start + step + i));
+                    }
+                    os.convolve()?;
+                    // The first taps - 1 outputs are circularly aliased; the
+                    // rest are the linear convolution.
+                    let useful = &os.time[taps - 1..];
+                    let count = step.min(input.len() - start);
+                    for (o, y) in output[start..start + count].iter_mut().zip(useful) {
+                        *o = y.real;
+                    }
+                    let second = (start + step).min(input.len());
+                    let count = step.min(input.len() - second);
+                    for (o, y) in output[second..second + count].iter_mut().zip(useful) {
+                        *o = y.imag;
+                    }
+                }
+            }
+        }
+        self.end_block();
+        Ok(())
+    }
+    
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl<F: Float> Filter<Complex<F>> for FastFirFilter<Complex<F>> {
+    fn process_sample(&mut self, input: Complex<F>) -> Complex<F> {
+        self.filter_sample(input)
+    }
+    
+    fn process_buffer(&mut self, input: &[Complex<F>], output: &mut [Complex<F>]) -> Result<()> {
+        self.begin_block(input, output)?;
+        match self.overlap_save.as_mut() {
+            None => self.direct_block(output),
+            Some(os) => {
+                let taps = self.coefficients.len();
+                let size = os.block.len();
+                let step = size - taps + 1;
+                for start in (0..input.len()).step_by(step) {
+                    let avail = (self.extended.len() - start).min(size);
+                    os.block[..avail].copy_from_slice(&self.extended[start..start + avail]);
+                    os.block[avail..].fill(Complex::default());
+                    os.convolve()?;
+                    let count = step.min(input.len() - start);
+                    output[start..start + count]
+                        .copy_from_slice(&os.time[taps - 1..taps - 1 + count]);
+                }
+            }
+        }
+        self.end_block();
+        Ok(())
+    }
+    
+    fn reset(&mut self) {
+        self.clear();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::buffer::Complex32;
 
     #[test]
     fn test_fir_filter_creation() {
@@ -236,6 +719,19 @@ mod tests {
         assert_eq!(filter.a_coeffs.len(), 2);
     }
 
+    #[test]
+    fn test_iir_normalises_a0() {
+        // 2 y[n] = x[n] + y[n-1]
+        let mut filter = IirFilter::new(vec![1.0], vec![2.0, -1.0]).unwrap();
+        let mut y = 0.0f64;
+        for x in [1.0, 0.0, 0.5, -1.0, 0.0] {
+            y = 0.5 * x + 0.5 * y;
+            assert!((filter.process_sample(x) - y).abs() < 1e-15);
+        }
+        let unit = IirFilter::new(vec![0.5], vec![1.0, -0.5]).unwrap();
+        assert!((filter.response(1000.0, 8000.0) - unit.response(1000.0, 8000.0)).magnitude() < 1e-15);
+    }
+
     #[test]
     fn test_filter_processing() {
         let mut filter = FirFilter::new(vec![0.5, 0.5]).unwrap();
@@ -249,4 +745,204 @@ mod tests {
         assert_eq!(output2, 0.5);
         assert_eq!(output3, 0.0);
     }
+
+    fn iq_signal(n: usize) -> Vec<Complex> {
+        let x = test_signal(2 * n);
+        (0..n).map(|i| Complex::new(x[2 * i], x[2 * i + 1])).collect()
+    }
+
+    fn assert_close(a: &[Complex], b: &[Complex]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b) {
+            assert!((*x - *y).magnitude() < 1e-9, "{:?} vs {:?}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_real_tap_filters_on_complex_samples() {
+        // Real taps act on I and Q independently.
+        let iq = iq_signal(500);
+        let i_part: Vec<f64> = iq.iter().map(|c| c.real).collect();
+        let q_part: Vec<f64> = iq.iter().map(|c| c.imag).collect();
+
+        let mut fir = FirFilter::lowpass(3000.0, 48000.0, 31).unwrap();
+        let mut iir = IirFilter::new(vec![0.2, 0.3], vec![1.0, -0.6, 0.1]).unwrap();
+        let mut sos = crate::design::iir_design(
+            crate::design::IirPrototype::Butterworth,
+            4,
+            BandType::Lowpass(3000.0),
+            48000.0,
+        )
+        .unwrap();
+        let run_real = |f: &mut dyn Filter<f64>, x: &[f64]| -> Vec<f64> {
+            f.reset();
+            x.iter().map(|&s| f.process_sample(s)).collect()
+        };
+        let run_complex = |f: &mut dyn Filter<Complex>, x: &[Complex]| -> Vec<Complex> {
+            f.reset();
+            x.iter().map(|&s| f.process_sample(s)).collect()
+        };
+        let split = |f: &mut dyn Filter<f64>| -> Vec<Complex> {
+            let i = run_real(f, &i_part);
+            let q = run_real(f, &q_part);
+            i.iter().zip(&q).map(|(&a, &b)| Complex::new(a, b)).collect()
+        };
+
+        let expected = split(&mut fir);
+        assert_close(&run_complex(&mut fir.into_complex(), &iq), &expected);
+        let expected = split(&mut iir);
+        assert_close(&run_complex(&mut iir.into_complex(), &iq), &expected);
+        let expected = split(&mut sos);
+        assert_close(&run_complex(&mut sos.cast::<Complex>(), &iq), &expected);
+    }
+
+    #[test]
+    fn test_single_precision_fir() {
+        let wide = FirFilter::lowpass(3000.0, 48000.0, 31).unwrap();
+        let taps: Vec<f32> = wide.coefficients().iter().map(|&t| t as f32).collect();
+        let mut wide = wide;
+        let mut narrow = FirFilter::new(taps.clone()).unwrap();
+
+        let x = test_signal(500);
+        for &s in &x {
+            let expected = wide.process_sample(s);
+            let got: f32 = narrow.process_sample(s as f32);
+            assert!((got as f64 - expected).abs() < 1e-5);
+        }
+
+        // Complex32 samples through the same real taps
+        let mut wide = FirFilter::lowpass(3000.0, 48000.0, 31).unwrap().into_complex();
+        let mut narrow = FirFilter::new(taps).unwrap().into_complex();
+        for c in iq_signal(200) {
+            let expected: Complex = wide.process_sample(c);
+            let got: Complex32 = narrow.process_sample(c.cast());
+            assert!((got.cast::<f64>() - expected).magnitude() < 1e-5);
+        }
+        assert!((narrow.response(1000.0, 48000.0) - wide.response(1000.0, 48000.0)).magnitude() < 1e-6);
+    }
+
+    #[test]
+    fn test_complex_tap_fir() {
+        let fs = 48000.0;
+        let mut usb = FirFilter::complex_bandpass(300.0, 3000.0, fs, 127).unwrap();
+        let delay = (usb.coefficients().len() - 1) / 2;
+        for f in [1500.0, -1500.0] {
+            Filter::<Complex>::reset(&mut usb);
+            let tone: Vec<Complex> = (0..2000)
+                .map(|n| Complex::from_phase(2.0 * std::f64::consts::PI * f * n as f64 / fs))
+                .collect();
+            let out: Vec<Complex> = tone.iter().map(|&s| usb.process_sample(s)).collect();
+            let expected = usb.response(f, fs);
+            // Steady-state output is the tone scaled by the response.
+            for n in 1000..2000 {
+                assert!((out[n] - tone[n] * expected).magnitude() < 1e-9);
+            }
+            if f > 0.0 {
+                assert!((expected.magnitude() - 1.0).abs() < 0.01);
+                assert!((out[1000] - tone[1000 - delay]).magnitude() < 0.01);
+            } else {
+                assert!(expected.magnitude() < 1e-2);
+            }
+        }
+
+        // A one-pole complex resonator: y[n] = x[n] + p y[n-1]
+        let p = Complex::from_phase(0.3) * 0.9;
+        let mut iir = IirFilter::new(vec![Complex::new(1.0, 0.0)], vec![Complex::new(1.0, 0.0), -p]).unwrap();
+        let mut y = Complex::default();
+        for (n, x) in iq_signal(200).into_iter().enumerate() {
+            y = x + p * y;
+            let got = iir.process_sample(x);
+            assert!((got - y).magnitude() < 1e-12, "sample {}", n);
+        }
+        assert!(IirFilter::new(vec![Complex::new(1.0, 0.0)], vec![Complex::default()]).is_err());
+    }
+
+    /// Reference linear convolution, output truncated to the input length.
+    fn convolve(x: &[f64], h: &[f64]) -> Vec<f64> {
+        (0..x.len())
+            .map(|n| {
+                h.iter()
+                    .enumerate()
+                    .filter(|(k, _)| *k <= n)
+                    .map(|(k, c)| c * x[n - k])
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn test_signal(n: usize) -> Vec<f64> {
+        (0..n).map(|i| ((i * 7919) % 97) as f64 / 48.0 - 1.0).collect()
+    }
+
+    #[test]
+    fn test_fast_fir_selects_engine_by_taps() {
+        assert!(!FastFirFilter::<f64>::new(vec![0.25; 8]).unwrap().uses_fft());
+        assert!(FastFirFilter::<Complex>::lowpass(1000.0, 48000.0, 101).unwrap().uses_fft());
+        assert!(FastFirFilter::<f64>::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_fast_fir_matches_direct_convolution() {
+        for taps in [5, 64, 101, 257] {
+            let h = FirFilter::lowpass(3000.0, 48000.0, taps).unwrap().coefficients().to_vec();
+            let x = test_signal(3000);
+            let expected = convolve(&x, &h);
+
+            // Stream in uneven chunks, mixing block and per-sample calls.
+            let mut filter = FastFirFilter::new(h).unwrap();
+            let mut got = Vec::new();
+            let mut pos = 0;
+            for (i, len) in [1usize, 700, 3, 1200, 1096].iter().enumerate() {
+                let chunk = &x[pos..pos + len];
+                if i % 2 == 0 {
+                    got.extend(chunk.iter().map(|&s| filter.process_sample(s)));
+                } else {
+                    let mut out = vec![0.0; chunk.len()];
+                    filter.process_buffer(chunk, &mut out).unwrap();
+                    got.extend(out);
+                }
+                pos += len;
+            }
+            for (a, b) in got.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-9, "{taps} taps: {a} vs {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_fir_single_precision() {
+        let h = FirFilter::lowpass(3000.0, 48000.0, 101).unwrap().coefficients().to_vec();
+        let x = test_signal(1000);
+        let expected = convolve(&x, &h);
+
+        let mut filter = FastFirFilter::new(h.iter().map(|&c| c as f32).collect()).unwrap();
+        let input: Vec<f32> = x.iter().map(|&s| s as f32).collect();
+        let mut out = vec![0.0f32; input.len()];
+        filter.process_buffer(&input, &mut out).unwrap();
+        for (a, b) in out.iter().zip(&expected) {
+            assert!((*a as f64 - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_fast_fir_complex_matches_real_per_component() {
+        let h = FirFilter::lowpass(2000.0, 48000.0, 129).unwrap().coefficients().to_vec();
+        let re = test_signal(1500);
+        let im: Vec<f64> = re.iter().rev().cloned().collect();
+        let x: Vec<Complex> = re.iter().zip(&im).map(|(&r, &i)| Complex::new(r, i)).collect();
+
+        let mut filter = FastFirFilter::new(h.clone()).unwrap();
+        let mut out = vec![Complex::default(); x.len()];
+        filter.process_buffer(&x, &mut out).unwrap();
+
+        let (er, ei) = (convolve(&re, &h), convolve(&im, &h));
+        for (n, y) in out.iter().enumerate() {
+            assert!((y.real - er[n]).abs() < 1e-9 && (y.imag - ei[n]).abs() < 1e-9);
+        }
+
+        Filter::<Complex>::reset(&mut filter);
+        let mut again = vec![Complex::default(); x.len()];
+        filter.process_buffer(&x, &mut again).unwrap();
+        assert_eq!(out, again);
+    }
 }
\ No newline at end of file
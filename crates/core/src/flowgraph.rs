@@ -0,0 +1,714 @@
+//! Streaming dataflow runtime
+//!
+//! A [`Flowgraph`] wires [`Source`]s, [`Block`]s and [`Sink`]s together
+//! through bounded channels and runs each stage on its own thread. Data moves
+//! in chunks; a connection holds at most
+//! [`buffer_depth`](Flowgraph::with_buffer_depth) chunks, so a slow stage
+//! holds back the ones upstream of it instead of letting memory and latency
+//! grow. Ports are typed, so a graph that connects an `f64` output to a
+//! [`Complex`] input does not compile.
+//!
+//! When a source runs dry (or is stopped through a [`StopHandle`]), the end
+//! of the stream propagates downstream: every block is flushed and every sink
+//! finished before [`Flowgraph::run`] returns.
+
+use crate::agc::Agc;
+use crate::buffer::{Complex, RingConsumer, Sample};
+use crate::channel::Channel;
+use crate::filter::Filter;
+use crate::hilbert::HilbertTransformer;
+use crate::nco::Ddc;
+use crate::resample::Resampler;
+use crate::{CoreError, Result};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+/// Chunks a connection buffers before its producer blocks
+pub const DEFAULT_BUFFER_DEPTH: usize = 4;
+
+/// How long a [`RingConsumer`] source sleeps when no samples are waiting
+const RING_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A processing stage with one typed input and one typed output
+pub trait Block: Send {
+    type Input: Send + 'static;
+    type Output: Send + 'static;
+
+    /// Process one input chunk, appending whatever is ready to `output`
+    fn work(&mut self, input: &[Self::Input], output: &mut Vec<Self::Output>) -> Result<()>;
+
+    /// Emit anything still held back once the input has ended
+    fn flush(&mut self, _output: &mut Vec<Self::Output>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The start of a graph: capture, a file, a test vector
+pub trait Source: Send {
+    type Output: Send + 'static;
+
+    /// Append the next chunk to `output`; return `false` once the stream
+    /// has ended. Live sources should block until data arrives rather than
+    /// return empty chunks in a tight loop.
+    fn generate(&mut self, output: &mut Vec<Self::Output>) -> Result<bool>;
+}
+
+/// The end of a graph: playback, a file, a decoded-message log
+pub trait Sink: Send {
+    type Input: Send + 'static;
+
+    /// Take one chunk
+    fn consume(&mut self, input: &[Self::Input]) -> Result<()>;
+
+    /// Called once after the last chunk
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An unconnected stage output carrying chunks of `T`
+///
+/// Every port must be fed to exactly one [`Flowgraph::block`],
+/// [`Flowgraph::sink`] or [`Flowgraph::split`]; a port that is dropped
+/// instead closes the stream for the stage that produced it.
+#[must_use = "an unconnected port stops the stage that feeds it"]
+pub struct Port<T> {
+    rx: Receiver<Vec<T>>,
+    /// Alive for as long as the port, or the stage it was fed to, is
+    _reader: Arc<()>,
+}
+
+impl<T> Port<T> {
+    /// Wait for the next chunk; `None` once the producer has finished
+    fn recv(&self) -> Option<Vec<T>> {
+        self.rx.recv().ok()
+    }
+}
+
+/// Stops a running graph's sources from another thread
+#[derive(Debug, Clone)]
+pub struct StopHandle {
+    stop: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    /// Ask every source to end its stream after the chunk it is on
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`stop`](StopHandle::stop) has been called
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+}
+
+type Stage = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// A graph of stages connected by bounded channels
+pub struct Flowgraph {
+    buffer_depth: usize,
+    stages: Vec<(String, Stage)>,
+    stop: StopHandle,
+}
+
+impl Default for Flowgraph {
+    fn default() -> Self {
+        Self {
+            buffer_depth: DEFAULT_BUFFER_DEPTH,
+            stages: Vec::new(),
+            stop: StopHandle {
+                stop: Arc::new(AtomicBool::new(false)),
+            },
+        }
+    }
+}
+
+impl Flowgraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many chunks each connection buffers (at least one). Latency
+    /// through a connection is bounded by this times the chunk size.
+    pub fn with_buffer_depth(mut self, depth: usize) -> Self {
+        self.buffer_depth = depth.max(1);
+        self
+    }
+
+    /// Handle that stops the graph's sources once it is running
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// Number of stages added so far
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Whether no stages have been added
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// A connection, plus a handle that tells its producer whether anything
+    /// is still reading it
+    fn connect<T>(&self) -> (SyncSender<Vec<T>>, Weak<()>, Port<T>) {
+        let (tx, rx) = sync_channel(self.buffer_depth);
+        let reader = Arc::new(());
+        (tx, Arc::downgrade(&reader), Port { rx, _reader: reader })
+    }
+
+    fn add_stage(&mut self, name: &str, stage: impl FnOnce() -> Result<()> + Send + 'static) {
+        self.stages.push((name.to_string(), Box::new(stage)));
+    }
+
+    /// Add a source and return its output port
+    pub fn source<S: Source + 'static>(&mut self, mut source: S) -> Port<S::Output> {
+        let (tx, reader, port) = self.connect();
+        let stop = self.stop.clone();
+        self.add_stage(std::any::type_name::<S>(), move || {
+            while !stop.is_stopped() {
+                let mut chunk = Vec::new();
+                let more = source.generate(&mut chunk)?;
+                // A closed output means everything downstream has gone. An
+                // idle live source sends nothing, so it checks for a reader.
+                let closed = if chunk.is_empty() {
+                    reader.strong_count() == 0
+                } else {
+                    tx.send(chunk).is_err()
+                };
+                if closed {
+                    break;
+                }
+                if !more {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        port
+    }
+
+    /// Feed `input` through `block` and return the block's output port
+    pub fn block<B: Block + 'static>(
+        &mut self,
+        input: Port<B::Input>,
+        mut block: B,
+    ) -> Port<B::Output> {
+        let (tx, reader, port) = self.connect();
+        self.add_stage(std::any::type_name::<B>(), move || {
+            let mut output = Vec::new();
+            while let Some(chunk) = input.recv() {
+                block.work(&chunk, &mut output)?;
+                let closed = if output.is_empty() {
+                    reader.strong_count() == 0
+                } else {
+                    tx.send(std::mem::take(&mut output)).is_err()
+                };
+                if closed {
+                    return Ok(());
+                }
+            }
+            block.flush(&mut output)?;
+            if !output.is_empty() {
+                // Nobody may be left to receive the tail; that is fine.
+                let _ = tx.send(output);
+            }
+            Ok(())
+        });
+        port
+    }
+
+    /// Copy one stream to `count` outputs, e.g. to run several demodulators
+    /// on the same audio. A branch that closes is dropped; the others carry
+    /// on.
+    pub fn split<T: Clone + Send + 'static>(
+        &mut self,
+        input: Port<T>,
+        count: usize,
+    ) -> Vec<Port<T>> {
+        let (senders, ports): (Vec<_>, Vec<_>) = (0..count)
+            .map(|_| {
+                let (tx, _, port) = self.connect();
+                (tx, port)
+            })
+            .unzip();
+        self.add_stage("split", move || {
+            let mut senders: Vec<SyncSender<Vec<T>>> = senders;
+            while let Some(chunk) = input.recv() {
+                senders.retain(|tx| tx.send(chunk.clone()).is_ok());
+                if senders.is_empty() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        ports
+    }
+
+    /// Terminate `input` in `sink`
+    pub fn sink<K: Sink + 'static>(&mut self, input: Port<K::Input>, mut sink: K) {
+        self.add_stage(std::any::type_name::<K>(), move || {
+            while let Some(chunk) = input.recv() {
+                sink.consume(&chunk)?;
+            }
+            sink.finish()
+        });
+    }
+
+    /// Run every stage on its own thread until the stream ends. Returns the
+    /// first error any stage raised; a failing stage closes its connections,
+    /// so the rest of the graph winds down rather than hangs.
+    pub fn run(self) -> Result<()> {
+        let mut handles = Vec::with_capacity(self.stages.len());
+        for (name, stage) in self.stages {
+            let handle = std::thread::Builder::new()
+                .name(format!("flowgraph: {}", name))
+                .spawn(stage)?;
+            handles.push((name, handle));
+        }
+
+        let mut result = Ok(());
+        for (name, handle) in handles {
+            let outcome = handle.join().unwrap_or_else(|_| {
+                Err(CoreError::FlowgraphError {
+                    msg: format!("stage {} panicked", name),
+                })
+            });
+            if result.is_ok() {
+                result = outcome;
+            }
+        }
+        result
+    }
+}
+
+/// Source that hands out a buffer in fixed-size chunks
+pub struct VecSource<T> {
+    data: std::vec::IntoIter<T>,
+    chunk_size: usize,
+}
+
+impl<T> VecSource<T> {
+    pub fn new(data: Vec<T>, chunk_size: usize) -> Self {
+        Self {
+            data: data.into_iter(),
+            chunk_size: chunk_size.max(1),
+        }
+    }
+}
+
+impl<T: Send + 'static> Source for VecSource<T> {
+    type Output = T;
+
+    fn generate(&mut self, output: &mut Vec<T>) -> Result<bool> {
+        output.extend(self.data.by_ref().take(self.chunk_size));
+        Ok(self.data.len() > 0)
+    }
+}
+
+/// Sink that collects everything it receives; keep a
+/// [`handle`](CollectSink::handle) to read the result after the graph runs
+pub struct CollectSink<T> {
+    items: Arc<Mutex<Vec<T>>>,
+}
+
+impl<T> Default for CollectSink<T> {
+    fn default() -> Self {
+        Self {
+            items: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl<T> CollectSink<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared view of the collected items
+    pub fn handle(&self) -> Arc<Mutex<Vec<T>>> {
+        self.items.clone()
+    }
+}
+
+impl<T: Clone + Send + 'static> Sink for CollectSink<T> {
+    type Input = T;
+
+    fn consume(&mut self, input: &[T]) -> Result<()> {
+        self.items
+            .lock()
+            .map_err(|_| CoreError::FlowgraphError {
+                msg: "collect sink lock poisoned".to_string(),
+            })?
+            .extend_from_slice(input);
+        Ok(())
+    }
+}
+
+/// Block built from a closure, for one-off glue between stages
+pub struct FnBlock<I, O, F> {
+    f: F,
+    _types: PhantomData<fn(I) -> O>,
+}
+
+impl<I, O, F> FnBlock<I, O, F>
+where
+    F: FnMut(&[I], &mut Vec<O>) -> Result<()>,
+{
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            _types: PhantomData,
+        }
+    }
+}
+
+impl<I, O, F> Block for FnBlock<I, O, F>
+where
+    I: Send + 'static,
+    O: Send + 'static,
+    F: FnMut(&[I], &mut Vec<O>) -> Result<()> + Send,
+{
+    type Input = I;
+    type Output = O;
+
+    fn work(&mut self, input: &[I], output: &mut Vec<O>) -> Result<()> {
+        (self.f)(input, output)
+    }
+}
+
+/// Runs any [`Filter`] on a stream of `T`
+pub struct FilterBlock<F, T> {
+    filter: F,
+    _sample: PhantomData<fn(T) -> T>,
+}
+
+impl<F: Filter<T>, T: Copy> FilterBlock<F, T> {
+    pub fn new(filter: F) -> Self {
+        Self {
+            filter,
+            _sample: PhantomData,
+        }
+    }
+}
+
+impl<F, T> Block for FilterBlock<F, T>
+where
+    F: Filter<T> + Send,
+    T: Copy + Send + 'static,
+{
+    type Input = T;
+    type Output = T;
+
+    fn work(&mut self, input: &[T], output: &mut Vec<T>) -> Result<()> {
+        output.extend(input.iter().map(|&x| self.filter.process_sample(x)));
+        Ok(())
+    }
+}
+
+/// Applies a [`Channel`] impairment (or a whole
+/// [`ChannelChain`](crate::channel::ChannelChain)) to the stream, chunk by
+/// chunk
+///
+/// Channel stages carry their phase, delay lines, fading and RNG state
+/// across calls, so the result matches applying the channel to the whole
+/// stream at once. Stages that set a level against the signal (AWGN,
+/// clipping, interferers) measure it over the stream so far.
+pub struct ChannelBlock<C> {
+    channel: C,
+}
+
+impl<C: Channel> ChannelBlock<C> {
+    pub fn new(channel: C) -> Self {
+        Self { channel }
+    }
+}
+
+impl<C: Channel + Send> Block for ChannelBlock<C> {
+    type Input = Complex;
+    type Output = Complex;
+
+    fn work(&mut self, input: &[Complex], output: &mut Vec<Complex>) -> Result<()> {
+        let mut samples = input.to_vec();
+        self.channel.apply(&mut samples);
+        output.append(&mut samples);
+        Ok(())
+    }
+}
+
+impl<T: Sample> Block for Resampler<T> {
+    type Input = T;
+    type Output = T;
+
+    fn work(&mut self, input: &[T], output: &mut Vec<T>) -> Result<()> {
+        // The resampler clears its output; blocks append to theirs.
+        let mut resampled = Vec::new();
+        self.process(input, &mut resampled)?;
+        output.append(&mut resampled);
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<T>) -> Result<()> {
+        let mut tail = Vec::new();
+        Resampler::flush(self, &mut tail)?;
+        output.append(&mut tail);
+        Ok(())
+    }
+}
+
+/// Levels real audio
+impl Block for Agc {
+    type Input = f64;
+    type Output = f64;
+
+    fn work(&mut self, input: &[f64], output: &mut Vec<f64>) -> Result<()> {
+        output.extend(self.process(input));
+        Ok(())
+    }
+}
+
+/// Turns real audio into its analytic signal
+impl Block for HilbertTransformer {
+    type Input = f64;
+    type Output = Complex;
+
+    fn work(&mut self, input: &[f64], output: &mut Vec<Complex>) -> Result<()> {
+        output.extend(self.process(input));
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<Complex>) -> Result<()> {
+        output.extend(HilbertTransformer::flush(self));
+        Ok(())
+    }
+}
+
+/// Mixes real audio down to decimated complex baseband
+impl Block for Ddc {
+    type Input = f64;
+    type Output = Complex;
+
+    fn work(&mut self, input: &[f64], output: &mut Vec<Complex>) -> Result<()> {
+        output.extend(self.process(input));
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<Complex>) -> Result<()> {
+        output.extend(Ddc::flush(self));
+        Ok(())
+    }
+}
+
+/// Reads a capture ring buffer until its producer is dropped
+impl<T: Copy + Send + 'static> Source for RingConsumer<T> {
+    type Output = T;
+
+    fn generate(&mut self, output: &mut Vec<T>) -> Result<bool> {
+        // Check before taking, so samples pushed just before the producer
+        // went away are still delivered.
+        let closed = self.is_closed();
+        let window = self.take();
+        if window.samples.is_empty() {
+            if closed {
+                return Ok(false);
+            }
+            std::thread::sleep(RING_POLL_INTERVAL);
+        }
+        output.extend(window.samples);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::ring_buffer;
+    use crate::channel::{
+        ChannelChain, FreqOffset, Multipath, MultipathTap, PhaseOffset, TimingOffset, Watterson,
+        WattersonPreset,
+    };
+    use crate::filter::FirFilter;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_pipeline_matches_direct_processing() {
+        let input: Vec<f64> = (0..5000)
+            .map(|n| (2.0 * PI * 0.01 * n as f64).sin() + 0.3 * (2.0 * PI * 0.3 * n as f64).sin())
+            .collect();
+        let fir = FirFilter::lowpass(1000.0, 8000.0, 31).unwrap();
+        let mut direct_fir = fir.clone();
+        let mut direct_resampler = Resampler::<f64>::new(8000.0, 12000.0).unwrap();
+        let filtered: Vec<f64> = input
+            .iter()
+            .map(|&x| direct_fir.process_sample(x))
+            .collect();
+        let expected = direct_resampler.process_all(&filtered).unwrap();
+
+        let mut graph = Flowgraph::new().with_buffer_depth(2);
+        let audio = graph.source(VecSource::new(input, 333));
+        let filtered = graph.block(audio, FilterBlock::new(fir));
+        let resampled = graph.block(filtered, Resampler::<f64>::new(8000.0, 12000.0).unwrap());
+        let sink = CollectSink::new();
+        let out = sink.handle();
+        graph.sink(resampled, sink);
+        assert_eq!(graph.len(), 4);
+        graph.run().unwrap();
+
+        let out = out.lock().unwrap();
+        assert_eq!(out.len(), expected.len());
+        for (a, b) in out.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_split_and_typed_adapters() {
+        let input: Vec<f64> = (0..2000)
+            .map(|n| (2.0 * PI * 0.125 * n as f64).cos())
+            .collect();
+        let mut graph = Flowgraph::new();
+        let audio = graph.source(VecSource::new(input, 128));
+        let iq = graph.block(audio, HilbertTransformer::default());
+        let mut branches = graph.split(iq, 2).into_iter();
+        let rotated = graph.block(
+            branches.next().unwrap(),
+            ChannelBlock::new(ChannelChain::new().then(PhaseOffset { radians: PI / 2.0 })),
+        );
+        let power = graph.block(
+            branches.next().unwrap(),
+            FnBlock::new(|input: &[Complex], output: &mut Vec<f64>| {
+                output.extend(input.iter().map(|c| c.norm_sqr()));
+                Ok(())
+            }),
+        );
+        let rotated_sink = CollectSink::new();
+        let power_sink = CollectSink::new();
+        let (rotated_out, power_out) = (rotated_sink.handle(), power_sink.handle());
+        graph.sink(rotated, rotated_sink);
+        graph.sink(power, power_sink);
+        graph.run().unwrap();
+
+        // The Hilbert transformer flushes its delay, so nothing is lost.
+        let rotated = rotated_out.lock().unwrap();
+        let power = power_out.lock().unwrap();
+        assert_eq!(
+            rotated.len(),
+            2000 + HilbertTransformer::default().group_delay()
+        );
+        assert_eq!(power.len(), rotated.len());
+        for (r, p) in rotated[200..1800].iter().zip(&power[200..1800]) {
+            assert!((r.norm_sqr() - p).abs() < 1e-9);
+            assert!((p - 1.0).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_channel_block_matches_whole_buffer_apply() {
+        let input: Vec<Complex> = (0..3000)
+            .map(|n| Complex::from_phase(2.0 * PI * 0.01 * n as f64))
+            .collect();
+        let chain = || {
+            ChannelChain::new()
+                .then(FreqOffset::new(37.0, 8000.0))
+                .then(Multipath::new(vec![
+                    MultipathTap {
+                        delay: 0,
+                        gain: Complex::new(1.0, 0.0),
+                    },
+                    MultipathTap {
+                        delay: 90,
+                        gain: Complex::new(0.0, 0.5),
+                    },
+                ]))
+                .then(TimingOffset::new(-2.3))
+                .then(Watterson::preset(WattersonPreset::Flutter, 8000.0, 3))
+        };
+        let mut expected = input.clone();
+        chain().apply(&mut expected);
+
+        // Chunks shorter than the echo delay and the interpolator's look-ahead,
+        // so any per-chunk restart shows.
+        let mut graph = Flowgraph::new();
+        let samples = graph.source(VecSource::new(input, 64));
+        let received = graph.block(samples, ChannelBlock::new(chain()));
+        let sink = CollectSink::new();
+        let out = sink.handle();
+        graph.sink(received, sink);
+        graph.run().unwrap();
+
+        assert_eq!(*out.lock().unwrap(), expected);
+    }
+
+    struct Failing;
+
+    impl Block for Failing {
+        type Input = f64;
+        type Output = f64;
+
+        fn work(&mut self, _input: &[f64], _output: &mut Vec<f64>) -> Result<()> {
+            Err(CoreError::FlowgraphError {
+                msg: "boom".to_string(),
+            })
+        }
+    }
+
+    /// Hands out one chunk, then polls forever without finding more
+    struct Idle {
+        sent: bool,
+    }
+
+    impl Source for Idle {
+        type Output = f64;
+
+        fn generate(&mut self, output: &mut Vec<f64>) -> Result<bool> {
+            if !self.sent {
+                output.push(1.0);
+                self.sent = true;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_errors_and_stop_wind_the_graph_down() {
+        // An endless source upstream of a failing block must not hang run().
+        let (mut producer, consumer) = ring_buffer::<f64>(1024, 8000.0).unwrap();
+        producer.push(&[1.0; 100]);
+        let mut graph = Flowgraph::new();
+        let samples = graph.source(consumer);
+        let failed = graph.block(samples, Failing);
+        graph.sink(failed, CollectSink::new());
+        let stop = graph.stop_handle();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            stop.stop();
+        });
+        assert!(matches!(graph.run(), Err(CoreError::FlowgraphError { .. })));
+        stopper.join().unwrap();
+
+        // An idle source notices a failed consumer without a stop handle.
+        let mut graph = Flowgraph::new();
+        let samples = graph.source(Idle { sent: false });
+        let failed = graph.block(samples, Failing);
+        graph.sink(failed, CollectSink::new());
+        assert!(matches!(graph.run(), Err(CoreError::FlowgraphError { .. })));
+
+        // Dropping the producer ends a ring-buffer source once it is drained.
+        let (mut producer, consumer) = ring_buffer::<f64>(1024, 8000.0).unwrap();
+        producer.push(&[0.5; 300]);
+        drop(producer);
+        let mut graph = Flowgraph::new();
+        let samples = graph.source(consumer);
+        let sink = CollectSink::new();
+        let out = sink.handle();
+        graph.sink(samples, sink);
+        graph.run().unwrap();
+        assert_eq!(out.lock().unwrap().len(), 300);
+    }
+}
@@ -0,0 +1,313 @@
+//! Carrier frequency estimation
+//!
+//! Estimators for the frequency of a tone or a modulated carrier:
+//!
+//! - [`fft_peak_frequency`]: the strongest FFT bin in a band, refined between
+//!   bins by parabolic (Hann window) or Jacobsen (rectangular window)
+//!   interpolation. Works on real or complex input at any offset.
+//! - [`mth_power_frequency`]: raises M-PSK baseband to the M-th power, which
+//!   strips the data modulation and leaves a tone at M times the offset.
+//! - [`kay_frequency`] and [`fitz_frequency`]: phase-difference and
+//!   autocorrelation estimators for a single complex tone; close to the
+//!   Cramér-Rao bound above their SNR threshold.
+//!
+//! All return Hz. The tone estimators expect complex input already mixed
+//! close to zero, e.g. by an [`Nco`](crate::nco::Nco) at the nominal carrier.
+
+use crate::buffer::{Complex, Sample};
+use crate::design::Window;
+use crate::{CoreError, Result};
+use rustfft::{num_complex::Complex64, FftPlanner};
+use std::f64::consts::PI;
+
+/// Sub-bin refinement of an FFT peak
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PeakInterpolation {
+    /// Parabola through the log magnitudes of the peak and its neighbours,
+    /// on a Hann-windowed FFT
+    Parabolic,
+    /// Jacobsen's complex three-bin estimator with Candan's bias correction,
+    /// on an unwindowed FFT
+    #[default]
+    Jacobsen,
+}
+
+/// Frequency of the strongest component between `low` and `high` Hz
+///
+/// The band may reach into negative frequencies for complex input; for real
+/// input keep it within `0..sample_rate / 2`, or the mirror image competes.
+pub fn fft_peak_frequency<T: Sample<Real = f64>>(
+    samples: &[T],
+    sample_rate: f64,
+    low: f64,
+    high: f64,
+    interpolation: PeakInterpolation,
+) -> Result<f64> {
+    if sample_rate <= 0.0 {
+        return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+    }
+    if samples.len() < 3 {
+        return Err(CoreError::BufferSizeMismatch {
+            expected: 3,
+            actual: samples.len(),
+        });
+    }
+    if low >= high || low < -sample_rate / 2.0 || high > sample_rate / 2.0 {
+        return Err(CoreError::InvalidFilterParameters {
+            msg: format!(
+                "Search band {}..{} Hz must be ascending and within +/-{} Hz",
+                low,
+                high,
+                sample_rate / 2.0
+            ),
+        });
+    }
+
+    let n = samples.len();
+    let window = match interpolation {
+        PeakInterpolation::Parabolic => {
+            // Periodic Hann, as in the spectrogram.
+            let mut w = Window::Hann.coefficients(n + 1);
+            w.truncate(n);
+            w
+        }
+        PeakInterpolation::Jacobsen => vec![1.0; n],
+    };
+    let mut spectrum: Vec<Complex64> = samples
+        .iter()
+        .zip(&window)
+        .map(|(&x, &w)| {
+            let c = x.to_complex();
+            Complex64::new(c.real * w, c.imag * w)
+        })
+        .collect();
+    FftPlanner::new().plan_fft_forward(n).process(&mut spectrum);
+
+    // Signed bin indices covering the band; bin k sits at k * fs / n.
+    let bin_width = sample_rate / n as f64;
+    let first = (low / bin_width).ceil() as i64;
+    let last = ((high / bin_width).floor() as i64).min(first + n as i64 - 1);
+    let at = |k: i64| spectrum[k.rem_euclid(n as i64) as usize];
+    let peak = (first..=last.max(first))
+        .max_by(|&a, &b| at(a).norm_sqr().total_cmp(&at(b).norm_sqr()))
+        .unwrap_or(first);
+
+    let (prev, centre, next) = (at(peak - 1), at(peak), at(peak + 1));
+    let delta = match interpolation {
+        PeakInterpolation::Parabolic => {
+            let (a, b, c) = (
+                prev.norm().max(1e-300).ln(),
+                centre.norm().max(1e-300).ln(),
+                next.norm().max(1e-300).ln(),
+            );
+            let curvature = a - 2.0 * b + c;
+            if curvature < 0.0 {
+                0.5 * (a - c) / curvature
+            } else {
+                0.0
+            }
+        }
+        PeakInterpolation::Jacobsen => {
+            let denominator = centre * 2.0 - prev - next;
+            if denominator.norm_sqr() > 0.0 {
+                let x = PI / n as f64;
+                ((prev - next) / denominator).re * x.tan() / x
+            } else {
+                0.0
+            }
+        }
+    };
+    Ok((peak as f64 + delta.clamp(-0.5, 0.5)) * bin_width)
+}
+
+/// Frequency offset of M-PSK baseband by the M-th power method
+///
+/// Raising each sample to the `order`-th power removes the modulation and
+/// leaves a tone at `order` times the offset, located with a Jacobsen FFT
+/// peak. The unambiguous range is `+/- sample_rate / (2 * order)`.
+pub fn mth_power_frequency(samples: &[Complex], order: usize, sample_rate: f64) -> Result<f64> {
+    if order == 0 {
+        return Err(CoreError::InvalidFilterParameters {
+            msg: "M-th power order must be at least 1".to_string(),
+        });
+    }
+    let powered: Vec<Complex> = samples
+        .iter()
+        .map(|&s| (1..order).fold(s, |acc, _| acc * s))
+        .collect();
+    let tone = fft_peak_frequency(
+        &powered,
+        sample_rate,
+        -sample_rate / 2.0,
+        sample_rate / 2.0,
+        PeakInterpolation::Jacobsen,
+    )?;
+    Ok(tone / order as f64)
+}
+
+/// Kay's weighted phase-difference estimate of a complex tone's frequency
+///
+/// Averages the phase step between consecutive samples with a parabolic
+/// window. Unambiguous over `+/- sample_rate / 2`, but its error grows
+/// quickly below roughly 5 dB SNR.
+pub fn kay_frequency(samples: &[Complex], sample_rate: f64) -> Result<f64> {
+    let n = samples.len();
+    if n < 2 {
+        return Err(CoreError::BufferSizeMismatch {
+            expected: 2,
+            actual: n,
+        });
+    }
+    let nf = n as f64;
+    let half = nf / 2.0;
+    let scale = 1.5 * nf / (nf * nf - 1.0);
+    let mean_step: f64 = samples
+        .windows(2)
+        .enumerate()
+        .map(|(t, pair)| {
+            let r = (t as f64 - (half - 1.0)) / half;
+            scale * (1.0 - r * r) * (pair[1] * pair[0].conj()).phase()
+        })
+        .sum();
+    Ok(mean_step * sample_rate / (2.0 * PI))
+}
+
+/// Fitz's autocorrelation estimate of a complex tone's frequency
+///
+/// Combines the phases of the autocorrelation at lags `1..=lags`. More lags
+/// lower the error but shrink the unambiguous range to
+/// `+/- sample_rate / (2 * lags)`; `lags` around a quarter to half of the
+/// block length is typical once a coarse estimate has been removed.
+pub fn fitz_frequency(samples: &[Complex], lags: usize, sample_rate: f64) -> Result<f64> {
+    if lags == 0 || lags >= samples.len() {
+        return Err(CoreError::BufferSizeMismatch {
+            expected: lags.max(1) + 1,
+            actual: samples.len(),
+        });
+    }
+    let phase_sum: f64 = (1..=lags)
+        .map(|m| {
+            samples[m..]
+                .iter()
+                .zip(samples)
+                .fold(Complex::default(), |acc, (&x, &y)| acc + x * y.conj())
+                .phase()
+        })
+        .sum();
+    let m = lags as f64;
+    Ok(phase_sum * sample_rate / (PI * m * (m + 1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use rand_distr::{Distribution, Normal};
+
+    const FS: f64 = 8000.0;
+
+    fn noisy_tone(freq: f64, len: usize, snr_db: f64, seed: u64) -> Vec<Complex> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let sigma = (0.5 * 10f64.powf(-snr_db / 10.0)).sqrt();
+        let normal = Normal::new(0.0, sigma).unwrap();
+        (0..len)
+            .map(|n| {
+                Complex::from_phase(2.0 * PI * freq * n as f64 / FS + 0.3)
+                    + Complex::new(normal.sample(&mut rng), normal.sample(&mut rng))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fft_peak_interpolates_between_bins() {
+        // 1000 samples at 8 kHz: 8 Hz bins; pick frequencies across a bin.
+        for freq in [1000.0, 1002.5, 1004.0, -733.3] {
+            let tone = noisy_tone(freq, 1000, 30.0, 1);
+            for interpolation in [PeakInterpolation::Parabolic, PeakInterpolation::Jacobsen] {
+                let estimate =
+                    fft_peak_frequency(&tone, FS, -FS / 2.0, FS / 2.0, interpolation).unwrap();
+                assert!(
+                    (estimate - freq).abs() < 0.4,
+                    "{:?} {} Hz: {}",
+                    interpolation,
+                    freq,
+                    estimate
+                );
+            }
+        }
+
+        // Real input: search the positive half only.
+        let real: Vec<f64> = (0..1000)
+            .map(|n| (2.0 * PI * 1234.5 * n as f64 / FS).cos())
+            .collect();
+        let estimate =
+            fft_peak_frequency(&real, FS, 0.0, FS / 2.0, PeakInterpolation::Jacobsen).unwrap();
+        assert!((estimate - 1234.5).abs() < 0.5, "{}", estimate);
+
+        // A band that excludes the strongest tone finds the weaker one.
+        let mut two: Vec<Complex> = noisy_tone(500.0, 1000, 40.0, 2);
+        for (n, s) in two.iter_mut().enumerate() {
+            *s = *s + Complex::from_phase(2.0 * PI * 1500.0 * n as f64 / FS) * 0.1;
+        }
+        let estimate =
+            fft_peak_frequency(&two, FS, 1000.0, 2000.0, PeakInterpolation::Parabolic).unwrap();
+        assert!((estimate - 1500.0).abs() < 0.5, "{}", estimate);
+    }
+
+    #[test]
+    fn test_mth_power_removes_psk_modulation() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for order in [2usize, 4, 8] {
+            let offset = 37.0;
+            let sps = 8;
+            let symbols: Vec<Complex> = (0..400)
+                .map(|_| {
+                    Complex::from_phase(2.0 * PI * rng.gen_range(0..order) as f64 / order as f64)
+                })
+                .collect();
+            let baseband: Vec<Complex> = symbols
+                .iter()
+                .flat_map(|&s| std::iter::repeat_n(s, sps))
+                .enumerate()
+                .map(|(n, s)| s * Complex::from_phase(2.0 * PI * offset * n as f64 / FS))
+                .collect();
+
+            let estimate = mth_power_frequency(&baseband, order, FS).unwrap();
+            assert!((estimate - offset).abs() < 0.2, "M={}: {}", order, estimate);
+        }
+        assert!(mth_power_frequency(&[Complex::default(); 16], 0, FS).is_err());
+    }
+
+    #[test]
+    fn test_kay_and_fitz_track_a_noisy_tone() {
+        let freq = 23.7;
+        let len = 512;
+        // Cramér-Rao bound on the frequency standard deviation, in Hz.
+        let snr = 10f64.powf(20.0 / 10.0);
+        let crb =
+            (12.0 / (snr * len as f64 * (len as f64 * len as f64 - 1.0))).sqrt() * FS / (2.0 * PI);
+
+        let trials = 50;
+        let (mut kay_mse, mut fitz_mse) = (0.0, 0.0);
+        for seed in 0..trials {
+            let tone = noisy_tone(freq, len, 20.0, 100 + seed);
+            kay_mse += (kay_frequency(&tone, FS).unwrap() - freq).powi(2);
+            fitz_mse += (fitz_frequency(&tone, len / 4, FS).unwrap() - freq).powi(2);
+        }
+        let kay_rmse = (kay_mse / trials as f64).sqrt();
+        let fitz_rmse = (fitz_mse / trials as f64).sqrt();
+        assert!(kay_rmse < 2.0 * crb, "Kay {} vs CRB {}", kay_rmse, crb);
+        assert!(fitz_rmse < 2.0 * crb, "Fitz {} vs CRB {}", fitz_rmse, crb);
+
+        // Kay covers the whole band; Fitz does with few enough lags.
+        let fast = noisy_tone(1500.0, len, 30.0, 3);
+        assert!((kay_frequency(&fast, FS).unwrap() - 1500.0).abs() < 1.0);
+        assert!((fitz_frequency(&fast, 2, FS).unwrap() - 1500.0).abs() < 1.0);
+
+        assert!(kay_frequency(&fast[..1], FS).is_err());
+        assert!(fitz_frequency(&fast[..4], 4, FS).is_err());
+        assert!(fitz_frequency(&fast, 0, FS).is_err());
+        assert!(fft_peak_frequency(&fast, FS, 100.0, 50.0, PeakInterpolation::Jacobsen).is_err());
+    }
+}
@@ -3,12 +3,20 @@
 //! This crate provides fundamental DSP operations, sample buffers,
 //! resampling, filtering, and FFT wrappers for OpenHam digital modes.
 
+pub mod agc;
 pub mod buffer;
 pub mod channel;
+pub mod design;
+pub mod detector;
 pub mod filter;
 pub mod fft;
+pub mod flowgraph;
+pub mod frequency;
+pub mod hilbert;
 pub mod metrics;
+pub mod nco;
 pub mod resample;
+pub mod scenario;
 pub mod error;
 
 pub use error::{CoreError, Result};
@@ -16,19 +24,40 @@ pub use error::{CoreError, Result};
 /// Re-export commonly used types
 pub mod prelude {
     pub use crate::{
-        buffer::{SampleBuffer, ComplexBuffer},
+        agc::{Agc, AgcConfig},
+        buffer::{ring_buffer, Complex32, Float, RingConsumer, RingProducer, SampleBuffer, ComplexBuffer, Sample, StreamWindow},
         channel::{
             add_awgn_ebn0, add_awgn_real_snr, add_awgn_snr, apply_freq_offset, apply_multipath,
-            apply_phase_offset, awgn_at_snr, measure_snr_db, signal_power, Awgn, Channel,
-            ChannelChain, FreqOffset, Multipath, MultipathTap, PhaseOffset,
+            apply_phase_offset, apply_timing_offset, awgn_at_snr, measure_snr_db, signal_power,
+            AdjacentChannel, Amplifier, AmplifierModel, Awgn, Channel, ChannelChain, Clipper,
+            Clipping, CwInterferer, DcOffset, FreqOffset, FrequencyDrift, ImpulseModel,
+            ImpulseNoise, IqImbalance, Multipath, MultipathTap, PhaseOffset, SampleRateOffset,
+            TimingOffset, Watterson, WattersonPath, WattersonPreset,
         },
-        filter::{Filter, FirFilter, IirFilter},
-        fft::{FftProcessor, FftConfig},
+        detector::{DetectorConfig, SignalDetector, SignalSegment},
+        design::{
+            fir_complex_bandpass, fir_equiripple, fir_window, iir_design, BandType, IirPrototype,
+            Window,
+        },
+        filter::{Biquad, FastFirFilter, Filter, FirFilter, IirFilter, SosFilter},
+        fft::{welch, FftProcessor, FftConfig, Spectrogram, SpectrogramConfig, Spectrum},
+        flowgraph::{
+            Block, ChannelBlock, CollectSink, FilterBlock, Flowgraph, FnBlock, Port, Sink, Source,
+            StopHandle, VecSource,
+        },
+        frequency::{
+            fft_peak_frequency, fitz_frequency, kay_frequency, mth_power_frequency,
+            PeakInterpolation,
+        },
+        hilbert::{analytic_signal, HilbertTransformer},
         metrics::{
             bpsk_ber_theory, count_bit_errors, ebn0_sweep, ebn0_sweep_to_csv, is_frame_error,
-            snr_sweep, sweep_to_csv, BitErrors, Ebn0Point, SweepPoint,
+            snr_sweep, sweep_to_csv, wilson_interval, BitErrors, Ebn0Point, Fading, SweepPoint,
+            TheoryModulation, Z_95,
         },
+        nco::{Ddc, Nco},
         resample::Resampler,
+        scenario::{ChannelScenario, ChannelStage},
         error::{CoreError, Result},
     };
 }
@@ -9,6 +9,7 @@ use crate::buffer::Complex;
 use crate::channel::{add_awgn_ebn0, add_awgn_snr};
 use rand::rngs::StdRng;
 use rand::SeedableRng;
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
 /// A tally of bit errors over some number of transmitted bits.
 #[derive(Debug, Clone, Copy, Default)]
@@ -49,6 +50,27 @@ pub fn is_frame_error(tx: &[u8], rx: &[u8]) -> bool {
     tx != rx
 }
 
+/// Two-sided 95% standard normal quantile, for [`wilson_interval`].
+pub const Z_95: f64 = 1.959_963_984_540_054;
+
+/// Wilson score interval for an error rate estimated from `errors` out of
+/// `trials` (use [`Z_95`] for a 95% interval). Unlike the normal
+/// approximation it stays inside `[0, 1]` and is still informative when no
+/// errors were seen. Zero trials yields the uninformative `(0.0, 1.0)`.
+pub fn wilson_interval(errors: usize, trials: usize, z: f64) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+    let n = trials as f64;
+    let p = errors as f64 / n;
+    let z2 = z * z;
+    let denom = 1.0 + z2 / n;
+    let center = (p + z2 / (2.0 * n)) / denom;
+    let half = z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denom;
+    // The interval always contains `p`; clamping only absorbs rounding.
+    ((center - half).clamp(0.0, p), (center + half).clamp(p, 1.0))
+}
+
 /// One point on a BER/FER-vs-SNR curve.
 #[derive(Debug, Clone, Copy)]
 pub struct SweepPoint {
@@ -106,12 +128,15 @@ pub struct Ebn0Point {
     pub ebn0_db: f64,
     pub ber: f64,
     pub fer: f64,
+    /// AWGN theoretical BER of the swept modulation at this Eb/N0.
+    pub theory_ber: f64,
     pub trials: usize,
 }
 
 /// Run a BER/FER sweep over Eb/N0 (dB). `bits_per_symbol` and
 /// `samples_per_symbol` describe the modulation so the noise is calibrated
-/// independently of oversampling, making results comparable to theory.
+/// independently of oversampling, making results comparable to theory;
+/// `theory` selects the reference curve carried in each point.
 #[allow(clippy::too_many_arguments)]
 pub fn ebn0_sweep(
     tx_bytes: &[u8],
@@ -119,6 +144,7 @@ pub fn ebn0_sweep(
     ebn0_db_points: &[f64],
     bits_per_symbol: f64,
     samples_per_symbol: f64,
+    theory: TheoryModulation,
     trials: usize,
     seed: u64,
     mut decode: impl FnMut(&[Complex]) -> Vec<u8>,
@@ -149,6 +175,7 @@ pub fn ebn0_sweep(
             ebn0_db,
             ber: bit_errs.ber(),
             fer: frame_errs as f64 / trials.max(1) as f64,
+            theory_ber: theory.ber(ebn0_db, Fading::Awgn),
             trials,
         });
     }
@@ -178,6 +205,177 @@ fn erfc(x: f64) -> f64 {
     1.0 - sign * y
 }
 
+/// Modulations with a theoretical error-rate reference, for labelling sweeps
+/// and spotting implementation loss. All formulas assume Gray coding where
+/// it applies and ideal detection of the stated kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TheoryModulation {
+    /// Coherent BPSK.
+    Bpsk,
+    /// Coherent M-PSK; `Psk(4)` is QPSK. `M` must be a power of two.
+    Psk(usize),
+    /// Differentially coherent BPSK.
+    Dpsk,
+    /// Differentially coherent QPSK.
+    Dqpsk,
+    /// Coherently detected orthogonal binary FSK.
+    CoherentFsk,
+    /// Envelope (non-coherently) detected orthogonal binary FSK.
+    NoncoherentFsk,
+    /// Square M-QAM: 4, 16, 64, 256 or 1024 points.
+    Qam(usize),
+}
+
+/// Channel the theoretical error rate is averaged over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fading {
+    /// Static channel, additive white Gaussian noise only.
+    #[default]
+    Awgn,
+    /// Flat Rayleigh fading; Eb/N0 is then the average over the fades.
+    Rayleigh,
+}
+
+/// Subintervals for the finite-range integrals below (midpoint rule; the
+/// integrands are smooth, so this is accurate to well under 0.1%).
+const THEORY_STEPS: usize = 1000;
+
+impl TheoryModulation {
+    /// Bits carried per symbol. Panics if the constellation size is invalid.
+    pub fn bits_per_symbol(self) -> f64 {
+        match self {
+            TheoryModulation::Bpsk
+            | TheoryModulation::Dpsk
+            | TheoryModulation::CoherentFsk
+            | TheoryModulation::NoncoherentFsk => 1.0,
+            TheoryModulation::Dqpsk => 2.0,
+            TheoryModulation::Psk(m) => {
+                assert!(m >= 2 && m.is_power_of_two(), "invalid PSK order {m}");
+                m.trailing_zeros() as f64
+            }
+            TheoryModulation::Qam(m) => {
+                assert!(
+                    m >= 4 && m.is_power_of_two() && m.trailing_zeros() % 2 == 0,
+                    "invalid square QAM order {m}"
+                );
+                m.trailing_zeros() as f64
+            }
+        }
+    }
+
+    /// Theoretical bit error rate at `ebn0_db`.
+    pub fn ber(self, ebn0_db: f64, fading: Fading) -> f64 {
+        let k = self.bits_per_symbol();
+        let gb = 10f64.powf(ebn0_db / 10.0);
+        match self {
+            TheoryModulation::Bpsk | TheoryModulation::Psk(2) => q_avg(2.0 * gb, fading),
+            TheoryModulation::Psk(m) => {
+                // Lu et al.'s Gray-code approximation; exact for QPSK.
+                let gs = k * gb;
+                let terms: f64 = (1..=(m / 4).max(1))
+                    .map(|i| {
+                        let s = ((2 * i - 1) as f64 * PI / m as f64).sin();
+                        q_avg(2.0 * gs * s * s, fading)
+                    })
+                    .sum();
+                (2.0 / k * terms).min(0.5)
+            }
+            TheoryModulation::Dpsk => 0.5 * exp_avg(gb, fading),
+            TheoryModulation::Dqpsk => {
+                // Simon & Alouini's single-integral form of
+                // Q1(a, b) - I0(ab) exp(-(a^2 + b^2) / 2) / 2.
+                let a2 = 2.0 * gb * (1.0 - FRAC_1_SQRT_2);
+                let b2 = 2.0 * gb * (1.0 + FRAC_1_SQRT_2);
+                let z = (a2 / b2).sqrt();
+                integrate(-PI, PI, |theta| {
+                    let d = 1.0 + 2.0 * z * theta.sin() + z * z;
+                    (1.0 - z * z) / d * exp_avg(b2 / 2.0 * d, fading)
+                }) / (4.0 * PI)
+            }
+            TheoryModulation::CoherentFsk => q_avg(gb, fading),
+            TheoryModulation::NoncoherentFsk => 0.5 * exp_avg(gb / 2.0, fading),
+            TheoryModulation::Qam(m) => {
+                // Gray-coded square QAM as two sqrt(M)-PAM rails.
+                let m = m as f64;
+                let rail = m.sqrt();
+                let terms: f64 = (1..=(rail as usize / 2))
+                    .map(|i| {
+                        let d = (2 * i - 1) as f64;
+                        q_avg(d * d * 3.0 * k * gb / (m - 1.0), fading)
+                    })
+                    .sum();
+                (4.0 / k * (1.0 - 1.0 / rail) * terms).min(0.5)
+            }
+        }
+    }
+
+    /// Theoretical symbol error rate at `ebn0_db`.
+    pub fn ser(self, ebn0_db: f64, fading: Fading) -> f64 {
+        let k = self.bits_per_symbol();
+        let gb = 10f64.powf(ebn0_db / 10.0);
+        let gs = k * gb;
+        match self {
+            TheoryModulation::Bpsk | TheoryModulation::Psk(2) => q_avg(2.0 * gs, fading),
+            TheoryModulation::Psk(m) => {
+                // Craig's form: exact for any M.
+                let s = (PI / m as f64).sin();
+                let top = (m - 1) as f64 * PI / m as f64;
+                integrate(0.0, top, |theta| {
+                    exp_avg(gs * s * s / theta.sin().powi(2), fading)
+                }) / PI
+            }
+            TheoryModulation::Dpsk => 0.5 * exp_avg(gs, fading),
+            TheoryModulation::Dqpsk => {
+                // Pawula's form for M-DPSK with M = 4.
+                let (s, c) = (PI / 4.0).sin_cos();
+                integrate(0.0, 3.0 * PI / 4.0, |theta| {
+                    exp_avg(gs * s * s / (1.0 + c * theta.cos()), fading)
+                }) / PI
+            }
+            TheoryModulation::CoherentFsk | TheoryModulation::NoncoherentFsk => {
+                self.ber(ebn0_db, fading)
+            }
+            TheoryModulation::Qam(m) => {
+                // 1 - (1 - p)^2 with p = 2 (1 - 1/sqrt(M)) Q(x); the Q^2 term
+                // uses Craig's form so it can be averaged over fading too.
+                let c = 1.0 - 1.0 / (m as f64).sqrt();
+                let x2 = 3.0 * gs / (m as f64 - 1.0);
+                let q2 = integrate(0.0, PI / 4.0, |theta| {
+                    exp_avg(x2 / (2.0 * theta.sin().powi(2)), fading)
+                }) / PI;
+                4.0 * c * q_avg(x2, fading) - 4.0 * c * c * q2
+            }
+        }
+    }
+}
+
+/// `Q(sqrt(x2))`, averaged over the fading distribution of the SNR term.
+fn q_avg(x2: f64, fading: Fading) -> f64 {
+    match fading {
+        Fading::Awgn => 0.5 * erfc((x2 / 2.0).sqrt()),
+        Fading::Rayleigh => 0.5 * (1.0 - (x2 / (2.0 + x2)).sqrt()),
+    }
+}
+
+/// `exp(-x)`, averaged over the fading distribution of the SNR term (the
+/// Rayleigh case is its moment-generating function).
+fn exp_avg(x: f64, fading: Fading) -> f64 {
+    match fading {
+        Fading::Awgn => (-x).exp(),
+        Fading::Rayleigh => 1.0 / (1.0 + x),
+    }
+}
+
+/// Midpoint-rule integral of `f` over `[a, b]`, avoiding the endpoints where
+/// Craig-form integrands are singular.
+fn integrate(a: f64, b: f64, f: impl Fn(f64) -> f64) -> f64 {
+    let h = (b - a) / THEORY_STEPS as f64;
+    (0..THEORY_STEPS)
+        .map(|i| f(a + (i as f64 + 0.5) * h))
+        .sum::<f64>()
+        * h
+}
+
 /// Render sweep points as CSV (header included).
 pub fn sweep_to_csv(points: &[SweepPoint]) -> String {
     let mut s = String::from("snr_db,ber,fer,trials\n");
@@ -190,18 +388,14 @@ pub fn sweep_to_csv(points: &[SweepPoint]) -> String {
     s
 }
 
-/// Render Eb/N0 sweep points as CSV, including the theoretical BPSK BER for
-/// reference (header included).
+/// Render Eb/N0 sweep points as CSV, including the theoretical BER of the
+/// swept modulation for reference (header included).
 pub fn ebn0_sweep_to_csv(points: &[Ebn0Point]) -> String {
-    let mut s = String::from("ebn0_db,ber,fer,bpsk_theory_ber,trials\n");
+    let mut s = String::from("ebn0_db,ber,fer,theory_ber,trials\n");
     for p in points {
         s.push_str(&format!(
             "{:.2},{:.6e},{:.6},{:.6e},{}\n",
-            p.ebn0_db,
-            p.ber,
-            p.fer,
-            bpsk_ber_theory(p.ebn0_db),
-            p.trials
+            p.ebn0_db, p.ber, p.fer, p.theory_ber, p.trials
         ));
     }
     s
@@ -241,6 +435,19 @@ mod tests {
         assert!(is_frame_error(b"x", b"y"));
     }
 
+    #[test]
+    fn wilson_interval_known_values() {
+        // 10 errors in 100 trials: the textbook 95% interval is [0.0552, 0.1744].
+        let (lo, hi) = wilson_interval(10, 100, Z_95);
+        assert!((lo - 0.0552).abs() < 1e-4 && (hi - 0.1744).abs() < 1e-4);
+        // No errors still bounds the rate away from zero from above.
+        let (lo, hi) = wilson_interval(0, 100, Z_95);
+        assert!(lo == 0.0 && (hi - 0.0370).abs() < 1e-4);
+        let (lo, hi) = wilson_interval(100, 100, Z_95);
+        assert!((lo - 0.9630).abs() < 1e-4 && hi == 1.0);
+        assert_eq!(wilson_interval(0, 0, Z_95), (0.0, 1.0));
+    }
+
     #[test]
     fn bpsk_theory_matches_known_values() {
         // Classic BPSK AWGN reference points.
@@ -252,6 +459,84 @@ mod tests {
         assert!(bpsk_ber_theory(4.0) > bpsk_ber_theory(8.0));
     }
 
+    #[test]
+    fn theory_matches_known_values() {
+        use TheoryModulation::*;
+        let awgn = |m: TheoryModulation, db| m.ber(db, Fading::Awgn);
+        let rayleigh = |m: TheoryModulation, db| m.ber(db, Fading::Rayleigh);
+
+        // Gray QPSK and 4-QAM have the BPSK bit error rate.
+        for db in [0.0, 6.0, 10.0] {
+            assert!((awgn(Psk(4), db) - bpsk_ber_theory(db)).abs() < 1e-12);
+            assert!((awgn(Qam(4), db) - bpsk_ber_theory(db)).abs() < 1e-12);
+        }
+        assert!((awgn(Dpsk, 10.0) - 0.5 * (-10f64).exp()).abs() < 1e-12);
+        assert!((awgn(NoncoherentFsk, 10.0) - 3.369e-3).abs() < 1e-6);
+        assert!((awgn(CoherentFsk, 10.0) - 7.827e-4).abs() < 1e-6);
+        assert!((awgn(Qam(16), 10.0) - 1.754e-3).abs() < 1e-5);
+        // DQPSK against a Monte Carlo run of the differential detector.
+        assert!((awgn(Dqpsk, 4.0) - 0.0488).abs() < 1e-3);
+        assert!((awgn(Dqpsk, 8.0) - 3.62e-3).abs() < 1e-4);
+        assert!((Dqpsk.ser(8.0, Fading::Awgn) - 7.25e-3).abs() < 2e-4);
+
+        // Rayleigh closed forms.
+        assert!((rayleigh(Bpsk, 10.0) - 0.5 * (1.0 - (10.0f64 / 11.0).sqrt())).abs() < 1e-12);
+        assert!((rayleigh(Dpsk, 10.0) - 1.0 / 22.0).abs() < 1e-12);
+        assert!((rayleigh(NoncoherentFsk, 10.0) - 1.0 / 12.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn theory_symbol_error_rates_are_consistent() {
+        use TheoryModulation::*;
+        // Craig's integral for BPSK-like QPSK rails: 1 - (1 - p)^2.
+        let p = bpsk_ber_theory(6.0);
+        assert!((Psk(4).ser(6.0, Fading::Awgn) - (2.0 * p - p * p)).abs() < 1e-6);
+        assert!((Qam(4).ser(6.0, Fading::Awgn) - (2.0 * p - p * p)).abs() < 1e-6);
+
+        // At high SNR M-PSK SER approaches 2 Q(sqrt(2 Es/N0) sin(pi/M)).
+        let gs = 3.0 * 10f64.powf(1.4);
+        let approx = erfc((gs).sqrt() * (PI / 8.0).sin());
+        let ser = Psk(8).ser(14.0, Fading::Awgn);
+        assert!((ser - approx).abs() / approx < 0.01, "{ser} vs {approx}");
+        // Gray coding: roughly one bit error per symbol error.
+        assert!((Psk(8).ber(14.0, Fading::Awgn) - ser / 3.0).abs() / ser < 0.01);
+
+        let modes = [
+            Bpsk,
+            Psk(8),
+            Psk(16),
+            Dpsk,
+            Dqpsk,
+            CoherentFsk,
+            NoncoherentFsk,
+            Qam(16),
+            Qam(64),
+            Qam(256),
+            Qam(1024),
+        ];
+        for m in modes {
+            // Stop the AWGN curves before they underflow the erfc approximation.
+            for (fading, top) in [(Fading::Awgn, 12), (Fading::Rayleigh, 30)] {
+                let curve: Vec<f64> = (0..=top).map(|db| m.ber(db as f64, fading)).collect();
+                // Dense constellations sit at the 0.5 cap at low SNR.
+                assert!(curve.windows(2).all(|w| w[1] <= w[0]), "{m:?} {fading:?}");
+                assert!(curve[top] < curve[0], "{m:?} {fading:?}");
+                assert!(
+                    curve.iter().all(|&b| b > 0.0 && b <= 0.5),
+                    "{m:?} {fading:?}"
+                );
+                assert!(
+                    m.ser(10.0, fading) >= m.ber(10.0, fading),
+                    "{m:?} {fading:?}"
+                );
+            }
+            // Fading costs SNR, and Rayleigh curves fall only 10x per 10 dB.
+            assert!(m.ber(15.0, Fading::Rayleigh) > m.ber(15.0, Fading::Awgn));
+            let ratio = m.ber(40.0, Fading::Rayleigh) / m.ber(50.0, Fading::Rayleigh);
+            assert!((ratio - 10.0).abs() < 0.5, "{m:?}: {ratio}");
+        }
+    }
+
     /// A 1-sample/symbol real-BPSK link (k=1, sps=1) driven by the Eb/N0 harness
     /// should track the theoretical BPSK curve.
     #[test]
@@ -280,9 +565,20 @@ mod tests {
         };
 
         // k=1, sps=1 -> Eb/N0 == per-sample SNR, directly comparable to theory.
-        let pts = ebn0_sweep(tx, &clean, &[2.0, 6.0], 1.0, 1.0, 4000, 2024, decode);
+        let pts = ebn0_sweep(
+            tx,
+            &clean,
+            &[2.0, 6.0],
+            1.0,
+            1.0,
+            TheoryModulation::Bpsk,
+            4000,
+            2024,
+            decode,
+        );
         for p in &pts {
             let theory = bpsk_ber_theory(p.ebn0_db);
+            assert!((p.theory_ber - theory).abs() < 1e-12);
             // Measured BER should be within a factor of ~2.5 of theory (finite trials).
             assert!(
                 p.ber > theory / 2.5 && p.ber < theory * 2.5 + 1e-4,
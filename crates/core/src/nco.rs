@@ -0,0 +1,337 @@
+//! Numerically controlled oscillator, mixer and digital down-converter
+//!
+//! [`Nco`] is the shared local oscillator for carrier generation and
+//! mixing. [`Ddc`] builds on it: mix a real or complex input down by a
+//! centre frequency, low-pass filter and decimate, producing complex
+//! baseband at a reduced output rate.
+
+use crate::buffer::{Complex, Sample};
+use crate::design::{self, BandType, Window};
+use crate::{CoreError, Result};
+use std::f64::consts::TAU;
+
+/// Samples after which the phase accumulator is folded back into `[0, 2π)`.
+const NCO_REBASE_INTERVAL: u64 = 1 << 20;
+
+/// Stopband attenuation of the DDC anti-alias filter.
+const DDC_ATTENUATION_DB: f64 = 60.0;
+
+/// Numerically controlled oscillator producing `e^{j phase}`
+///
+/// The phase is computed as `offset + increment * n` rather than summed
+/// sample by sample, so long runs do not accumulate rounding drift.
+#[derive(Debug, Clone)]
+pub struct Nco {
+    sample_rate: f64,
+    increment: f64,
+    offset: f64,
+    counter: u64,
+}
+
+impl Nco {
+    /// Create an oscillator at `frequency` Hz (negative frequencies rotate clockwise)
+    pub fn new(frequency: f64, sample_rate: f64) -> Result<Self> {
+        if sample_rate <= 0.0 {
+            return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+        }
+
+        Ok(Self {
+            sample_rate,
+            increment: TAU * frequency / sample_rate,
+            offset: 0.0,
+            counter: 0,
+        })
+    }
+
+    /// Current frequency in Hz
+    pub fn frequency(&self) -> f64 {
+        self.increment * self.sample_rate / TAU
+    }
+
+    /// Change the frequency, keeping the phase continuous
+    pub fn set_frequency(&mut self, frequency: f64) {
+        self.rebase();
+        self.increment = TAU * frequency / self.sample_rate;
+    }
+
+    /// Nudge the frequency by `delta` Hz, e.g. from a frequency-locked loop
+    pub fn adjust_frequency(&mut self, delta: f64) {
+        self.set_frequency(self.frequency() + delta);
+    }
+
+    /// Current phase in radians, in `[0, 2π)`
+    pub fn phase(&self) -> f64 {
+        (self.offset + self.increment * self.counter as f64).rem_euclid(TAU)
+    }
+
+    /// Jump to `radians`
+    pub fn set_phase(&mut self, radians: f64) {
+        self.offset = radians;
+        self.counter = 0;
+    }
+
+    /// Rotate the phase by `radians`, e.g. from a phase-locked loop
+    pub fn adjust_phase(&mut self, radians: f64) {
+        self.rebase();
+        self.offset += radians;
+    }
+
+    /// Oscillator output for the current sample, then advance
+    pub fn step(&mut self) -> Complex {
+        let out = Complex::from_phase(self.offset + self.increment * self.counter as f64);
+        self.counter += 1;
+        if self.counter == NCO_REBASE_INTERVAL {
+            self.rebase();
+        }
+        out
+    }
+
+    /// Shift `input` down by the oscillator frequency
+    pub fn mix_down<T: Sample<Real = f64>>(&mut self, input: T) -> Complex {
+        input.to_complex() * self.step().conj()
+    }
+
+    /// Shift `input` up by the oscillator frequency
+    pub fn mix_up(&mut self, input: Complex) -> Complex {
+        input * self.step()
+    }
+
+    /// Mix a block down to baseband
+    pub fn mix_down_block<T: Sample<Real = f64>>(&mut self, input: &[T]) -> Vec<Complex> {
+        input.iter().map(|&x| self.mix_down(x)).collect()
+    }
+
+    /// Return to zero phase, keeping the frequency
+    pub fn reset(&mut self) {
+        self.offset = 0.0;
+        self.counter = 0;
+    }
+
+    fn rebase(&mut self) {
+        self.offset = self.phase();
+        self.counter = 0;
+    }
+}
+
+/// Digital down-converter: NCO mixer, anti-alias low-pass and decimator
+///
+/// Only every `decimation`-th filter output is computed. Output sample `k`
+/// corresponds to input sample `k * decimation`, delayed by
+/// [`group_delay`](Ddc::group_delay).
+#[derive(Debug, Clone)]
+pub struct Ddc {
+    nco: Nco,
+    taps: Vec<f64>,
+    history: Vec<Complex>,
+    index: usize,
+    decimation: usize,
+    phase: usize,
+    sample_rate: f64,
+}
+
+impl Ddc {
+    /// Create a down-converter centred on `center_frequency` Hz that keeps
+    /// `-bandwidth..bandwidth` Hz around it and decimates by `decimation`
+    ///
+    /// `bandwidth` must be below half the output rate. The anti-alias filter
+    /// is a Kaiser design whose stopband starts where aliases would fold
+    /// back into the kept band.
+    pub fn new(
+        center_frequency: f64,
+        sample_rate: f64,
+        decimation: usize,
+        bandwidth: f64,
+    ) -> Result<Self> {
+        if sample_rate <= 0.0 {
+            return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+        }
+        if decimation == 0 {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: "Decimation factor must be greater than 0".to_string(),
+            });
+        }
+        let output_rate = sample_rate / decimation as f64;
+        if bandwidth <= 0.0 || bandwidth >= output_rate / 2.0 {
+            return Err(CoreError::InvalidFilterParameters {
+                msg: format!(
+                    "DDC bandwidth {} Hz must be below half the output rate ({} Hz)",
+                    bandwidth, output_rate
+                ),
+            });
+        }
+
+        let stop = (output_rate - bandwidth).min(sample_rate / 2.0);
+        let (num_taps, beta) =
+            design::kaiser_parameters(DDC_ATTENUATION_DB, stop - bandwidth, sample_rate)?;
+        let taps = design::fir_window(
+            BandType::Lowpass((bandwidth + stop) / 2.0),
+            sample_rate,
+            num_taps,
+            Window::Kaiser(beta),
+        )?;
+
+        Ok(Self {
+            nco: Nco::new(center_frequency, sample_rate)?,
+            history: vec![Complex::default(); taps.len()],
+            taps,
+            index: 0,
+            decimation,
+            phase: 0,
+            sample_rate,
+        })
+    }
+
+    /// Output sample rate in Hz
+    pub fn output_rate(&self) -> f64 {
+        self.sample_rate / self.decimation as f64
+    }
+
+    /// Decimation factor
+    pub fn decimation(&self) -> usize {
+        self.decimation
+    }
+
+    /// Delay of the anti-alias filter in input samples
+    pub fn group_delay(&self) -> f64 {
+        (self.taps.len() - 1) as f64 / 2.0
+    }
+
+    /// The mixing oscillator, for retuning or frequency tracking
+    pub fn nco_mut(&mut self) -> &mut Nco {
+        &mut self.nco
+    }
+
+    /// Down-convert a block; state carries over between calls
+    pub fn process<T: Sample<Real = f64>>(&mut self, input: &[T]) -> Vec<Complex> {
+        let len = self.history.len();
+        let mut output = Vec::with_capacity(input.len() / self.decimation + 1);
+        for &x in input {
+            self.history[self.index] = self.nco.mix_down(x);
+            if self.phase == 0 {
+                let mut acc = Complex::default();
+                for (k, &tap) in self.taps.iter().enumerate() {
+                    acc = acc + self.history[(self.index + len - k) % len] * tap;
+                }
+                output.push(acc);
+            }
+            self.index = (self.index + 1) % len;
+            self.phase = (self.phase + 1) % self.decimation;
+        }
+        output
+    }
+
+    /// Push zeros through the filter so the tail of the last block, held
+    /// back by the group delay, comes out
+    pub fn flush(&mut self) -> Vec<Complex> {
+        let outputs = (self.group_delay() / self.decimation as f64).ceil() as usize;
+        let pending = (self.decimation - self.phase) % self.decimation;
+        self.process(&vec![0.0; pending + outputs * self.decimation])
+    }
+
+    /// Clear the filter history and return the oscillator to zero phase
+    pub fn reset(&mut self) {
+        self.nco.reset();
+        self.history.fill(Complex::default());
+        self.index = 0;
+        self.phase = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_nco_frequency_and_phase_control() {
+        let mut nco = Nco::new(1000.0, 8000.0).unwrap();
+        for n in 0..100 {
+            let expected = Complex::from_phase(2.0 * PI * 1000.0 * n as f64 / 8000.0);
+            assert!((nco.step() - expected).magnitude() < 1e-12);
+        }
+
+        // Frequency change keeps the phase continuous.
+        let before = nco.phase();
+        nco.set_frequency(-500.0);
+        assert!((nco.phase() - before).abs() < 1e-12);
+        assert!((nco.frequency() + 500.0).abs() < 1e-9);
+        let first = nco.step();
+        let second = nco.step();
+        assert!(((second * first.conj()).phase() + 2.0 * PI * 500.0 / 8000.0).abs() < 1e-12);
+
+        nco.set_phase(1.0);
+        nco.adjust_phase(0.5);
+        assert!((nco.phase() - 1.5).abs() < 1e-12);
+        nco.reset();
+        assert!(nco.phase().abs() < 1e-12);
+        assert!(Nco::new(1000.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_nco_mixing_round_trip() {
+        let mut up = Nco::new(1500.0, 48000.0).unwrap();
+        let mut down = Nco::new(1500.0, 48000.0).unwrap();
+        let x = Complex::new(0.3, -0.7);
+        for _ in 0..1000 {
+            let y = down.mix_down(up.mix_up(x));
+            assert!((y - x).magnitude() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_nco_long_run_stays_on_frequency() {
+        // Run past the rebase interval and check the phase against the
+        // closed-form value.
+        let mut nco = Nco::new(1234.5, 48000.0).unwrap();
+        let n = NCO_REBASE_INTERVAL + 1000;
+        for _ in 0..n {
+            nco.step();
+        }
+        let expected = (2.0 * PI * 1234.5 * n as f64 / 48000.0).rem_euclid(TAU);
+        assert!((nco.phase() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ddc_shifts_and_decimates() {
+        let fs = 48000.0;
+        let mut ddc = Ddc::new(6000.0, fs, 6, 1000.0).unwrap();
+        assert_eq!(ddc.output_rate(), 8000.0);
+
+        // Real tone 300 Hz above the centre plus an interferer 8 kHz above
+        // it; the tone's negative-frequency image lands at -12.3 kHz.
+        let input: Vec<f64> = (0..48000)
+            .map(|n| {
+                let t = n as f64 / fs;
+                (2.0 * PI * 6300.0 * t).cos() + (2.0 * PI * 14000.0 * t).cos()
+            })
+            .collect();
+        let mut out = ddc.process(&input[..20000]);
+        out.extend(ddc.process(&input[20000..]));
+        assert_eq!(out.len(), 8000);
+
+        // Steady state: a 300 Hz complex exponential of amplitude 1/2 (the
+        // real cosine's positive-frequency half), delayed by the filter.
+        let delay = ddc.group_delay();
+        for (k, y) in out.iter().enumerate().skip(1000) {
+            let t = (k as f64 * 6.0 - delay) / fs;
+            let expected = Complex::from_phase(2.0 * PI * 300.0 * t) * 0.5;
+            assert!((*y - expected).magnitude() < 2e-3, "k={} {:?}", k, y);
+        }
+
+        assert!(Ddc::new(1500.0, fs, 6, 4000.0).is_err());
+        assert!(Ddc::new(1500.0, fs, 0, 1000.0).is_err());
+    }
+
+    #[test]
+    fn test_ddc_flush_emits_delayed_tail() {
+        let mut ddc = Ddc::new(0.0, 8000.0, 4, 300.0).unwrap();
+        let delay = ddc.group_delay();
+        // Input time that output sample `len - 1` lines up with
+        let represented = |len: usize| 4.0 * (len - 1) as f64 - delay;
+
+        let mut out = ddc.process(&[1.0; 400]);
+        assert!(represented(out.len()) < 396.0);
+        out.extend(ddc.flush());
+        assert!(represented(out.len()) >= 396.0 && represented(out.len()) < 400.0);
+    }
+}
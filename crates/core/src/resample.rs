@@ -1,17 +1,54 @@
 //! Sample rate conversion and resampling
+//!
+//! [`Resampler`] is a windowed-sinc polyphase interpolator. The prototype
+//! lowpass is stored as a table of `PHASES` branches and the coefficients for
+//! an arbitrary fractional position are linearly interpolated between the two
+//! nearest branches (a first-order Farrow structure), so any ratio — including
+//! irrational and slowly drifting ones such as 44.1 kHz soundcards feeding a
+//! 48 kHz modem — is handled without a rational approximation. When
+//! decimating, the kernel is stretched so the cutoff follows the output
+//! Nyquist frequency and out-of-band energy does not alias.
+//!
+//! State (input history and fractional phase) is kept between
+//! [`Resampler::process`] calls, so a stream fed in arbitrary chunks produces
+//! exactly the same output as the same stream fed in one block.
 
+use crate::buffer::{Float, Sample};
+use crate::design::bessel_i0;
 use crate::{CoreError, Result};
+use std::f64::consts::PI;
 
-/// Sample rate converter/resampler
-pub struct Resampler {
+/// Zero crossings of the prototype sinc on each side of the centre, at unity
+/// ratio. Decimation widens the kernel proportionally.
+const HALF_TAPS: usize = 16;
+
+/// Number of stored polyphase branches.
+const PHASES: usize = 128;
+
+/// Passband edge as a fraction of the lower of the two Nyquist frequencies.
+const CUTOFF: f64 = 0.9;
+
+/// Kaiser window shape (~80 dB stopband).
+const KAISER_BETA: f64 = 8.0;
+
+/// Streaming arbitrary-ratio sample rate converter, filtering at the
+/// precision of its samples
+pub struct Resampler<T: Sample = f64> {
     input_rate: f64,
     output_rate: f64,
     ratio: f64,
-    buffer: Vec<f64>,
+    /// Kernel half-width in input samples.
+    half_width: usize,
+    /// `(PHASES + 1) * 2 * half_width` coefficients, one row per branch.
+    table: Vec<T::Real>,
+    buffer: Vec<T>,
+    /// Integer part of the next output position, as an index into `buffer`.
+    index: usize,
+    /// Fractional part of the next output position, in `[0, 1)`.
     phase: f64,
 }
 
-impl Resampler {
+impl<T: Sample> Resampler<T> {
     /// Create a new resampler
     pub fn new(input_rate: f64, output_rate: f64) -> Result<Self> {
         if input_rate <= 0.0 {
@@ -20,84 +57,188 @@ impl Resampler {
         if output_rate <= 0.0 {
             return Err(CoreError::InvalidSampleRate { rate: output_rate });
         }
-        
+
         let ratio = input_rate / output_rate;
-        
-        Ok(Self {
+        // Scale the cutoff down when decimating so it tracks the output band.
+        let scale = (1.0 / ratio).min(1.0);
+        let half_width = (HALF_TAPS as f64 / scale).ceil() as usize;
+        let table = design_table(half_width, scale)
+            .into_iter()
+            .map(T::Real::narrow)
+            .collect();
+
+        let mut resampler = Self {
             input_rate,
             output_rate,
             ratio,
+            half_width,
+            table,
             buffer: Vec::new(),
+            index: 0,
             phase: 0.0,
-        })
+        };
+        resampler.reset();
+        Ok(resampler)
     }
-    
+
     /// Get the input sample rate
     pub fn input_rate(&self) -> f64 {
         self.input_rate
     }
-    
+
     /// Get the output sample rate
     pub fn output_rate(&self) -> f64 {
         self.output_rate
     }
-    
-    /// Get the resampling ratio
+
+    /// Get the resampling ratio (input samples per output sample)
     pub fn ratio(&self) -> f64 {
         self.ratio
     }
-    
-    /// Process a buffer of samples with linear interpolation
-    pub fn process(&mut self, input: &[f64], output: &mut Vec<f64>) -> Result<()> {
+
+    /// Change the ratio on the fly, e.g. to track a drifting soundcard clock.
+    /// The anti-alias filter keeps its original design, so this is meant for
+    /// small adjustments around the rate given to [`Resampler::new`].
+    pub fn set_ratio(&mut self, ratio: f64) -> Result<()> {
+        if !(ratio > 0.0 && ratio.is_finite()) {
+            return Err(CoreError::ResampleError {
+                msg: format!("Invalid resampling ratio: {}", ratio),
+            });
+        }
+        self.ratio = ratio;
+        self.output_rate = self.input_rate / ratio;
+        Ok(())
+    }
+
+    /// Resample a chunk of a stream. `output` is cleared and receives every
+    /// output sample whose kernel is fully covered by the input so far; the
+    /// remainder is produced by later calls (or by [`Resampler::flush`]).
+    pub fn process(&mut self, input: &[T], output: &mut Vec<T>) -> Result<()> {
         output.clear();
-        
-        // Add new input samples to buffer
         self.buffer.extend_from_slice(input);
-        
-        // Generate output samples
-        while self.phase < self.buffer.len() as f64 - 1.0 {
-            let index = self.phase as usize;
-            let frac = self.phase - index as f64;
-            
-            // Linear interpolation between samples
-            let sample = if index + 1 < self.buffer.len() {
-                self.buffer[index] * (1.0 - frac) + self.buffer[index + 1] * frac
-            } else {
-                self.buffer[index]
-            };
-            
-            output.push(sample);
-            self.phase += self.ratio;
-        }
-        
-        // Remove consumed samples from buffer
-        let consumed = self.phase as usize;
-        if consumed > 0 {
-            self.buffer.drain(0..consumed.min(self.buffer.len()));
-            self.phase -= consumed as f64;
-        }
-        
+        self.drain_ready(self.buffer.len(), output);
         Ok(())
     }
-    
+
+    /// End the stream: emit the outputs still waiting on look-ahead (as if the
+    /// input were followed by silence) and reset for a new stream.
+    pub fn flush(&mut self, output: &mut Vec<T>) -> Result<()> {
+        output.clear();
+        let end = self.buffer.len();
+        self.buffer.resize(end + self.half_width, T::default());
+        self.drain_ready(end, output);
+        self.reset();
+        Ok(())
+    }
+
+    /// Convenience for whole-buffer conversion: process then flush.
+    pub fn process_all(&mut self, input: &[T]) -> Result<Vec<T>> {
+        let mut output = Vec::with_capacity(self.output_length(input.len()));
+        self.process(input, &mut output)?;
+        let mut tail = Vec::new();
+        self.flush(&mut tail)?;
+        output.extend(tail);
+        Ok(output)
+    }
+
     /// Reset the resampler state
     pub fn reset(&mut self) {
+        // Pre-roll with silence so the first output lines up with the first
+        // input sample instead of being delayed by the kernel half-width.
         self.buffer.clear();
+        self.buffer.resize(self.half_width - 1, T::default());
+        self.index = self.half_width - 1;
         self.phase = 0.0;
     }
-    
+
     /// Calculate the expected output length for a given input length
     pub fn output_length(&self, input_length: usize) -> usize {
         ((input_length as f64 / self.ratio).ceil() as usize).max(1)
     }
+
+    /// Emit outputs positioned before `end` whose kernel lies inside the
+    /// buffer, then discard input no longer needed.
+    fn drain_ready(&mut self, end: usize, output: &mut Vec<T>) {
+        let taps = 2 * self.half_width;
+        // The tolerance absorbs rounding in the accumulated phase, so an output
+        // landing exactly on `end` is not emitted one sample early.
+        let end = end as f64 - 1e-9;
+        while (self.index as f64 + self.phase) < end
+            && self.index + self.half_width < self.buffer.len()
+        {
+            let p = self.phase * PHASES as f64;
+            let branch = (p as usize).min(PHASES - 1);
+            let frac = T::Real::narrow(p - branch as f64);
+            let lo = &self.table[branch * taps..(branch + 1) * taps];
+            let hi = &self.table[(branch + 1) * taps..(branch + 2) * taps];
+            let start = self.index + 1 - self.half_width;
+
+            let mut acc = T::default();
+            for (k, &x) in self.buffer[start..start + taps].iter().enumerate() {
+                acc = acc + x * (lo[k] + frac * (hi[k] - lo[k]));
+            }
+            output.push(acc);
+
+            self.phase += self.ratio;
+            let whole = self.phase.floor();
+            self.index += whole as usize;
+            self.phase -= whole;
+        }
+
+        let consumed = (self.index + 1).saturating_sub(self.half_width);
+        let consumed = consumed.min(self.buffer.len());
+        if consumed > 0 {
+            self.buffer.drain(0..consumed);
+            self.index -= consumed;
+        }
+    }
+}
+
+/// Build the polyphase table. Row `p` holds the kernel sampled at offsets
+/// `p / PHASES + half_width - 1 - k` for `k` in `0..2 * half_width`; an extra
+/// row `PHASES` (one full sample later) lets the last branch interpolate.
+fn design_table(half_width: usize, scale: f64) -> Vec<f64> {
+    let taps = 2 * half_width;
+    let fc = CUTOFF * scale;
+    let mut table = Vec::with_capacity((PHASES + 1) * taps);
+    for p in 0..=PHASES {
+        let row_start = table.len();
+        for k in 0..taps {
+            let x = p as f64 / PHASES as f64 + half_width as f64 - 1.0 - k as f64;
+            table.push(kaiser_sinc(x, half_width, fc));
+        }
+        // Unity DC gain on every branch, so a constant input stays constant.
+        let row = &mut table[row_start..];
+        let sum: f64 = row.iter().sum();
+        for c in row.iter_mut() {
+            *c /= sum;
+        }
+    }
+    table
+}
+
+/// Kaiser-windowed sinc lowpass with cutoff `fc` (as a fraction of Nyquist),
+/// evaluated `x` samples from the centre. The window spans `half_width`
+/// samples on each side and the kernel is zero outside it.
+pub(crate) fn kaiser_sinc(x: f64, half_width: usize, fc: f64) -> f64 {
+    let r = x / half_width as f64;
+    if r.abs() >= 1.0 {
+        return 0.0;
+    }
+    let sinc = if x == 0.0 {
+        1.0
+    } else {
+        (PI * fc * x).sin() / (PI * fc * x)
+    };
+    let window = bessel_i0(KAISER_BETA * (1.0 - r * r).sqrt()) / bessel_i0(KAISER_BETA);
+    fc * sinc * window
 }
 
 /// Rational resampler for exact integer ratios
 pub struct RationalResampler {
     upsampling_factor: usize,
     downsampling_factor: usize,
-    buffer: Vec<f64>,
-    phase: usize,
+    inner: Resampler<f64>,
 }
 
 impl RationalResampler {
@@ -113,15 +254,14 @@ impl RationalResampler {
                 msg: "Downsampling factor must be greater than 0".to_string(),
             });
         }
-        
+
         Ok(Self {
             upsampling_factor,
             downsampling_factor,
-            buffer: Vec::new(),
-            phase: 0,
+            inner: Resampler::new(downsampling_factor as f64, upsampling_factor as f64)?,
         })
     }
-    
+
     /// Create a resampler from sample rates (finds best rational approximation)
     pub fn from_rates(input_rate: f64, output_rate: f64, max_factor: usize) -> Result<Self> {
         if input_rate <= 0.0 {
@@ -130,38 +270,32 @@ impl RationalResampler {
         if output_rate <= 0.0 {
             return Err(CoreError::InvalidSampleRate { rate: output_rate });
         }
-        
+
         let ratio = output_rate / input_rate;
         let (up, down) = rational_approximation(ratio, max_factor);
-        
+
         Self::new(up, down)
     }
-    
-    /// Process samples with exact rational resampling
+
+    /// Get the upsampling factor
+    pub fn upsampling_factor(&self) -> usize {
+        self.upsampling_factor
+    }
+
+    /// Get the downsampling factor
+    pub fn downsampling_factor(&self) -> usize {
+        self.downsampling_factor
+    }
+
+    /// Process samples at exactly `up / down` times the input rate, with the
+    /// same anti-aliasing and streaming behaviour as [`Resampler`].
     pub fn process(&mut self, input: &[f64], output: &mut Vec<f64>) -> Result<()> {
-        output.clear();
-        
-        for &sample in input {
-            // Upsample by inserting zeros
-            for i in 0..self.upsampling_factor {
-                let upsampled = if i == 0 { sample } else { 0.0 };
-                
-                // Downsample by taking every Nth sample
-                if self.phase == 0 {
-                    output.push(upsampled);
-                }
-                
-                self.phase = (self.phase + 1) % self.downsampling_factor;
-            }
-        }
-        
-        Ok(())
+        self.inner.process(input, output)
     }
-    
+
     /// Reset the resampler state
     pub fn reset(&mut self) {
-        self.buffer.clear();
-        self.phase = 0;
+        self.inner.reset();
     }
 }
 
@@ -170,41 +304,48 @@ fn rational_approximation(x: f64, max_denominator: usize) -> (usize, usize) {
     if x <= 0.0 {
         return (0, 1);
     }
-    
+
     let mut x = x;
     let mut a = x.floor() as usize;
     let mut p0 = 1;
     let mut q0 = 0;
     let mut p1 = a;
     let mut q1 = 1;
-    
+
     while q1 <= max_denominator && (x - a as f64).abs() > 1e-15 {
         x = 1.0 / (x - a as f64);
         a = x.floor() as usize;
-        
+
         let p2 = a * p1 + p0;
         let q2 = a * q1 + q0;
-        
+
         if q2 > max_denominator {
             break;
         }
-        
+
         p0 = p1;
         q0 = q1;
         p1 = p2;
         q1 = q2;
     }
-    
+
     (p1, q1)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::buffer::Complex;
+
+    fn tone(freq: f64, rate: f64, n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| (2.0 * PI * freq * i as f64 / rate).sin())
+            .collect()
+    }
 
     #[test]
     fn test_resampler_creation() {
-        let resampler = Resampler::new(48000.0, 44100.0).unwrap();
+        let resampler = Resampler::<f64>::new(48000.0, 44100.0).unwrap();
         assert_eq!(resampler.input_rate(), 48000.0);
         assert_eq!(resampler.output_rate(), 44100.0);
         assert!((resampler.ratio() - 48000.0 / 44100.0).abs() < 1e-10);
@@ -222,7 +363,7 @@ mod tests {
         let (num, den) = rational_approximation(1.5, 100);
         assert_eq!(num, 3);
         assert_eq!(den, 2);
-        
+
         let (num, den) = rational_approximation(22050.0 / 48000.0, 1000);
         // Should find a reasonable approximation
         assert!(num > 0 && den > 0);
@@ -234,11 +375,131 @@ mod tests {
         let mut resampler = Resampler::new(2000.0, 1000.0).unwrap(); // 2:1 downsampling
         let input = vec![1.0, 2.0, 3.0, 4.0];
         let mut output = Vec::new();
-        
+
         resampler.process(&input, &mut output).unwrap();
-        
+        resampler.flush(&mut output).unwrap();
+
         // Should produce approximately half the samples
         assert!(!output.is_empty());
         assert!(output.len() <= input.len());
     }
+
+    #[test]
+    fn soundcard_rate_tone_is_reconstructed() {
+        // 1 kHz at 44.1 kHz -> 48 kHz must match the ideal 48 kHz tone.
+        let input = tone(1000.0, 44100.0, 44100);
+        let out = Resampler::new(44100.0, 48000.0)
+            .unwrap()
+            .process_all(&input)
+            .unwrap();
+        assert_eq!(out.len(), 48000);
+        let ideal = tone(1000.0, 48000.0, out.len());
+        // Skip the edges, where the stream starts and ends in silence.
+        let err = out[200..out.len() - 200]
+            .iter()
+            .zip(&ideal[200..])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        assert!(err < 1e-3, "max error {err}");
+    }
+
+    #[test]
+    fn single_precision_tone_is_reconstructed() {
+        let input: Vec<f32> = tone(1000.0, 44100.0, 44100).into_iter().map(|x| x as f32).collect();
+        let out = Resampler::new(44100.0, 48000.0)
+            .unwrap()
+            .process_all(&input)
+            .unwrap();
+        assert_eq!(out.len(), 48000);
+        let ideal = tone(1000.0, 48000.0, out.len());
+        let err = out[200..out.len() - 200]
+            .iter()
+            .zip(&ideal[200..])
+            .map(|(&a, b)| (a as f64 - b).abs())
+            .fold(0.0, f64::max);
+        assert!(err < 1e-3, "max error {err}");
+    }
+
+    #[test]
+    fn chunked_stream_matches_single_block() {
+        let input = tone(700.0, 48000.0, 5000);
+        let mut whole = Resampler::new(48000.0, 44100.0).unwrap();
+        let one_shot = whole.process_all(&input).unwrap();
+
+        let mut streaming = Resampler::new(48000.0, 44100.0).unwrap();
+        let mut chunked = Vec::new();
+        let mut out = Vec::new();
+        for chunk in input.chunks(77) {
+            streaming.process(chunk, &mut out).unwrap();
+            chunked.extend_from_slice(&out);
+        }
+        streaming.flush(&mut out).unwrap();
+        chunked.extend_from_slice(&out);
+        assert_eq!(one_shot, chunked);
+    }
+
+    #[test]
+    fn decimation_rejects_out_of_band_tone() {
+        // 6 kHz does not fit in an 8 kHz output; it must not alias to 2 kHz.
+        let input = tone(6000.0, 48000.0, 48000);
+        let out = Resampler::new(48000.0, 8000.0)
+            .unwrap()
+            .process_all(&input)
+            .unwrap();
+        let power = out[100..out.len() - 100].iter().map(|x| x * x).sum::<f64>()
+            / (out.len() - 200) as f64;
+        assert!(10.0 * power.log10() < -50.0, "aliased power {power}");
+    }
+
+    #[test]
+    fn complex_tone_keeps_frequency_and_amplitude() {
+        let rate_in = 8000.0;
+        let rate_out = 12000.0;
+        let f = 500.0;
+        let input: Vec<Complex> = (0..4000)
+            .map(|i| {
+                let ph = 2.0 * PI * f * i as f64 / rate_in;
+                Complex::new(ph.cos(), ph.sin())
+            })
+            .collect();
+        let out = Resampler::new(rate_in, rate_out)
+            .unwrap()
+            .process_all(&input)
+            .unwrap();
+        for (i, s) in out.iter().enumerate().skip(100).take(5000) {
+            let ph = 2.0 * PI * f * i as f64 / rate_out;
+            let ideal = Complex::new(ph.cos(), ph.sin());
+            assert!((*s - ideal).magnitude() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn ratio_can_drift_while_streaming() {
+        let mut r = Resampler::new(48000.0, 48000.0).unwrap();
+        let input = tone(300.0, 48000.0, 48000);
+        let mut out = Vec::new();
+        let mut total = 0;
+        for (i, chunk) in input.chunks(4800).enumerate() {
+            // Ramp up to a 100 ppm clock error.
+            r.set_ratio(1.0 + 1e-5 * i as f64).unwrap();
+            r.process(chunk, &mut out).unwrap();
+            total += out.len();
+        }
+        assert!(total < 48000 && total > 47900, "produced {total}");
+        assert!(r.set_ratio(0.0).is_err());
+    }
+
+    #[test]
+    fn rational_resampler_output_count() {
+        let mut r = RationalResampler::new(3, 2).unwrap();
+        let mut out = Vec::new();
+        let mut total = 0;
+        for chunk in vec![0.5; 2000].chunks(100) {
+            r.process(chunk, &mut out).unwrap();
+            total += out.len();
+        }
+        // 1.5x the input less the look-ahead still held back.
+        assert!((2970..=3000).contains(&total), "produced {total}");
+        assert!(out.iter().all(|x| (x - 0.5).abs() < 1e-9));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,521 @@
+//! Declarative channel scenarios
+//!
+//! A [`ChannelScenario`] is a list of [`ChannelStage`] descriptions, with all
+//! parameters and seeds, that can be stored as TOML or JSON and rebuilt into
+//! a [`ChannelChain`]. Attaching the file to a bug report makes the channel
+//! exactly replayable:
+//!
+//! ```toml
+//! description = "CCIR moderate, 30 Hz off, 12 dB SNR"
+//!
+//! [[stages]]
+//! type = "watterson"
+//! preset = "ccir_moderate"
+//! seed = 7
+//!
+//! [[stages]]
+//! type = "freq_offset"
+//! freq_hz = 30.0
+//!
+//! [[stages]]
+//! type = "awgn"
+//! snr_db = 12.0
+//! seed = 1
+//! ```
+//!
+//! Stages that need a sample rate take it from [`ChannelScenario::build`]
+//! (or from the WAV file in [`ChannelScenario::apply_to_wav`]), so the same
+//! scenario works at any rate.
+
+use crate::channel::{
+    AdjacentChannel, Amplifier, Awgn, Channel, ChannelChain, Clipper, CwInterferer, DcOffset,
+    FreqOffset, FrequencyDrift, ImpulseModel, ImpulseNoise, IqImbalance, Multipath, PhaseOffset,
+    SampleRateOffset, TimingOffset, Watterson, WattersonPath, WattersonPreset,
+};
+use crate::hilbert::analytic_signal;
+use crate::{CoreError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// One stage of a scenario, mirroring the [`crate::channel`] stage of the
+/// same name. Random stages carry their seed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelStage {
+    /// [`Awgn`] at `snr_db`
+    Awgn {
+        snr_db: f64,
+        seed: u64,
+    },
+    /// [`ImpulseNoise`] at `snr_db` against the total noise power
+    ImpulseNoise {
+        model: ImpulseModel,
+        snr_db: f64,
+        seed: u64,
+    },
+    /// Constant [`FreqOffset`]
+    FreqOffset {
+        freq_hz: f64,
+    },
+    /// Linear [`FrequencyDrift`]
+    FrequencyDrift {
+        initial_offset_hz: f64,
+        rate_hz_per_s: f64,
+    },
+    PhaseOffset(PhaseOffset),
+    Multipath(Multipath),
+    TimingOffset(TimingOffset),
+    /// [`SampleRateOffset`] of `ppm` parts per million
+    SampleRateOffset {
+        ppm: f64,
+    },
+    Clipper(Clipper),
+    Amplifier(Amplifier),
+    DcOffset(DcOffset),
+    IqImbalance(IqImbalance),
+    /// [`Watterson`] channel with one of the standard presets
+    Watterson {
+        preset: WattersonPreset,
+        seed: u64,
+    },
+    /// [`Watterson`] channel with explicit paths
+    WattersonPaths {
+        paths: Vec<WattersonPath>,
+        seed: u64,
+    },
+    /// [`CwInterferer`]
+    CwInterferer {
+        offset_hz: f64,
+        sir_db: f64,
+    },
+    /// [`AdjacentChannel`] playing a WAV recording; a relative path is
+    /// resolved against the scenario file's directory
+    AdjacentChannel {
+        wav: PathBuf,
+        offset_hz: f64,
+        sir_db: f64,
+    },
+}
+
+impl ChannelStage {
+    /// Append the stage this describes to `chain`
+    fn push(&self, chain: ChannelChain, sample_rate: f64, base_dir: &Path) -> Result<ChannelChain> {
+        Ok(match self {
+            ChannelStage::Awgn { snr_db, seed } => chain.then(Awgn::new(*snr_db, *seed)),
+            ChannelStage::ImpulseNoise {
+                model,
+                snr_db,
+                seed,
+            } => {
+                let stage = ImpulseNoise::new(*model, *snr_db, *seed)
+                    .map_err(|e| CoreError::InvalidScenario { msg: e.to_string() })?;
+                chain.then(stage)
+            }
+            ChannelStage::FreqOffset { freq_hz } => chain.then(FreqOffset::new(*freq_hz, sample_rate)),
+            ChannelStage::FrequencyDrift {
+                initial_offset_hz,
+                rate_hz_per_s,
+            } => chain.then(FrequencyDrift::new(
+                *initial_offset_hz,
+                *rate_hz_per_s,
+                sample_rate,
+            )),
+            ChannelStage::PhaseOffset(stage) => chain.then(stage.clone()),
+            ChannelStage::Multipath(stage) => chain.then(stage.clone()),
+            ChannelStage::TimingOffset(stage) => chain.then(stage.clone()),
+            ChannelStage::SampleRateOffset { ppm } => chain.then(SampleRateOffset::new(*ppm)?),
+            ChannelStage::Clipper(stage) => chain.then(stage.clone()),
+            ChannelStage::Amplifier(stage) => chain.then(stage.clone()),
+            ChannelStage::DcOffset(stage) => chain.then(stage.clone()),
+            ChannelStage::IqImbalance(stage) => chain.then(stage.clone()),
+            ChannelStage::Watterson { preset, seed } => {
+                chain.then(Watterson::preset(*preset, sample_rate, *seed))
+            }
+            ChannelStage::WattersonPaths { paths, seed } => {
+                chain.then(Watterson::new(paths, sample_rate, *seed))
+            }
+            ChannelStage::CwInterferer { offset_hz, sir_db } => {
+                chain.then(CwInterferer::new(*offset_hz, *sir_db, sample_rate))
+            }
+            ChannelStage::AdjacentChannel {
+                wav,
+                offset_hz,
+                sir_db,
+            } => {
+                let (audio, _) = read_wav(&base_dir.join(wav))?;
+                if audio.is_empty() {
+                    return Err(CoreError::InvalidScenario {
+                        msg: format!("adjacent-channel recording {:?} is empty", wav),
+                    });
+                }
+                let interferer = analytic_signal(&audio);
+                chain.then(AdjacentChannel::new(
+                    interferer,
+                    *offset_hz,
+                    *sir_db,
+                    sample_rate,
+                ))
+            }
+        })
+    }
+}
+
+/// A replayable channel: an ordered list of stages
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelScenario {
+    /// Free-form note, e.g. what the scenario reproduces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Stages in the order they are applied
+    #[serde(default)]
+    pub stages: Vec<ChannelStage>,
+    /// Directory that relative paths in stages are resolved against; set by
+    /// [`load`](ChannelScenario::load)
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+impl ChannelScenario {
+    /// Empty scenario (a transparent channel)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the description
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Append a stage
+    pub fn then(mut self, stage: ChannelStage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Parse a TOML scenario
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|e| CoreError::InvalidScenario { msg: e.to_string() })
+    }
+
+    /// Parse a JSON scenario
+    pub fn from_json_str(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|e| CoreError::InvalidScenario { msg: e.to_string() })
+    }
+
+    /// Render as TOML
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| CoreError::InvalidScenario { msg: e.to_string() })
+    }
+
+    /// Render as JSON
+    pub fn to_json_string(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| CoreError::InvalidScenario { msg: e.to_string() })
+    }
+
+    /// Load a scenario file: `.json` is read as JSON, anything else as TOML
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let mut scenario = if is_json(path) {
+            Self::from_json_str(&text)?
+        } else {
+            Self::from_toml_str(&text)?
+        };
+        scenario.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(scenario)
+    }
+
+    /// Save a scenario file, in JSON or TOML by extension as for [`load`](Self::load)
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = if is_json(path) {
+            self.to_json_string()?
+        } else {
+            self.to_toml_string()?
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Build the channel for a signal at `sample_rate` Hz. Every call starts
+    /// from the stored seeds, so each built chain produces the same output.
+    pub fn build(&self, sample_rate: f64) -> Result<ChannelChain> {
+        if sample_rate <= 0.0 {
+            return Err(CoreError::InvalidSampleRate { rate: sample_rate });
+        }
+        self.stages
+            .iter()
+            .try_fold(ChannelChain::new(), |chain, stage| {
+                stage.push(chain, sample_rate, &self.base_dir)
+            })
+    }
+
+    /// Apply the scenario to real audio.
+    ///
+    /// The channel stages work on complex baseband, so the audio is first
+    /// turned into its analytic signal: frequency offsets then shift the
+    /// spectrum instead of mirroring it, and SNRs refer to the real signal
+    /// that comes back out. The output length may differ from the input if
+    /// the scenario resamples.
+    pub fn apply_real(&self, samples: &[f64], sample_rate: f64) -> Result<Vec<f64>> {
+        let mut chain = self.build(sample_rate)?;
+        let mut signal = analytic_signal(samples);
+        chain.apply(&mut signal);
+        Ok(signal.iter().map(|s| s.real).collect())
+    }
+
+    /// Run a mono WAV file through the scenario and write the result with the
+    /// same sample rate and sample format. Only the first channel of a
+    /// multi-channel file is used.
+    pub fn apply_to_wav(&self, input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<()> {
+        let (samples, spec) = read_wav(input.as_ref())?;
+        let processed = self.apply_real(&samples, spec.sample_rate as f64)?;
+        write_wav(output.as_ref(), &processed, spec)
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+/// First channel of a WAV file, scaled to [-1, 1]
+fn read_wav(path: &Path) -> Result<(Vec<f64>, hound::WavSpec)> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let channels = spec.channels.max(1) as usize;
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .step_by(channels)
+            .map(|s| s.map(f64::from))
+            .collect::<std::result::Result<Vec<_>, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f64;
+            reader
+                .samples::<i32>()
+                .step_by(channels)
+                .map(|s| s.map(|v| v as f64 * scale))
+                .collect::<std::result::Result<Vec<_>, _>>()?
+        }
+    };
+    Ok((samples, spec))
+}
+
+/// Write mono samples in `spec`'s rate and format, clipping integer formats
+fn write_wav(path: &Path, samples: &[f64], spec: hound::WavSpec) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        ..spec
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    match spec.sample_format {
+        hound::SampleFormat::Float => {
+            for &s in samples {
+                writer.write_sample(s as f32)?;
+            }
+        }
+        hound::SampleFormat::Int => {
+            let full_scale = (1u64 << (spec.bits_per_sample - 1)) as f64;
+            for &s in samples {
+                let v = (s * full_scale)
+                    .round()
+                    .clamp(-full_scale, full_scale - 1.0);
+                writer.write_sample(v as i32)?;
+            }
+        }
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Complex;
+    use crate::channel::{AmplifierModel, Clipping, MultipathTap};
+    use std::f64::consts::PI;
+
+    const EXAMPLE: &str = r#"
+description = "CCIR moderate, 30 Hz off, 12 dB SNR"
+
+[[stages]]
+type = "watterson"
+preset = "ccir_moderate"
+seed = 7
+
+[[stages]]
+type = "freq_offset"
+freq_hz = 30.0
+
+[[stages]]
+type = "awgn"
+snr_db = 12.0
+seed = 1
+"#;
+
+    fn tone(frequency: f64, len: usize) -> Vec<f64> {
+        (0..len)
+            .map(|n| 0.5 * (2.0 * PI * frequency * n as f64 / 8000.0).cos())
+            .collect()
+    }
+
+    #[test]
+    fn test_scenario_parses_and_replays() {
+        let scenario = ChannelScenario::from_toml_str(EXAMPLE).unwrap();
+        assert_eq!(scenario.stages.len(), 3);
+        assert_eq!(
+            scenario.stages[0],
+            ChannelStage::Watterson {
+                preset: WattersonPreset::CcirModerate,
+                seed: 7
+            }
+        );
+
+        let input = tone(1000.0, 8000);
+        let first = scenario.apply_real(&input, 8000.0).unwrap();
+        let second = scenario.apply_real(&input, 8000.0).unwrap();
+        assert_eq!(first.len(), input.len());
+        assert_eq!(first, second);
+        assert_ne!(first, input);
+
+        let err = ChannelScenario::from_toml_str("[[stages]]\ntype = \"warp_drive\"\n");
+        assert!(matches!(err, Err(CoreError::InvalidScenario { .. })));
+        let bad = ChannelScenario::new().then(ChannelStage::SampleRateOffset { ppm: -2e6 });
+        assert!(bad.build(8000.0).is_err());
+        let bad = ChannelScenario::new().then(ChannelStage::ImpulseNoise {
+            model: ImpulseModel::BernoulliGaussian {
+                probability: 2.0,
+                impulse_ratio_db: 20.0,
+            },
+            snr_db: 10.0,
+            seed: 1,
+        });
+        assert!(matches!(bad.build(8000.0), Err(CoreError::InvalidScenario { .. })));
+        assert!(ChannelScenario::new().build(0.0).is_err());
+    }
+
+    #[test]
+    fn test_every_stage_round_trips() {
+        let scenario = ChannelScenario::new()
+            .with_description("all stages")
+            .then(ChannelStage::Awgn {
+                snr_db: 10.0,
+                seed: 3,
+            })
+            .then(ChannelStage::ImpulseNoise {
+                model: ImpulseModel::BernoulliGaussian {
+                    probability: 0.01,
+                    impulse_ratio_db: 20.0,
+                },
+                snr_db: 15.0,
+                seed: 4,
+            })
+            .then(ChannelStage::FreqOffset { freq_hz: 30.0 })
+            .then(ChannelStage::FrequencyDrift {
+                initial_offset_hz: 5.0,
+                rate_hz_per_s: 0.5,
+            })
+            .then(ChannelStage::PhaseOffset(PhaseOffset { radians: 0.3 }))
+            .then(ChannelStage::Multipath(Multipath::new(vec![
+                MultipathTap {
+                    delay: 0,
+                    gain: Complex::new(1.0, 0.0),
+                },
+                MultipathTap {
+                    delay: 12,
+                    gain: Complex::new(0.2, -0.1),
+                },
+            ])))
+            .then(ChannelStage::TimingOffset(TimingOffset::new(0.25)))
+            .then(ChannelStage::SampleRateOffset { ppm: 50.0 })
+            .then(ChannelStage::Clipper(Clipper::new(Clipping::Soft, 6.0)))
+            .then(ChannelStage::Amplifier(Amplifier::new(
+                AmplifierModel::Rapp { smoothness: 2.0 },
+                3.0,
+            )))
+            .then(ChannelStage::DcOffset(DcOffset {
+                offset: Complex::new(0.01, 0.0),
+            }))
+            .then(ChannelStage::IqImbalance(IqImbalance {
+                gain_db: 0.5,
+                phase_deg: 2.0,
+            }))
+            .then(ChannelStage::Watterson {
+                preset: WattersonPreset::Flutter,
+                seed: 5,
+            })
+            .then(ChannelStage::WattersonPaths {
+                paths: WattersonPreset::CcirPoor.paths(),
+                seed: 6,
+            })
+            .then(ChannelStage::CwInterferer {
+                offset_hz: 400.0,
+                sir_db: 20.0,
+            });
+
+        let toml = scenario.to_toml_string().unwrap();
+        assert_eq!(ChannelScenario::from_toml_str(&toml).unwrap(), scenario);
+        let json = scenario.to_json_string().unwrap();
+        assert_eq!(ChannelScenario::from_json_str(&json).unwrap(), scenario);
+
+        let out = scenario.apply_real(&tone(1000.0, 4000), 8000.0).unwrap();
+        assert!(out.iter().all(|s| s.is_finite()));
+    }
+
+    #[test]
+    fn test_real_audio_is_shifted_not_mirrored() {
+        // A 30 Hz offset on a 1 kHz tone must give 1030 Hz, not 970 + 1030 Hz.
+        let scenario = ChannelScenario::new().then(ChannelStage::FreqOffset { freq_hz: 30.0 });
+        let out = scenario.apply_real(&tone(1000.0, 8000), 8000.0).unwrap();
+        let expected = tone(1030.0, 8000);
+        let err = out[100..7900]
+            .iter()
+            .zip(&expected[100..7900])
+            .fold(0.0f64, |m, (a, b)| m.max((a - b).abs()));
+        assert!(err < 1e-3, "max error {}", err);
+    }
+
+    #[test]
+    fn test_apply_to_wav_and_load() {
+        let dir = std::env::temp_dir().join(format!("openham-scenario-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        write_wav(&dir.join("in.wav"), &tone(1000.0, 8000), spec).unwrap();
+        write_wav(&dir.join("qrm.wav"), &tone(300.0, 1000), spec).unwrap();
+
+        // Relative recording paths resolve next to the scenario file.
+        let scenario =
+            ChannelScenario::from_toml_str(EXAMPLE)
+                .unwrap()
+                .then(ChannelStage::AdjacentChannel {
+                    wav: PathBuf::from("qrm.wav"),
+                    offset_hz: 1500.0,
+                    sir_db: 10.0,
+                });
+        scenario.save(dir.join("scenario.json")).unwrap();
+        let loaded = ChannelScenario::load(dir.join("scenario.json")).unwrap();
+        assert_eq!(loaded.stages, scenario.stages);
+
+        loaded
+            .apply_to_wav(dir.join("in.wav"), dir.join("out.wav"))
+            .unwrap();
+        let (out, out_spec) = read_wav(&dir.join("out.wav")).unwrap();
+        assert_eq!(out_spec, spec);
+        assert_eq!(out.len(), 8000);
+
+        // Same scenario, same file, same output.
+        loaded
+            .apply_to_wav(dir.join("in.wav"), dir.join("again.wav"))
+            .unwrap();
+        assert_eq!(read_wav(&dir.join("again.wav")).unwrap().0, out);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
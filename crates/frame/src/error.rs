@@ -11,6 +11,9 @@ pub enum FrameError {
     #[error("Frame size mismatch: expected {expected}, got {actual}")]
     SizeMismatch { expected: usize, actual: usize },
     
+    #[error("Payload CRC mismatch: frame carries {expected:#010x}, payload hashes to {actual:#010x}")]
+    PayloadCrcMismatch { expected: u32, actual: u32 },
+    
     #[error("FEC decoding failed: {msg}")]
     FecDecodingFailed { msg: String },
     
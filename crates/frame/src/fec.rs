@@ -1,5 +1,6 @@
 //! Forward Error Correction (FEC) implementations
 
+use crate::framing::{bits_to_bytes, bytes_to_bits};
 use crate::{FrameError, Result};
 
 /// Generic FEC encoder trait
@@ -34,17 +35,122 @@ pub struct ErrorStats {
     pub uncorrectable_errors: usize,
 }
 
+/// Primitive polynomial x^8 + x^4 + x^3 + x^2 + 1, the usual choice for
+/// RS(255, k) over GF(256)
+pub const DEFAULT_PRIMITIVE_POLY: u16 = 0x11d;
+
+/// GF(2^8) arithmetic through log/antilog tables
+#[derive(Debug, Clone)]
+struct GaloisField {
+    /// `exp[i] = alpha^i`, doubled so products of two logs need no reduction
+    exp: [u8; 510],
+    log: [u8; 256],
+}
+
+impl GaloisField {
+    /// Build the field generated by `primitive_poly`, a degree-8 polynomial
+    /// with `x` as a primitive element
+    fn new(primitive_poly: u16) -> Result<Self> {
+        if !(0x100..0x200).contains(&primitive_poly) {
+            return Err(FrameError::InvalidFecParameters {
+                msg: format!("Field polynomial {:#x} is not of degree 8", primitive_poly),
+            });
+        }
+
+        let mut exp = [0u8; 510];
+        let mut log = [0u8; 256];
+        let mut x = 1u16;
+        for i in 0..255 {
+            if i > 0 && x == 1 {
+                return Err(FrameError::InvalidFecParameters {
+                    msg: format!("Field polynomial {:#x} is not primitive", primitive_poly),
+                });
+            }
+            exp[i] = x as u8;
+            exp[i + 255] = x as u8;
+            log[x as usize] = i as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= primitive_poly;
+            }
+        }
+        if x != 1 {
+            return Err(FrameError::InvalidFecParameters {
+                msg: format!("Field polynomial {:#x} is not primitive", primitive_poly),
+            });
+        }
+
+        Ok(Self { exp, log })
+    }
+
+    /// `alpha^power`
+    fn alpha(&self, power: usize) -> u8 {
+        self.exp[power % 255]
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+        }
+    }
+
+    /// `a / b`; `b` must be non-zero
+    fn div(&self, a: u8, b: u8) -> u8 {
+        debug_assert!(b != 0, "division by zero in GF(256)");
+        if a == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+        }
+    }
+
+    /// Evaluate a polynomial stored lowest degree first
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, &c| self.mul(acc, x) ^ c)
+    }
+}
+
 /// Reed-Solomon encoder/decoder
+///
+/// A systematic RS(n, k) code over GF(256): each block is its data symbols
+/// followed by `n - k` parity symbols, and up to `(n - k) / 2` symbol
+/// errors, or `n - k` erasures, or any mix with `2 * errors + erasures <=
+/// n - k`, are corrected per block.
+///
+/// Input longer than `k` bytes is split into blocks of `k`, and a shorter
+/// final block is sent as a shortened code: the missing leading data
+/// symbols are taken as zero and never transmitted, so `m` data bytes cost
+/// `m + n - k` bytes on air.
+///
+/// [`error_stats`](FecDecoder::error_stats) reports, for the last decode,
+/// the number of symbols corrected, the number of blocks that arrived with
+/// errors, and the number of those that could not be corrected.
+#[derive(Debug, Clone)]
 pub struct ReedSolomon {
     n: usize, // Total symbols
     k: usize, // Data symbols
     t: usize, // Error correction capability
+    fcr: usize,
+    gf: GaloisField,
+    /// Generator polynomial, highest degree first; monic, so `generator[0] == 1`
+    generator: Vec<u8>,
+    stats: ErrorStats,
 }
 
 impl ReedSolomon {
-    /// Create a new Reed-Solomon codec
+    /// Create a new Reed-Solomon codec over the field of
+    /// [`DEFAULT_PRIMITIVE_POLY`], with generator roots starting at `alpha^0`
     pub fn new(n: usize, k: usize) -> Result<Self> {
-        if n <= k {
+        Self::with_field(n, k, DEFAULT_PRIMITIVE_POLY, 0)
+    }
+
+    /// Create a codec over the field generated by `primitive_poly`, whose
+    /// generator polynomial has the `n - k` consecutive roots
+    /// `alpha^fcr, alpha^(fcr + 1), ...`
+    pub fn with_field(n: usize, k: usize, primitive_poly: u16, fcr: usize) -> Result<Self> {
+        if n <= k || k == 0 {
             return Err(FrameError::InvalidFecParameters {
                 msg: format!("Invalid RS parameters: n={}, k={}", n, k),
             });
@@ -56,9 +162,34 @@ impl ReedSolomon {
             });
         }
         
+        if fcr > 254 {
+            return Err(FrameError::InvalidFecParameters {
+                msg: format!("RS first consecutive root {} must be below 255", fcr),
+            });
+        }
+        
+        let gf = GaloisField::new(primitive_poly)?;
         let t = (n - k) / 2;
         
-        Ok(Self { n, k, t })
+        // g(x) = (x - alpha^fcr)(x - alpha^(fcr + 1))...
+        let mut generator = vec![1u8];
+        for i in 0..n - k {
+            let root = gf.alpha(fcr + i);
+            generator.push(0);
+            for j in (1..generator.len()).rev() {
+                generator[j] ^= gf.mul(generator[j - 1], root);
+            }
+        }
+        
+        Ok(Self {
+            n,
+            k,
+            t,
+            fcr,
+            gf,
+            generator,
+            stats: ErrorStats::default(),
+        })
     }
     
     /// Create RS(255,223) - commonly used configuration
@@ -70,21 +201,236 @@ impl ReedSolomon {
     pub fn rs_255_239() -> Result<Self> {
         Self::new(255, 239)
     }
+    
+    /// Number of parity symbols per block
+    pub fn parity_len(&self) -> usize {
+        self.n - self.k
+    }
+    
+    /// Number of unknown symbol errors correctable per block
+    pub fn correction_capability(&self) -> usize {
+        self.t
+    }
+    
+    /// Decode, treating the symbols at `erasures` (indices into
+    /// `encoded_data`) as unreliable, e.g. where the demodulator had low
+    /// confidence. Each erasure costs half as much correction capacity as an
+    /// unknown error.
+    pub fn decode_with_erasures(&mut self, encoded_data: &[u8], erasures: &[usize]) -> Result<Vec<u8>> {
+        let (decoded, stats) = self.decode_blocks(encoded_data, erasures);
+        self.stats = stats;
+        decoded
+    }
+    
+    /// Parity symbols for one (possibly shortened) block of data
+    fn parity(&self, data: &[u8]) -> Vec<u8> {
+        let nroots = self.n - self.k;
+        let mut parity = vec![0u8; nroots];
+        for &symbol in data {
+            let feedback = symbol ^ parity[0];
+            parity.rotate_left(1);
+            parity[nroots - 1] = 0;
+            if feedback != 0 {
+                for (p, &g) in parity.iter_mut().zip(&self.generator[1..]) {
+                    *p ^= self.gf.mul(feedback, g);
+                }
+            }
+        }
+        parity
+    }
+    
+    /// Syndromes `S_j = c(alpha^(fcr + j))`, lowest `j` first
+    fn syndromes(&self, block: &[u8]) -> Vec<u8> {
+        (0..self.n - self.k)
+            .map(|j| {
+                let root = self.gf.alpha(self.fcr + j);
+                block.iter().fold(0, |acc, &c| self.gf.mul(acc, root) ^ c)
+            })
+            .collect()
+    }
+    
+    /// Split `encoded_data` into blocks and correct each one, collecting
+    /// statistics whether or not decoding succeeds
+    fn decode_blocks(&self, encoded_data: &[u8], erasures: &[usize]) -> (Result<Vec<u8>>, ErrorStats) {
+        let mut stats = ErrorStats::default();
+        let nroots = self.n - self.k;
+        
+        if let Some(&pos) = erasures.iter().find(|&&pos| pos >= encoded_data.len()) {
+            let err = FrameError::FecDecodingFailed {
+                msg: format!("Erasure at {} is beyond the {} received bytes", pos, encoded_data.len()),
+            };
+            return (Err(err), stats);
+        }
+        
+        let mut decoded = Vec::with_capacity(encoded_data.len());
+        let mut failure = None;
+        for (index, chunk) in encoded_data.chunks(self.n).enumerate() {
+            if chunk.len() <= nroots {
+                let err = FrameError::FecDecodingFailed {
+                    msg: format!("Truncated RS block: {} bytes with {} parity", chunk.len(), nroots),
+                };
+                return (Err(err), stats);
+            }
+            
+            let start = index * self.n;
+            let mut block_erasures: Vec<usize> = erasures
+                .iter()
+                .filter(|&&pos| pos >= start && pos < start + chunk.len())
+                .map(|&pos| pos - start)
+                .collect();
+            block_erasures.sort_unstable();
+            block_erasures.dedup();
+            
+            let mut block = chunk.to_vec();
+            match self.correct_block(&mut block, &block_erasures) {
+                Ok(0) => {}
+                Ok(corrected) => {
+                    stats.detected_errors += 1;
+                    stats.corrected_errors += corrected;
+                }
+                Err(err) => {
+                    stats.detected_errors += 1;
+                    stats.uncorrectable_errors += 1;
+                    failure.get_or_insert(err);
+                }
+            }
+            decoded.extend_from_slice(&block[..block.len() - nroots]);
+        }
+        
+        match failure {
+            Some(err) => (Err(err), stats),
+            None => (Ok(decoded), stats),
+        }
+    }
+    
+    /// Correct one (possibly shortened) block in place with
+    /// Berlekamp-Massey, Chien search and Forney, returning the number of
+    /// symbols changed. The block is left untouched if it cannot be
+    /// corrected.
+    fn correct_block(&self, block: &mut [u8], erasures: &[usize]) -> Result<usize> {
+        let gf = &self.gf;
+        let nroots = self.n - self.k;
+        let len = block.len();
+        
+        if erasures.len() > nroots {
+            return Err(FrameError::FecDecodingFailed {
+                msg: format!("{} erasures exceed the {} RS parity symbols", erasures.len(), nroots),
+            });
+        }
+        
+        let syndromes = self.syndromes(block);
+        if syndromes.iter().all(|&s| s == 0) {
+            return Ok(0);
+        }
+        
+        // Symbol i of the block is the coefficient of x^(len - 1 - i); its
+        // locator is alpha to that power. Polynomials below are stored
+        // lowest degree first.
+        let locator_power = |i: usize| len - 1 - i;
+        
+        // Erasure locator: product of (1 - X x) over the erased symbols.
+        let mut lambda = vec![0u8; nroots + 1];
+        lambda[0] = 1;
+        for (count, &pos) in erasures.iter().enumerate() {
+            let x = gf.alpha(locator_power(pos));
+            for j in (1..=count + 1).rev() {
+                lambda[j] ^= gf.mul(x, lambda[j - 1]);
+            }
+        }
+        
+        // Berlekamp-Massey, started from the erasure locator.
+        let num_erasures = erasures.len();
+        let mut previous = lambda.clone();
+        let mut degree = num_erasures;
+        for r in num_erasures..nroots {
+            let discrepancy = (0..=r).fold(0, |acc, i| acc ^ gf.mul(lambda[i], syndromes[r - i]));
+            previous.rotate_right(1);
+            previous[0] = 0;
+            if discrepancy == 0 {
+                continue;
+            }
+            let next: Vec<u8> = lambda
+                .iter()
+                .zip(&previous)
+                .map(|(&l, &b)| l ^ gf.mul(discrepancy, b))
+                .collect();
+            if 2 * degree <= r + num_erasures {
+                degree = r + 1 + num_erasures - degree;
+                previous = lambda.iter().map(|&l| gf.div(l, discrepancy)).collect();
+            }
+            lambda = next;
+        }
+        let lambda_degree = lambda.iter().rposition(|&c| c != 0).unwrap_or(0);
+        
+        // Chien search over the transmitted positions only: a root that
+        // falls in the shortened (virtual) part means the block is beyond
+        // repair.
+        let positions: Vec<usize> = (0..len)
+            .filter(|&i| gf.eval(&lambda, gf.alpha(255 - locator_power(i) % 255)) == 0)
+            .collect();
+        if lambda_degree == 0 || positions.len() != lambda_degree {
+            return Err(FrameError::FecDecodingFailed {
+                msg: format!(
+                    "Uncorrectable RS block: {} error locations found for a degree {} locator",
+                    positions.len(),
+                    lambda_degree
+                ),
+            });
+        }
+        
+        // Forney: error value = X^(1 - fcr) * omega(X^-1) / lambda'(X^-1),
+        // with omega = S * lambda mod x^nroots.
+        let mut omega = vec![0u8; nroots];
+        for (i, &s) in syndromes.iter().enumerate() {
+            for (j, &l) in lambda.iter().take(nroots - i).enumerate() {
+                omega[i + j] ^= gf.mul(s, l);
+            }
+        }
+        let derivative: Vec<u8> = lambda
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &l)| if i % 2 == 1 { l } else { 0 })
+            .collect();
+        
+        let mut corrected = block.to_vec();
+        let mut changed = 0;
+        for &pos in &positions {
+            let power = locator_power(pos);
+            let x_inv = gf.alpha(255 - power % 255);
+            let denominator = gf.eval(&derivative, x_inv);
+            if denominator == 0 {
+                return Err(FrameError::FecDecodingFailed {
+                    msg: "Uncorrectable RS block: repeated error locator root".to_string(),
+                });
+            }
+            let scale = gf.alpha((power * (256 - self.fcr)) % 255);
+            let magnitude = gf.mul(scale, gf.div(gf.eval(&omega, x_inv), denominator));
+            if magnitude != 0 {
+                corrected[pos] ^= magnitude;
+                changed += 1;
+            }
+        }
+        
+        if self.syndromes(&corrected).iter().any(|&s| s != 0) {
+            return Err(FrameError::FecDecodingFailed {
+                msg: "Uncorrectable RS block: residual syndrome after correction".to_string(),
+            });
+        }
+        
+        block.copy_from_slice(&corrected);
+        Ok(changed)
+    }
 }
 
 impl FecEncoder for ReedSolomon {
     fn encode(&mut self, data: &[u8]) -> Result<Vec<u8>> {
-        if data.len() > self.k {
-            return Err(FrameError::InvalidFecParameters {
-                msg: format!("Data too long for RS({},{}): {} bytes", self.n, self.k, data.len()),
-            });
+        let mut encoded = Vec::with_capacity(data.len() + self.overhead_bytes(data.len()));
+        for block in data.chunks(self.k) {
+            encoded.extend_from_slice(block);
+            encoded.extend(self.parity(block));
         }
         
-        // TODO: Implement actual Reed-Solomon encoding
-        // For now, return a placeholder that appends parity bytes
-        let mut encoded = data.to_vec();
-        encoded.resize(self.n, 0); // Pad with zeros as placeholder parity
-        
         Ok(encoded)
     }
     
@@ -100,34 +446,126 @@ impl FecEncoder for ReedSolomon {
 
 impl FecDecoder for ReedSolomon {
     fn decode(&mut self, encoded_data: &[u8]) -> Result<Vec<u8>> {
-        if encoded_data.len() != self.n {
-            return Err(FrameError::FecDecodingFailed {
-                msg: format!("Invalid RS block size: expected {}, got {}", self.n, encoded_data.len()),
+        self.decode_with_erasures(encoded_data, &[])
+    }
+    
+    fn can_correct(&self, encoded_data: &[u8]) -> bool {
+        self.decode_blocks(encoded_data, &[]).0.is_ok()
+    }
+    
+    fn error_stats(&self) -> ErrorStats {
+        self.stats.clone()
+    }
+}
+
+/// How a convolutional code block starts and ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Termination {
+    /// Start from the zero state and flush with `K - 1` zero tail bits
+    #[default]
+    ZeroTail,
+    /// Preload the encoder with the last `K - 1` data bits so it starts and
+    /// ends in the same state: no tail bits, at the cost of a decoder that
+    /// has to find that state
+    TailBiting,
+}
+
+/// Puncturing pattern for a convolutional code
+///
+/// Row `i` says, for each step of the period, whether the output of
+/// polynomial `i` is sent. The standard patterns are for rate 1/2 mother
+/// codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PuncturePattern {
+    rows: Vec<Vec<bool>>,
+}
+
+impl PuncturePattern {
+    /// Create a pattern from one row per polynomial; all rows must have the
+    /// same non-zero length and at least one bit must be kept per step
+    pub fn new(rows: Vec<Vec<bool>>) -> Result<Self> {
+        let period = rows.first().map_or(0, Vec::len);
+        if period == 0 || rows.iter().any(|row| row.len() != period) {
+            return Err(FrameError::InvalidFecParameters {
+                msg: "Puncture pattern rows must be non-empty and of equal length".to_string(),
             });
         }
         
-        // TODO: Implement actual Reed-Solomon decoding
-        // For now, return the first k bytes
-        Ok(encoded_data[..self.k].to_vec())
+        if (0..period).any(|step| rows.iter().all(|row| !row[step])) {
+            return Err(FrameError::InvalidFecParameters {
+                msg: "Puncture pattern drops every output of a step".to_string(),
+            });
+        }
+        
+        Ok(Self { rows })
     }
     
-    fn can_correct(&self, encoded_data: &[u8]) -> bool {
-        // TODO: Implement syndrome calculation
-        encoded_data.len() == self.n
+    /// Rate 2/3 from rate 1/2: X 10, Y 11
+    pub fn rate_2_3() -> Self {
+        Self::from_masks(&[&[1, 0], &[1, 1]])
     }
     
-    fn error_stats(&self) -> ErrorStats {
-        // TODO: Return actual error statistics
-        ErrorStats::default()
+    /// Rate 3/4 from rate 1/2: X 101, Y 110
+    pub fn rate_3_4() -> Self {
+        Self::from_masks(&[&[1, 0, 1], &[1, 1, 0]])
+    }
+    
+    /// Rate 5/6 from rate 1/2: X 10101, Y 11010
+    pub fn rate_5_6() -> Self {
+        Self::from_masks(&[&[1, 0, 1, 0, 1], &[1, 1, 0, 1, 0]])
+    }
+    
+    fn from_masks(masks: &[&[u8]]) -> Self {
+        Self {
+            rows: masks
+                .iter()
+                .map(|mask| mask.iter().map(|&bit| bit != 0).collect())
+                .collect(),
+        }
+    }
+    
+    /// Number of encoder steps before the pattern repeats
+    pub fn period(&self) -> usize {
+        self.rows[0].len()
+    }
+    
+    /// Number of coded bits sent per period
+    pub fn kept_per_period(&self) -> usize {
+        self.rows.iter().flatten().filter(|&&keep| keep).count()
+    }
+    
+    /// Whether the output of polynomial `output` at encoder step `step` is sent
+    fn keeps(&self, step: usize, output: usize) -> bool {
+        self.rows[output][step % self.period()]
     }
 }
 
 /// Convolutional encoder/decoder
+///
+/// Coded bits are packed MSB first, one group of outputs (one per
+/// polynomial, after puncturing) per input bit, and the last byte is padded
+/// with zeros.
+///
+/// Decoding is Viterbi with a sliding traceback window. [`decode`] takes
+/// the packed hard decisions; [`decode_soft`] and [`decode_quantized`] take
+/// one soft value per coded bit. After a decode,
+/// [`error_stats`](FecDecoder::error_stats) reports as corrected the coded
+/// bits whose hard decision disagrees with the winning path (for hard input
+/// this is its path metric), and as detected the number of blocks that
+/// needed any correction. Viterbi decoding cannot tell when it fails, so
+/// nothing is ever reported as uncorrectable.
+///
+/// [`decode`]: FecDecoder::decode
+/// [`decode_soft`]: Convolutional::decode_soft
+/// [`decode_quantized`]: Convolutional::decode_quantized
 pub struct Convolutional {
     constraint_length: usize,
     code_rate: (usize, usize), // (k, n) where k input bits produce n output bits
     polynomials: Vec<u32>,
-    state: u32,
+    termination: Termination,
+    puncturing: Option<PuncturePattern>,
+    traceback_depth: Option<usize>,
+    stats: ErrorStats,
 }
 
 impl Convolutional {
@@ -145,6 +583,12 @@ impl Convolutional {
             });
         }
         
+        if let Some(&poly) = polynomials.iter().find(|&&p| p == 0 || p >> constraint_length != 0) {
+            return Err(FrameError::InvalidFecParameters {
+                msg: format!("Polynomial {:#o} does not fit constraint length {}", poly, constraint_length),
+            });
+        }
+        
         // Rate 1/n where n is number of polynomials
         let code_rate = (1, polynomials.len());
         
@@ -152,7 +596,10 @@ impl Convolutional {
             constraint_length,
             code_rate,
             polynomials,
-            state: 0,
+            termination: Termination::ZeroTail,
+            puncturing: None,
+            traceback_depth: None,
+            stats: ErrorStats::default(),
         })
     }
     
@@ -165,77 +612,801 @@ impl Convolutional {
     pub fn rate_1_3_k7() -> Result<Self> {
         Self::new(7, vec![0o171, 0o133, 0o165])
     }
+    
+    /// Set how blocks are terminated
+    pub fn with_termination(mut self, termination: Termination) -> Self {
+        self.termination = termination;
+        self
+    }
+    
+    /// Puncture the coded stream; the pattern needs one row per polynomial
+    pub fn with_puncturing(mut self, pattern: PuncturePattern) -> Result<Self> {
+        if pattern.rows.len() != self.polynomials.len() {
+            return Err(FrameError::InvalidFecParameters {
+                msg: format!(
+                    "Puncture pattern has {} rows for {} polynomials",
+                    pattern.rows.len(),
+                    self.polynomials.len()
+                ),
+            });
+        }
+        self.puncturing = Some(pattern);
+        Ok(self)
+    }
+    
+    /// Set the Viterbi traceback depth in input bits
+    pub fn with_traceback_depth(mut self, depth: usize) -> Self {
+        self.traceback_depth = Some(depth.max(1));
+        self
+    }
+    
+    /// Viterbi traceback depth: as set, or by default five constraint
+    /// lengths, doubled for punctured codes
+    pub fn traceback_depth(&self) -> usize {
+        self.traceback_depth.unwrap_or_else(|| {
+            let depth = 5 * self.constraint_length;
+            if self.puncturing.is_some() {
+                2 * depth
+            } else {
+                depth
+            }
+        })
+    }
+    
+    /// Decode soft decisions, one log-likelihood ratio `ln(P(0) / P(1))`
+    /// per coded bit, so positive values favour 0 and 0.0 means no
+    /// information. Up to 7 trailing values of byte padding are ignored.
+    pub fn decode_soft(&mut self, llrs: &[f64]) -> Result<Vec<u8>> {
+        let data_bits = self.data_bits_for(llrs.len())?;
+        let depunctured = self.depuncture(&llrs[..self.coded_bits(data_bits)], data_bits);
+        let (bits, corrected) = self.viterbi(&depunctured, data_bits);
+        self.stats = ErrorStats {
+            corrected_errors: corrected,
+            detected_errors: usize::from(corrected > 0),
+            uncorrectable_errors: 0,
+        };
+        Ok(bits_to_bytes(&bits))
+    }
+    
+    /// Decode 3-bit quantized soft decisions: 0 is a confident 0, 7 a
+    /// confident 1
+    pub fn decode_quantized(&mut self, symbols: &[u8]) -> Result<Vec<u8>> {
+        if let Some(&symbol) = symbols.iter().find(|&&s| s > 7) {
+            return Err(FrameError::FecDecodingFailed {
+                msg: format!("Quantized soft decision {} is outside 0..=7", symbol),
+            });
+        }
+        let llrs: Vec<f64> = symbols.iter().map(|&s| 3.5 - s as f64).collect();
+        self.decode_soft(&llrs)
+    }
+    
+    /// Number of polynomials, i.e. coded bits per step before puncturing
+    fn outputs(&self) -> usize {
+        self.polynomials.len()
+    }
+    
+    /// Whether a block of `data_bits` is long enough to hold a tail-biting
+    /// start state; always true for zero-tail blocks
+    fn tail_biting_fits(&self, data_bits: usize) -> bool {
+        self.termination == Termination::ZeroTail || data_bits >= self.constraint_length - 1
+    }
+    
+    /// Encoder steps for `data_bits` input bits, including any tail
+    fn steps(&self, data_bits: usize) -> usize {
+        match self.termination {
+            Termination::ZeroTail => data_bits + self.constraint_length - 1,
+            Termination::TailBiting => data_bits,
+        }
+    }
+    
+    /// Coded bits sent for `data_bits` input bits
+    fn coded_bits(&self, data_bits: usize) -> usize {
+        let steps = self.steps(data_bits);
+        match &self.puncturing {
+            None => steps * self.outputs(),
+            Some(pattern) => {
+                let full = steps / pattern.period() * pattern.kept_per_period();
+                let partial = (0..steps % pattern.period())
+                    .map(|step| (0..self.outputs()).filter(|&i| pattern.keeps(step, i)).count())
+                    .sum::<usize>();
+                full + partial
+            }
+        }
+    }
+    
+    /// Whole bytes of data that encode to `received` coded bits, allowing
+    /// for the zero padding of the last byte
+    fn data_bits_for(&self, received: usize) -> Result<usize> {
+        let mut bytes = 0;
+        loop {
+            let coded = self.coded_bits(bytes * 8);
+            if coded > received {
+                break;
+            }
+            if received - coded < 8 && self.tail_biting_fits(bytes * 8) {
+                return Ok(bytes * 8);
+            }
+            bytes += 1;
+        }
+        Err(FrameError::FecDecodingFailed {
+            msg: format!("{} coded bits do not match any whole number of data bytes", received),
+        })
+    }
+    
+    /// Coded outputs of the register holding `state` plus the new `input` bit
+    fn branch_outputs(&self, state: usize, input: usize) -> impl Iterator<Item = u8> + '_ {
+        let register = (input << (self.constraint_length - 1)) | state;
+        self.polynomials
+            .iter()
+            .map(move |&poly| ((register as u32 & poly).count_ones() & 1) as u8)
+    }
+    
+    /// Encode a bit sequence from `start_state`, before puncturing
+    fn encode_bits(&self, bits: &[u8], start_state: usize) -> Vec<u8> {
+        let mut state = start_state;
+        let mut coded = Vec::with_capacity(bits.len() * self.outputs());
+        for &bit in bits {
+            let input = (bit & 1) as usize;
+            coded.extend(self.branch_outputs(state, input));
+            state = (input << (self.constraint_length - 2)) | (state >> 1);
+        }
+        coded
+    }
+    
+    /// Input bits for the whole block, tail included, and the state the
+    /// encoder starts from
+    fn block_bits(&self, data_bits: &[u8]) -> (Vec<u8>, usize) {
+        let memory = self.constraint_length - 1;
+        match self.termination {
+            Termination::ZeroTail => {
+                let mut bits = data_bits.to_vec();
+                bits.resize(data_bits.len() + memory, 0);
+                (bits, 0)
+            }
+            Termination::TailBiting => {
+                // The state after the last data bit holds the newest bit at
+                // the top.
+                let start = data_bits
+                    .iter()
+                    .rev()
+                    .take(memory)
+                    .enumerate()
+                    .fold(0, |state, (age, &bit)| state | (((bit & 1) as usize) << (memory - 1 - age)));
+                (data_bits.to_vec(), start)
+            }
+        }
+    }
+    
+    /// Remove the punctured bits from an unpunctured coded stream
+    fn puncture(&self, coded: Vec<u8>) -> Vec<u8> {
+        match &self.puncturing {
+            None => coded,
+            Some(pattern) => coded
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| pattern.keeps(i / self.outputs(), i % self.outputs()))
+                .map(|(_, bit)| bit)
+                .collect(),
+        }
+    }
+    
+    /// Put erasures (0.0) back where bits were punctured
+    fn depuncture(&self, llrs: &[f64], data_bits: usize) -> Vec<f64> {
+        match &self.puncturing {
+            None => llrs.to_vec(),
+            Some(pattern) => {
+                let mut received = llrs.iter();
+                (0..self.steps(data_bits) * self.outputs())
+                    .map(|i| {
+                        if pattern.keeps(i / self.outputs(), i % self.outputs()) {
+                            *received.next().unwrap_or(&0.0)
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+    
+    /// Viterbi decode an unpunctured stream of LLRs into `data_bits` bits,
+    /// also returning how many non-erased hard decisions the decoded path
+    /// contradicts
+    fn viterbi(&self, llrs: &[f64], data_bits: usize) -> (Vec<u8>, usize) {
+        let n = self.outputs();
+        let steps = llrs.len() / n;
+        let num_states = 1usize << (self.constraint_length - 1);
+        let low_mask = (num_states >> 1) - 1;
+        let depth = self.traceback_depth();
+        
+        // Tail-biting blocks are decoded circularly: the end of the block is
+        // run in front of it and the start after it, so the trellis has
+        // settled on the right state by the time the real block is reached.
+        let (wrap, received) = match self.termination {
+            Termination::ZeroTail => (0, llrs.to_vec()),
+            Termination::TailBiting => {
+                let wrap = depth.min(steps);
+                let mut extended = llrs[(steps - wrap) * n..].to_vec();
+                extended.extend_from_slice(llrs);
+                extended.extend_from_slice(&llrs[..wrap * n]);
+                (wrap, extended)
+            }
+        };
+        let total_steps = received.len() / n;
+        
+        // Branch cost: how strongly the received values contradict the
+        // expected outputs. Precomputed per register value.
+        let expected: Vec<Vec<u8>> = (0..2 * num_states)
+            .map(|register| self.branch_outputs(register & (num_states - 1), register / num_states).collect())
+            .collect();
+        
+        let mut metrics = vec![f64::INFINITY; num_states];
+        match self.termination {
+            Termination::ZeroTail => metrics[0] = 0.0,
+            Termination::TailBiting => metrics.fill(0.0),
+        }
+        let mut next = vec![0.0; num_states];
+        // decisions[t][s] is the low bit of the predecessor of state s after step t
+        let mut decisions: Vec<Vec<u8>> = Vec::with_capacity(total_steps);
+        let mut decoded = Vec::with_capacity(total_steps);
+        
+        for (t, symbols) in received.chunks_exact(n).enumerate() {
+            let mut choice = vec![0u8; num_states];
+            for (state, slot) in next.iter_mut().enumerate() {
+                let input = state >> (self.constraint_length - 2);
+                let mut best = f64::INFINITY;
+                for low in 0..2 {
+                    let previous = ((state & low_mask) << 1) | low;
+                    let register = input * num_states + previous;
+                    let cost: f64 = expected[register]
+                        .iter()
+                        .zip(symbols)
+                        .map(|(&bit, &llr)| if bit == 0 { (-llr).max(0.0) } else { llr.max(0.0) })
+                        .sum();
+                    let metric = metrics[previous] + cost;
+                    if metric < best {
+                        best = metric;
+                        choice[state] = low as u8;
+                    }
+                }
+                *slot = best;
+            }
+            std::mem::swap(&mut metrics, &mut next);
+            decisions.push(choice);
+            
+            // Once the window is full, commit the oldest undecided bit.
+            if t + 1 >= depth {
+                let mut state = best_state(&metrics);
+                for choice in decisions[t + 1 - depth..].iter().skip(1).rev() {
+                    state = ((state & low_mask) << 1) | choice[state] as usize;
+                }
+                decoded.push((state >> (self.constraint_length - 2)) as u8);
+            }
+        }
+        
+        // Trace the rest back from the end of the block.
+        let mut state = match self.termination {
+            Termination::ZeroTail => 0,
+            Termination::TailBiting => best_state(&metrics),
+        };
+        let undecided = total_steps - decoded.len();
+        let mut tail = Vec::with_capacity(undecided);
+        for choice in decisions[decoded.len()..].iter().rev() {
+            tail.push((state >> (self.constraint_length - 2)) as u8);
+            state = ((state & low_mask) << 1) | choice[state] as usize;
+        }
+        decoded.extend(tail.into_iter().rev());
+        
+        let bits = decoded[wrap..wrap + data_bits].to_vec();
+        
+        // Re-encode to count the received bits the decoded path overrides.
+        let (block, start) = self.block_bits(&bits);
+        let recoded = self.encode_bits(&block, start);
+        let corrected = recoded
+            .iter()
+            .zip(llrs)
+            .filter(|&(&bit, &llr)| llr != 0.0 && (llr < 0.0) != (bit == 1))
+            .count();
+        
+        (bits, corrected)
+    }
+}
+
+/// Index of the smallest path metric
+fn best_state(metrics: &[f64]) -> usize {
+    metrics
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(state, _)| state)
 }
 
 impl FecEncoder for Convolutional {
     fn encode(&mut self, data: &[u8]) -> Result<Vec<u8>> {
-        let mut encoded = Vec::new();
+        let data_bits = bytes_to_bits(data);
+        if !self.tail_biting_fits(data_bits.len()) {
+            return Err(FrameError::InvalidFecParameters {
+                msg: format!(
+                    "Tail-biting with K={} needs at least {} data bits",
+                    self.constraint_length,
+                    self.constraint_length - 1
+                ),
+            });
+        }
         
-        for &byte in data {
-            for bit_pos in (0..8).rev() {
-                let input_bit = (byte >> bit_pos) & 1;
-                
-                // Shift input bit into encoder state
-                self.state = (self.state >> 1) | ((input_bit as u32) << (self.constraint_length - 1));
-                
-                // Generate output bits for each polynomial
-                let mut output_byte = 0u8;
-                for (i, &poly) in self.polynomials.iter().enumerate() {
-                    let output_bit = (self.state & poly).count_ones() & 1;
-                    output_byte |= (output_bit as u8) << (7 - i);
+        let (block, start) = self.block_bits(&data_bits);
+        let mut coded = self.puncture(self.encode_bits(&block, start));
+        
+        // Pad the last byte with zeros
+        coded.resize(coded.len().div_ceil(8) * 8, 0);
+        Ok(bits_to_bytes(&coded))
+    }
+    
+    fn code_rate(&self) -> f64 {
+        match &self.puncturing {
+            None => self.code_rate.0 as f64 / self.code_rate.1 as f64,
+            Some(pattern) => pattern.period() as f64 / pattern.kept_per_period() as f64,
+        }
+    }
+    
+    fn overhead_bytes(&self, input_len: usize) -> usize {
+        self.coded_bits(input_len * 8).div_ceil(8) - input_len
+    }
+}
+
+impl FecDecoder for Convolutional {
+    fn decode(&mut self, encoded_data: &[u8]) -> Result<Vec<u8>> {
+        let llrs: Vec<f64> = bytes_to_bits(encoded_data)
+            .into_iter()
+            .map(|bit| if bit == 0 { 1.0 } else { -1.0 })
+            .collect();
+        self.decode_soft(&llrs)
+    }
+    
+    fn can_correct(&self, _encoded_data: &[u8]) -> bool {
+        // Convolutional codes can always attempt correction
+        true
+    }
+    
+    fn error_stats(&self) -> ErrorStats {
+        self.stats.clone()
+    }
+}
+
+/// LLR given to shortened bits, which are known to be zero
+const KNOWN_BIT_LLR: f64 = 1e6;
+
+/// IEEE 802.11n rate 1/2 base matrix for n = 648 (Z = 27); -1 is an all-zero block
+const IEEE_802_11N_648_1_2: [[i16; 24]; 12] = [
+    [0, -1, -1, -1, 0, 0, -1, -1, 0, -1, -1, 0, 1, 0, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [22, 0, -1, -1, 17, -1, 0, 0, 12, -1, -1, -1, -1, 0, 0, -1, -1, -1, -1, -1, -1, -1, -1, -1],
+    [6, -1, 0, -1, 10, -1, -1, -1, 24, -1, 0, -1, -1, -1, 0, 0, -1, -1, -1, -1, -1, -1, -1, -1],
+    [2, -1, -1, 0, 20, -1, -1, -1, 25, 0, -1, -1, -1, -1, -1, 0, 0, -1, -1, -1, -1, -1, -1, -1],
+    [23, -1, -1, -1, 3, -1, -1, -1, 0, -1, 9, 11, -1, -1, -1, -1, 0, 0, -1, -1, -1, -1, -1, -1],
+    [24, -1, 23, 1, 17, -1, 3, -1, 10, -1, -1, -1, -1, -1, -1, -1, -1, 0, 0, -1, -1, -1, -1, -1],
+    [25, -1, -1, -1, 8, -1, -1, -1, 7, 18, -1, -1, 0, -1, -1, -1, -1, -1, 0, 0, -1, -1, -1, -1],
+    [13, 24, -1, -1, 0, -1, 8, -1, 6, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 0, 0, -1, -1, -1],
+    [7, 20, -1, 16, 22, 10, -1, -1, 23, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 0, 0, -1, -1],
+    [11, -1, -1, -1, 19, -1, -1, -1, 13, -1, 3, 17, -1, -1, -1, -1, -1, -1, -1, -1, -1, 0, 0, -1],
+    [25, -1, 8, -1, 23, 18, -1, 14, 9, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 0, 0],
+    [3, -1, -1, -1, 16, -1, -1, 2, 25, 5, -1, -1, 1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 0],
+];
+
+/// IEEE 802.11n rate 2/3 base matrix for n = 648 (Z = 27)
+const IEEE_802_11N_648_2_3: [[i16; 24]; 8] = [
+    [25, 26, 14, -1, 20, -1, 2, -1, 4, -1, -1, 8, -1, 16, -1, 18, 1, 0, -1, -1, -1, -1, -1, -1],
+    [10, 9, 15, 11, -1, 0, -1, 1, -1, -1, 18, -1, 8, -1, 10, -1, -1, 0, 0, -1, -1, -1, -1, -1],
+    [16, 2, 20, 26, 21, -1, 6, -1, 1, 26, -1, 7, -1, -1, -1, -1, -1, -1, 0, 0, -1, -1, -1, -1],
+    [10, 13, 5, 0, -1, 3, -1, 7, -1, -1, 26, -1, -1, 13, -1, 16, -1, -1, -1, 0, 0, -1, -1, -1],
+    [23, 14, 24, -1, 12, -1, 19, -1, 17, -1, -1, -1, 20, -1, 21, -1, 0, -1, -1, -1, 0, 0, -1, -1],
+    [6, 22, 9, 20, -1, 25, -1, 17, -1, 8, -1, 14, -1, 18, -1, -1, -1, -1, -1, -1, -1, 0, 0, -1],
+    [14, 23, 21, 11, 20, -1, 24, -1, 18, -1, 19, -1, -1, -1, -1, 22, -1, -1, -1, -1, -1, -1, 0, 0],
+    [17, 11, 11, 20, -1, 21, -1, 26, -1, 3, -1, -1, 18, -1, 26, -1, 1, -1, -1, -1, -1, -1, -1, 0],
+];
+
+/// IEEE 802.11n rate 3/4 base matrix for n = 648 (Z = 27)
+const IEEE_802_11N_648_3_4: [[i16; 24]; 6] = [
+    [16, 17, 22, 24, 9, 3, 14, -1, 4, 2, 7, -1, 26, -1, 2, -1, 21, -1, 1, 0, -1, -1, -1, -1],
+    [25, 12, 12, 3, 3, 26, 6, 21, -1, 15, 22, -1, 15, -1, 4, -1, -1, 16, -1, 0, 0, -1, -1, -1],
+    [25, 18, 26, 16, 22, 23, 9, -1, 0, -1, 4, -1, 4, -1, 8, 23, 11, -1, -1, -1, 0, 0, -1, -1],
+    [9, 7, 0, 1, 17, -1, -1, 7, 3, -1, 3, 23, -1, 16, -1, -1, 21, -1, 0, -1, -1, 0, 0, -1],
+    [24, 5, 26, 7, 1, -1, -1, 15, 24, 15, -1, 8, -1, 13, -1, 13, -1, 11, -1, -1, -1, -1, 0, 0],
+    [2, 2, 19, 14, 24, 1, 15, 19, -1, 21, -1, 2, -1, 24, -1, 3, -1, 2, 1, -1, -1, -1, -1, 0],
+];
+
+/// Lifting size of the 802.11n 648-bit codes
+const IEEE_802_11N_648_Z: usize = 27;
+
+/// Lifting size of [`Ldpc::short_frame`]: 816 information bits, 102 bytes
+const SHORT_FRAME_Z: usize = 68;
+
+/// Quasi-cyclic LDPC encoder/decoder
+///
+/// The parity-check matrix is given as a base matrix of cyclic shifts of a
+/// `Z x Z` identity, with the 802.11n parity structure: the first parity
+/// column has equal shifts in its top and bottom rows and a zero shift in
+/// one row between them, and the rest form a zero-shift dual diagonal.
+/// That structure makes encoding a linear-time back substitution.
+///
+/// Codewords are systematic. Each block carries `k / 8` whole data bytes
+/// followed by the `n - k` parity bits, and the coded bits of all blocks are
+/// packed MSB first with the last byte padded with zeros. Information bits
+/// with no data byte, including all of them past the end of a short final
+/// block, are zero and are not sent.
+///
+/// Decoding is layered normalized min-sum that stops as soon as every
+/// parity check is satisfied. After a decode,
+/// [`error_stats`](FecDecoder::error_stats) reports the received hard
+/// decisions the decoder flipped, the blocks that arrived with failed
+/// checks, and the blocks that still had failed checks after the last
+/// iteration.
+#[derive(Debug, Clone)]
+pub struct Ldpc {
+    n: usize, // Codeword bits
+    k: usize, // Information bits
+    z: usize,
+    base: Vec<Vec<i16>>,
+    /// Row of the first parity column with a zero shift
+    middle_row: usize,
+    /// Variable nodes of each parity check
+    checks: Vec<Vec<usize>>,
+    max_iterations: usize,
+    normalization: f64,
+    iterations: usize,
+    stats: ErrorStats,
+}
+
+impl Ldpc {
+    /// Default iteration limit
+    pub const DEFAULT_MAX_ITERATIONS: usize = 50;
+    
+    /// Default min-sum normalization factor
+    pub const DEFAULT_NORMALIZATION: f64 = 0.75;
+    
+    /// Create a codec from a base matrix of shifts (-1 for an all-zero
+    /// block) lifted by `z`; the last rows-many columns must have the
+    /// 802.11n parity structure
+    pub fn from_base_matrix(base: Vec<Vec<i16>>, z: usize) -> Result<Self> {
+        let invalid = |msg: String| Err(FrameError::InvalidFecParameters { msg });
+        let mb = base.len();
+        let nb = base.first().map_or(0, Vec::len);
+        if z == 0 || mb < 3 || nb <= mb || base.iter().any(|row| row.len() != nb) {
+            return invalid(format!("Invalid LDPC base matrix: {} rows of {} columns, Z={}", mb, nb, z));
+        }
+        if base.iter().flatten().any(|&shift| shift < -1 || shift as isize >= z as isize) {
+            return invalid(format!("LDPC shifts must be -1 or below Z={}", z));
+        }
+        
+        let kb = nb - mb;
+        let column = |j: usize| base.iter().map(move |row| row[j]);
+        let special: Vec<usize> = column(kb)
+            .enumerate()
+            .filter(|&(_, s)| s >= 0)
+            .map(|(i, _)| i)
+            .collect();
+        let middle_row = match special.as_slice() {
+            &[0, middle, last]
+                if last == mb - 1 && base[0][kb] == base[last][kb] && base[middle][kb] == 0 =>
+            {
+                middle
+            }
+            _ => return invalid("LDPC first parity column lacks the 802.11n structure".to_string()),
+        };
+        for j in 1..mb {
+            let expected = (0..mb).map(|i| if i == j - 1 || i == j { 0 } else { -1 });
+            if !column(kb + j).eq(expected) {
+                return invalid(format!("LDPC parity column {} is not on the dual diagonal", kb + j));
+            }
+        }
+        
+        let mut checks = vec![Vec::new(); mb * z];
+        for (i, row) in base.iter().enumerate() {
+            for (j, &shift) in row.iter().enumerate() {
+                if shift >= 0 {
+                    for r in 0..z {
+                        checks[i * z + r].push(j * z + (r + shift as usize) % z);
+                    }
                 }
-                
-                encoded.push(output_byte);
             }
         }
         
-        // Add tail bits to flush encoder
-        for _ in 0..(self.constraint_length - 1) {
-            self.state >>= 1;
+        Ok(Self {
+            n: nb * z,
+            k: kb * z,
+            z,
+            base,
+            middle_row,
+            checks,
+            max_iterations: Self::DEFAULT_MAX_ITERATIONS,
+            normalization: Self::DEFAULT_NORMALIZATION,
+            iterations: 0,
+            stats: ErrorStats::default(),
+        })
+    }
+    
+    /// IEEE 802.11n rate 1/2 code, n = 648
+    pub fn ieee_802_11n_648_1_2() -> Self {
+        Self::from_table(&IEEE_802_11N_648_1_2, IEEE_802_11N_648_Z)
+    }
+    
+    /// IEEE 802.11n rate 2/3 code, n = 648
+    pub fn ieee_802_11n_648_2_3() -> Self {
+        Self::from_table(&IEEE_802_11N_648_2_3, IEEE_802_11N_648_Z)
+    }
+    
+    /// IEEE 802.11n rate 3/4 code, n = 648
+    pub fn ieee_802_11n_648_3_4() -> Self {
+        Self::from_table(&IEEE_802_11N_648_3_4, IEEE_802_11N_648_Z)
+    }
+    
+    /// Rate 1/2 code with n = 1632, so a ~100-byte frame fits one codeword.
+    ///
+    /// This is the 802.11n rate 1/2 base graph lifted to Z = 68, with the
+    /// shifts scaled as 802.16e scales its rate 1/2 code: `floor(s * 68 / 27)`.
+    pub fn short_frame() -> Self {
+        let base = IEEE_802_11N_648_1_2
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&s| match s {
+                        -1 => s,
+                        _ => (s as usize * SHORT_FRAME_Z / IEEE_802_11N_648_Z) as i16,
+                    })
+                    .collect()
+            })
+            .collect();
+        Self::from_base_matrix(base, SHORT_FRAME_Z).expect("scaled 802.11n base matrix is valid")
+    }
+    
+    fn from_table<const N: usize>(table: &[[i16; N]], z: usize) -> Self {
+        let base = table.iter().map(|row| row.to_vec()).collect();
+        Self::from_base_matrix(base, z).expect("802.11n base matrix is valid")
+    }
+    
+    /// Set the iteration limit
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations.max(1);
+        self
+    }
+    
+    /// Set the factor check-to-variable messages are scaled by
+    pub fn with_normalization(mut self, normalization: f64) -> Self {
+        self.normalization = normalization;
+        self
+    }
+    
+    /// Codeword length in bits
+    pub fn codeword_bits(&self) -> usize {
+        self.n
+    }
+    
+    /// Information bits per codeword
+    pub fn information_bits(&self) -> usize {
+        self.k
+    }
+    
+    /// Most iterations any block needed in the last decode
+    pub fn last_iterations(&self) -> usize {
+        self.iterations
+    }
+    
+    /// Whole data bytes carried per block
+    fn block_bytes(&self) -> usize {
+        self.k / 8
+    }
+    
+    /// Coded bits sent for `data_len` bytes
+    fn coded_bits(&self, data_len: usize) -> usize {
+        data_len * 8 + data_len.div_ceil(self.block_bytes()) * (self.n - self.k)
+    }
+    
+    /// Data bytes that encode to `received` coded bits, allowing for the
+    /// zero padding of the last byte
+    fn data_len_for(&self, received: usize) -> Result<usize> {
+        let mut len = 0;
+        while self.coded_bits(len) <= received {
+            if received - self.coded_bits(len) < 8 {
+                return Ok(len);
+            }
+            len += 1;
+        }
+        Err(FrameError::FecDecodingFailed {
+            msg: format!("{} coded bits do not match any whole number of data bytes", received),
+        })
+    }
+    
+    /// Parity bits of an information word of `k` bits
+    fn parity(&self, info: &[u8]) -> Vec<u8> {
+        let z = self.z;
+        let mb = self.base.len();
+        let kb = self.k / z;
+        
+        // lambda_i: the information part of block row i
+        let mut lambda = vec![vec![0u8; z]; mb];
+        for (i, row) in self.base.iter().enumerate() {
+            for (j, &shift) in row[..kb].iter().enumerate() {
+                if shift >= 0 {
+                    for r in 0..z {
+                        lambda[i][r] ^= info[j * z + (r + shift as usize) % z];
+                    }
+                }
+            }
+        }
+        
+        // Adding all block rows cancels every parity block but the first.
+        let mut blocks = vec![vec![0u8; z]; mb];
+        for l in &lambda {
+            for (p, &bit) in blocks[0].iter_mut().zip(l) {
+                *p ^= bit;
+            }
+        }
+        let top_shift = self.base[0][kb] as usize;
+        for r in 0..z {
+            blocks[1][r] = lambda[0][r] ^ blocks[0][(r + top_shift) % z];
+        }
+        for i in 1..mb - 1 {
+            for r in 0..z {
+                let mut bit = lambda[i][r] ^ blocks[i][r];
+                if i == self.middle_row {
+                    bit ^= blocks[0][r];
+                }
+                blocks[i + 1][r] = bit;
+            }
+        }
+        blocks.concat()
+    }
+    
+    /// Whether every parity check holds for hard decisions `bits`
+    fn checks_hold(&self, bits: &[u8]) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.iter().fold(0, |acc, &v| acc ^ bits[v]) == 0)
+    }
+    
+    /// Decode soft decisions, one log-likelihood ratio `ln(P(0) / P(1))`
+    /// per coded bit, so positive values favour 0. Up to 7 trailing values
+    /// of byte padding are ignored.
+    pub fn decode_soft(&mut self, llrs: &[f64]) -> Result<Vec<u8>> {
+        let data_len = self.data_len_for(llrs.len())?;
+        let mut stats = ErrorStats::default();
+        let mut decoded = Vec::with_capacity(data_len);
+        let mut failure = None;
+        let mut offset = 0;
+        self.iterations = 0;
+        
+        let parity_bits = self.n - self.k;
+        let mut remaining = data_len;
+        while remaining > 0 {
+            let bytes = remaining.min(self.block_bytes());
+            let data_bits = bytes * 8;
+            let mut channel = vec![KNOWN_BIT_LLR; self.n];
+            channel[..data_bits].copy_from_slice(&llrs[offset..offset + data_bits]);
+            offset += data_bits;
+            channel[self.k..].copy_from_slice(&llrs[offset..offset + parity_bits]);
+            offset += parity_bits;
+            remaining -= bytes;
             
-            let mut output_byte = 0u8;
-            for (i, &poly) in self.polynomials.iter().enumerate() {
-                let output_bit = (self.state & poly).count_ones() & 1;
-                output_byte |= (output_bit as u8) << (7 - i);
+            let received: Vec<u8> = channel.iter().map(|&llr| u8::from(llr < 0.0)).collect();
+            let (bits, iterations) = self.min_sum(&channel);
+            self.iterations = self.iterations.max(iterations);
+            if !self.checks_hold(&received) {
+                stats.detected_errors += 1;
+            }
+            if self.checks_hold(&bits) {
+                stats.corrected_errors += bits
+                    .iter()
+                    .zip(&received)
+                    .zip(&channel)
+                    .filter(|&((&b, &r), &llr)| llr != 0.0 && b != r)
+                    .count();
+            } else {
+                stats.uncorrectable_errors += 1;
+                failure.get_or_insert(FrameError::FecDecodingFailed {
+                    msg: format!("LDPC block did not converge in {} iterations", iterations),
+                });
+            }
+            decoded.extend(bits_to_bytes(&bits[..data_bits]));
+        }
+        
+        self.stats = stats;
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(decoded),
+        }
+    }
+    
+    /// Layered normalized min-sum on one codeword, returning the hard
+    /// decisions and the number of iterations run
+    fn min_sum(&self, channel: &[f64]) -> (Vec<u8>, usize) {
+        let mut posterior = channel.to_vec();
+        let mut messages: Vec<Vec<f64>> = self.checks.iter().map(|check| vec![0.0; check.len()]).collect();
+        let mut incoming = Vec::new();
+        let hard = |posterior: &[f64]| -> Vec<u8> {
+            posterior.iter().map(|&llr| u8::from(llr < 0.0)).collect()
+        };
+        
+        let mut bits = hard(&posterior);
+        if self.checks_hold(&bits) {
+            return (bits, 0);
+        }
+        
+        for iteration in 1..=self.max_iterations {
+            for (check, check_messages) in self.checks.iter().zip(&mut messages) {
+                incoming.clear();
+                incoming.extend(check.iter().zip(check_messages.iter()).map(|(&v, &m)| posterior[v] - m));
+                
+                let mut min1 = f64::INFINITY;
+                let mut min2 = f64::INFINITY;
+                let mut min_index = 0;
+                let mut negative = false;
+                for (e, &value) in incoming.iter().enumerate() {
+                    let magnitude = value.abs();
+                    negative ^= value < 0.0;
+                    if magnitude < min1 {
+                        min2 = min1;
+                        min1 = magnitude;
+                        min_index = e;
+                    } else if magnitude < min2 {
+                        min2 = magnitude;
+                    }
+                }
+                
+                for (e, (&v, message)) in check.iter().zip(check_messages.iter_mut()).enumerate() {
+                    let magnitude = if e == min_index { min2 } else { min1 };
+                    let sign = if negative != (incoming[e] < 0.0) { -1.0 } else { 1.0 };
+                    *message = sign * self.normalization * magnitude;
+                    posterior[v] = incoming[e] + *message;
+                }
             }
             
-            encoded.push(output_byte);
+            bits = hard(&posterior);
+            if self.checks_hold(&bits) {
+                return (bits, iteration);
+            }
+        }
+        (bits, self.max_iterations)
+    }
+}
+
+impl FecEncoder for Ldpc {
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut coded = Vec::with_capacity(self.coded_bits(data.len()));
+        for block in data.chunks(self.block_bytes()) {
+            let mut info = bytes_to_bits(block);
+            coded.extend_from_slice(&info);
+            info.resize(self.k, 0);
+            coded.extend(self.parity(&info));
         }
         
-        Ok(encoded)
+        // Pad the last byte with zeros
+        coded.resize(coded.len().div_ceil(8) * 8, 0);
+        Ok(bits_to_bytes(&coded))
     }
     
     fn code_rate(&self) -> f64 {
-        self.code_rate.0 as f64 / self.code_rate.1 as f64
+        self.k as f64 / self.n as f64
     }
     
     fn overhead_bytes(&self, input_len: usize) -> usize {
-        let input_bits = input_len * 8;
-        let output_bits = input_bits * self.code_rate.1 / self.code_rate.0;
-        let tail_bits = (self.constraint_length - 1) * self.code_rate.1;
-        (output_bits + tail_bits + 7) / 8 // Convert to bytes
+        self.coded_bits(input_len).div_ceil(8) - input_len
     }
 }
 
-impl FecDecoder for Convolutional {
+impl FecDecoder for Ldpc {
     fn decode(&mut self, encoded_data: &[u8]) -> Result<Vec<u8>> {
-        // TODO: Implement Viterbi decoding algorithm
-        // For now, return a placeholder that takes every nth bit
-        let rate_ratio = self.code_rate.1 / self.code_rate.0;
-        let decoded_len = encoded_data.len() / rate_ratio;
-        let decoded = vec![0u8; decoded_len];
-        
-        Ok(decoded)
+        let llrs: Vec<f64> = bytes_to_bits(encoded_data)
+            .into_iter()
+            .map(|bit| if bit == 0 { 1.0 } else { -1.0 })
+            .collect();
+        self.decode_soft(&llrs)
     }
     
-    fn can_correct(&self, _encoded_data: &[u8]) -> bool {
-        // Convolutional codes can always attempt correction
-        true
+    fn can_correct(&self, encoded_data: &[u8]) -> bool {
+        self.clone().decode(encoded_data).is_ok()
     }
     
     fn error_stats(&self) -> ErrorStats {
-        // TODO: Return actual Viterbi decoder statistics
-        ErrorStats::default()
+        self.stats.clone()
     }
 }
 
@@ -335,6 +1506,121 @@ mod tests {
         assert_eq!(rs.t, 16);
     }
 
+    fn corrupt(block: &mut [u8], positions: &[usize]) {
+        for (i, &pos) in positions.iter().enumerate() {
+            block[pos] ^= 0x5a ^ i as u8;
+        }
+    }
+
+    #[test]
+    fn test_reed_solomon_encoding_is_systematic() {
+        let mut rs = ReedSolomon::rs_255_223().unwrap();
+        let data: Vec<u8> = (0..223).map(|i| (i * 37 + 11) as u8).collect();
+        let encoded = rs.encode(&data).unwrap();
+        assert_eq!(encoded.len(), 255);
+        assert_eq!(&encoded[..223], &data[..]);
+        assert!(rs.syndromes(&encoded).iter().all(|&s| s == 0));
+        assert!(rs.can_correct(&encoded));
+
+        assert_eq!(rs.decode(&encoded).unwrap(), data);
+        let stats = rs.error_stats();
+        assert_eq!(stats.corrected_errors, 0);
+        assert_eq!(stats.detected_errors, 0);
+    }
+
+    #[test]
+    fn test_reed_solomon_corrects_errors() {
+        let mut rs = ReedSolomon::rs_255_223().unwrap();
+        let data: Vec<u8> = (0..223).map(|i| (i * 7) as u8).collect();
+        let mut encoded = rs.encode(&data).unwrap();
+        let positions: Vec<usize> = (0..16).map(|i| i * 15 + 3).collect();
+        corrupt(&mut encoded, &positions);
+
+        assert!(rs.can_correct(&encoded));
+        assert_eq!(rs.decode(&encoded).unwrap(), data);
+        let stats = rs.error_stats();
+        assert_eq!(stats.corrected_errors, 16);
+        assert_eq!(stats.detected_errors, 1);
+        assert_eq!(stats.uncorrectable_errors, 0);
+
+        // One more error than the code can handle.
+        corrupt(&mut encoded, &[250]);
+        assert!(!rs.can_correct(&encoded));
+        assert!(rs.decode(&encoded).is_err());
+        let stats = rs.error_stats();
+        assert_eq!(stats.uncorrectable_errors, 1);
+        assert_eq!(stats.corrected_errors, 0);
+    }
+
+    #[test]
+    fn test_reed_solomon_erasures() {
+        let mut rs = ReedSolomon::rs_255_223().unwrap();
+        let data: Vec<u8> = (0..223).map(|i| (i * 13 + 5) as u8).collect();
+        let encoded = rs.encode(&data).unwrap();
+
+        // 2t erasures, twice what could be fixed blind.
+        let erased: Vec<usize> = (0..32).map(|i| i * 7 + 1).collect();
+        let mut received = encoded.clone();
+        corrupt(&mut received, &erased);
+        assert!(rs.decode(&received).is_err());
+        assert_eq!(rs.decode_with_erasures(&received, &erased).unwrap(), data);
+        assert_eq!(rs.error_stats().corrected_errors, 32);
+
+        // Mixed: 2 * 10 errors + 12 erasures = 32 parity symbols. An erased
+        // symbol that happens to be right is not counted as corrected.
+        let errors: Vec<usize> = (0..10).map(|i| i * 20 + 2).collect();
+        let erased: Vec<usize> = (0..12).map(|i| i * 20 + 9).collect();
+        let mut received = encoded.clone();
+        corrupt(&mut received, &errors);
+        corrupt(&mut received, &erased[..8]);
+        assert_eq!(rs.decode_with_erasures(&received, &erased).unwrap(), data);
+        assert_eq!(rs.error_stats().corrected_errors, 18);
+
+        assert!(rs.decode_with_erasures(&encoded, &[255]).is_err());
+        let too_many: Vec<usize> = (0..33).collect();
+        assert!(rs.decode_with_erasures(&encoded, &too_many).is_err());
+    }
+
+    #[test]
+    fn test_reed_solomon_shortened_and_multi_block() {
+        let mut rs = ReedSolomon::rs_255_223().unwrap();
+        let short = b"CQ CQ DE N0CALL K";
+        let mut encoded = rs.encode(short).unwrap();
+        assert_eq!(encoded.len(), short.len() + 32);
+        corrupt(&mut encoded, &(0..16).map(|i| i * 3).collect::<Vec<_>>());
+        assert_eq!(rs.decode(&encoded).unwrap(), short);
+        assert_eq!(rs.error_stats().corrected_errors, 16);
+
+        let data: Vec<u8> = (0..500).map(|i| (i % 251) as u8).collect();
+        let mut encoded = rs.encode(&data).unwrap();
+        assert_eq!(encoded.len(), data.len() + rs.overhead_bytes(data.len()));
+        corrupt(&mut encoded, &[10, 300, 590]);
+        assert_eq!(rs.decode(&encoded).unwrap(), data);
+        let stats = rs.error_stats();
+        assert_eq!(stats.corrected_errors, 3);
+        assert_eq!(stats.detected_errors, 3);
+
+        // A final block with nothing but parity cannot be decoded.
+        assert!(rs.decode(&encoded[..255 + 32]).is_err());
+    }
+
+    #[test]
+    fn test_reed_solomon_field_parameters() {
+        // CCSDS field polynomial and first root.
+        let mut rs = ReedSolomon::with_field(255, 223, 0x187, 112).unwrap();
+        let data: Vec<u8> = (0..100).map(|i| (i * 3 + 1) as u8).collect();
+        let mut encoded = rs.encode(&data).unwrap();
+        corrupt(&mut encoded, &[0, 50, 99, 131]);
+        assert_eq!(rs.decode_with_erasures(&encoded, &[131]).unwrap(), data);
+        assert_eq!(rs.error_stats().corrected_errors, 4);
+
+        // The AES polynomial is irreducible but x does not generate its field.
+        assert!(ReedSolomon::with_field(255, 223, 0x11b, 0).is_err());
+        assert!(ReedSolomon::with_field(255, 223, 0x3d, 0).is_err());
+        assert!(ReedSolomon::new(10, 10).is_err());
+        assert!(ReedSolomon::new(256, 200).is_err());
+    }
+
     #[test]
     fn test_convolutional_creation() {
         let conv = Convolutional::nasa_standard().unwrap();
@@ -342,6 +1628,229 @@ mod tests {
         assert_eq!(conv.code_rate, (1, 2));
     }
 
+    fn flip_bits(encoded: &mut [u8], positions: &[usize]) {
+        for &pos in positions {
+            encoded[pos / 8] ^= 0x80 >> (pos % 8);
+        }
+    }
+
+    #[test]
+    fn test_viterbi_hard_decisions() {
+        let mut conv = Convolutional::nasa_standard().unwrap();
+        let data = b"CQ CQ CQ DE N0CALL N0CALL K";
+        let mut encoded = conv.encode(data).unwrap();
+        assert_eq!(encoded.len(), data.len() + conv.overhead_bytes(data.len()));
+        assert_eq!(encoded.len(), (data.len() * 16 + 12).div_ceil(8));
+
+        assert_eq!(conv.decode(&encoded).unwrap(), data);
+        assert_eq!(conv.error_stats().corrected_errors, 0);
+
+        // Scattered errors, one every 40 coded bits, including the tail.
+        let errors: Vec<usize> = (0..11).map(|i| i * 40 + 5).collect();
+        flip_bits(&mut encoded, &errors);
+        assert_eq!(conv.decode(&encoded).unwrap(), data);
+        let stats = conv.error_stats();
+        assert_eq!(stats.corrected_errors, errors.len());
+        assert_eq!(stats.detected_errors, 1);
+        assert_eq!(stats.uncorrectable_errors, 0);
+
+        // A shallow traceback still works on sparse errors.
+        let mut shallow = Convolutional::nasa_standard().unwrap().with_traceback_depth(10);
+        assert_eq!(shallow.traceback_depth(), 10);
+        assert_eq!(shallow.decode(&encoded).unwrap(), data);
+        assert!(conv.decode(&encoded[..3]).is_err());
+    }
+
+    #[test]
+    fn test_viterbi_soft_decisions() {
+        let mut conv = Convolutional::nasa_standard().unwrap();
+        let data: Vec<u8> = (0..32).map(|i| (i * 29 + 3) as u8).collect();
+        let coded = bytes_to_bits(&conv.encode(&data).unwrap());
+        let clean: Vec<f64> = coded.iter().map(|&b| if b == 0 { 4.0 } else { -4.0 }).collect();
+
+        // A burst of weak wrong decisions next to confident right ones:
+        // too dense for hard decisions, easy with soft ones.
+        let mut llrs = clean.clone();
+        for llr in &mut llrs[100..112] {
+            *llr = -0.3 * llr.signum();
+        }
+        let hard: Vec<u8> = llrs.iter().map(|&l| u8::from(l < 0.0)).collect();
+        let mut padded = hard.clone();
+        padded.resize(hard.len().div_ceil(8) * 8, 0);
+        assert_ne!(conv.decode(&bits_to_bytes(&padded)).unwrap(), data);
+        assert_eq!(conv.decode_soft(&llrs).unwrap(), data);
+        assert_eq!(conv.error_stats().corrected_errors, 12);
+
+        let quantized: Vec<u8> = llrs
+            .iter()
+            .map(|&l| if l.abs() < 1.0 { if l > 0.0 { 3 } else { 4 } } else if l > 0.0 { 0 } else { 7 })
+            .collect();
+        assert_eq!(conv.decode_quantized(&quantized).unwrap(), data);
+        assert!(conv.decode_quantized(&[8; 64]).is_err());
+    }
+
+    #[test]
+    fn test_viterbi_punctured() {
+        let data: Vec<u8> = (0..40).map(|i| (i * 11 + 7) as u8).collect();
+        let patterns = [
+            (PuncturePattern::rate_2_3(), 2.0 / 3.0),
+            (PuncturePattern::rate_3_4(), 3.0 / 4.0),
+            (PuncturePattern::rate_5_6(), 5.0 / 6.0),
+        ];
+        for (pattern, rate) in patterns {
+            let mut conv = Convolutional::nasa_standard().unwrap().with_puncturing(pattern).unwrap();
+            assert!((conv.code_rate() - rate).abs() < 1e-12);
+            assert_eq!(conv.traceback_depth(), 70);
+
+            let mut encoded = conv.encode(&data).unwrap();
+            let coded_bits = ((data.len() * 8 + 6) as f64 / rate).ceil() as usize;
+            assert_eq!(encoded.len(), coded_bits.div_ceil(8));
+            assert_eq!(conv.decode(&encoded).unwrap(), data);
+
+            flip_bits(&mut encoded, &[20, 150, 300]);
+            assert_eq!(conv.decode(&encoded).unwrap(), data);
+            assert_eq!(conv.error_stats().corrected_errors, 3);
+        }
+
+        let three_rows = PuncturePattern::new(vec![vec![true, true]; 3]).unwrap();
+        assert!(Convolutional::nasa_standard().unwrap().with_puncturing(three_rows).is_err());
+        assert!(PuncturePattern::new(vec![vec![true, false], vec![true, false]]).is_err());
+        assert!(PuncturePattern::new(vec![vec![true], vec![true, false]]).is_err());
+    }
+
+    #[test]
+    fn test_viterbi_tail_biting() {
+        let mut conv = Convolutional::nasa_standard()
+            .unwrap()
+            .with_termination(Termination::TailBiting);
+        let data: Vec<u8> = (0..24).map(|i| (i * 53 + 200) as u8).collect();
+        let mut encoded = conv.encode(&data).unwrap();
+        assert_eq!(encoded.len(), data.len() * 2);
+        assert_eq!(conv.overhead_bytes(data.len()), data.len());
+        assert_eq!(conv.decode(&encoded).unwrap(), data);
+
+        // Errors at both ends of the circular block.
+        flip_bits(&mut encoded, &[0, 3, 120, 250, 380, 383]);
+        assert_eq!(conv.decode(&encoded).unwrap(), data);
+        assert_eq!(conv.error_stats().corrected_errors, 6);
+
+        // K=15 cannot hold its start state in a single byte.
+        let mut long = Convolutional::new(15, vec![0o46321, 0o51271])
+            .unwrap()
+            .with_termination(Termination::TailBiting);
+        assert!(long.encode(&[0xa5]).is_err());
+        assert!(Convolutional::new(7, vec![0o400]).is_err());
+    }
+
+    /// BPSK LLRs for `coded` through AWGN at `es_n0_db`
+    fn awgn_llrs(coded: &[u8], es_n0_db: f64, seed: u64) -> Vec<f64> {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let sigma = (0.5 / 10f64.powf(es_n0_db / 10.0)).sqrt();
+        coded
+            .iter()
+            .map(|&bit| {
+                let (u1, u2): (f64, f64) = (rng.gen::<f64>().max(1e-300), rng.gen());
+                let noise = sigma * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                let symbol = if bit == 0 { 1.0 } else { -1.0 };
+                2.0 * (symbol + noise) / (sigma * sigma)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ldpc_codewords_satisfy_checks() {
+        let codes = [
+            (Ldpc::ieee_802_11n_648_1_2(), 648, 324),
+            (Ldpc::ieee_802_11n_648_2_3(), 648, 432),
+            (Ldpc::ieee_802_11n_648_3_4(), 648, 486),
+            (Ldpc::short_frame(), 1632, 816),
+        ];
+        for (mut code, n, k) in codes {
+            assert_eq!(code.codeword_bits(), n);
+            assert_eq!(code.information_bits(), k);
+            assert!((code.code_rate() - k as f64 / n as f64).abs() < 1e-12);
+
+            let info: Vec<u8> = (0..k).map(|i| ((i * 7 + i / 5) % 3 == 0) as u8).collect();
+            let mut codeword = info.clone();
+            codeword.extend(code.parity(&info));
+            assert!(code.checks_hold(&codeword));
+
+            let data: Vec<u8> = (0..k / 8).map(|i| (i * 31 + 9) as u8).collect();
+            let encoded = code.encode(&data).unwrap();
+            assert_eq!(encoded.len(), data.len() + code.overhead_bytes(data.len()));
+            assert_eq!(code.decode(&encoded).unwrap(), data);
+            assert_eq!(code.last_iterations(), 0);
+        }
+        assert_eq!(Ldpc::short_frame().block_bytes(), 102);
+    }
+
+    #[test]
+    fn test_ldpc_soft_decoding_in_noise() {
+        let mut code = Ldpc::ieee_802_11n_648_1_2();
+        let data: Vec<u8> = (0..40).map(|i| (i * 97 + 13) as u8).collect();
+        let coded = bytes_to_bits(&code.encode(&data).unwrap());
+
+        // Es/N0 = 0 dB (Eb/N0 = 3 dB): about 8% of hard decisions are wrong.
+        for seed in 0..5 {
+            let llrs = awgn_llrs(&coded, 0.0, seed);
+            assert_eq!(code.decode_soft(&llrs).unwrap(), data, "seed {}", seed);
+            let stats = code.error_stats();
+            assert!(stats.corrected_errors > 20, "seed {}: {:?}", seed, stats);
+            assert_eq!(stats.detected_errors, 1);
+            assert!(code.last_iterations() > 1);
+        }
+
+        // Far below capacity the decoder gives up instead of guessing.
+        let llrs = awgn_llrs(&coded, -6.0, 1);
+        assert!(code.decode_soft(&llrs).is_err());
+        let stats = code.error_stats();
+        assert_eq!(stats.uncorrectable_errors, 1);
+        assert_eq!(stats.corrected_errors, 0);
+        assert_eq!(code.last_iterations(), Ldpc::DEFAULT_MAX_ITERATIONS);
+    }
+
+    #[test]
+    fn test_ldpc_shortened_blocks_and_hard_decisions() {
+        // 100 bytes at 60 bytes per rate 3/4 block: one full block and one
+        // shortened block.
+        let mut code = Ldpc::ieee_802_11n_648_3_4().with_max_iterations(20);
+        let data: Vec<u8> = (0..100).map(|i| (i * 17 + 1) as u8).collect();
+        let mut encoded = code.encode(&data).unwrap();
+        assert_eq!(encoded.len(), (800 + 2 * 162usize).div_ceil(8));
+
+        for pos in [3, 200, 500, 700, 1000] {
+            encoded[pos / 8] ^= 0x80 >> (pos % 8);
+        }
+        assert!(code.can_correct(&encoded));
+        assert_eq!(code.decode(&encoded).unwrap(), data);
+        let stats = code.error_stats();
+        assert_eq!(stats.corrected_errors, 5);
+        assert_eq!(stats.detected_errors, 2);
+        assert!(code.decode(&encoded[..10]).is_err());
+
+        let short = b"CQ CQ DE N0CALL";
+        let mut frame = Ldpc::short_frame().with_normalization(0.8);
+        let encoded = frame.encode(short).unwrap();
+        assert_eq!(encoded.len(), (short.len() * 8 + 816usize).div_ceil(8));
+        assert_eq!(frame.decode(&encoded).unwrap(), short);
+    }
+
+    #[test]
+    fn test_ldpc_rejects_bad_base_matrix() {
+        let mut base: Vec<Vec<i16>> = IEEE_802_11N_648_1_2.iter().map(|row| row.to_vec()).collect();
+        assert!(Ldpc::from_base_matrix(base.clone(), 27).is_ok());
+        assert!(Ldpc::from_base_matrix(base.clone(), 20).is_err());
+        assert!(Ldpc::from_base_matrix(base.clone(), 0).is_err());
+        base[3][14] = 0;
+        assert!(Ldpc::from_base_matrix(base.clone(), 27).is_err());
+        base[3][14] = -1;
+        base[11][12] = 2;
+        assert!(Ldpc::from_base_matrix(base, 27).is_err());
+    }
+
     #[test]
     fn test_parity_check() {
         let mut parity = ParityCheck::new(true);
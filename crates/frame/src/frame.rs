@@ -3,9 +3,67 @@
 use crate::{FrameError, Result};
 use serde::{Deserialize, Serialize};
 
+/// Frame format version
+///
+/// A v2 header starts with the version in its high nibble. A v1 header
+/// starts with the frame type instead, and every v1 frame type in use is
+/// below 0x10, so its high nibble reads as 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FrameVersion {
+    /// 8-byte header with a one's-complement checksum; no payload check
+    V1,
+    /// 9-byte header protected by CRC-16/CCITT; the payload is followed by
+    /// its CRC-32
+    #[default]
+    V2,
+}
+
+impl FrameVersion {
+    /// Header size in bytes
+    pub fn header_size(self) -> usize {
+        match self {
+            FrameVersion::V1 => 8,
+            FrameVersion::V2 => 9,
+        }
+    }
+
+    /// Bytes following the payload
+    pub fn trailer_size(self) -> usize {
+        match self {
+            FrameVersion::V1 => 0,
+            FrameVersion::V2 => 4,
+        }
+    }
+
+    fn from_nibble(nibble: u8) -> Result<Self> {
+        match nibble {
+            0 => Ok(FrameVersion::V1),
+            2 => Ok(FrameVersion::V2),
+            _ => Err(FrameError::InvalidFormat {
+                msg: format!("Unsupported frame version {}", nibble),
+            }),
+        }
+    }
+}
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF, no
+/// reflection
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
 /// Frame header containing metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrameHeader {
+    /// Format version
+    pub version: FrameVersion,
     /// Frame type identifier
     pub frame_type: u8,
     /// Sequence number
@@ -14,17 +72,19 @@ pub struct FrameHeader {
     pub payload_length: u16,
     /// Frame flags
     pub flags: u8,
-    /// Header checksum
+    /// Header checksum: CRC-16/CCITT for v2, one's-complement sum for v1
     pub checksum: u16,
 }
 
 impl FrameHeader {
-    /// Size of the frame header in bytes
-    pub const SIZE: usize = 8;
+    /// Size of the largest (v2) frame header in bytes; see
+    /// [`FrameVersion::header_size`] for a particular version
+    pub const SIZE: usize = 9;
     
-    /// Create a new frame header
+    /// Create a new v2 frame header
     pub fn new(frame_type: u8, sequence: u16, payload_length: u16, flags: u8) -> Self {
         let mut header = Self {
+            version: FrameVersion::V2,
             frame_type,
             sequence,
             payload_length,
@@ -35,15 +95,49 @@ impl FrameHeader {
         header
     }
     
+    /// Switch the header to another format version
+    pub fn with_version(mut self, version: FrameVersion) -> Self {
+        self.version = version;
+        self.checksum = self.calculate_checksum();
+        self
+    }
+    
+    /// Header size in bytes
+    pub fn size(&self) -> usize {
+        self.version.header_size()
+    }
+    
+    /// Bytes the whole frame occupies: header, payload and trailer
+    pub fn frame_size(&self) -> usize {
+        self.size() + self.payload_length as usize + self.version.trailer_size()
+    }
+    
+    /// Header bytes up to, not including, the checksum
+    fn field_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.size());
+        if self.version == FrameVersion::V2 {
+            bytes.push(2 << 4);
+        }
+        bytes.push(self.frame_type);
+        bytes.extend_from_slice(&self.sequence.to_be_bytes());
+        bytes.extend_from_slice(&self.payload_length.to_be_bytes());
+        bytes.push(self.flags);
+        bytes
+    }
+    
     /// Calculate header checksum
     fn calculate_checksum(&self) -> u16 {
-        // Simple checksum calculation (CRC16 would be better)
-        let mut sum = 0u16;
-        sum = sum.wrapping_add(self.frame_type as u16);
-        sum = sum.wrapping_add(self.sequence);
-        sum = sum.wrapping_add(self.payload_length);
-        sum = sum.wrapping_add(self.flags as u16);
-        !sum // One's complement
+        match self.version {
+            FrameVersion::V1 => {
+                let mut sum = 0u16;
+                sum = sum.wrapping_add(self.frame_type as u16);
+                sum = sum.wrapping_add(self.sequence);
+                sum = sum.wrapping_add(self.payload_length);
+                sum = sum.wrapping_add(self.flags as u16);
+                !sum // One's complement
+            }
+            FrameVersion::V2 => crc16_ccitt(&self.field_bytes()),
+        }
     }
     
     /// Validate header checksum
@@ -53,30 +147,33 @@ impl FrameHeader {
     
     /// Serialize header to bytes
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(Self::SIZE);
-        bytes.push(self.frame_type);
-        bytes.extend_from_slice(&self.sequence.to_be_bytes());
-        bytes.extend_from_slice(&self.payload_length.to_be_bytes());
-        bytes.push(self.flags);
+        let mut bytes = self.field_bytes();
         bytes.extend_from_slice(&self.checksum.to_be_bytes());
         bytes
     }
     
-    /// Deserialize header from bytes
+    /// Deserialize a header of either version from bytes
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        if bytes.len() < Self::SIZE {
+        let version = match bytes.first() {
+            Some(&first) => FrameVersion::from_nibble(first >> 4)?,
+            None => FrameVersion::V1,
+        };
+        if bytes.len() < version.header_size() {
             return Err(FrameError::InvalidFormat {
                 msg: format!("Header too short: {} bytes", bytes.len()),
             });
         }
         
-        let frame_type = bytes[0];
-        let sequence = u16::from_be_bytes([bytes[1], bytes[2]]);
-        let payload_length = u16::from_be_bytes([bytes[3], bytes[4]]);
-        let flags = bytes[5];
-        let checksum = u16::from_be_bytes([bytes[6], bytes[7]]);
+        // Past the v2 version byte the two layouts are the same.
+        let fields = &bytes[version.header_size() - FrameVersion::V1.header_size()..];
+        let frame_type = fields[0];
+        let sequence = u16::from_be_bytes([fields[1], fields[2]]);
+        let payload_length = u16::from_be_bytes([fields[3], fields[4]]);
+        let flags = fields[5];
+        let checksum = u16::from_be_bytes([fields[6], fields[7]]);
         
         let header = Self {
+            version,
             frame_type,
             sequence,
             payload_length,
@@ -84,7 +181,9 @@ impl FrameHeader {
             checksum,
         };
         
-        if !header.validate_checksum() {
+        // The checksum is recomputed over a canonical version byte, so the
+        // received one is checked separately: its low nibble is reserved.
+        if !header.validate_checksum() || (version == FrameVersion::V2 && bytes[0] != 2 << 4) {
             return Err(FrameError::InvalidFormat {
                 msg: "Header checksum mismatch".to_string(),
             });
@@ -102,7 +201,7 @@ pub struct Frame {
 }
 
 impl Frame {
-    /// Create a new frame
+    /// Create a new v2 frame
     pub fn new(frame_type: u8, sequence: u16, payload: Vec<u8>, flags: u8) -> Self {
         let header = FrameHeader::new(frame_type, sequence, payload.len() as u16, flags);
         Self { header, payload }
@@ -110,27 +209,26 @@ impl Frame {
     
     /// Get total frame size in bytes
     pub fn total_size(&self) -> usize {
-        FrameHeader::SIZE + self.payload.len()
+        self.header.size() + self.payload.len() + self.header.version.trailer_size()
     }
     
     /// Serialize frame to bytes
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = self.header.to_bytes();
         bytes.extend_from_slice(&self.payload);
+        if self.header.version == FrameVersion::V2 {
+            bytes.extend_from_slice(&crc32fast::hash(&self.payload).to_be_bytes());
+        }
         bytes
     }
     
-    /// Deserialize frame from bytes
+    /// Deserialize a frame of either version from bytes. A v2 frame whose
+    /// payload fails its CRC-32 is rejected with
+    /// [`FrameError::PayloadCrcMismatch`].
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        if bytes.len() < FrameHeader::SIZE {
-            return Err(FrameError::InvalidFormat {
-                msg: "Frame too short for header".to_string(),
-            });
-        }
-        
-        let header = FrameHeader::from_bytes(&bytes[..FrameHeader::SIZE])?;
+        let header = FrameHeader::from_bytes(bytes)?;
         
-        let expected_total_size = FrameHeader::SIZE + header.payload_length as usize;
+        let expected_total_size = header.frame_size();
         if bytes.len() < expected_total_size {
             return Err(FrameError::SizeMismatch {
                 expected: expected_total_size,
@@ -138,7 +236,16 @@ impl Frame {
             });
         }
         
-        let payload = bytes[FrameHeader::SIZE..expected_total_size].to_vec();
+        let payload_end = header.size() + header.payload_length as usize;
+        let payload = bytes[header.size()..payload_end].to_vec();
+        if header.version == FrameVersion::V2 {
+            let trailer = &bytes[payload_end..expected_total_size];
+            let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+            let actual = crc32fast::hash(&payload);
+            if expected != actual {
+                return Err(FrameError::PayloadCrcMismatch { expected, actual });
+            }
+        }
         
         Ok(Self { header, payload })
     }
@@ -149,6 +256,7 @@ pub struct FrameBuilder {
     frame_type: u8,
     sequence: u16,
     flags: u8,
+    version: FrameVersion,
 }
 
 impl FrameBuilder {
@@ -158,6 +266,7 @@ impl FrameBuilder {
             frame_type,
             sequence: 0,
             flags: 0,
+            version: FrameVersion::default(),
         }
     }
     
@@ -173,9 +282,17 @@ impl FrameBuilder {
         self
     }
     
+    /// Set the format version, e.g. v1 for receivers that predate v2
+    pub fn version(mut self, version: FrameVersion) -> Self {
+        self.version = version;
+        self
+    }
+    
     /// Build frame with payload
     pub fn build(self, payload: Vec<u8>) -> Frame {
-        Frame::new(self.frame_type, self.sequence, payload, self.flags)
+        let header = FrameHeader::new(self.frame_type, self.sequence, payload.len() as u16, self.flags)
+            .with_version(self.version);
+        Frame { header, payload }
     }
 }
 
@@ -256,4 +373,50 @@ mod tests {
         assert_eq!(frame.header.flags, frame_flags::PRIORITY);
         assert_eq!(frame.payload, payload);
     }
+
+    #[test]
+    fn test_v2_integrity_checks() {
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+
+        let frame = Frame::new(frame_types::DATA, 7, b"integrity".to_vec(), frame_flags::NONE);
+        let bytes = frame.to_bytes();
+        assert_eq!(bytes[0], 0x20);
+        assert_eq!(bytes.len(), frame.total_size());
+        assert_eq!(bytes.len(), FrameHeader::SIZE + 9 + 4);
+
+        // A flipped payload bit no longer gets through.
+        let mut corrupt = bytes.clone();
+        corrupt[FrameHeader::SIZE + 2] ^= 0x10;
+        assert!(matches!(Frame::from_bytes(&corrupt), Err(FrameError::PayloadCrcMismatch { .. })));
+
+        // Nor does any single-bit header error, including in the version byte.
+        for bit in 0..FrameHeader::SIZE * 8 {
+            let mut corrupt = bytes.clone();
+            corrupt[bit / 8] ^= 0x80 >> (bit % 8);
+            assert!(Frame::from_bytes(&corrupt).is_err(), "bit {}", bit);
+        }
+        let mut future = bytes.clone();
+        future[0] = 0x30;
+        assert!(matches!(Frame::from_bytes(&future), Err(FrameError::InvalidFormat { .. })));
+    }
+
+    #[test]
+    fn test_v1_frames_still_parse() {
+        // A v1 frame exactly as earlier releases put it on the air.
+        let mut legacy = vec![0x01, 0x00, 0x2A, 0x00, 0x02, 0x00];
+        legacy.extend_from_slice(&(!(1u16 + 42 + 2)).to_be_bytes());
+        legacy.extend_from_slice(b"hi");
+        let frame = Frame::from_bytes(&legacy).unwrap();
+        assert_eq!(frame.header.version, FrameVersion::V1);
+        assert_eq!(frame.header.sequence, 42);
+        assert_eq!(frame.payload, b"hi");
+        assert_eq!(frame.total_size(), legacy.len());
+        assert_eq!(frame.to_bytes(), legacy);
+
+        let built = FrameBuilder::new(frame_types::DATA)
+            .sequence(42)
+            .version(FrameVersion::V1)
+            .build(b"hi".to_vec());
+        assert_eq!(built.to_bytes(), legacy);
+    }
 }
\ No newline at end of file
@@ -10,13 +10,16 @@
 //!   timing/AGC) and a sync word, then hand the bytes to a modulator.
 //! - **Receive:** take the demodulated **bit** stream, correlate the sync word
 //!   at every bit offset (tolerating a few bit errors), byte-align from there,
-//!   and slice out each frame using its header length.
+//!   and slice out each frame using its header length. A v2 frame whose
+//!   payload fails its CRC-32 is dropped and counted in [`AcquisitionStats`].
 //!
 //! Working at bit granularity is what makes acquisition robust: after timing
 //! recovery the demodulator yields correct symbols, but the frame can begin at
 //! any bit position in that stream.
 
-use crate::frame::Frame;
+use crate::frame::{Frame, FrameHeader};
+use crate::FrameError;
+use openham_core::flowgraph::Block;
 
 /// Preamble: alternating bits (`0x55 = 01010101`) give the demodulator a clean
 /// run to settle symbol timing and gain before the sync word arrives.
@@ -64,6 +67,16 @@ pub fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
     bytes
 }
 
+/// What a scan turned up besides the frames themselves
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AcquisitionStats {
+    /// Frames accepted
+    pub frames: usize,
+    /// Frames whose header checked out but whose payload failed its CRC-32;
+    /// these are dropped, not returned
+    pub payload_errors: usize,
+}
+
 /// Locates frames in a demodulated bit stream by correlating the sync word.
 #[derive(Debug, Clone)]
 pub struct Acquisition {
@@ -94,9 +107,38 @@ impl Acquisition {
     /// At each bit offset the sync word is correlated; on a match (within the
     /// error budget) the following bits are byte-aligned and parsed as a
     /// [`Frame`]. A frame is accepted only if its header checksum validates, so
-    /// false sync locks are rejected. Scanning then resumes after the accepted
-    /// frame.
+    /// false sync locks are rejected, and, for v2, only if its payload CRC
+    /// does too. Scanning then resumes after the frame.
     pub fn find_frames(&self, bits: &[u8]) -> Vec<Frame> {
+        self.find_frames_with_stats(bits).0
+    }
+
+    /// [`find_frames`](Acquisition::find_frames), also counting the frames
+    /// dropped for a corrupt payload
+    pub fn find_frames_with_stats(&self, bits: &[u8]) -> (Vec<Frame>, AcquisitionStats) {
+        let (located, stats) = self.locate_frames(bits);
+        (located.into_iter().map(|(_, frame)| frame).collect(), stats)
+    }
+
+    /// [`find_frames_with_stats`](Acquisition::find_frames_with_stats), with
+    /// each frame paired with the bit offset of its sync word in `bits`, so
+    /// the caller can place the frame in time
+    pub fn locate_frames(&self, bits: &[u8]) -> (Vec<(usize, Frame)>, AcquisitionStats) {
+        let (located, stats, _) = self.scan(bits, None);
+        (located, stats)
+    }
+
+    /// Scan `bits` as [`find_frames`](Acquisition::find_frames) does. With
+    /// `max_frame_bytes` set, the bits are the head of a longer stream:
+    /// scanning stops at the first sync whose frame (of at most that many
+    /// bytes) has not fully arrived. Returns the frames with their sync
+    /// offsets, the counts and the offset scanning stopped at; nothing before
+    /// it can start another frame.
+    fn scan(
+        &self,
+        bits: &[u8],
+        max_frame_bytes: Option<usize>,
+    ) -> (Vec<(usize, Frame)>, AcquisitionStats, usize) {
         let sync_bits = bytes_to_bits(&SYNC_WORD);
         let sync_len = sync_bits.len();
         // Also match the bitwise-inverse sync. A coherent demodulator (e.g. BPSK)
@@ -105,6 +147,7 @@ impl Acquisition {
         // inverting the payload bits before parsing.
         let inv_sync: Vec<u8> = sync_bits.iter().map(|b| b ^ 1).collect();
         let mut frames = Vec::new();
+        let mut stats = AcquisitionStats::default();
 
         let mut i = 0usize;
         while i + sync_len <= bits.len() {
@@ -129,23 +172,126 @@ impl Acquisition {
                 } else {
                     bits_to_bytes(&bits[start..])
                 };
-                if let Ok(frame) = Frame::from_bytes(&frame_bytes) {
-                    // Advance past the bits this frame consumed and keep scanning.
-                    i = start + frame.total_size() * 8;
-                    frames.push(frame);
-                    continue;
+                if let Some(max) = max_frame_bytes {
+                    if frame_pending(&frame_bytes, max) {
+                        return (frames, stats, i);
+                    }
+                }
+                match Frame::from_bytes(&frame_bytes) {
+                    Ok(frame) => {
+                        // Advance past the bits this frame consumed and keep scanning.
+                        let sync_offset = i;
+                        i = start + frame.total_size() * 8;
+                        frames.push((sync_offset, frame));
+                        stats.frames += 1;
+                        continue;
+                    }
+                    Err(FrameError::PayloadCrcMismatch { .. }) => {
+                        // The header is sound, so its length is too: skip the
+                        // whole frame rather than relock inside its payload.
+                        if let Ok(header) = FrameHeader::from_bytes(&frame_bytes) {
+                            i = start + header.frame_size() * 8;
+                            stats.payload_errors += 1;
+                            continue;
+                        }
+                    }
+                    Err(_) => {}
                 }
             }
             i += 1;
         }
-        frames
+        (frames, stats, i)
+    }
+}
+
+/// Whether `bytes` after a sync match could still grow into a frame of at
+/// most `max_frame_bytes`: either the header hasn't arrived yet, or it has, is
+/// valid, and the payload (and trailer) it announces hasn't.
+fn frame_pending(bytes: &[u8], max_frame_bytes: usize) -> bool {
+    if bytes.len() < FrameHeader::SIZE {
+        return true;
+    }
+    match FrameHeader::from_bytes(bytes) {
+        Ok(header) => {
+            let total = header.frame_size();
+            total <= max_frame_bytes && bytes.len() < total
+        }
+        Err(_) => false,
+    }
+}
+
+/// Largest frame an [`AcquisitionBlock`] waits for by default, in bytes
+pub const DEFAULT_MAX_FRAME_BYTES: usize = 4096;
+
+/// Frame acquisition as a flowgraph [`Block`]: demodulated bits in, frames
+/// out
+///
+/// Unlike [`Acquisition::find_frames`], frames may straddle input chunks.
+/// Bits are kept only until the scan has settled them, so memory stays
+/// bounded by the largest frame awaited.
+#[derive(Debug, Clone)]
+pub struct AcquisitionBlock {
+    acquisition: Acquisition,
+    max_frame_bytes: usize,
+    bits: Vec<u8>,
+    stats: AcquisitionStats,
+}
+
+impl AcquisitionBlock {
+    pub fn new(acquisition: Acquisition) -> Self {
+        Self {
+            acquisition,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            bits: Vec::new(),
+            stats: AcquisitionStats::default(),
+        }
+    }
+
+    /// Set the largest frame, header included, to wait for; a header that
+    /// announces more is treated as a false lock.
+    pub fn with_max_frame_bytes(mut self, max: usize) -> Self {
+        self.max_frame_bytes = max;
+        self
+    }
+
+    /// Counts over everything scanned so far
+    pub fn stats(&self) -> AcquisitionStats {
+        self.stats
+    }
+
+    fn add_stats(&mut self, stats: AcquisitionStats) {
+        self.stats.frames += stats.frames;
+        self.stats.payload_errors += stats.payload_errors;
+    }
+}
+
+impl Block for AcquisitionBlock {
+    type Input = u8;
+    type Output = Frame;
+
+    fn work(&mut self, input: &[u8], output: &mut Vec<Frame>) -> openham_core::Result<()> {
+        self.bits.extend_from_slice(input);
+        let (frames, stats, settled) =
+            self.acquisition.scan(&self.bits, Some(self.max_frame_bytes));
+        output.extend(frames.into_iter().map(|(_, frame)| frame));
+        self.add_stats(stats);
+        self.bits.drain(..settled);
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<Frame>) -> openham_core::Result<()> {
+        let (frames, stats, _) = self.acquisition.scan(&self.bits, None);
+        output.extend(frames.into_iter().map(|(_, frame)| frame));
+        self.add_stats(stats);
+        self.bits.clear();
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::frame::{frame_flags, frame_types};
+    use crate::frame::{frame_flags, frame_types, FrameBuilder, FrameVersion};
 
     fn make_frame(payload: &[u8]) -> Frame {
         Frame::new(frame_types::DATA, 9, payload.to_vec(), frame_flags::NONE)
@@ -181,6 +327,10 @@ mod tests {
         let found = Acquisition::new().find_frames(&bits);
         assert_eq!(found.len(), 1, "should find exactly one frame");
         assert_eq!(found[0].payload, payload);
+
+        // The sync word follows the junk and the preamble.
+        let (located, _) = Acquisition::new().locate_frames(&bits);
+        assert_eq!(located[0].0, 11 + PREAMBLE.len() * 8);
     }
 
     #[test]
@@ -236,4 +386,59 @@ mod tests {
         assert_eq!(found[0].payload, b"first");
         assert_eq!(found[1].payload, b"second frame");
     }
+
+    #[test]
+    fn streaming_block_acquires_frames_across_chunks() {
+        let f1 = make_frame(b"first");
+        let f2 = make_frame(b"a second, longer frame");
+        let mut bits = vec![1, 0, 1];
+        bits.extend(bytes_to_bits(&add_preamble_sync(&f1.to_bytes())));
+        bits.extend(vec![0; 100]);
+        let inverted: Vec<u8> = bytes_to_bits(&add_preamble_sync(&f2.to_bytes()))
+            .iter()
+            .map(|b| b ^ 1)
+            .collect();
+        bits.extend(inverted);
+        bits.extend_from_slice(&[0, 1, 1]);
+
+        // Chunk sizes that split the sync words and headers.
+        for chunk_size in [1, 7, 50, bits.len()] {
+            let mut block = AcquisitionBlock::new(Acquisition::new());
+            let mut frames = Vec::new();
+            for chunk in bits.chunks(chunk_size) {
+                block.work(chunk, &mut frames).unwrap();
+                assert!(block.bits.len() < 400, "unsettled bits pile up");
+            }
+            block.flush(&mut frames).unwrap();
+            let payloads: Vec<&[u8]> = frames.iter().map(|f| f.payload.as_slice()).collect();
+            assert_eq!(payloads, [&b"first"[..], b"a second, longer frame"], "chunk {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn drops_and_counts_payload_corrupt_frames() {
+        let corrupt = make_frame(b"garbled text");
+        let mut corrupt_bytes = add_preamble_sync(&corrupt.to_bytes());
+        corrupt_bytes[PREAMBLE.len() + SYNC_WORD.len() + FrameHeader::SIZE + 3] ^= 0x04;
+        let legacy = FrameBuilder::new(frame_types::DATA)
+            .version(FrameVersion::V1)
+            .build(b"legacy".to_vec());
+        let mut bits = bytes_to_bits(&corrupt_bytes);
+        bits.extend(bytes_to_bits(&add_preamble_sync(&make_frame(b"clean").to_bytes())));
+        bits.extend(bytes_to_bits(&add_preamble_sync(&legacy.to_bytes())));
+
+        let (found, stats) = Acquisition::new().find_frames_with_stats(&bits);
+        let payloads: Vec<&[u8]> = found.iter().map(|f| f.payload.as_slice()).collect();
+        assert_eq!(payloads, [&b"clean"[..], b"legacy"]);
+        assert_eq!(stats, AcquisitionStats { frames: 2, payload_errors: 1 });
+
+        let mut block = AcquisitionBlock::new(Acquisition::new());
+        let mut frames = Vec::new();
+        for chunk in bits.chunks(13) {
+            block.work(chunk, &mut frames).unwrap();
+        }
+        block.flush(&mut frames).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(block.stats(), stats);
+    }
 }
@@ -3,12 +3,16 @@
 use crate::{FrameError, Result};
 
 /// Generic interleaver trait
-pub trait Interleaver {
+///
+/// The interleavers only move elements around, so the same permutation
+/// applies to bytes, hard bits or soft-decision LLRs (`f64`) on their way
+/// to a soft-input decoder.
+pub trait Interleaver<T: Copy = u8> {
     /// Interleave data to spread errors
-    fn interleave(&mut self, data: &[u8]) -> Result<Vec<u8>>;
+    fn interleave(&mut self, data: &[T]) -> Result<Vec<T>>;
     
     /// Deinterleave data to concentrate errors
-    fn deinterleave(&mut self, data: &[u8]) -> Result<Vec<u8>>;
+    fn deinterleave(&mut self, data: &[T]) -> Result<Vec<T>>;
     
     /// Reset interleaver state
     fn reset(&mut self);
@@ -38,8 +42,8 @@ impl BlockInterleaver {
     }
 }
 
-impl Interleaver for BlockInterleaver {
-    fn interleave(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+impl<T: Copy + Default> Interleaver<T> for BlockInterleaver {
+    fn interleave(&mut self, data: &[T]) -> Result<Vec<T>> {
         let block_size = self.block_size();
         
         if data.len() % block_size != 0 {
@@ -67,7 +71,7 @@ impl Interleaver for BlockInterleaver {
         Ok(result)
     }
     
-    fn deinterleave(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+    fn deinterleave(&mut self, data: &[T]) -> Result<Vec<T>> {
         let block_size = self.block_size();
         
         if data.len() % block_size != 0 {
@@ -84,7 +88,7 @@ impl Interleaver for BlockInterleaver {
             let block = &data[block_start..block_end];
             
             // Create temporary matrix
-            let mut matrix = vec![vec![0u8; self.cols]; self.rows];
+            let mut matrix = vec![vec![T::default(); self.cols]; self.rows];
             
             // Fill matrix column by column
             let mut index = 0;
@@ -112,10 +116,13 @@ impl Interleaver for BlockInterleaver {
 }
 
 /// Convolutional interleaver implementation
-pub struct ConvolutionalInterleaver {
+///
+/// The delay lines start out, and are reset to, `T::default()`: zero bytes,
+/// or 0.0 LLRs that a soft decoder treats as erasures.
+pub struct ConvolutionalInterleaver<T = u8> {
     branches: usize,
     depth: usize,
-    delays: Vec<Vec<u8>>,
+    delays: Vec<Vec<T>>,
     input_index: usize,
     output_index: usize,
 }
@@ -123,6 +130,14 @@ pub struct ConvolutionalInterleaver {
 impl ConvolutionalInterleaver {
     /// Create a new convolutional interleaver
     pub fn new(branches: usize, depth: usize) -> Result<Self> {
+        Self::with_elements(branches, depth)
+    }
+}
+
+impl<T: Copy + Default> ConvolutionalInterleaver<T> {
+    /// Create a convolutional interleaver over any element type, e.g. `f64`
+    /// LLRs; [`new`](ConvolutionalInterleaver::new) is the byte form
+    pub fn with_elements(branches: usize, depth: usize) -> Result<Self> {
         if branches == 0 {
             return Err(FrameError::InterleavingError {
                 msg: "Number of branches must be greater than 0".to_string(),
@@ -133,7 +148,7 @@ impl ConvolutionalInterleaver {
         let mut delays = Vec::with_capacity(branches);
         for i in 0..branches {
             let delay_length = i * depth;
-            delays.push(vec![0u8; delay_length]);
+            delays.push(vec![T::default(); delay_length]);
         }
         
         Ok(Self {
@@ -151,8 +166,8 @@ impl ConvolutionalInterleaver {
     }
 }
 
-impl Interleaver for ConvolutionalInterleaver {
-    fn interleave(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+impl<T: Copy + Default> Interleaver<T> for ConvolutionalInterleaver<T> {
+    fn interleave(&mut self, data: &[T]) -> Result<Vec<T>> {
         let mut result = Vec::with_capacity(data.len());
         
         for &byte in data {
@@ -181,7 +196,7 @@ impl Interleaver for ConvolutionalInterleaver {
         Ok(result)
     }
     
-    fn deinterleave(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+    fn deinterleave(&mut self, data: &[T]) -> Result<Vec<T>> {
         let mut result = Vec::with_capacity(data.len());
         
         for &byte in data {
@@ -210,7 +225,7 @@ impl Interleaver for ConvolutionalInterleaver {
     
     fn reset(&mut self) {
         for delay in &mut self.delays {
-            delay.fill(0);
+            delay.fill(T::default());
         }
         self.input_index = 0;
         self.output_index = 0;
@@ -218,8 +233,8 @@ impl Interleaver for ConvolutionalInterleaver {
 }
 
 /// Helical interleaver (variant of convolutional)
-pub struct HelicalInterleaver {
-    matrix: Vec<Vec<u8>>,
+pub struct HelicalInterleaver<T = u8> {
+    matrix: Vec<Vec<T>>,
     rows: usize,
     cols: usize,
     input_pos: (usize, usize),
@@ -229,13 +244,21 @@ pub struct HelicalInterleaver {
 impl HelicalInterleaver {
     /// Create a new helical interleaver
     pub fn new(rows: usize, cols: usize) -> Result<Self> {
+        Self::with_elements(rows, cols)
+    }
+}
+
+impl<T: Copy + Default> HelicalInterleaver<T> {
+    /// Create a helical interleaver over any element type; [`new`](HelicalInterleaver::new)
+    /// is the byte form
+    pub fn with_elements(rows: usize, cols: usize) -> Result<Self> {
         if rows == 0 || cols == 0 {
             return Err(FrameError::InterleavingError {
                 msg: "Interleaver dimensions must be greater than 0".to_string(),
             });
         }
         
-        let matrix = vec![vec![0u8; cols]; rows];
+        let matrix = vec![vec![T::default(); cols]; rows];
         
         Ok(Self {
             matrix,
@@ -259,8 +282,8 @@ impl HelicalInterleaver {
     }
 }
 
-impl Interleaver for HelicalInterleaver {
-    fn interleave(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+impl<T: Copy + Default> Interleaver<T> for HelicalInterleaver<T> {
+    fn interleave(&mut self, data: &[T]) -> Result<Vec<T>> {
         let mut result = Vec::with_capacity(data.len());
         
         for &byte in data {
@@ -279,7 +302,7 @@ impl Interleaver for HelicalInterleaver {
         Ok(result)
     }
     
-    fn deinterleave(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+    fn deinterleave(&mut self, data: &[T]) -> Result<Vec<T>> {
         // For helical interleaver, deinterleaving is the same as interleaving
         // with different starting positions
         self.interleave(data)
@@ -287,7 +310,7 @@ impl Interleaver for HelicalInterleaver {
     
     fn reset(&mut self) {
         for row in &mut self.matrix {
-            row.fill(0);
+            row.fill(T::default());
         }
         self.input_pos = (0, 0);
         self.output_pos = (0, 0);
@@ -327,6 +350,20 @@ mod tests {
         assert_eq!(interleaved, vec![0, 2, 1, 3]);
     }
 
+    #[test]
+    fn test_interleavers_permute_soft_values() {
+        let llrs: Vec<f64> = (0..12).map(|i| i as f64 - 5.5).collect();
+        let mut block = BlockInterleaver::new(3, 4).unwrap();
+        let interleaved = block.interleave(&llrs).unwrap();
+        assert_eq!(interleaved[..3], [-5.5, -1.5, 2.5]);
+        assert_eq!(block.deinterleave(&interleaved).unwrap(), llrs);
+
+        // Delay lines start out holding erasures.
+        let mut convolutional = ConvolutionalInterleaver::<f64>::with_elements(2, 1).unwrap();
+        let out = convolutional.interleave(&[3.0; 4]).unwrap();
+        assert_eq!(out, vec![3.0, 0.0, 3.0, 3.0]);
+    }
+
     #[test]
     fn test_convolutional_interleaver_creation() {
         let interleaver = ConvolutionalInterleaver::new(4, 2).unwrap();
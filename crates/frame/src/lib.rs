@@ -8,6 +8,7 @@ pub mod framing;
 pub mod fec;
 pub mod interleave;
 pub mod multimedia;
+pub mod sweep;
 pub mod error;
 
 pub use error::{FrameError, Result};
@@ -15,14 +16,18 @@ pub use error::{FrameError, Result};
 /// Re-export commonly used types
 pub mod prelude {
     pub use crate::{
-        frame::{Frame, FrameBuilder, FrameHeader},
-        framing::{add_preamble_sync, bits_to_bytes, bytes_to_bits, Acquisition, PREAMBLE, SYNC_WORD},
-        fec::{FecEncoder, FecDecoder, ReedSolomon, Convolutional},
+        frame::{Frame, FrameBuilder, FrameHeader, FrameVersion},
+        framing::{
+            add_preamble_sync, bits_to_bytes, bytes_to_bits, Acquisition, AcquisitionBlock,
+            AcquisitionStats, PREAMBLE, SYNC_WORD,
+        },
+        fec::{FecEncoder, FecDecoder, ReedSolomon, Convolutional, Ldpc},
         interleave::{Interleaver, BlockInterleaver, ConvolutionalInterleaver},
         multimedia::{
             MultimediaHeader, MultimediaFrame, MediaType, CompressionType,
             FrameSplitter, FrameAssembler, TransmissionFrame,
         },
+        sweep::{frame_sweep, frame_sweep_to_csv, FrameSweepConfig, FrameSweepPoint},
         error::{FrameError, Result},
     };
 }
@@ -0,0 +1,374 @@
+//! Frame-error-rate sweeps through the full frame stack.
+//!
+//! [`openham_core::metrics`] measures raw bit errors on a bare payload. This
+//! harness measures what a user actually sees: each trial builds a [`Frame`]
+//! around a fresh random payload, wraps it with [`add_preamble_sync`],
+//! modulates it, passes it through a [`ChannelChain`], demodulates, and runs
+//! [`Acquisition::find_frames`] on the recovered bits. A trial is counted as
+//!
+//! - a **missed sync** when no acquired frame carries the transmitted header
+//!   and no frame was dropped for a corrupt payload,
+//! - a **false sync** when any acquired frame carries some other header (a
+//!   lock on noise or on a corrupted stream that happened to pass the header
+//!   checksum),
+//! - a **frame error** when the transmitted payload was not recovered intact
+//!   (missed syncs included).
+//!
+//! Like the bit-level sweeps, the modem is supplied by the caller as a pair of
+//! closures, so this crate does not depend on `openham-modem`. The channel is
+//! built per SNR point by a caller-supplied factory; how the SNR is applied
+//! (per-sample SNR, Eb/N0, fading preset...) is up to that factory.
+//!
+//! Each SNR point stops early once enough frame errors have been collected,
+//! and points run on a pool of worker threads. Every point is seeded from the
+//! sweep seed and its index, so results do not depend on the thread count.
+
+use crate::frame::{frame_flags, frame_types, Frame};
+use crate::framing::{add_preamble_sync, Acquisition};
+use openham_core::buffer::Complex;
+use openham_core::channel::{Channel, ChannelChain};
+use openham_core::metrics::{wilson_interval, Z_95};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Frame sweep settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSweepConfig {
+    /// Payload bytes per frame
+    pub payload_len: usize,
+    /// Trials run at every point before early stopping is considered
+    pub min_trials: usize,
+    /// Hard cap on trials per point
+    pub max_trials: usize,
+    /// Stop a point once this many frame errors have been seen
+    pub target_errors: usize,
+    /// Zero samples placed before the burst (receiver listening to silence)
+    pub lead_in_samples: usize,
+    /// Zero samples placed after the burst, so filter and channel tails are
+    /// not cut off
+    pub tail_samples: usize,
+    /// Worker threads; 0 uses the available parallelism
+    pub threads: usize,
+    /// Seed for payloads and channel factories
+    pub seed: u64,
+}
+
+impl Default for FrameSweepConfig {
+    fn default() -> Self {
+        Self {
+            payload_len: 32,
+            min_trials: 20,
+            max_trials: 1000,
+            target_errors: 50,
+            lead_in_samples: 0,
+            tail_samples: 0,
+            threads: 0,
+            seed: 2024,
+        }
+    }
+}
+
+/// Outcome counts at one SNR point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameSweepPoint {
+    pub snr_db: f64,
+    pub trials: usize,
+    pub frame_errors: usize,
+    pub missed_syncs: usize,
+    pub false_syncs: usize,
+}
+
+impl FrameSweepPoint {
+    /// Frame error rate
+    pub fn fer(&self) -> f64 {
+        rate(self.frame_errors, self.trials)
+    }
+
+    /// Fraction of frames whose sync and header were not acquired
+    pub fn missed_sync_rate(&self) -> f64 {
+        rate(self.missed_syncs, self.trials)
+    }
+
+    /// Fraction of trials with at least one spurious frame lock
+    pub fn false_sync_rate(&self) -> f64 {
+        rate(self.false_syncs, self.trials)
+    }
+
+    /// 95% Wilson interval on the frame error rate
+    pub fn fer_interval(&self) -> (f64, f64) {
+        wilson_interval(self.frame_errors, self.trials, Z_95)
+    }
+
+    /// 95% Wilson interval on the missed-sync rate
+    pub fn missed_sync_interval(&self) -> (f64, f64) {
+        wilson_interval(self.missed_syncs, self.trials, Z_95)
+    }
+
+    /// 95% Wilson interval on the false-sync rate
+    pub fn false_sync_interval(&self) -> (f64, f64) {
+        wilson_interval(self.false_syncs, self.trials, Z_95)
+    }
+}
+
+fn rate(count: usize, trials: usize) -> f64 {
+    if trials == 0 {
+        0.0
+    } else {
+        count as f64 / trials as f64
+    }
+}
+
+/// Run a frame-level sweep over `snr_db_points`.
+///
+/// * `modulate` — turns the framed bytes (preamble, sync, frame) into samples.
+/// * `demodulate` — turns received samples into a bit stream, one bit per
+///   byte (a `BitDemodulator`, or [`bytes_to_bits`] over a byte-oriented
+///   demodulator). The frame may start at any bit offset.
+/// * `make_channel` — builds the channel for one SNR point from the point's
+///   SNR and a seed. It is called once per point on the worker thread, and the
+///   chain is reused for every trial at that point.
+///
+/// Points are returned in the order given.
+pub fn frame_sweep<M, D, C>(
+    config: &FrameSweepConfig,
+    snr_db_points: &[f64],
+    modulate: M,
+    demodulate: D,
+    make_channel: C,
+) -> Vec<FrameSweepPoint>
+where
+    M: Fn(&[u8]) -> Vec<Complex> + Sync,
+    D: Fn(&[Complex]) -> Vec<u8> + Sync,
+    C: Fn(f64, u64) -> ChannelChain + Sync,
+{
+    let threads = match config.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .min(snr_db_points.len().max(1));
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; snr_db_points.len()]);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&snr_db) = snr_db_points.get(index) else {
+                    break;
+                };
+                let point = run_point(
+                    config,
+                    snr_db,
+                    point_seed(config.seed, index),
+                    &modulate,
+                    &demodulate,
+                    &make_channel,
+                );
+                results.lock().unwrap()[index] = Some(point);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|point| point.expect("every point is swept"))
+        .collect()
+}
+
+/// Decorrelate per-point seeds (SplitMix64 increment) so neighbouring points
+/// do not share noise.
+fn point_seed(seed: u64, index: usize) -> u64 {
+    seed.wrapping_add((index as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+fn run_point(
+    config: &FrameSweepConfig,
+    snr_db: f64,
+    seed: u64,
+    modulate: &impl Fn(&[u8]) -> Vec<Complex>,
+    demodulate: &impl Fn(&[Complex]) -> Vec<u8>,
+    make_channel: &impl Fn(f64, u64) -> ChannelChain,
+) -> FrameSweepPoint {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut channel = make_channel(snr_db, rng.gen());
+    let acquisition = Acquisition::new();
+    let mut point = FrameSweepPoint {
+        snr_db,
+        trials: 0,
+        frame_errors: 0,
+        missed_syncs: 0,
+        false_syncs: 0,
+    };
+
+    while point.trials < config.max_trials
+        && (point.trials < config.min_trials || point.frame_errors < config.target_errors)
+    {
+        let payload: Vec<u8> = (0..config.payload_len).map(|_| rng.gen()).collect();
+        let sent = Frame::new(
+            frame_types::DATA,
+            point.trials as u16,
+            payload,
+            frame_flags::NONE,
+        );
+
+        let mut samples = vec![Complex::default(); config.lead_in_samples];
+        samples.extend(modulate(&add_preamble_sync(&sent.to_bytes())));
+        samples.extend(vec![Complex::default(); config.tail_samples]);
+        channel.apply(&mut samples);
+
+        let (frames, stats) = acquisition.find_frames_with_stats(&demodulate(&samples));
+        // A frame dropped for its payload CRC was still acquired: its header
+        // passed a CRC-16, which noise all but never does.
+        let mut acquired = stats.payload_errors > 0;
+        let mut intact = false;
+        let mut spurious = false;
+        for frame in frames {
+            if frame.header.to_bytes() == sent.header.to_bytes() {
+                acquired = true;
+                intact |= frame.payload == sent.payload;
+            } else {
+                spurious = true;
+            }
+        }
+
+        point.trials += 1;
+        point.frame_errors += usize::from(!intact);
+        point.missed_syncs += usize::from(!acquired);
+        point.false_syncs += usize::from(spurious);
+    }
+    point
+}
+
+/// Render frame sweep points as CSV with 95% Wilson bounds (header included).
+pub fn frame_sweep_to_csv(points: &[FrameSweepPoint]) -> String {
+    let mut s = String::from(
+        "snr_db,trials,fer,fer_low,fer_high,missed_sync,missed_low,missed_high,\
+         false_sync,false_low,false_high\n",
+    );
+    for p in points {
+        let (fer_low, fer_high) = p.fer_interval();
+        let (missed_low, missed_high) = p.missed_sync_interval();
+        let (false_low, false_high) = p.false_sync_interval();
+        s.push_str(&format!(
+            "{:.2},{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6}\n",
+            p.snr_db,
+            p.trials,
+            p.fer(),
+            fer_low,
+            fer_high,
+            p.missed_sync_rate(),
+            missed_low,
+            missed_high,
+            p.false_sync_rate(),
+            false_low,
+            false_high
+        ));
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::bytes_to_bits;
+    use openham_core::channel::Awgn;
+
+    // One real sample per bit, +1/-1: per-sample SNR equals Eb/N0.
+    fn modulate(bytes: &[u8]) -> Vec<Complex> {
+        bytes_to_bits(bytes)
+            .into_iter()
+            .map(|bit| Complex::new(if bit == 1 { 1.0 } else { -1.0 }, 0.0))
+            .collect()
+    }
+
+    fn demodulate(samples: &[Complex]) -> Vec<u8> {
+        samples.iter().map(|s| u8::from(s.real > 0.0)).collect()
+    }
+
+    fn awgn(snr_db: f64, seed: u64) -> ChannelChain {
+        ChannelChain::new().then(Awgn::new(snr_db, seed))
+    }
+
+    #[test]
+    fn fer_falls_with_snr_and_bounds_hold() {
+        let config = FrameSweepConfig {
+            payload_len: 16,
+            max_trials: 200,
+            target_errors: 30,
+            threads: 2,
+            ..FrameSweepConfig::default()
+        };
+        let points = frame_sweep(&config, &[0.0, 4.0, 12.0], modulate, demodulate, awgn);
+        assert_eq!(points.len(), 3);
+
+        // 0 dB: 8% raw BER corrupts essentially every 224-bit frame, and the
+        // point stops as soon as the error target is met.
+        assert_eq!(
+            points[0].trials,
+            config.min_trials.max(config.target_errors)
+        );
+        assert!(points[0].fer() > 0.95);
+        // 12 dB: error-free, so the point runs to the trial cap.
+        assert_eq!(points[2].trials, config.max_trials);
+        assert_eq!(points[2].frame_errors, 0);
+        assert!(points[2].fer_interval().1 < 0.02);
+        assert!(points[1].fer() < points[0].fer());
+
+        for p in &points {
+            assert!(p.missed_syncs <= p.frame_errors);
+            let (low, high) = p.fer_interval();
+            assert!(low <= p.fer() && p.fer() <= high);
+        }
+    }
+
+    #[test]
+    fn results_do_not_depend_on_thread_count() {
+        let snrs = [2.0, 5.0, 8.0];
+        let run = |threads| {
+            let config = FrameSweepConfig {
+                payload_len: 8,
+                max_trials: 60,
+                lead_in_samples: 13,
+                tail_samples: 5,
+                threads,
+                ..FrameSweepConfig::default()
+            };
+            frame_sweep(&config, &snrs, modulate, demodulate, awgn)
+        };
+        assert_eq!(run(1), run(3));
+    }
+
+    #[test]
+    fn counts_missed_and_false_syncs() {
+        let config = FrameSweepConfig {
+            payload_len: 4,
+            max_trials: 25,
+            threads: 1,
+            ..FrameSweepConfig::default()
+        };
+
+        // A receiver that hears nothing misses every frame.
+        let deaf = |_: &[Complex]| Vec::new();
+        let points = frame_sweep(&config, &[10.0], modulate, deaf, awgn);
+        assert_eq!(points[0].missed_syncs, 25);
+        assert_eq!(points[0].frame_errors, 25);
+        assert_eq!(points[0].false_syncs, 0);
+
+        // A receiver that always hears a stale frame locks falsely every time.
+        let stale = add_preamble_sync(&Frame::new(frame_types::ACK, 999, vec![1], 0).to_bytes());
+        let haunted = |samples: &[Complex]| {
+            let mut bits = demodulate(samples);
+            bits.extend(bytes_to_bits(&stale));
+            bits
+        };
+        let points = frame_sweep(&config, &[30.0], modulate, haunted, awgn);
+        assert_eq!(points[0].false_syncs, 25);
+        assert_eq!(points[0].frame_errors, 0);
+        assert!(points[0].false_sync_interval().0 > 0.8);
+    }
+}
@@ -7,7 +7,7 @@
 //! This is a diagnostic tool, not a pass/fail test. It prints a punch-list.
 
 use openham_core::buffer::Complex;
-use openham_core::metrics::{count_bit_errors, ebn0_sweep};
+use openham_core::metrics::{count_bit_errors, ebn0_sweep, Fading, TheoryModulation};
 use openham_modem::afsk::{AfskConfig, AfskDemodulator, AfskModulator};
 use openham_modem::bpsk::{BpskDemodulator, BpskModulator};
 use openham_modem::common::{Demodulator, ModulationConfig, Modulator};
@@ -25,6 +25,7 @@ struct Mode {
     make_demod: MakeDemod,
     k: f64,   // bits per symbol (for Eb/N0 labeling)
     sps: f64, // samples per symbol
+    theory: TheoryModulation,
 }
 
 /// Standard config: 48 kHz, given baud, 1500 Hz carrier.
@@ -41,6 +42,7 @@ fn modes() -> Vec<Mode> {
             make_demod: Box::new(|| Box::new(BpskDemodulator::new(cfg(1_200.0)).unwrap())),
             k: 1.0,
             sps,
+            theory: TheoryModulation::Bpsk,
         },
         Mode {
             name: "fsk@1200",
@@ -48,6 +50,7 @@ fn modes() -> Vec<Mode> {
             make_demod: Box::new(|| Box::new(FskDemodulator::new(cfg(1_200.0)).unwrap())),
             k: 1.0,
             sps,
+            theory: TheoryModulation::NoncoherentFsk,
         },
         Mode {
             name: "fsk@250",
@@ -55,6 +58,7 @@ fn modes() -> Vec<Mode> {
             make_demod: Box::new(|| Box::new(FskDemodulator::new(cfg(250.0)).unwrap())),
             k: 1.0,
             sps: 48_000.0 / 250.0,
+            theory: TheoryModulation::NoncoherentFsk,
         },
         Mode {
             name: "fsk@125",
@@ -62,6 +66,7 @@ fn modes() -> Vec<Mode> {
             make_demod: Box::new(|| Box::new(FskDemodulator::new(cfg(125.0)).unwrap())),
             k: 1.0,
             sps: 48_000.0 / 125.0,
+            theory: TheoryModulation::NoncoherentFsk,
         },
         Mode {
             name: "afsk",
@@ -74,6 +79,7 @@ fn modes() -> Vec<Mode> {
             }),
             k: 1.0,
             sps,
+            theory: TheoryModulation::NoncoherentFsk,
         },
         Mode {
             name: "psk4(qpsk)",
@@ -85,6 +91,7 @@ fn modes() -> Vec<Mode> {
             }),
             k: 2.0,
             sps,
+            theory: TheoryModulation::Psk(4),
         },
         Mode {
             name: "psk8",
@@ -96,6 +103,7 @@ fn modes() -> Vec<Mode> {
             }),
             k: 3.0,
             sps,
+            theory: TheoryModulation::Psk(8),
         },
         Mode {
             name: "qam16",
@@ -107,6 +115,7 @@ fn modes() -> Vec<Mode> {
             }),
             k: 4.0,
             sps,
+            theory: TheoryModulation::Qam(16),
         },
         Mode {
             name: "qam64",
@@ -118,6 +127,7 @@ fn modes() -> Vec<Mode> {
             }),
             k: 6.0,
             sps,
+            theory: TheoryModulation::Qam(64),
         },
         Mode {
             name: "ofdm64",
@@ -128,8 +138,10 @@ fn modes() -> Vec<Mode> {
                 Box::new(OfdmDemodulator::new(cfg(1_200.0), OfdmConfig::amateur_radio_64()).unwrap())
             }),
             // OFDM Eb/N0 labeling is approximate (multi-carrier); curve still informative.
+            // Its subcarriers are BPSK, so that is the closest reference.
             k: 1.0,
             sps,
+            theory: TheoryModulation::Bpsk,
         },
     ]
 }
@@ -139,10 +151,10 @@ fn main() {
 
     println!("Raw-modem survey (no codec / no outer frame), payload = {} bytes\n", payload.len());
     println!(
-        "{:<12} {:>10} {:>10}   {:<28} {:>10} {:>10} {:>10}",
-        "mode", "clean_ber", "rx/tx", "clean status", "ber@2dB", "ber@6dB", "ber@10dB"
+        "{:<12} {:>10} {:>10}   {:<28} {:>10} {:>10} {:>10} {:>10}",
+        "mode", "clean_ber", "rx/tx", "clean status", "ber@2dB", "ber@6dB", "ber@10dB", "theory@6dB"
     );
-    println!("{}", "-".repeat(100));
+    println!("{}", "-".repeat(111));
 
     for m in modes() {
         // 1. Modulate.
@@ -188,6 +200,7 @@ fn main() {
                 &[2.0, 6.0, 10.0],
                 m.k,
                 m.sps,
+                m.theory,
                 40,
                 2024,
                 |s| {
@@ -207,14 +220,15 @@ fn main() {
         };
 
         println!(
-            "{:<12} {:>10.4} {:>10}   {:<28} {:>10} {:>10} {:>10}",
+            "{:<12} {:>10.4} {:>10}   {:<28} {:>10} {:>10} {:>10} {:>10.2e}",
             m.name,
             clean_ber,
             format!("{}/{}", rx_clean.len(), payload.len()),
             status,
             b2,
             b6,
-            b10
+            b10,
+            m.theory.ber(6.0, Fading::Awgn)
         );
     }
     println!("\n(experimental modes chaos/freq-hop/rotating/waterfall not surveyed — demods pending)");
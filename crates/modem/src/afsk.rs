@@ -4,8 +4,10 @@
 //! It modulates digital data by shifting between two audio frequencies.
 
 use crate::{ModemError, Result};
-use crate::common::{Modulator, Demodulator, ModulationConfig, SignalQuality};
+use crate::common::{tone_llr, Demodulator, ModulationConfig, Modulator, SignalQuality, SoftDemodulator};
 use openham_core::buffer::Complex;
+use openham_core::design::{iir_design, BandType, IirPrototype};
+use openham_core::filter::SosFilter;
 use std::f64::consts::PI;
 
 /// AFSK configuration parameters
@@ -57,6 +59,15 @@ impl AfskConfig {
             filter_bandwidth: 500.0,
         }
     }
+
+    /// Butterworth band-pass `filter_bandwidth` Hz wide, centred between the
+    /// mark and space tones, for [`AfskDemodulator::with_channel_filter`]
+    pub fn channel_filter(&self, sample_rate: f64) -> Result<SosFilter> {
+        let center = (self.mark_frequency + self.space_frequency) / 2.0;
+        let low = (center - self.filter_bandwidth / 2.0).max(1.0);
+        let high = (center + self.filter_bandwidth / 2.0).min(0.49 * sample_rate);
+        Ok(iir_design(IirPrototype::Butterworth, 4, BandType::Bandpass(low, high), sample_rate)?)
+    }
 }
 
 /// AFSK modulator
@@ -187,6 +198,7 @@ pub struct AfskDemodulator {
     bit_duration: f64,
     sync_detected: bool,
     signal_quality: SignalQuality,
+    channel_filter: Option<SosFilter>,
 }
 
 impl AfskDemodulator {
@@ -199,27 +211,45 @@ impl AfskDemodulator {
             bit_duration,
             sync_detected: false,
             signal_quality: SignalQuality::default(),
+            channel_filter: None,
         })
     }
 
+    /// Band-limit the received audio with `filter` before tone detection,
+    /// typically [`AfskConfig::channel_filter`].
+    pub fn with_channel_filter(mut self, filter: SosFilter) -> Self {
+        self.channel_filter = Some(filter);
+        self
+    }
+
     /// Recover the bit stream at the best symbol-timing offset using per-symbol
     /// mark/space energy detection. Trying every offset lets the receiver lock
     /// onto a burst that begins anywhere in the stream — required for live audio
     /// where a transmission does not start at the first sample.
     fn demod_bits(&self, samples: &[Complex]) -> (Vec<u8>, SignalQuality) {
+        let (tones, quality) = self.tone_energies(samples);
+        let bits = tones.iter().map(|&(mark, space)| u8::from(mark > space)).collect();
+        (bits, quality)
+    }
+
+    /// Per-symbol (mark, space) energies at the best symbol-timing offset,
+    /// shared by the hard and soft decisions
+    fn tone_energies(&self, samples: &[Complex]) -> (Vec<(f64, f64)>, SignalQuality) {
         let sps = self.bit_duration as usize;
         if sps == 0 || samples.len() < sps {
             return (Vec::new(), SignalQuality::default());
         }
+        let filtered = crate::common::channel_filtered(self.channel_filter.as_ref(), samples);
+        let samples = &*filtered;
         let mark = self.afsk_config.mark_frequency;
         let space = self.afsk_config.space_frequency;
         let fs = self.config.sample_rate;
 
-        let mut best_bits: Vec<u8> = Vec::new();
+        let mut best_tones: Vec<(f64, f64)> = Vec::new();
         let mut best_energies: Vec<(f64, f64)> = Vec::new(); // (winner, loser) per symbol
         let mut best_strength = -1.0f64;
         for offset in 0..sps {
-            let mut bits = Vec::new();
+            let mut tones = Vec::new();
             let mut energies = Vec::new();
             let mut strength = 0.0f64;
             let mut idx = offset;
@@ -237,16 +267,16 @@ impl AfskDemodulator {
                 let e_space = si * si + sq * sq;
                 strength += (e_mark - e_space).abs();
                 energies.push((e_mark.max(e_space), e_mark.min(e_space)));
-                bits.push(if e_mark > e_space { 1 } else { 0 });
+                tones.push((e_mark, e_space));
                 idx += sps;
             }
             if strength > best_strength {
                 best_strength = strength;
-                best_bits = bits;
+                best_tones = tones;
                 best_energies = energies;
             }
         }
-        (best_bits, crate::common::discrimination_quality(&best_energies))
+        (best_tones, crate::common::discrimination_quality(&best_energies))
     }
 }
 
@@ -296,6 +326,18 @@ impl crate::common::BitDemodulator for AfskDemodulator {
     }
 }
 
+impl SoftDemodulator for AfskDemodulator {
+    /// Tone-energy-ratio LLRs, one per symbol
+    fn demodulate_soft(&mut self, samples: &[Complex], output: &mut Vec<f64>) -> Result<()> {
+        output.clear();
+        let (tones, quality) = self.tone_energies(samples);
+        self.signal_quality = quality;
+        self.sync_detected = tones.len() > 16;
+        output.extend(tones.iter().map(|&(mark, space)| tone_llr(mark, space)));
+        Ok(())
+    }
+}
+
 /// Simple tone detector using correlation
 struct ToneDetector {
     frequency: f64,
@@ -413,4 +455,36 @@ mod tests {
         let expected_samples = 8 * modulator.samples_per_symbol();
         assert!(output.len() >= expected_samples);
     }
+
+    #[test]
+    fn test_channel_filter_rejects_out_of_band_interferer() {
+        let mod_config = ModulationConfig::new(48000.0, 1200.0, 1700.0).unwrap();
+        let afsk_config = AfskConfig::bell_202();
+        let mut modulator = AfskModulator::new(mod_config.clone(), afsk_config.clone()).unwrap();
+        let payload = b"AFSK CHANNEL FILTER";
+        let mut signal = Vec::new();
+        modulator.modulate(payload, &mut signal).unwrap();
+
+        // Two bytes of silence either side keep filter edge effects out of
+        // the payload, then a strong 5.1 kHz tone is added throughout.
+        let pad = 16 * modulator.samples_per_symbol();
+        let mut rx = vec![Complex::new(0.0, 0.0); pad];
+        rx.extend_from_slice(&signal);
+        rx.extend(vec![Complex::new(0.0, 0.0); pad]);
+        for (n, s) in rx.iter_mut().enumerate() {
+            s.real += 30.0 * (2.0 * PI * 5100.0 * n as f64 / 48000.0).cos();
+        }
+
+        let decode = |demodulator: &mut AfskDemodulator| {
+            let mut out = Vec::new();
+            demodulator.demodulate(&rx, &mut out).unwrap();
+            out[2..2 + payload.len()].to_vec()
+        };
+        let mut plain = AfskDemodulator::new(mod_config.clone(), afsk_config.clone()).unwrap();
+        assert_ne!(decode(&mut plain), payload);
+
+        let filter = afsk_config.channel_filter(48000.0).unwrap();
+        let mut filtered = AfskDemodulator::new(mod_config, afsk_config).unwrap().with_channel_filter(filter);
+        assert_eq!(decode(&mut filtered), payload);
+    }
 }
\ No newline at end of file
@@ -1,16 +1,21 @@
 //! Binary Phase Shift Keying (BPSK) implementation
 
 use crate::{ModemError, Result};
-use crate::common::{Modulator, Demodulator, ModulationConfig, SignalQuality, PulseShaper};
+use crate::common::{
+    clamp_llr, Demodulator, ModulationConfig, Modulator, PulseShaper, SignalQuality, SoftDemodulator,
+};
 use openham_core::buffer::Complex;
-use std::f64::consts::PI;
+use openham_core::frequency::mth_power_frequency;
+use openham_core::nco::{Ddc, Nco};
+
+/// Samples per symbol the demodulator works at after down-conversion.
+const BASEBAND_SAMPLES_PER_SYMBOL: usize = 8;
 
 /// BPSK modulator
 pub struct BpskModulator {
     config: ModulationConfig,
     pulse_shaper: PulseShaper,
-    phase: f64,
-    sample_counter: f64,
+    carrier: Nco,
 }
 
 impl BpskModulator {
@@ -23,23 +28,19 @@ impl BpskModulator {
             config.filter_length,
         )?;
         
+        let carrier = Nco::new(config.carrier_frequency, config.sample_rate)?;
+        
         Ok(Self {
             config,
             pulse_shaper,
-            phase: 0.0,
-            sample_counter: 0.0,
+            carrier,
         })
     }
     
     /// Generate carrier wave
     fn generate_carrier(&mut self, symbol: f64) -> Complex {
-        let omega = 2.0 * PI * self.config.carrier_frequency / self.config.sample_rate;
-        let phase = omega * self.sample_counter;
-        
-        self.sample_counter += 1.0;
-        
         // BPSK: multiply carrier by symbol (+1 or -1)
-        Complex::new(symbol * phase.cos(), symbol * phase.sin())
+        self.carrier.step() * symbol
     }
 }
 
@@ -75,8 +76,7 @@ impl Modulator for BpskModulator {
     
     fn reset(&mut self) {
         self.pulse_shaper.reset();
-        self.phase = 0.0;
-        self.sample_counter = 0.0;
+        self.carrier.reset();
     }
 }
 
@@ -84,8 +84,8 @@ impl Modulator for BpskModulator {
 pub struct BpskDemodulator {
     config: ModulationConfig,
     pulse_shaper: PulseShaper,
-    phase: f64,
-    sample_counter: f64,
+    ddc: Ddc,
+    auto_tune: bool,
     is_sync: bool,
     signal_quality: SignalQuality,
 }
@@ -100,31 +100,38 @@ impl BpskDemodulator {
             config.filter_length,
         )?;
         
+        // Down-convert to a few samples per symbol, keeping an integer
+        // number of baseband samples in each symbol.
+        let sps = samples_per_symbol as usize;
+        let decimation = (1..=sps / BASEBAND_SAMPLES_PER_SYMBOL)
+            .rev()
+            .find(|&d| sps.is_multiple_of(d))
+            .unwrap_or(1);
+        let output_rate = config.sample_rate / decimation as f64;
+        let ddc = Ddc::new(
+            config.carrier_frequency,
+            config.sample_rate,
+            decimation,
+            config.symbol_rate.min(0.4 * output_rate),
+        )?;
+        
         Ok(Self {
             config,
             pulse_shaper,
-            phase: 0.0,
-            sample_counter: 0.0,
+            ddc,
+            auto_tune: false,
             is_sync: false,
             signal_quality: SignalQuality::default(),
         })
     }
-    
-    /// Demodulate to baseband
-    fn demodulate_to_baseband(&mut self, sample: Complex) -> Complex {
-        let omega = 2.0 * PI * self.config.carrier_frequency / self.config.sample_rate;
-        let phase = omega * self.sample_counter;
-        
-        self.sample_counter += 1.0;
-        
-        // Local oscillator for demodulation (note: conjugate for downmixing)
-        let lo = Complex::new(phase.cos(), -phase.sin());
-        
-        // Mix down to baseband
-        Complex::new(
-            sample.real * lo.real - sample.imag * lo.imag,
-            sample.real * lo.imag + sample.imag * lo.real,
-        )
+
+    /// Estimate the carrier offset of each capture and retune to it before
+    /// deciding bits; the offset is reported in
+    /// [`SignalQuality::frequency_offset_hz`]. The pull-in range is about
+    /// twice the symbol rate.
+    pub fn with_auto_tune(mut self, enabled: bool) -> Self {
+        self.auto_tune = enabled;
+        self
     }
     
     /// Detect synchronization (simplified)
@@ -146,20 +153,36 @@ impl BpskDemodulator {
     /// symbols — which strips the 0/π data modulation — averaging, and halving
     /// the resulting angle. A 180° ambiguity remains (squaring loses the sign);
     /// the framing layer's inversion-tolerant sync search resolves it.
-    fn recover_bits(&self, samples: &[Complex]) -> (Vec<u8>, SignalQuality) {
+    fn recover_bits(&mut self, samples: &[Complex]) -> (Vec<u8>, SignalQuality) {
+        let (projections, _, quality) = self.recover_symbols(samples);
+        let bits = projections.iter().map(|&re| u8::from(re > 0.0)).collect();
+        (bits, quality)
+    }
+
+    /// Symbols projected onto the recovered carrier phase, where positive
+    /// means bit 1, with the factor that turns a projection into the LLR of
+    /// a 0 (`2 A / sigma^2` for amplitude `A` and per-axis noise variance
+    /// `sigma^2`, negated) and the signal quality. See
+    /// [`recover_bits`](Self::recover_bits) for the recovery itself.
+    fn recover_symbols(&mut self, samples: &[Complex]) -> (Vec<f64>, f64, SignalQuality) {
         let sps = self.config.samples_per_symbol() as usize;
         if sps == 0 || samples.len() < sps {
-            return (Vec::new(), SignalQuality::default());
+            return (Vec::new(), 0.0, SignalQuality::default());
         }
-        let omega = 2.0 * PI * self.config.carrier_frequency / self.config.sample_rate;
-
-        // Downmix to complex baseband with a conjugate LO at the absolute index.
-        let mut bb: Vec<Complex> = Vec::with_capacity(samples.len());
-        for (i, s) in samples.iter().enumerate() {
-            let phase = omega * (i as f64);
-            let (c, sn) = (phase.cos(), phase.sin());
-            bb.push(Complex::new(s.real * c + s.imag * sn, s.imag * c - s.real * sn));
+
+        // Down-convert to complex baseband, LO phase referenced to the first
+        // sample, flushing the filter so the final symbol is complete.
+        let mut bb = self.down_convert(samples, 0.0);
+        let mut offset = 0.0;
+        if self.auto_tune {
+            // Squaring strips the 0/π modulation, leaving a tone at twice the
+            // carrier offset.
+            if let Ok(estimate) = mth_power_frequency(&bb, 2, self.ddc.output_rate()) {
+                offset = estimate;
+                bb = self.down_convert(samples, offset);
+            }
         }
+        let sps = sps / self.ddc.decimation();
 
         // Symbol-timing offset search by total symbol magnitude (phase-invariant).
         let mut best_syms: Vec<Complex> = Vec::new();
@@ -200,11 +223,11 @@ impl BpskDemodulator {
         let max_mag = best_syms.iter().map(|s| s.magnitude()).fold(0.0_f64, f64::max);
         let thresh = 0.3 * max_mag;
 
-        let mut bits = Vec::with_capacity(best_syms.len());
+        let mut projections = Vec::with_capacity(best_syms.len());
         let (mut amp_sum, mut count) = (0.0f64, 0usize);
         for s in &best_syms {
             let re = s.real * ct + s.imag * st;
-            bits.push(if re > 0.0 { 1u8 } else { 0u8 });
+            projections.push(re);
             if s.magnitude() > thresh {
                 amp_sum += re.abs();
                 count += 1;
@@ -231,13 +254,33 @@ impl BpskDemodulator {
                 snr_db: if evm > 0.0 { -20.0 * evm.log10() } else { 99.0 },
                 evm_percent: evm * 100.0,
                 phase_error_deg: theta.to_degrees(),
+                frequency_offset_hz: offset,
                 ..Default::default()
             }
         } else {
             SignalQuality::default()
         };
 
-        (bits, quality)
+        // err2 sums the noise over both axes.
+        let llr_scale = if amp > 0.0 && count > 0 {
+            -2.0 * amp / (err2 / (2.0 * count as f64)).max(1e-12 * amp * amp)
+        } else {
+            0.0
+        };
+
+        (projections, llr_scale, quality)
+    }
+
+    /// Down-convert a whole capture with the LO `offset` Hz from the
+    /// nominal carrier
+    fn down_convert(&mut self, samples: &[Complex], offset: f64) -> Vec<Complex> {
+        self.ddc.reset();
+        self.ddc
+            .nco_mut()
+            .set_frequency(self.config.carrier_frequency + offset);
+        let mut bb = self.ddc.process(samples);
+        bb.extend(self.ddc.flush());
+        bb
     }
 }
 
@@ -273,13 +316,22 @@ impl Demodulator for BpskDemodulator {
 
     fn reset(&mut self) {
         self.pulse_shaper.reset();
-        self.phase = 0.0;
-        self.sample_counter = 0.0;
+        self.ddc.reset();
         self.is_sync = false;
         self.signal_quality = SignalQuality::default();
     }
 }
 
+impl SoftDemodulator for BpskDemodulator {
+    fn demodulate_soft(&mut self, samples: &[Complex], output: &mut Vec<f64>) -> Result<()> {
+        output.clear();
+        let (projections, llr_scale, quality) = self.recover_symbols(samples);
+        self.signal_quality = quality;
+        output.extend(projections.iter().map(|&re| clamp_llr(llr_scale * re)));
+        Ok(())
+    }
+}
+
 impl crate::common::BitDemodulator for BpskDemodulator {
     fn demodulate_bits(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()> {
         output.clear();
@@ -323,4 +375,67 @@ mod tests {
         let expected_samples = 8 * modulator.samples_per_symbol();
         assert_eq!(output.len(), expected_samples);
     }
+
+    #[test]
+    fn test_bpsk_auto_tune_recovers_offset_carrier() {
+        use openham_core::metrics::count_bit_errors;
+
+        // Transmitter 50 Hz above where the receiver expects it.
+        let payload = b"AUTO TUNE 50 HZ OFF";
+        let tx_config = ModulationConfig::new(48000.0, 125.0, 1550.0).unwrap();
+        let mut modulator = BpskModulator::new(tx_config).unwrap();
+        let mut samples = Vec::new();
+        modulator.modulate(payload, &mut samples).unwrap();
+
+        let rx_config = ModulationConfig::new(48000.0, 125.0, 1500.0).unwrap();
+        let decode = |auto_tune: bool| {
+            let mut demodulator = BpskDemodulator::new(rx_config.clone())
+                .unwrap()
+                .with_auto_tune(auto_tune);
+            let mut bytes = Vec::new();
+            demodulator.demodulate(&samples, &mut bytes).unwrap();
+            // Squaring-based phase recovery leaves a 180 degree ambiguity.
+            let inverted: Vec<u8> = bytes.iter().map(|b| !b).collect();
+            let ber = count_bit_errors(payload, &bytes)
+                .ber()
+                .min(count_bit_errors(payload, &inverted).ber());
+            (ber, demodulator.signal_quality())
+        };
+
+        let (ber, quality) = decode(true);
+        assert_eq!(ber, 0.0);
+        assert!((quality.frequency_offset_hz - 50.0).abs() < 0.5, "{:?}", quality);
+
+        let (ber, quality) = decode(false);
+        assert!(ber > 0.1, "untuned BER {}", ber);
+        assert_eq!(quality.frequency_offset_hz, 0.0);
+    }
+
+    #[test]
+    fn test_bpsk_soft_decisions() {
+        use crate::common::BitDemodulator;
+        use openham_core::channel::awgn_at_snr;
+
+        let config = ModulationConfig::new(48000.0, 125.0, 1500.0).unwrap();
+        let mut modulator = BpskModulator::new(config.clone()).unwrap();
+        let mut clean = Vec::new();
+        modulator.modulate(b"SOFT BITS", &mut clean).unwrap();
+
+        let mut confidence = Vec::new();
+        // 384 samples per symbol: the matched filter gains about 26 dB.
+        for snr_db in [-25.0, -15.0] {
+            let samples = awgn_at_snr(&clean, snr_db, 7);
+            let mut demodulator = BpskDemodulator::new(config.clone()).unwrap();
+            let (mut bits, mut llrs) = (Vec::new(), Vec::new());
+            demodulator.demodulate_bits(&samples, &mut bits).unwrap();
+            demodulator.demodulate_soft(&samples, &mut llrs).unwrap();
+            // One LLR per hard bit, negative exactly where the bit is 1.
+            assert_eq!(llrs.len(), bits.len());
+            for (&bit, &llr) in bits.iter().zip(&llrs) {
+                assert_eq!(bit == 1, llr < 0.0);
+            }
+            confidence.push(llrs.iter().map(|l| l.abs()).sum::<f64>() / llrs.len() as f64);
+        }
+        assert!(confidence[1] > 3.0 * confidence[0], "{:?}", confidence);
+    }
 }
\ No newline at end of file
@@ -1,13 +1,17 @@
 //! Common modulation traits and utilities
 
 use crate::{ModemError, Result};
-use openham_core::buffer::Complex;
+use openham_core::buffer::{Complex, Float};
+use openham_core::filter::{FastFirFilter, Filter, SosFilter};
+use openham_core::flowgraph::Block;
+use std::borrow::Cow;
+use std::marker::PhantomData;
 use serde::{Deserialize, Serialize};
 
-/// Generic modulator trait
-pub trait Modulator {
+/// Generic modulator trait, over the precision of the samples it produces
+pub trait Modulator<F: Float = f64> {
     /// Modulate bits to complex samples
-    fn modulate(&mut self, bits: &[u8], output: &mut Vec<Complex>) -> Result<()>;
+    fn modulate(&mut self, bits: &[u8], output: &mut Vec<Complex<F>>) -> Result<()>;
     
     /// Get samples per symbol
     fn samples_per_symbol(&self) -> usize;
@@ -19,10 +23,10 @@ pub trait Modulator {
     fn reset(&mut self);
 }
 
-/// Generic demodulator trait
-pub trait Demodulator {
+/// Generic demodulator trait, over the precision of the samples it consumes
+pub trait Demodulator<F: Float = f64> {
     /// Demodulate complex samples to bits
-    fn demodulate(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()>;
+    fn demodulate(&mut self, samples: &[Complex<F>], output: &mut Vec<u8>) -> Result<()>;
     
     /// Check if synchronized to signal
     fn is_synchronized(&self) -> bool;
@@ -45,6 +49,461 @@ pub trait BitDemodulator {
     fn demodulate_bits(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()>;
 }
 
+/// Largest LLR magnitude the soft demodulators emit, so a clean signal or a
+/// bad noise estimate cannot swamp the FEC decoder with infinite confidence
+pub const MAX_LLR: f64 = 50.0;
+
+/// Demodulate to soft bits: one log-likelihood ratio `ln(P(0) / P(1))` per
+/// bit, for the same bits and in the same order as the hard decisions. A
+/// positive value favours 0, its magnitude is the confidence, and 0.0 means
+/// no information.
+///
+/// This is the input the soft-decision decoders in `openham_frame::fec`
+/// expect; going through hard bits first throws away 2-3 dB.
+pub trait SoftDemodulator {
+    fn demodulate_soft(&mut self, samples: &[Complex], output: &mut Vec<f64>) -> Result<()>;
+}
+
+/// Max-log LLRs for the bits of one received symbol, appended to `output`
+///
+/// `constellation` pairs each point with its bit label, MSB first over
+/// `bits_per_symbol` bits, and `noise_variance` is the complex noise power
+/// `E|n|^2`. Each bit's LLR is the squared distance to the nearest point
+/// labelled 1 minus that to the nearest point labelled 0, over the noise
+/// power.
+pub fn max_log_llrs(
+    received: Complex,
+    constellation: &[(Complex, u16)],
+    bits_per_symbol: usize,
+    noise_variance: f64,
+    output: &mut Vec<f64>,
+) {
+    for k in (0..bits_per_symbol).rev() {
+        let (mut nearest0, mut nearest1) = (f64::INFINITY, f64::INFINITY);
+        for &(point, label) in constellation {
+            let distance = (received - point).norm_sqr();
+            if (label >> k) & 1 == 0 {
+                nearest0 = nearest0.min(distance);
+            } else {
+                nearest1 = nearest1.min(distance);
+            }
+        }
+        output.push(clamp_llr((nearest1 - nearest0) / noise_variance));
+    }
+}
+
+/// Decision-directed complex noise power: the mean squared distance from
+/// each symbol to its nearest constellation point
+pub fn decision_noise_variance(symbols: &[Complex], constellation: &[(Complex, u16)]) -> f64 {
+    if symbols.is_empty() {
+        return 1.0;
+    }
+    let total: f64 = symbols
+        .iter()
+        .map(|&s| {
+            constellation
+                .iter()
+                .map(|&(point, _)| (s - point).norm_sqr())
+                .fold(f64::INFINITY, f64::min)
+        })
+        .sum();
+    (total / symbols.len() as f64).max(1e-12)
+}
+
+/// Limit an LLR to [`MAX_LLR`] in magnitude; NaN becomes 0.0
+pub(crate) fn clamp_llr(llr: f64) -> f64 {
+    if llr.is_nan() {
+        0.0
+    } else {
+        llr.clamp(-MAX_LLR, MAX_LLR)
+    }
+}
+
+/// LLR of a non-coherent mark/space decision from the tone energies: the
+/// log of the space-to-mark energy ratio, as mark is bit 1
+pub(crate) fn tone_llr(mark_energy: f64, space_energy: f64) -> f64 {
+    let floor = 1e-12 * (mark_energy + space_energy) + f64::MIN_POSITIVE;
+    clamp_llr(((space_energy + floor) / (mark_energy + floor)).ln())
+}
+
+/// Runs a [`Modulator`] as a flowgraph [`Block`]: bytes in, samples out
+pub struct ModulatorBlock<M, F = f64> {
+    modulator: M,
+    _precision: PhantomData<fn() -> F>,
+}
+
+impl<M: Modulator<F>, F: Float> ModulatorBlock<M, F> {
+    pub fn new(modulator: M) -> Self {
+        Self {
+            modulator,
+            _precision: PhantomData,
+        }
+    }
+}
+
+impl<M: Modulator<F> + Send, F: Float> Block for ModulatorBlock<M, F> {
+    type Input = u8;
+    type Output = Complex<F>;
+
+    fn work(&mut self, input: &[u8], output: &mut Vec<Complex<F>>) -> openham_core::Result<()> {
+        // Modulators may overwrite their output; blocks append to theirs.
+        let mut samples = Vec::new();
+        self.modulator
+            .modulate(input, &mut samples)
+            .map_err(flowgraph_error)?;
+        output.append(&mut samples);
+        Ok(())
+    }
+}
+
+/// Samples the demodulator blocks hand their demodulator at a time, unless
+/// set with `with_window`
+pub const DEFAULT_DEMOD_WINDOW: usize = 65536;
+
+/// How many items either side of the expected position the demodulator
+/// blocks search when lining up one window's output with the last's
+const WINDOW_ALIGN_SEARCH: isize = 8;
+
+/// One decision a demodulator block emits: a hard bit or an LLR
+trait Decision: Copy {
+    /// +1 if `a` and `b` agree, -1 if they are opposite, 0 if unknown
+    fn agreement(a: Self, b: Self) -> f64;
+
+    /// The same decision with the carrier phase flipped by 180 degrees
+    fn inverted(self) -> Self;
+}
+
+impl Decision for u8 {
+    fn agreement(a: u8, b: u8) -> f64 {
+        if a == b {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+
+    fn inverted(self) -> u8 {
+        self ^ 1
+    }
+}
+
+impl Decision for f64 {
+    fn agreement(a: f64, b: f64) -> f64 {
+        match (a * b).partial_cmp(&0.0) {
+            Some(std::cmp::Ordering::Greater) => 1.0,
+            Some(std::cmp::Ordering::Less) => -1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn inverted(self) -> f64 {
+        -self
+    }
+}
+
+/// Sliding window shared by the demodulator blocks
+///
+/// The demodulators acquire carrier and symbol timing over the buffer they
+/// are given, so the blocks run them over windows of `window` samples, each
+/// overlapping the last by half. Decisions near a window's edges rest on a
+/// poorly settled estimate, so a window emits only up to the middle of its
+/// overlap with the next one. The next window's decisions are lined up
+/// against the last window's over the overlap, which also takes out a
+/// flipped carrier phase, and carry on where it stopped. Samples and
+/// decisions are dropped once the window has moved past them.
+struct DemodWindow<S, T> {
+    window: usize,
+    samples: Vec<S>,
+    /// Decisions from the last window, and how many of them went out
+    previous: Vec<T>,
+    emitted: usize,
+    started: bool,
+}
+
+impl<S: Copy, T: Decision> DemodWindow<S, T> {
+    fn new() -> Self {
+        Self {
+            window: DEFAULT_DEMOD_WINDOW,
+            samples: Vec::new(),
+            previous: Vec::new(),
+            emitted: 0,
+            started: false,
+        }
+    }
+
+    fn hop(&self) -> usize {
+        self.window / 2
+    }
+
+    /// Demodulate every full window `input` completes
+    fn work(
+        &mut self,
+        input: &[S],
+        output: &mut Vec<T>,
+        mut demodulate: impl FnMut(&[S], &mut Vec<T>) -> Result<()>,
+    ) -> Result<()> {
+        self.samples.extend_from_slice(input);
+        while self.samples.len() >= self.window {
+            let mut decisions = Vec::new();
+            demodulate(&self.samples[..self.window], &mut decisions)?;
+            self.emit(self.window, decisions, false, output);
+            let hop = self.hop();
+            self.samples.drain(..hop);
+        }
+        Ok(())
+    }
+
+    /// Demodulate what is left of the stream and emit up to its end
+    fn flush(
+        &mut self,
+        output: &mut Vec<T>,
+        mut demodulate: impl FnMut(&[S], &mut Vec<T>) -> Result<()>,
+    ) -> Result<()> {
+        let samples = std::mem::take(&mut self.samples);
+        if !samples.is_empty() {
+            let mut decisions = Vec::new();
+            demodulate(&samples, &mut decisions)?;
+            self.emit(samples.len(), decisions, true, output);
+        }
+        self.previous.clear();
+        Ok(())
+    }
+
+    fn emit(&mut self, len: usize, mut decisions: Vec<T>, last: bool, output: &mut Vec<T>) {
+        if decisions.is_empty() {
+            if last {
+                output.extend_from_slice(&self.previous[self.emitted..]);
+            }
+            self.previous.clear();
+            return;
+        }
+        let samples_per_item = len as f64 / decisions.len() as f64;
+        let item_at = |sample: usize| (sample as f64 / samples_per_item).round() as usize;
+        let start = if !self.previous.is_empty() {
+            let (shift, inverted) =
+                align(&self.previous, &decisions, item_at(self.hop()) as isize);
+            if inverted {
+                decisions.iter_mut().for_each(|d| *d = d.inverted());
+            }
+            (self.emitted as isize - shift).max(0) as usize
+        } else if self.started {
+            // Nothing to line up with; pick up where the last window stopped
+            item_at(self.hop() / 2)
+        } else {
+            0
+        };
+        let end = if last {
+            decisions.len()
+        } else {
+            item_at(len - self.hop() / 2).min(decisions.len())
+        };
+        output.extend_from_slice(&decisions[start.min(end)..end]);
+        self.started = true;
+        self.emitted = end;
+        self.previous = decisions;
+    }
+}
+
+/// The shift (`next[j]` is `previous[j + shift]`) and polarity that best
+/// line up two windows' decisions, searched around `expected`. Ties go to
+/// the shift nearest `expected`, then to the uninverted polarity.
+fn align<T: Decision>(previous: &[T], next: &[T], expected: isize) -> (isize, bool) {
+    let mut best = (expected, false, f64::NEG_INFINITY);
+    for distance in 0..=WINDOW_ALIGN_SEARCH {
+        for shift in [expected - distance, expected + distance] {
+            if shift < 0 || shift as usize >= previous.len() {
+                continue;
+            }
+            let score: f64 = previous[shift as usize..]
+                .iter()
+                .zip(next)
+                .map(|(&a, &b)| T::agreement(a, b))
+                .sum();
+            if score > best.2 {
+                best = (shift, false, score);
+            }
+            if -score > best.2 {
+                best = (shift, true, -score);
+            }
+        }
+    }
+    (best.0, best.1)
+}
+
+/// Runs a [`Demodulator`] as a flowgraph [`Block`]: samples in, bytes out
+///
+/// The demodulator runs over a sliding window of the stream, see
+/// [`with_window`](DemodulatorBlock::with_window); its bits are stitched
+/// into one continuous stream and packed MSB first, as [`Demodulator`]
+/// does.
+pub struct DemodulatorBlock<D, F: Float = f64> {
+    demodulator: D,
+    window: DemodWindow<Complex<F>, u8>,
+    bits: Vec<u8>,
+}
+
+impl<D: Demodulator<F>, F: Float> DemodulatorBlock<D, F> {
+    pub fn new(demodulator: D) -> Self {
+        Self {
+            demodulator,
+            window: DemodWindow::new(),
+            bits: Vec::new(),
+        }
+    }
+
+    /// Set how many samples the demodulator sees at a time (at least two);
+    /// successive windows overlap by half. Longer windows give the carrier
+    /// and timing estimates more to work with, shorter ones less latency.
+    pub fn with_window(mut self, samples: usize) -> Self {
+        self.window.window = samples.max(2);
+        self
+    }
+
+    /// Demodulate `samples` and unpack the bytes to one bit per element
+    fn demodulate_unpacked(
+        demodulator: &mut D,
+        samples: &[Complex<F>],
+        bits: &mut Vec<u8>,
+    ) -> Result<()> {
+        let mut bytes = Vec::new();
+        demodulator.demodulate(samples, &mut bytes)?;
+        bits.extend(bytes.iter().flat_map(|byte| (0..8).rev().map(move |k| (byte >> k) & 1)));
+        Ok(())
+    }
+
+    /// Pack whole bytes from the front of `bits`
+    fn pack(&mut self, output: &mut Vec<u8>) {
+        let whole = self.bits.len() / 8 * 8;
+        output.extend(
+            self.bits[..whole]
+                .chunks(8)
+                .map(|byte| byte.iter().fold(0u8, |acc, &b| (acc << 1) | b)),
+        );
+        self.bits.drain(..whole);
+    }
+}
+
+impl<D: Demodulator<F> + Send, F: Float> Block for DemodulatorBlock<D, F> {
+    type Input = Complex<F>;
+    type Output = u8;
+
+    fn work(&mut self, input: &[Complex<F>], output: &mut Vec<u8>) -> openham_core::Result<()> {
+        let demodulator = &mut self.demodulator;
+        self.window
+            .work(input, &mut self.bits, |samples, bits| {
+                Self::demodulate_unpacked(demodulator, samples, bits)
+            })
+            .map_err(flowgraph_error)?;
+        self.pack(output);
+        Ok(())
+    }
+
+    fn flush(&mut self, output: &mut Vec<u8>) -> openham_core::Result<()> {
+        let demodulator = &mut self.demodulator;
+        self.window
+            .flush(&mut self.bits, |samples, bits| {
+                Self::demodulate_unpacked(demodulator, samples, bits)
+            })
+            .map_err(flowgraph_error)?;
+        // Pad the last partial byte, as the demodulators do.
+        self.bits.resize(self.bits.len().next_multiple_of(8), 0);
+        self.pack(output);
+        Ok(())
+    }
+}
+
+/// Runs a [`BitDemodulator`] as a flowgraph [`Block`], emitting one bit per
+/// element for the frame-acquisition stage; windowing works as for
+/// [`DemodulatorBlock`]
+pub struct BitDemodulatorBlock<D> {
+    demodulator: D,
+    window: DemodWindow<Complex, u8>,
+}
+
+impl<D: BitDemodulator> BitDemodulatorBlock<D> {
+    pub fn new(demodulator: D) -> Self {
+        Self {
+            demodulator,
+            window: DemodWindow::new(),
+        }
+    }
+
+    /// See [`DemodulatorBlock::with_window`]
+    pub fn with_window(mut self, samples: usize) -> Self {
+        self.window.window = samples.max(2);
+        self
+    }
+}
+
+impl<D: BitDemodulator + Send> Block for BitDemodulatorBlock<D> {
+    type Input = Complex;
+    type Output = u8;
+
+    fn work(&mut self, input: &[Complex], output: &mut Vec<u8>) -> openham_core::Result<()> {
+        let demodulator = &mut self.demodulator;
+        self.window
+            .work(input, output, |samples, bits| {
+                demodulator.demodulate_bits(samples, bits)
+            })
+            .map_err(flowgraph_error)
+    }
+
+    fn flush(&mut self, output: &mut Vec<u8>) -> openham_core::Result<()> {
+        let demodulator = &mut self.demodulator;
+        self.window
+            .flush(output, |samples, bits| demodulator.demodulate_bits(samples, bits))
+            .map_err(flowgraph_error)
+    }
+}
+
+/// Runs a [`SoftDemodulator`] as a flowgraph [`Block`], emitting one LLR per
+/// bit for a soft-decision decoder; windowing works as for
+/// [`DemodulatorBlock`]
+pub struct SoftDemodulatorBlock<D> {
+    demodulator: D,
+    window: DemodWindow<Complex, f64>,
+}
+
+impl<D: SoftDemodulator> SoftDemodulatorBlock<D> {
+    pub fn new(demodulator: D) -> Self {
+        Self {
+            demodulator,
+            window: DemodWindow::new(),
+        }
+    }
+
+    /// See [`DemodulatorBlock::with_window`]
+    pub fn with_window(mut self, samples: usize) -> Self {
+        self.window.window = samples.max(2);
+        self
+    }
+}
+
+impl<D: SoftDemodulator + Send> Block for SoftDemodulatorBlock<D> {
+    type Input = Complex;
+    type Output = f64;
+
+    fn work(&mut self, input: &[Complex], output: &mut Vec<f64>) -> openham_core::Result<()> {
+        let demodulator = &mut self.demodulator;
+        self.window
+            .work(input, output, |samples, llrs| {
+                demodulator.demodulate_soft(samples, llrs)
+            })
+            .map_err(flowgraph_error)
+    }
+
+    fn flush(&mut self, output: &mut Vec<f64>) -> openham_core::Result<()> {
+        let demodulator = &mut self.demodulator;
+        self.window
+            .flush(output, |samples, llrs| demodulator.demodulate_soft(samples, llrs))
+            .map_err(flowgraph_error)
+    }
+}
+
+fn flowgraph_error(e: ModemError) -> openham_core::CoreError {
+    openham_core::CoreError::FlowgraphError { msg: e.to_string() }
+}
+
 /// Signal quality metrics
 #[derive(Debug, Clone, Default)]
 pub struct SignalQuality {
@@ -80,6 +539,28 @@ pub fn discrimination_quality(energies: &[(f64, f64)]) -> SignalQuality {
     }
 }
 
+/// Apply an optional receive channel filter to the real part of `samples`.
+/// The filter runs forward and backward so symbol timing is not shifted by
+/// its group delay. Used by the FSK/AFSK demodulators ahead of tone detection.
+pub(crate) fn channel_filtered<'a, F: Float>(
+    filter: Option<&SosFilter<F>>,
+    samples: &'a [Complex<F>],
+) -> Cow<'a, [Complex<F>]> {
+    match filter {
+        Some(filter) => {
+            let real: Vec<F> = samples.iter().map(|s| s.real).collect();
+            Cow::Owned(
+                filter
+                    .filter_zero_phase(&real)
+                    .into_iter()
+                    .map(|x| Complex::from_parts(x, F::default()))
+                    .collect(),
+            )
+        }
+        None => Cow::Borrowed(samples),
+    }
+}
+
 /// Common modulation configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModulationConfig {
@@ -148,9 +629,7 @@ impl ModulationConfig {
 
 /// Pulse shaping filter design
 pub struct PulseShaper {
-    filter_taps: Vec<f64>,
-    delay_line: Vec<Complex>,
-    index: usize,
+    fir: FastFirFilter<Complex>,
 }
 
 impl PulseShaper {
@@ -184,9 +663,7 @@ impl PulseShaper {
         }
         
         Ok(Self {
-            filter_taps,
-            delay_line: vec![Complex::default(); length],
-            index: 0,
+            fir: FastFirFilter::new(filter_taps)?,
         })
     }
     
@@ -215,30 +692,27 @@ impl PulseShaper {
         numerator / denominator
     }
     
+    /// Get the filter taps
+    pub fn taps(&self) -> &[f64] {
+        self.fir.coefficients()
+    }
+    
     /// Filter a complex sample
     pub fn filter(&mut self, input: Complex) -> Complex {
-        // Store input in delay line
-        self.delay_line[self.index] = input;
-        
-        // Compute convolution
-        let mut output = Complex::new(0.0, 0.0);
-        for (i, &tap) in self.filter_taps.iter().enumerate() {
-            let delay_index = (self.index + self.delay_line.len() - i) % self.delay_line.len();
-            let sample = self.delay_line[delay_index];
-            output.real += tap * sample.real;
-            output.imag += tap * sample.imag;
-        }
-        
-        // Update delay line index
-        self.index = (self.index + 1) % self.delay_line.len();
-        
-        output
+        self.fir.process_sample(input)
+    }
+    
+    /// Filter a block of complex samples. Long filters use FFT convolution,
+    /// so prefer this over per-sample [`PulseShaper::filter`] for whole buffers.
+    pub fn filter_block(&mut self, input: &[Complex]) -> Result<Vec<Complex>> {
+        let mut output = vec![Complex::default(); input.len()];
+        self.fir.process_buffer(input, &mut output)?;
+        Ok(output)
     }
     
     /// Reset filter state
     pub fn reset(&mut self) {
-        self.delay_line.fill(Complex::default());
-        self.index = 0;
+        self.fir.reset();
     }
 }
 
@@ -337,7 +811,7 @@ mod tests {
     #[test]
     fn test_pulse_shaper_creation() {
         let shaper = PulseShaper::root_raised_cosine(4.0, 0.35, 41).unwrap();
-        assert_eq!(shaper.filter_taps.len(), 41);
+        assert_eq!(shaper.taps().len(), 41);
     }
 
     #[test]
@@ -345,4 +819,29 @@ mod tests {
         assert!(ModulationConfig::new(-1.0, 1000.0, 1500.0).is_err());
         assert!(ModulationConfig::new(48000.0, 50000.0, 1500.0).is_err());
     }
+
+    #[test]
+    fn test_soft_decision_helpers() {
+        // Gray-labelled QPSK: the first bit picks the sign of I, the second Q.
+        let constellation = [
+            (Complex::new(1.0, 1.0), 0b00),
+            (Complex::new(1.0, -1.0), 0b01),
+            (Complex::new(-1.0, 1.0), 0b10),
+            (Complex::new(-1.0, -1.0), 0b11),
+        ];
+        let mut llrs = Vec::new();
+        max_log_llrs(Complex::new(0.9, -0.2), &constellation, 2, 0.5, &mut llrs);
+        assert!((llrs[0] - 7.2).abs() < 1e-9);
+        assert!((llrs[1] + 1.6).abs() < 1e-9);
+        max_log_llrs(Complex::new(1.0, 1.0), &constellation, 2, 1e-9, &mut llrs);
+        assert_eq!(llrs[2..], [MAX_LLR, MAX_LLR]);
+
+        let received = [Complex::new(0.9, 1.1), Complex::new(-1.2, -1.0)];
+        assert!((decision_noise_variance(&received, &constellation) - 0.03).abs() < 1e-9);
+
+        assert!(tone_llr(1.0, 4.0) > 0.0);
+        assert!((tone_llr(4.0, 1.0) + 4.0f64.ln()).abs() < 1e-9);
+        assert_eq!(tone_llr(0.0, 0.0), 0.0);
+        assert!(tone_llr(0.0, 1.0) > 20.0);
+    }
 }
\ No newline at end of file
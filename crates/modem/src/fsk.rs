@@ -1,21 +1,37 @@
 //! Frequency Shift Keying (FSK) implementation
+//!
+//! Both ends are generic over the sample precision: `FskModulator<f32>` and
+//! `FskDemodulator<f32>` produce and consume [`Complex32`] samples, keeping
+//! their tone references and channel filter in `f32`.
 
 use crate::{ModemError, Result};
-use crate::common::{Modulator, Demodulator, ModulationConfig, SignalQuality};
-use openham_core::buffer::Complex;
+use crate::common::{tone_llr, Demodulator, ModulationConfig, Modulator, SignalQuality, SoftDemodulator};
+use openham_core::buffer::{Complex, Float};
+#[cfg(doc)]
+use openham_core::buffer::Complex32;
+use openham_core::filter::SosFilter;
 use std::f64::consts::PI;
+use std::marker::PhantomData;
 
 /// FSK modulator
-pub struct FskModulator {
+pub struct FskModulator<F: Float = f64> {
     config: ModulationConfig,
     phase: f64,
     freq_mark: f64,   // Frequency for '1' bit
     freq_space: f64,  // Frequency for '0' bit
+    _precision: PhantomData<F>,
 }
 
 impl FskModulator {
     /// Create a new FSK modulator
     pub fn new(config: ModulationConfig) -> Result<Self> {
+        Self::with_precision(config)
+    }
+}
+
+impl<F: Float> FskModulator<F> {
+    /// Create a new FSK modulator producing samples of precision `F`
+    pub fn with_precision(config: ModulationConfig) -> Result<Self> {
         let shift = 500.0; // 500 Hz frequency shift
         let freq_mark = config.carrier_frequency + shift / 2.0;
         let freq_space = config.carrier_frequency - shift / 2.0;
@@ -25,12 +41,13 @@ impl FskModulator {
             phase: 0.0,
             freq_mark,
             freq_space,
+            _precision: PhantomData,
         })
     }
 }
 
-impl Modulator for FskModulator {
-    fn modulate(&mut self, bits: &[u8], output: &mut Vec<Complex>) -> Result<()> {
+impl<F: Float> Modulator<F> for FskModulator<F> {
+    fn modulate(&mut self, bits: &[u8], output: &mut Vec<Complex<F>>) -> Result<()> {
         let samples_per_symbol = self.samples_per_symbol();
         
         for &byte in bits {
@@ -39,11 +56,8 @@ impl Modulator for FskModulator {
                 let freq = if bit == 1 { self.freq_mark } else { self.freq_space };
                 
                 for _ in 0..samples_per_symbol {
-                    let sample = Complex {
-                        real: (2.0 * PI * freq * self.phase / self.config.sample_rate).cos(),
-                        imag: (2.0 * PI * freq * self.phase / self.config.sample_rate).sin(),
-                    };
-                    output.push(sample);
+                    let angle = 2.0 * PI * freq * self.phase / self.config.sample_rate;
+                    output.push(Complex::from_parts(F::narrow(angle.cos()), F::narrow(angle.sin())));
                     self.phase += 1.0;
                     
                     // Prevent phase overflow
@@ -71,73 +85,119 @@ impl Modulator for FskModulator {
 }
 
 /// FSK demodulator
-pub struct FskDemodulator {
+pub struct FskDemodulator<F: Float = f64> {
     config: ModulationConfig,
-    freq_mark: f64,   // Frequency for '1' bit
-    freq_space: f64,  // Frequency for '0' bit
     signal_quality: SignalQuality,
+    channel_filter: Option<SosFilter<F>>,
+    /// One symbol of `e^{-j 2 pi f t}` at the mark ('1') frequency
+    mark_reference: Vec<Complex<F>>,
+    /// The same at the space ('0') frequency
+    space_reference: Vec<Complex<F>>,
 }
 
 impl FskDemodulator {
     /// Create a new FSK demodulator
     pub fn new(config: ModulationConfig) -> Result<Self> {
+        Self::with_precision(config)
+    }
+}
+
+impl<F: Float> FskDemodulator<F> {
+    /// Create a new FSK demodulator for samples of precision `F`
+    pub fn with_precision(config: ModulationConfig) -> Result<Self> {
         let shift = 500.0; // 500 Hz frequency shift
         let freq_mark = config.carrier_frequency + shift / 2.0;
         let freq_space = config.carrier_frequency - shift / 2.0;
 
+        let sps = config.samples_per_symbol() as usize;
+        let reference = |freq: f64| -> Vec<Complex<F>> {
+            (0..sps)
+                .map(|k| {
+                    let t = k as f64 / config.sample_rate;
+                    let angle = 2.0 * PI * freq * t;
+                    Complex::from_parts(F::narrow(angle.cos()), F::narrow(-angle.sin()))
+                })
+                .collect()
+        };
+        let mark_reference = reference(freq_mark);
+        let space_reference = reference(freq_space);
+
         Ok(Self {
             config,
-            freq_mark,
-            freq_space,
             signal_quality: SignalQuality::default(),
+            channel_filter: None,
+            mark_reference,
+            space_reference,
         })
     }
 
+    /// Band-limit the received audio with `filter` before tone detection,
+    /// e.g. a band-pass from [`openham_core::design::iir_design`] around the
+    /// mark and space tones ([`SosFilter::cast`] it to `F` first).
+    pub fn with_channel_filter(mut self, filter: SosFilter<F>) -> Self {
+        self.channel_filter = Some(filter);
+        self
+    }
+
     /// Recover the bit stream at the best symbol-timing offset using per-symbol
     /// noncoherent mark/space energy detection. Trying every offset lets the
     /// receiver lock onto a burst that begins anywhere in the stream.
-    fn demod_bits(&self, samples: &[Complex]) -> (Vec<u8>, SignalQuality) {
+    fn demod_bits(&self, samples: &[Complex<F>]) -> (Vec<u8>, SignalQuality) {
+        let (tones, quality) = self.tone_energies(samples);
+        let bits = tones.iter().map(|&(mark, space)| u8::from(mark > space)).collect();
+        (bits, quality)
+    }
+
+    /// Per-symbol (mark, space) energies at the best symbol-timing offset,
+    /// shared by the hard and soft decisions
+    fn tone_energies(&self, samples: &[Complex<F>]) -> (Vec<(f64, f64)>, SignalQuality) {
         let sps = self.config.samples_per_symbol() as usize;
         if sps == 0 || samples.len() < sps {
             return (Vec::new(), SignalQuality::default());
         }
-        let mut best_bits: Vec<u8> = Vec::new();
+        let filtered = crate::common::channel_filtered(self.channel_filter.as_ref(), samples);
+        let samples = &*filtered;
+        let mut best_tones: Vec<(f64, f64)> = Vec::new();
         let mut best_energies: Vec<(f64, f64)> = Vec::new();
         let mut best_strength = -1.0f64;
         for offset in 0..sps {
-            let mut bits = Vec::new();
+            let mut tones = Vec::new();
             let mut energies = Vec::new();
             let mut strength = 0.0f64;
             let mut idx = offset;
             while idx + sps <= samples.len() {
                 let win = &samples[idx..idx + sps];
-                let (mut mi, mut mq, mut si, mut sq) = (0.0, 0.0, 0.0, 0.0);
-                for (k, s) in win.iter().enumerate() {
-                    let t = k as f64 / self.config.sample_rate;
-                    mi += s.real * (2.0 * PI * self.freq_mark * t).cos();
-                    mq += s.real * -(2.0 * PI * self.freq_mark * t).sin();
-                    si += s.real * (2.0 * PI * self.freq_space * t).cos();
-                    sq += s.real * -(2.0 * PI * self.freq_space * t).sin();
+                let (mut mi, mut mq, mut si, mut sq) =
+                    (F::default(), F::default(), F::default(), F::default());
+                for ((s, mark), space) in win
+                    .iter()
+                    .zip(&self.mark_reference)
+                    .zip(&self.space_reference)
+                {
+                    mi += s.real * mark.real;
+                    mq += s.real * mark.imag;
+                    si += s.real * space.real;
+                    sq += s.real * space.imag;
                 }
-                let e_mark = mi * mi + mq * mq;
-                let e_space = si * si + sq * sq;
+                let e_mark = (mi * mi + mq * mq).widen();
+                let e_space = (si * si + sq * sq).widen();
                 strength += (e_mark - e_space).abs();
                 energies.push((e_mark.max(e_space), e_mark.min(e_space)));
-                bits.push(if e_mark > e_space { 1 } else { 0 });
+                tones.push((e_mark, e_space));
                 idx += sps;
             }
             if strength > best_strength {
                 best_strength = strength;
-                best_bits = bits;
+                best_tones = tones;
                 best_energies = energies;
             }
         }
-        (best_bits, crate::common::discrimination_quality(&best_energies))
+        (best_tones, crate::common::discrimination_quality(&best_energies))
     }
 }
 
-impl Demodulator for FskDemodulator {
-    fn demodulate(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()> {
+impl<F: Float> Demodulator<F> for FskDemodulator<F> {
+    fn demodulate(&mut self, samples: &[Complex<F>], output: &mut Vec<u8>) -> Result<()> {
         output.clear();
         let (bits, quality) = self.demod_bits(samples);
         self.signal_quality = quality;
@@ -180,6 +240,17 @@ impl crate::common::BitDemodulator for FskDemodulator {
     }
 }
 
+impl SoftDemodulator for FskDemodulator {
+    /// Tone-energy-ratio LLRs, one per symbol
+    fn demodulate_soft(&mut self, samples: &[Complex], output: &mut Vec<f64>) -> Result<()> {
+        output.clear();
+        let (tones, quality) = self.tone_energies(samples);
+        self.signal_quality = quality;
+        output.extend(tones.iter().map(|&(mark, space)| tone_llr(mark, space)));
+        Ok(())
+    }
+}
+
 // (no additional helpers)
 
 #[cfg(test)]
@@ -214,4 +285,49 @@ mod tests {
             assert_eq!(roundtrip(baud, payload), payload, "baud {baud}");
         }
     }
+
+    #[test]
+    fn fsk_single_precision_roundtrip() {
+        // Native f32 samples end to end, through an f32 channel filter.
+        use openham_core::buffer::Complex32;
+        use openham_core::design::{iir_design, BandType, IirPrototype};
+
+        let config = ModulationConfig::new(48000.0, 250.0, 1500.0).unwrap();
+        let payload = b"FSK F32 PATH";
+        let mut m = FskModulator::<f32>::with_precision(config.clone()).unwrap();
+        let mut samples: Vec<Complex32> = Vec::new();
+        m.modulate(payload, &mut samples).unwrap();
+        assert_eq!(samples.len(), payload.len() * 8 * m.samples_per_symbol());
+
+        let filter = iir_design(IirPrototype::Butterworth, 4, BandType::Bandpass(1000.0, 2000.0), 48000.0)
+            .unwrap()
+            .cast::<f32>();
+        let mut d = FskDemodulator::<f32>::with_precision(config.clone())
+            .unwrap()
+            .with_channel_filter(filter);
+        let mut out = Vec::new();
+        d.demodulate(&samples, &mut out).unwrap();
+        assert_eq!(out, payload);
+
+        // Same decisions as the f64 path.
+        assert_eq!(roundtrip(250.0, payload), out);
+    }
+
+    #[test]
+    fn fsk_soft_decisions() {
+        let config = ModulationConfig::new(48000.0, 250.0, 1500.0).unwrap();
+        let payload = b"FSK SOFT";
+        let mut m = FskModulator::new(config.clone()).unwrap();
+        let mut samples = Vec::new();
+        m.modulate(payload, &mut samples).unwrap();
+        let mut d = FskDemodulator::new(config).unwrap();
+        let mut llrs = Vec::new();
+        d.demodulate_soft(&samples, &mut llrs).unwrap();
+        assert_eq!(llrs.len(), payload.len() * 8);
+        for (i, &llr) in llrs.iter().enumerate() {
+            let bit = (payload[i / 8] >> (7 - i % 8)) & 1;
+            assert_eq!(bit == 1, llr < 0.0, "bit {}", i);
+            assert!(llr.abs() > 5.0);
+        }
+    }
 }
\ No newline at end of file
@@ -29,7 +29,11 @@ pub mod prelude {
             FrequencyHoppingModulator, WaterfallModulator,
             MultiToneConfig, ChaosConfig,
         },
-        common::{BitDemodulator, Modulator, Demodulator, ModulationConfig, SignalQuality},
+        common::{
+            BitDemodulator, BitDemodulatorBlock, Modulator, ModulatorBlock, Demodulator,
+            DemodulatorBlock, ModulationConfig, SignalQuality, SoftDemodulator,
+            SoftDemodulatorBlock,
+        },
         error::{ModemError, Result},
     };
 }
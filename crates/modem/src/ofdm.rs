@@ -4,7 +4,7 @@
 //! channel estimation for robust multipath communication.
 
 use crate::Result;
-use crate::common::{Modulator, Demodulator, ModulationConfig, SignalQuality};
+use crate::common::{clamp_llr, Demodulator, ModulationConfig, Modulator, SignalQuality, SoftDemodulator};
 use openham_core::buffer::Complex;
 use openham_core::fft::{FftProcessor, FftConfig};
 use std::f64::consts::PI;
@@ -453,14 +453,36 @@ impl OfdmDemodulator {
         
         bytes
     }
-}
 
-impl Demodulator for OfdmDemodulator {
-    fn demodulate(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()> {
-        output.clear();
+    
+    /// Channel gain on `carrier`: the pilot estimates, interpolated linearly
+    /// between pilots and held flat beyond the outermost ones
+    fn carrier_gain(&self, carrier: usize) -> Complex {
+        // estimate_channel only visits pilots that have a symbol
+        let known = self.ofdm_config.pilot_symbols.len().min(self.ofdm_config.pilot_carriers.len());
+        let pilots = &self.ofdm_config.pilot_carriers[..known];
+        let below = pilots.iter().copied().filter(|&p| p <= carrier).max();
+        let above = pilots.iter().copied().filter(|&p| p >= carrier).min();
+        match (below, above) {
+            (Some(lo), Some(hi)) if lo != hi => {
+                let w = (carrier - lo) as f64 / (hi - lo) as f64;
+                self.channel_estimates[lo] * (1.0 - w) + self.channel_estimates[hi] * w
+            }
+            (Some(p), _) | (None, Some(p)) => self.channel_estimates[p],
+            (None, None) => Complex::new(1.0, 0.0),
+        }
+    }
+    
+    /// Data-carrier values of each complete OFDM symbol the buffer now
+    /// holds, paired with the channel gain on each of those carriers
+    fn receive_symbols(&mut self, samples: &[Complex]) -> Result<Vec<(Vec<Complex>, Vec<Complex>)>> {
+        let mut received = Vec::new();
         
-        // Add samples to buffer
-        self.symbol_buffer.extend_from_slice(samples);
+        // Add samples to buffer. The modulator emits a real signal (mirrored
+        // carriers), so only the real part is used; an analytic input would
+        // otherwise smear the carriers next to DC and Nyquist.
+        self.symbol_buffer
+            .extend(samples.iter().map(|s| Complex::new(s.real, 0.0)));
         
         // Process complete OFDM symbols
         let symbol_length = self.ofdm_config.symbol_length();
@@ -524,17 +546,31 @@ impl Demodulator for OfdmDemodulator {
                 }
             }
             
-            // Demodulate symbols to bits
-            let symbol_bits = self.demodulate_symbols(&data_symbols);
-            output.extend(symbol_bits);
-            
             // Update signal quality (simplified)
             let avg_power: f64 = data_symbols.iter().map(|s| s.norm_sqr()).sum::<f64>() / data_symbols.len() as f64;
             if avg_power > 0.0 {
                 self.signal_quality.snr_db = 10.0 * avg_power.log10();
             }
+            
+            let gains = self.ofdm_config.data_carriers
+                .iter()
+                .filter(|&&carrier_idx| carrier_idx < freq_frame.len())
+                .map(|&carrier_idx| self.carrier_gain(carrier_idx))
+                .collect();
+            received.push((data_symbols, gains));
         }
         
+        Ok(received)
+    }
+}
+
+impl Demodulator for OfdmDemodulator {
+    fn demodulate(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()> {
+        output.clear();
+        for (data_symbols, _) in self.receive_symbols(samples)? {
+            // Demodulate symbols to bits
+            output.extend(self.demodulate_symbols(&data_symbols));
+        }
         Ok(())
     }
     
@@ -555,6 +591,39 @@ impl Demodulator for OfdmDemodulator {
     }
 }
 
+impl SoftDemodulator for OfdmDemodulator {
+    /// QPSK LLRs, I bit then Q bit per data carrier, weighted by each
+    /// carrier's channel gain: a carrier in a fade yields weak LLRs instead
+    /// of confident errors
+    fn demodulate_soft(&mut self, samples: &[Complex], output: &mut Vec<f64>) -> Result<()> {
+        output.clear();
+        // Constellation points sit at (+-a, +-a).
+        let a = 1.0 / 2.0_f64.sqrt();
+        for (data_symbols, gains) in self.receive_symbols(samples)? {
+            if data_symbols.is_empty() {
+                continue;
+            }
+            // Noise power from the decision errors across this symbol's carriers
+            let mut noise = 0.0;
+            let mut gain_power = 0.0;
+            for (&y, &h) in data_symbols.iter().zip(&gains) {
+                let z = y * h.conj();
+                let decided = Complex::new(a.copysign(z.real), a.copysign(z.imag));
+                noise += (y - h * decided).norm_sqr();
+                gain_power += h.norm_sqr();
+            }
+            let count = data_symbols.len() as f64;
+            let noise_variance = (noise / count).max(1e-12 * gain_power / count).max(f64::MIN_POSITIVE);
+            for (&y, &h) in data_symbols.iter().zip(&gains) {
+                let z = y * h.conj();
+                output.push(clamp_llr(4.0 * a * z.real / noise_variance));
+                output.push(clamp_llr(4.0 * a * z.imag / noise_variance));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -616,4 +685,32 @@ mod tests {
         // Should be multiple of symbol length
         assert_eq!(output.len() % modulator.samples_per_symbol(), 0);
     }
+
+    #[test]
+    fn test_ofdm_soft_decisions() {
+        let mod_config = ModulationConfig::new(48000.0, 1000.0, 1500.0).unwrap();
+        let mut modulator = OfdmModulator::new(mod_config.clone(), OfdmConfig::amateur_radio_64()).unwrap();
+        let payload: Vec<u8> = (0..28).map(|i| (i * 37 + 11) as u8).collect();
+        let mut samples = Vec::new();
+        modulator.modulate(&payload, &mut samples).unwrap();
+
+        let mut demodulator = OfdmDemodulator::new(mod_config, OfdmConfig::amateur_radio_64()).unwrap();
+        let (mut bytes, mut llrs) = (Vec::new(), Vec::new());
+        demodulator.demodulate(&samples, &mut bytes).unwrap();
+        demodulator.reset();
+        demodulator.demodulate_soft(&samples, &mut llrs).unwrap();
+        assert_eq!(llrs.len(), bytes.len() * 8);
+        for (i, &llr) in llrs.iter().enumerate() {
+            let bit = (bytes[i / 8] >> (7 - i % 8)) & 1;
+            assert_eq!(bit == 1, llr < 0.0, "bit {}", i);
+        }
+
+        // Data carriers take the gain of the pilots either side of them,
+        // or of the nearest pilot beyond the outermost ones.
+        demodulator.channel_estimates[5] = Complex::new(0.5, 0.0);
+        demodulator.channel_estimates[15] = Complex::new(1.0, 0.0);
+        let between = demodulator.carrier_gain(10);
+        assert!((between.real - 0.75).abs() < 1e-12 && between.imag.abs() < 1e-12);
+        assert_eq!(demodulator.carrier_gain(2).real, 0.5);
+    }
 }
\ No newline at end of file
@@ -4,8 +4,12 @@
 //! constellation mapping and differential encoding options.
 
 use crate::Result;
-use crate::common::{Modulator, Demodulator, ModulationConfig, SignalQuality, PulseShaper, GardnerTimingRecovery};
+use crate::common::{
+    decision_noise_variance, max_log_llrs, Demodulator, GardnerTimingRecovery, ModulationConfig, Modulator,
+    PulseShaper, SignalQuality, SoftDemodulator,
+};
 use openham_core::buffer::Complex;
+use openham_core::nco::Nco;
 use std::f64::consts::PI;
 
 /// PSK constellation types
@@ -133,8 +137,7 @@ pub struct PskModulator {
     config: ModulationConfig,
     psk_config: PskConfig,
     pulse_shaper: PulseShaper,
-    phase: f64,
-    sample_counter: f64,
+    carrier: Nco,
     previous_symbol: Complex,
 }
 
@@ -148,12 +151,13 @@ impl PskModulator {
             config.filter_length,
         )?;
         
+        let carrier = Nco::new(config.carrier_frequency, config.sample_rate)?;
+        
         Ok(Self {
             config,
             psk_config,
             pulse_shaper,
-            phase: 0.0,
-            sample_counter: 0.0,
+            carrier,
             previous_symbol: Complex::new(1.0, 0.0),
         })
     }
@@ -215,18 +219,8 @@ impl PskModulator {
     
     /// Generate carrier wave
     fn generate_carrier(&mut self, symbol: Complex) -> Complex {
-        let omega = 2.0 * PI * self.config.carrier_frequency / self.config.sample_rate;
-        let phase = omega * self.sample_counter;
-        
-        self.sample_counter += 1.0;
-        
-        let carrier = Complex::new(phase.cos(), phase.sin());
-        
         // Multiply baseband symbol with carrier
-        Complex::new(
-            symbol.real * carrier.real - symbol.imag * carrier.imag,
-            symbol.real * carrier.imag + symbol.imag * carrier.real,
-        )
+        self.carrier.mix_up(symbol)
     }
 }
 
@@ -246,6 +240,7 @@ impl Modulator for PskModulator {
         }
         
         // Process symbols
+        let mut held = Vec::with_capacity(bit_stream.len().div_ceil(bits_per_symbol) * samples_per_symbol);
         for symbol_bits in bit_stream.chunks(bits_per_symbol) {
             // Pack bits into symbol value
             let mut symbol_value = 0u8;
@@ -262,14 +257,15 @@ impl Modulator for PskModulator {
             // Apply differential encoding
             constellation_symbol = self.apply_differential_encoding(constellation_symbol);
             
-            // Generate samples for this symbol
-            for _ in 0..samples_per_symbol {
-                let shaped = self.pulse_shaper.filter(constellation_symbol);
-                let modulated = self.generate_carrier(shaped);
-                output.push(modulated);
-            }
+            // Hold the symbol for one symbol period
+            held.extend(std::iter::repeat_n(constellation_symbol, samples_per_symbol));
         }
         
+        // Pulse-shape the whole burst as one block so long filters take the
+        // FFT path, then move it up to the carrier
+        let shaped = self.pulse_shaper.filter_block(&held)?;
+        output.extend(shaped.into_iter().map(|s| self.generate_carrier(s)));
+        
         Ok(())
     }
     
@@ -283,8 +279,7 @@ impl Modulator for PskModulator {
     
     fn reset(&mut self) {
         self.pulse_shaper.reset();
-        self.phase = 0.0;
-        self.sample_counter = 0.0;
+        self.carrier.reset();
         self.previous_symbol = Complex::new(1.0, 0.0);
     }
 }
@@ -294,8 +289,7 @@ pub struct PskDemodulator {
     config: ModulationConfig,
     psk_config: PskConfig,
     pulse_shaper: PulseShaper,
-    phase: f64,
-    sample_counter: f64,
+    carrier: Nco,
     is_sync: bool,
     signal_quality: SignalQuality,
     previous_symbol: Complex,
@@ -319,12 +313,13 @@ impl PskDemodulator {
             constellation_points.push(psk_config.psk_type.map_bits_to_symbol(i as u8));
         }
         
+        let carrier = Nco::new(config.carrier_frequency, config.sample_rate)?;
+        
         Ok(Self {
             config,
             psk_config,
             pulse_shaper,
-            phase: 0.0,
-            sample_counter: 0.0,
+            carrier,
             is_sync: false,
             signal_quality: SignalQuality::default(),
             previous_symbol: Complex::new(1.0, 0.0),
@@ -334,17 +329,7 @@ impl PskDemodulator {
     
     /// Demodulate to baseband
     fn demodulate_to_baseband(&mut self, sample: Complex) -> Complex {
-        let omega = 2.0 * PI * self.config.carrier_frequency / self.config.sample_rate;
-        let phase = omega * self.sample_counter;
-        
-        self.sample_counter += 1.0;
-        
-        let lo = Complex::new(phase.cos(), -phase.sin());
-        
-        Complex::new(
-            sample.real * lo.real - sample.imag * lo.imag,
-            sample.real * lo.imag + sample.imag * lo.real,
-        )
+        self.carrier.mix_down(sample)
     }
     
     /// Find closest constellation point
@@ -423,147 +408,149 @@ impl PskDemodulator {
         self.previous_symbol = symbol;
         result
     }
-}
-
-impl Demodulator for PskDemodulator {
-    fn demodulate(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()> {
-        output.clear();
-
-        let samples_per_symbol = self.config.samples_per_symbol() as usize;
-        if samples_per_symbol == 0 || samples.is_empty() { return Ok(()); }
+    
+    /// Recover symbol-spaced samples, rotated onto the constellation: Gardner
+    /// timing recovery, a global rotation chosen by the earliest sync pattern
+    /// (or the best fit), then decision-directed phase tracking
+    fn recover_symbols(&mut self, samples: &[Complex]) -> Result<Vec<Complex>> {
         let bits_per_symbol = self.psk_config.psk_type.bits_per_symbol();
 
         // Precompute baseband then perform Gardner timing recovery to extract symbols
-        self.sample_counter = 0.0;
-        let mut bb: Vec<Complex> = Vec::with_capacity(samples.len());
-        for &s in samples {
-            let b = self.demodulate_to_baseband(s);
-            let shaped = self.pulse_shaper.filter(b);
-            bb.push(shaped);
-        }
+        self.carrier.reset();
+        self.pulse_shaper.reset();
+        let mixed: Vec<Complex> = samples
+            .iter()
+            .map(|&s| self.demodulate_to_baseband(s))
+            .collect();
+        let bb = self.pulse_shaper.filter_block(&mixed)?;
         let mut gardner = GardnerTimingRecovery::new(self.config.samples_per_symbol(), 0.01, 0.707);
         let mut syms: Vec<Complex> = Vec::new();
         gardner.process(&bb, &mut syms)?;
 
-        // Try multiple sampling offsets and pick the one with earliest preamble
-        let sync: [u8; 8] = [0x55, 0x55, 0x55, 0x55, 0xAA, 0xAA, 0x7E, 0x7E];
-        let mut candidate_streams: Vec<Vec<u8>> = Vec::new();
-        let mut best: Option<(usize, usize)> = None; // (offset, pos)
-
         self.previous_symbol = Complex::new(1.0, 0.0);
         let m = self.psk_config.psk_type.constellation_size();
-        // Use symbol-spaced samples from Gardner; treat a single logical offset domain
-        for offset in 0..1 {
-            let mut tmp_bits: Vec<u8> = Vec::new();
-            let mut tmp_bytes: Vec<u8> = Vec::new();
-            // Decide a single global phase rotation for this offset
-            let mut best_rotation: usize = 0;
-            let mut best_sync_pos: Option<usize> = None;
-            let mut best_avg_err = f64::INFINITY;
-
-            // Evaluate each rotation by decoding a small window and searching for early sync
-            for r in 0..m {
-                let base = 2.0 * std::f64::consts::PI * (r as f64) / (m as f64);
-                let rot = Complex::new(base.cos(), base.sin());
-                let mut test_bytes: Vec<u8> = Vec::new();
-                let mut test_bits: Vec<u8> = Vec::new();
-                let mut i = 0;
-                let mut err_acc = 0.0;
-                let mut sym_count = 0usize;
-                while i < syms.len() && sym_count < 128 { // analyze first ~128 symbols
-                    let s = self.apply_differential_decoding(syms[i]);
-                    let sr = Complex::new(s.real * rot.real - s.imag * rot.imag, s.real * rot.imag + s.imag * rot.real);
-                    let (bits_cand, err) = self.find_closest_constellation_point(sr);
-                    err_acc += err;
-                    sym_count += 1;
-                    let final_bits = self.remove_gray_coding(bits_cand);
-                    for k in (0..bits_per_symbol).rev() {
-                        let bit = (final_bits >> k) & 1;
-                        test_bits.push(bit);
-                        if test_bits.len() == 8 {
-                            let mut byte = 0u8;
-                            for (j, &b) in test_bits.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
-                            test_bytes.push(byte);
-                            test_bits.clear();
-                        }
-                    }
-                    i += 1;
-                }
-                if !test_bits.is_empty() {
-                    let mut byte = 0u8;
-                    for (j, &b) in test_bits.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
-                    test_bytes.push(byte);
-                }
-                // Look for sync pattern
-                let sync: [u8; 8] = [0x55, 0x55, 0x55, 0x55, 0xAA, 0xAA, 0x7E, 0x7E];
-                let mut pos_opt = None;
-                if test_bytes.len() >= sync.len() {
-                    for pos in 0..=test_bytes.len() - sync.len() {
-                        if &test_bytes[pos..pos+sync.len()] == sync { pos_opt = Some(pos); break; }
-                    }
-                }
-                let avg_err = if sym_count > 0 { err_acc / sym_count as f64 } else { f64::INFINITY };
-                match (best_sync_pos, pos_opt) {
-                    (None, Some(p)) => { best_sync_pos = Some(p); best_rotation = r; best_avg_err = avg_err; }
-                    (Some(bp), Some(p)) => { if p < bp { best_sync_pos = Some(p); best_rotation = r; best_avg_err = avg_err; } }
-                    (None, None) => { if avg_err < best_avg_err { best_rotation = r; best_avg_err = avg_err; } }
-                    _ => {}
-                }
-            }
+        let mut recovered = Vec::with_capacity(syms.len());
+        // Decide a single global phase rotation
+        let mut best_rotation: usize = 0;
+        let mut best_sync_pos: Option<usize> = None;
+        let mut best_avg_err = f64::INFINITY;
 
-            // Decode full stream with chosen rotation and decision-directed phase tracking
-            let base = 2.0 * std::f64::consts::PI * (best_rotation as f64) / (m as f64);
-            let mut theta = base; // initial phase
-            let mut rot = Complex::new(theta.cos(), theta.sin());
-            let mu = 0.05f64; // PLL step size
+        // Evaluate each rotation by decoding a small window and searching for early sync
+        for r in 0..m {
+            let base = 2.0 * std::f64::consts::PI * (r as f64) / (m as f64);
+            let rot = Complex::new(base.cos(), base.sin());
+            let mut test_bytes: Vec<u8> = Vec::new();
+            let mut test_bits: Vec<u8> = Vec::new();
             let mut i = 0;
-            while i < syms.len() {
+            let mut err_acc = 0.0;
+            let mut sym_count = 0usize;
+            while i < syms.len() && sym_count < 128 { // analyze first ~128 symbols
                 let s = self.apply_differential_decoding(syms[i]);
-                // Apply current rotation
                 let sr = Complex::new(s.real * rot.real - s.imag * rot.imag, s.real * rot.imag + s.imag * rot.real);
                 let (bits_cand, err) = self.find_closest_constellation_point(sr);
-                self.signal_quality.evm_percent = (err.sqrt() * 100.0).min(100.0);
-                // Decision-directed phase error: angle between received and decided point
-                let decided = self.psk_config.psk_type.map_bits_to_symbol(bits_cand);
-                let e_re = sr.real * decided.real + sr.imag * decided.imag;
-                let e_im = sr.imag * decided.real - sr.real * decided.imag;
-                let error_angle = e_im.atan2(e_re);
-                theta -= mu * error_angle; // negative feedback
-                rot = Complex::new(theta.cos(), theta.sin());
-
+                err_acc += err;
+                sym_count += 1;
                 let final_bits = self.remove_gray_coding(bits_cand);
                 for k in (0..bits_per_symbol).rev() {
                     let bit = (final_bits >> k) & 1;
-                    tmp_bits.push(bit);
-                    if tmp_bits.len() == 8 {
+                    test_bits.push(bit);
+                    if test_bits.len() == 8 {
                         let mut byte = 0u8;
-                        for (j, &b) in tmp_bits.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
-                        tmp_bytes.push(byte);
-                        tmp_bits.clear();
+                        for (j, &b) in test_bits.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
+                        test_bytes.push(byte);
+                        test_bits.clear();
                     }
                 }
                 i += 1;
             }
-            if !tmp_bits.is_empty() {
+            if !test_bits.is_empty() {
                 let mut byte = 0u8;
-                for (j, &b) in tmp_bits.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
-                tmp_bytes.push(byte);
+                for (j, &b) in test_bits.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
+                test_bytes.push(byte);
             }
-
+            // Look for sync pattern
+            let sync: [u8; 8] = [0x55, 0x55, 0x55, 0x55, 0xAA, 0xAA, 0x7E, 0x7E];
             let mut pos_opt = None;
-            if tmp_bytes.len() >= sync.len() {
-                for pos in 0..=tmp_bytes.len() - sync.len() { if &tmp_bytes[pos..pos+sync.len()] == sync { pos_opt = Some(pos); break; } }
+            if test_bytes.len() >= sync.len() {
+                for pos in 0..=test_bytes.len() - sync.len() {
+                    if &test_bytes[pos..pos+sync.len()] == sync { pos_opt = Some(pos); break; }
+                }
             }
-            if let Some(pos) = pos_opt {
-                match best { None => best = Some((offset, pos)), Some((_, bp)) if pos < bp => best = Some((offset, pos)), _ => {} }
+            let avg_err = if sym_count > 0 { err_acc / sym_count as f64 } else { f64::INFINITY };
+            match (best_sync_pos, pos_opt) {
+                (None, Some(p)) => { best_sync_pos = Some(p); best_rotation = r; best_avg_err = avg_err; }
+                (Some(bp), Some(p)) => { if p < bp { best_sync_pos = Some(p); best_rotation = r; best_avg_err = avg_err; } }
+                // Rotations by a symmetry of the constellation give the
+                // same error up to rounding; keep the earliest (unrotated)
+                // unless another is better by more than rounding noise.
+                (None, None) => { if avg_err < best_avg_err - 1e-9 { best_rotation = r; best_avg_err = avg_err; } }
+                _ => {}
             }
-            candidate_streams.push(tmp_bytes);
         }
 
-        if let Some((bo, _)) = best { output.extend_from_slice(&candidate_streams[bo]); self.is_sync = true; return Ok(()); }
-        if let Some((bo, _)) = candidate_streams.iter().enumerate().max_by_key(|(_, v)| v.len()) {
-            output.extend_from_slice(&candidate_streams[bo]); self.is_sync = true; return Ok(());
+        // Decode full stream with chosen rotation and decision-directed phase tracking
+        let base = 2.0 * std::f64::consts::PI * (best_rotation as f64) / (m as f64);
+        let mut theta = base; // initial phase
+        let mut rot = Complex::new(theta.cos(), theta.sin());
+        let mu = 0.05f64; // PLL step size
+        let mut i = 0;
+        while i < syms.len() {
+            let s = self.apply_differential_decoding(syms[i]);
+            // Apply current rotation
+            let sr = Complex::new(s.real * rot.real - s.imag * rot.imag, s.real * rot.imag + s.imag * rot.real);
+            let (bits_cand, err) = self.find_closest_constellation_point(sr);
+            self.signal_quality.evm_percent = (err.sqrt() * 100.0).min(100.0);
+            // Decision-directed phase error: angle between received and decided point
+            let decided = self.psk_config.psk_type.map_bits_to_symbol(bits_cand);
+            let e_re = sr.real * decided.real + sr.imag * decided.imag;
+            let e_im = sr.imag * decided.real - sr.real * decided.imag;
+            let error_angle = e_im.atan2(e_re);
+            theta -= mu * error_angle; // negative feedback
+            rot = Complex::new(theta.cos(), theta.sin());
+
+            recovered.push(sr);
+            i += 1;
+        }
+        Ok(recovered)
+    }
+    
+    /// Gray-decoded bits of the nearest constellation point
+    fn symbol_bits(&self, symbol: Complex) -> u8 {
+        self.remove_gray_coding(self.find_closest_constellation_point(symbol).0)
+    }
+    
+    /// Constellation points with the bits each decodes to
+    fn labelled_constellation(&self) -> Vec<(Complex, u16)> {
+        self.constellation_points
+            .iter()
+            .enumerate()
+            .map(|(i, &point)| (point, self.remove_gray_coding(i as u8) as u16))
+            .collect()
+    }
+}
+
+impl Demodulator for PskDemodulator {
+    fn demodulate(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()> {
+        output.clear();
+
+        let samples_per_symbol = self.config.samples_per_symbol() as usize;
+        if samples_per_symbol == 0 || samples.is_empty() { return Ok(()); }
+        let bits_per_symbol = self.psk_config.psk_type.bits_per_symbol();
+
+        let symbols = self.recover_symbols(samples)?;
+        let mut bits: Vec<u8> = Vec::with_capacity(symbols.len() * bits_per_symbol);
+        for &symbol in &symbols {
+            let final_bits = self.symbol_bits(symbol);
+            for k in (0..bits_per_symbol).rev() {
+                bits.push((final_bits >> k) & 1);
+            }
         }
+        for chunk in bits.chunks(8) {
+            let mut byte = 0u8;
+            for (j, &b) in chunk.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
+            output.push(byte);
+        }
+        self.is_sync = true;
         Ok(())
     }
     
@@ -577,14 +564,33 @@ impl Demodulator for PskDemodulator {
     
     fn reset(&mut self) {
         self.pulse_shaper.reset();
-        self.phase = 0.0;
-        self.sample_counter = 0.0;
+        self.carrier.reset();
         self.is_sync = false;
         self.signal_quality = SignalQuality::default();
         self.previous_symbol = Complex::new(1.0, 0.0);
     }
 }
 
+impl SoftDemodulator for PskDemodulator {
+    /// Max-log LLRs over the constellation, with the noise power estimated
+    /// from the decision errors over the whole capture
+    fn demodulate_soft(&mut self, samples: &[Complex], output: &mut Vec<f64>) -> Result<()> {
+        output.clear();
+        if self.config.samples_per_symbol() as usize == 0 || samples.is_empty() {
+            return Ok(());
+        }
+        let symbols = self.recover_symbols(samples)?;
+        let constellation = self.labelled_constellation();
+        let noise_variance = decision_noise_variance(&symbols, &constellation);
+        let bits_per_symbol = self.psk_config.psk_type.bits_per_symbol();
+        for &symbol in &symbols {
+            max_log_llrs(symbol, &constellation, bits_per_symbol, noise_variance, output);
+        }
+        self.is_sync = true;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -662,4 +668,23 @@ mod tests {
         let expected_samples = 4 * modulator.samples_per_symbol();
         assert_eq!(output.len(), expected_samples);
     }
+
+    #[test]
+    fn test_psk_soft_decisions_match_hard() {
+        let mod_config = ModulationConfig::new(48000.0, 500.0, 1500.0).unwrap();
+        let mut modulator = PskModulator::new(mod_config.clone(), PskConfig::qpsk()).unwrap();
+        let mut samples = Vec::new();
+        modulator.modulate(b"QPSK SOFT DECISIONS", &mut samples).unwrap();
+
+        let mut demodulator = PskDemodulator::new(mod_config, PskConfig::qpsk()).unwrap();
+        let (mut bytes, mut llrs) = (Vec::new(), Vec::new());
+        demodulator.demodulate(&samples, &mut bytes).unwrap();
+        demodulator.demodulate_soft(&samples, &mut llrs).unwrap();
+        assert!(!llrs.is_empty());
+        assert_eq!(llrs.len().div_ceil(8), bytes.len());
+        for (i, &llr) in llrs.iter().enumerate() {
+            let bit = (bytes[i / 8] >> (7 - i % 8)) & 1;
+            assert_eq!(bit == 1, llr < 0.0, "bit {}", i);
+        }
+    }
 }
\ No newline at end of file
@@ -4,9 +4,12 @@
 //! with constellation shaping and adaptive equalization.
 
 use crate::Result;
-use crate::common::{Modulator, Demodulator, ModulationConfig, SignalQuality, PulseShaper, GardnerTimingRecovery};
+use crate::common::{
+    decision_noise_variance, max_log_llrs, Demodulator, GardnerTimingRecovery, ModulationConfig, Modulator,
+    PulseShaper, SignalQuality, SoftDemodulator,
+};
 use openham_core::buffer::Complex;
-use std::f64::consts::PI;
+use openham_core::nco::Nco;
 
 /// QAM constellation types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -157,8 +160,7 @@ pub struct QamModulator {
     config: ModulationConfig,
     qam_config: QamConfig,
     pulse_shaper: PulseShaper,
-    phase: f64,
-    sample_counter: f64,
+    carrier: Nco,
 }
 
 impl QamModulator {
@@ -171,12 +173,13 @@ impl QamModulator {
             config.filter_length,
         )?;
         
+        let carrier = Nco::new(config.carrier_frequency, config.sample_rate)?;
+        
         Ok(Self {
             config,
             qam_config,
             pulse_shaper,
-            phase: 0.0,
-            sample_counter: 0.0,
+            carrier,
         })
     }
     
@@ -212,18 +215,8 @@ impl QamModulator {
     
     /// Generate carrier wave
     fn generate_carrier(&mut self, symbol: Complex) -> Complex {
-        let omega = 2.0 * PI * self.config.carrier_frequency / self.config.sample_rate;
-        let phase = omega * self.sample_counter;
-        
-        self.sample_counter += 1.0;
-        
-        let carrier = Complex::new(phase.cos(), phase.sin());
-        
         // Multiply baseband symbol with carrier
-        Complex::new(
-            symbol.real * carrier.real - symbol.imag * carrier.imag,
-            symbol.real * carrier.imag + symbol.imag * carrier.real,
-        )
+        self.carrier.mix_up(symbol)
     }
 }
 
@@ -243,6 +236,7 @@ impl Modulator for QamModulator {
         }
         
         // Process symbols
+        let mut held = Vec::with_capacity(bit_stream.len().div_ceil(bits_per_symbol) * samples_per_symbol);
         for symbol_bits in bit_stream.chunks(bits_per_symbol) {
             // Pack bits into symbol value
             let mut symbol_value = 0u16;
@@ -265,14 +259,15 @@ impl Modulator for QamModulator {
             // Apply constellation shaping
             constellation_symbol = self.apply_constellation_shaping(constellation_symbol);
             
-            // Generate samples for this symbol
-            for _ in 0..samples_per_symbol {
-                let shaped = self.pulse_shaper.filter(constellation_symbol);
-                let modulated = self.generate_carrier(shaped);
-                output.push(modulated);
-            }
+            // Hold the symbol for one symbol period
+            held.extend(std::iter::repeat_n(constellation_symbol, samples_per_symbol));
         }
         
+        // Pulse-shape the whole burst as one block so long filters take the
+        // FFT path, then move it up to the carrier
+        let shaped = self.pulse_shaper.filter_block(&held)?;
+        output.extend(shaped.into_iter().map(|s| self.generate_carrier(s)));
+        
         Ok(())
     }
     
@@ -286,8 +281,7 @@ impl Modulator for QamModulator {
     
     fn reset(&mut self) {
         self.pulse_shaper.reset();
-        self.phase = 0.0;
-        self.sample_counter = 0.0;
+        self.carrier.reset();
     }
 }
 
@@ -296,8 +290,7 @@ pub struct QamDemodulator {
     config: ModulationConfig,
     qam_config: QamConfig,
     pulse_shaper: PulseShaper,
-    phase: f64,
-    sample_counter: f64,
+    carrier: Nco,
     is_sync: bool,
     signal_quality: SignalQuality,
     equalizer_taps: Vec<Complex>,
@@ -338,12 +331,13 @@ impl QamDemodulator {
             Complex::new(0.0, 0.0),
         ];
 
+        let carrier = Nco::new(config.carrier_frequency, config.sample_rate)?;
+        
         Ok(Self {
             config,
             qam_config,
             pulse_shaper,
-            phase: 0.0,
-            sample_counter: 0.0,
+            carrier,
             is_sync: false,
             signal_quality: SignalQuality::default(),
             equalizer_taps,
@@ -354,17 +348,7 @@ impl QamDemodulator {
     
     /// Demodulate to baseband
     fn demodulate_to_baseband(&mut self, sample: Complex) -> Complex {
-        let omega = 2.0 * PI * self.config.carrier_frequency / self.config.sample_rate;
-        let phase = omega * self.sample_counter;
-        
-        self.sample_counter += 1.0;
-        
-        let lo = Complex::new(phase.cos(), -phase.sin());
-        
-        Complex::new(
-            sample.real * lo.real - sample.imag * lo.imag,
-            sample.real * lo.imag + sample.imag * lo.real,
-        )
+        self.carrier.mix_down(sample)
     }
     
     /// Slice a symbol via nearest-neighbor search on precomputed constellation; returns binary bits and EVM
@@ -409,24 +393,21 @@ impl QamDemodulator {
             self.equalizer_taps[1].imag /= tap_magnitude;
         }
     }
-}
-
-impl Demodulator for QamDemodulator {
-    fn demodulate(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()> {
-        output.clear();
-
-        let samples_per_symbol = self.config.samples_per_symbol() as usize;
-        if samples_per_symbol == 0 || samples.is_empty() { return Ok(()); }
+    
+    /// Recover equalized symbol-spaced samples: Gardner timing recovery, a
+    /// global rotation chosen by the earliest sync pattern (or the lowest
+    /// EVM), then decision-directed phase tracking
+    fn recover_symbols(&mut self, samples: &[Complex]) -> Result<Vec<Complex>> {
         let bits_per_symbol = self.qam_config.qam_type.bits_per_symbol();
 
         // Precompute baseband
-        self.sample_counter = 0.0;
-        let mut bb: Vec<Complex> = Vec::with_capacity(samples.len());
-        for &s in samples {
-            let b = self.demodulate_to_baseband(s);
-            let shaped = self.pulse_shaper.filter(b);
-            bb.push(shaped);
-        }
+        self.carrier.reset();
+        self.pulse_shaper.reset();
+        let mixed: Vec<Complex> = samples
+            .iter()
+            .map(|&s| self.demodulate_to_baseband(s))
+            .collect();
+        let bb = self.pulse_shaper.filter_block(&mixed)?;
 
         // Perform Gardner timing recovery to get symbol-spaced sequence
         let mut gardner = GardnerTimingRecovery::new(self.config.samples_per_symbol(), 0.01, 0.707);
@@ -434,127 +415,123 @@ impl Demodulator for QamDemodulator {
         gardner.process(&bb, &mut syms)?;
 
         let sync: [u8; 8] = [0x55, 0x55, 0x55, 0x55, 0xAA, 0xAA, 0x7E, 0x7E];
-        let mut candidate_streams: Vec<Vec<u8>> = Vec::new();
-        let mut best: Option<(usize, usize)> = None;
-
-        for offset in 0..1 {
-            let mut tmp_bits: Vec<u8> = Vec::new();
-            let mut tmp_bytes: Vec<u8> = Vec::new();
-            // Determine a single global rotation to align constellation bytes for this offset
-            let m = 32; // denser rotation grid for QAM
-            let mut best_rot: f64 = 0.0;
-            let mut best_sync_pos: Option<usize> = None;
-            let mut best_evm = f64::INFINITY;
-            for r in 0..m {
-                let base = 2.0 * std::f64::consts::PI * (r as f64) / (m as f64);
-                let rot = Complex::new(base.cos(), base.sin());
-                let mut bits_try: Vec<u8> = Vec::new();
-                let mut bytes_try: Vec<u8> = Vec::new();
-                let mut i = 0;
-                let mut evm_acc = 0.0;
-                let mut cnt = 0usize;
-                while i < syms.len() && cnt < 256 {
-                    let shaped = syms[i];
-                    let rotated = Complex::new(
-                        shaped.real * rot.real - shaped.imag * rot.imag,
-                        shaped.real * rot.imag + shaped.imag * rot.real,
-                    );
-                    let (symbol_bits, err, _decided) = self.slice_symbol(rotated);
-                    evm_acc += err;
-                    cnt += 1;
-                    for k in (0..bits_per_symbol).rev() {
-                        let bit = (symbol_bits >> k) & 1;
-                        bits_try.push(bit as u8);
-                        if bits_try.len() == 8 {
-                            let mut byte = 0u8;
-                            for (j, &b) in bits_try.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
-                            bytes_try.push(byte);
-                            bits_try.clear();
-                        }
-                    }
-                    i += 1;
-                }
-                if !bits_try.is_empty() {
-                    let mut byte = 0u8;
-                    for (j, &b) in bits_try.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
-                    bytes_try.push(byte);
-                }
-                let mut pos_opt = None;
-                if bytes_try.len() >= sync.len() {
-                    for pos in 0..=bytes_try.len() - sync.len() { if &bytes_try[pos..pos+sync.len()] == sync { pos_opt = Some(pos); break; } }
-                }
-                let evm_avg = if cnt > 0 { evm_acc / cnt as f64 } else { f64::INFINITY };
-                match (best_sync_pos, pos_opt) {
-                    (None, Some(p)) => { best_sync_pos = Some(p); best_rot = base; best_evm = evm_avg; }
-                    (Some(bp), Some(p)) => { if p < bp { best_sync_pos = Some(p); best_rot = base; best_evm = evm_avg; } }
-                    _ => { if evm_avg < best_evm { best_rot = base; best_evm = evm_avg; } }
-                }
-            }
-            let rot = Complex::new(best_rot.cos(), best_rot.sin());
-            let mut theta: f64 = 0.0;
-            let mu: f64 = 0.02;
+        let mut recovered = Vec::with_capacity(syms.len());
+        // Determine a single global rotation to align constellation bytes
+        let m = 32; // denser rotation grid for QAM
+        let mut best_rot: f64 = 0.0;
+        let mut best_sync_pos: Option<usize> = None;
+        let mut best_evm = f64::INFINITY;
+        for r in 0..m {
+            let base = 2.0 * std::f64::consts::PI * (r as f64) / (m as f64);
+            let rot = Complex::new(base.cos(), base.sin());
+            let mut bits_try: Vec<u8> = Vec::new();
+            let mut bytes_try: Vec<u8> = Vec::new();
             let mut i = 0;
-            while i < syms.len() {
+            let mut evm_acc = 0.0;
+            let mut cnt = 0usize;
+            while i < syms.len() && cnt < 256 {
                 let shaped = syms[i];
-                // Coarse rotation
-                let rb = Complex::new(
+                let rotated = Complex::new(
                     shaped.real * rot.real - shaped.imag * rot.imag,
                     shaped.real * rot.imag + shaped.imag * rot.real,
                 );
-                // Fine rotation via PLL correction (-theta)
-                let ct = theta.cos();
-                let st = theta.sin();
-                let rotated = Complex::new(
-                    rb.real * ct + rb.imag * st,
-                    -rb.real * st + rb.imag * ct,
-                );
-                // Equalize
-                let equalized = if self.equalizer_enabled {
-                    Complex::new(
-                        rotated.real * self.equalizer_taps[1].real - rotated.imag * self.equalizer_taps[1].imag,
-                        rotated.real * self.equalizer_taps[1].imag + rotated.imag * self.equalizer_taps[1].real,
-                    )
-                } else { rotated };
-                let (symbol_bits, err, decided_point) = self.slice_symbol(equalized);
-                self.signal_quality.evm_percent = (err.sqrt() * 100.0 / 2.0).min(100.0);
-                // Update equalizer toward decided point
-                self.update_equalizer(equalized, decided_point);
-                // Decision-directed phase error: rotated * conj(decided)
-                let realp = equalized.real * decided_point.real + equalized.imag * decided_point.imag;
-                let imagp = equalized.imag * decided_point.real - equalized.real * decided_point.imag;
-                let err_phase = imagp.atan2(realp);
-                theta += mu * err_phase;
+                let (symbol_bits, err, _decided) = self.slice_symbol(rotated);
+                evm_acc += err;
+                cnt += 1;
                 for k in (0..bits_per_symbol).rev() {
                     let bit = (symbol_bits >> k) & 1;
-                    tmp_bits.push(bit as u8);
-                    if tmp_bits.len() == 8 {
+                    bits_try.push(bit as u8);
+                    if bits_try.len() == 8 {
                         let mut byte = 0u8;
-                        for (j, &b) in tmp_bits.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
-                        tmp_bytes.push(byte);
-                        tmp_bits.clear();
+                        for (j, &b) in bits_try.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
+                        bytes_try.push(byte);
+                        bits_try.clear();
                     }
                 }
                 i += 1;
             }
-            if !tmp_bits.is_empty() {
+            if !bits_try.is_empty() {
                 let mut byte = 0u8;
-                for (j, &b) in tmp_bits.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
-                tmp_bytes.push(byte);
+                for (j, &b) in bits_try.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
+                bytes_try.push(byte);
             }
             let mut pos_opt = None;
-            if tmp_bytes.len() >= sync.len() {
-                for pos in 0..=tmp_bytes.len() - sync.len() { if &tmp_bytes[pos..pos+sync.len()] == sync { pos_opt = Some(pos); break; } }
+            if bytes_try.len() >= sync.len() {
+                for pos in 0..=bytes_try.len() - sync.len() { if &bytes_try[pos..pos+sync.len()] == sync { pos_opt = Some(pos); break; } }
             }
-            if let Some(pos) = pos_opt {
-                match best { None => best = Some((offset, pos)), Some((_, bp)) if pos < bp => best = Some((offset, pos)), _ => {} }
+            let evm_avg = if cnt > 0 { evm_acc / cnt as f64 } else { f64::INFINITY };
+            match (best_sync_pos, pos_opt) {
+                (None, Some(p)) => { best_sync_pos = Some(p); best_rot = base; best_evm = evm_avg; }
+                (Some(bp), Some(p)) => { if p < bp { best_sync_pos = Some(p); best_rot = base; best_evm = evm_avg; } }
+                // Rotations by a symmetry of the constellation give the
+                // same error up to rounding; keep the earliest (unrotated)
+                // unless another is better by more than rounding noise.
+                _ => { if evm_avg < best_evm - 1e-9 { best_rot = base; best_evm = evm_avg; } }
             }
-            candidate_streams.push(tmp_bytes);
         }
+        let rot = Complex::new(best_rot.cos(), best_rot.sin());
+        let mut theta: f64 = 0.0;
+        let mu: f64 = 0.02;
+        let mut i = 0;
+        while i < syms.len() {
+            let shaped = syms[i];
+            // Coarse rotation
+            let rb = Complex::new(
+                shaped.real * rot.real - shaped.imag * rot.imag,
+                shaped.real * rot.imag + shaped.imag * rot.real,
+            );
+            // Fine rotation via PLL correction (-theta)
+            let ct = theta.cos();
+            let st = theta.sin();
+            let rotated = Complex::new(
+                rb.real * ct + rb.imag * st,
+                -rb.real * st + rb.imag * ct,
+            );
+            // Equalize
+            let equalized = if self.equalizer_enabled {
+                Complex::new(
+                    rotated.real * self.equalizer_taps[1].real - rotated.imag * self.equalizer_taps[1].imag,
+                    rotated.real * self.equalizer_taps[1].imag + rotated.imag * self.equalizer_taps[1].real,
+                )
+            } else { rotated };
+            let (_, err, decided_point) = self.slice_symbol(equalized);
+            self.signal_quality.evm_percent = (err.sqrt() * 100.0 / 2.0).min(100.0);
+            // Update equalizer toward decided point
+            self.update_equalizer(equalized, decided_point);
+            // Decision-directed phase error: rotated * conj(decided)
+            let realp = equalized.real * decided_point.real + equalized.imag * decided_point.imag;
+            let imagp = equalized.imag * decided_point.real - equalized.real * decided_point.imag;
+            let err_phase = imagp.atan2(realp);
+            theta += mu * err_phase;
+            recovered.push(equalized);
+            i += 1;
+        }
+        Ok(recovered)
+    }
+}
+
+impl Demodulator for QamDemodulator {
+    fn demodulate(&mut self, samples: &[Complex], output: &mut Vec<u8>) -> Result<()> {
+        output.clear();
 
-        if let Some((bo, _)) = best { output.extend_from_slice(&candidate_streams[bo]); self.is_sync = true; return Ok(()); }
-        if let Some((bo, _)) = candidate_streams.iter().enumerate().max_by_key(|(_, v)| v.len()) {
-            output.extend_from_slice(&candidate_streams[bo]); self.is_sync = true; return Ok(());
+        let samples_per_symbol = self.config.samples_per_symbol() as usize;
+        if samples_per_symbol == 0 || samples.is_empty() { return Ok(()); }
+        let bits_per_symbol = self.qam_config.qam_type.bits_per_symbol();
+
+        let symbols = self.recover_symbols(samples)?;
+        let mut bits: Vec<u8> = Vec::with_capacity(symbols.len() * bits_per_symbol);
+        for &symbol in &symbols {
+            let (symbol_bits, _, _) = self.slice_symbol(symbol);
+            for k in (0..bits_per_symbol).rev() {
+                bits.push(((symbol_bits >> k) & 1) as u8);
+            }
         }
+        for chunk in bits.chunks(8) {
+            let mut byte = 0u8;
+            for (j, &b) in chunk.iter().enumerate() { if b != 0 { byte |= 1 << (7 - j); } }
+            output.push(byte);
+        }
+        self.is_sync = true;
         Ok(())
     }
     
@@ -568,8 +545,7 @@ impl Demodulator for QamDemodulator {
     
     fn reset(&mut self) {
         self.pulse_shaper.reset();
-        self.phase = 0.0;
-        self.sample_counter = 0.0;
+        self.carrier.reset();
         self.is_sync = false;
         self.signal_quality = SignalQuality::default();
         
@@ -582,6 +558,25 @@ impl Demodulator for QamDemodulator {
     }
 }
 
+impl SoftDemodulator for QamDemodulator {
+    /// Max-log LLRs over the constellation, with the noise power estimated
+    /// from the decision errors over the whole capture
+    fn demodulate_soft(&mut self, samples: &[Complex], output: &mut Vec<f64>) -> Result<()> {
+        output.clear();
+        if self.config.samples_per_symbol() as usize == 0 || samples.is_empty() {
+            return Ok(());
+        }
+        let symbols = self.recover_symbols(samples)?;
+        let noise_variance = decision_noise_variance(&symbols, &self.const_points_bin);
+        let bits_per_symbol = self.qam_config.qam_type.bits_per_symbol();
+        for &symbol in &symbols {
+            max_log_llrs(symbol, &self.const_points_bin, bits_per_symbol, noise_variance, output);
+        }
+        self.is_sync = true;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -654,4 +649,23 @@ mod tests {
         let expected_samples = 4 * modulator.samples_per_symbol();
         assert_eq!(output.len(), expected_samples);
     }
+
+    #[test]
+    fn test_qam_soft_decisions_match_hard() {
+        let mod_config = ModulationConfig::new(48000.0, 500.0, 1500.0).unwrap();
+        let mut modulator = QamModulator::new(mod_config.clone(), QamConfig::qam16()).unwrap();
+        let mut samples = Vec::new();
+        modulator.modulate(b"16QAM SOFT DECISIONS", &mut samples).unwrap();
+
+        let mut demodulator = QamDemodulator::new(mod_config, QamConfig::qam16()).unwrap();
+        let (mut bytes, mut llrs) = (Vec::new(), Vec::new());
+        demodulator.demodulate(&samples, &mut bytes).unwrap();
+        demodulator.demodulate_soft(&samples, &mut llrs).unwrap();
+        assert!(!llrs.is_empty());
+        assert_eq!(llrs.len().div_ceil(8), bytes.len());
+        for (i, &llr) in llrs.iter().enumerate() {
+            let bit = (bytes[i / 8] >> (7 - i % 8)) & 1;
+            assert_eq!(bit == 1, llr < 0.0, "bit {}", i);
+        }
+    }
 }
\ No newline at end of file
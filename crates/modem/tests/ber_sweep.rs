@@ -5,7 +5,10 @@
 //! theoretical BPSK reference.
 
 use openham_core::buffer::Complex;
-use openham_core::metrics::{bpsk_ber_theory, count_bit_errors, ebn0_sweep, ebn0_sweep_to_csv};
+use openham_core::channel::{Channel, ChannelChain, SampleRateOffset, TimingOffset};
+use openham_core::metrics::{
+    bpsk_ber_theory, count_bit_errors, ebn0_sweep, ebn0_sweep_to_csv, TheoryModulation,
+};
 use openham_modem::bpsk::{BpskDemodulator, BpskModulator};
 use openham_modem::common::{Demodulator, ModulationConfig, Modulator};
 
@@ -46,7 +49,17 @@ fn bpsk_ber_vs_ebn0_curve() {
 
     let sps = make_config().samples_per_symbol(); // 40
     let ebn0_points = [0.0, 2.0, 4.0, 6.0, 8.0, 10.0];
-    let points = ebn0_sweep(payload, &clean, &ebn0_points, 1.0, sps, 60, 2024, demodulate_bpsk);
+    let points = ebn0_sweep(
+        payload,
+        &clean,
+        &ebn0_points,
+        1.0,
+        sps,
+        TheoryModulation::Bpsk,
+        60,
+        2024,
+        demodulate_bpsk,
+    );
     println!("BPSK measured vs theory:\n{}", ebn0_sweep_to_csv(&points));
 
     let low = points.first().unwrap().ber; // 0 dB
@@ -68,3 +81,33 @@ fn bpsk_ber_vs_ebn0_curve() {
         "0 dB BER {low} implausibly low — noise may not be reaching the demod"
     );
 }
+
+#[test]
+fn bpsk_survives_sample_clock_offset() {
+    // Soundcard clocks 100 ppm apart plus an arbitrary sampling phase: the
+    // demodulator's timing recovery has to follow the slipping symbols.
+    let payload = b"OPENHAM BPSK CLOCK DRIFT 0123456789";
+    let mut rx = modulate_bpsk(payload);
+    // Keep capturing a little past the end of the transmission, so the
+    // channel's delay doesn't cut off the last symbol.
+    rx.extend(vec![Complex::default(); 100]);
+    let mut channel = ChannelChain::new()
+        .then(TimingOffset::new(0.4))
+        .then(SampleRateOffset::new(100.0).unwrap());
+    channel.apply(&mut rx);
+
+    let sps = make_config().samples_per_symbol();
+    let points = ebn0_sweep(
+        payload,
+        &rx,
+        &[10.0],
+        1.0,
+        sps,
+        TheoryModulation::Bpsk,
+        20,
+        7,
+        demodulate_bpsk,
+    );
+    println!("BPSK with 100 ppm clock offset:\n{}", ebn0_sweep_to_csv(&points));
+    assert!(points[0].ber < 0.01, "BER {} with clock offset", points[0].ber);
+}
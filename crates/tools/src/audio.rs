@@ -4,13 +4,16 @@
 //! output device, and [`LiveCapture`] continuously reads the default input
 //! device into a buffer. Both use the f32 sample format (universally supported)
 //! at a requested sample rate; the modem runs at that same rate so transmit and
-//! receive agree.
+//! receive agree. If the input device cannot run at the modem rate, capture
+//! falls back to a rate it supports and resamples to the modem rate.
 
 use anyhow::{anyhow, Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{SampleFormat, SampleRate, SupportedStreamConfig};
+use openham_core::buffer::{ring_buffer, RingConsumer, StreamWindow};
+use openham_core::resample::Resampler;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 /// Print the available audio input/output devices.
 pub fn list_devices() -> Result<()> {
@@ -88,6 +91,28 @@ fn f32_config(
     ))
 }
 
+/// Find an f32 device configuration as close as possible to `sample_rate`,
+/// preferring the device's default configuration when it is f32.
+fn nearest_f32_config(device: &cpal::Device, sample_rate: u32) -> Result<SupportedStreamConfig> {
+    if let Ok(cfg) = f32_config(device, sample_rate, false) {
+        return Ok(cfg);
+    }
+    if let Ok(def) = device.default_input_config() {
+        if def.sample_format() == SampleFormat::F32 {
+            return Ok(def);
+        }
+    }
+    let ranges: Vec<_> = device.supported_input_configs().context("query input configs")?.collect();
+    ranges
+        .into_iter()
+        .find(|r| r.sample_format() == SampleFormat::F32)
+        .map(|r| {
+            let rate = sample_rate.clamp(r.min_sample_rate().0, r.max_sample_rate().0);
+            r.with_sample_rate(SampleRate(rate))
+        })
+        .ok_or_else(|| anyhow!("input device has no f32 configuration"))
+}
+
 /// Play real mono samples (≈ [-1, 1]) to the default output device at
 /// `sample_rate` Hz, blocking until playback finishes.
 pub fn play_real_samples(samples: &[f32], sample_rate: u32, device: Option<&str>) -> Result<()> {
@@ -135,12 +160,27 @@ pub fn play_real_samples(samples: &[f32], sample_rate: u32, device: Option<&str>
     Ok(())
 }
 
-/// Continuous capture from the default input device. Captured samples accumulate
-/// in an internal buffer; call [`LiveCapture::take`] to drain what has arrived.
+/// Seconds of audio the capture ring buffer holds before samples are dropped.
+const CAPTURE_BUFFER_SECS: u32 = 10;
+
+/// Frames down-mixed per push in the capture callback. The scratch buffer is
+/// allocated up front, so the realtime callback itself never allocates.
+const CAPTURE_SCRATCH_FRAMES: usize = 1024;
+
+/// Continuous capture from the default input device. The audio callback writes
+/// into a lock-free ring buffer; call [`LiveCapture::take`] to drain what has
+/// arrived, stamped with its place in the stream.
 pub struct LiveCapture {
-    buffer: Arc<Mutex<Vec<f32>>>,
+    buffer: RingConsumer<f32>,
     _stream: cpal::Stream,
+    /// Converts from `device_rate` to `sample_rate` when the two differ.
+    resampler: Option<Resampler<f32>>,
+    /// Device-rate index the resampler expects next; anything else is a gap.
+    next_input: u64,
+    /// Stream index of the resampler's next output.
+    next_output: u64,
     pub sample_rate: u32,
+    pub device_rate: u32,
     pub device_name: String,
 }
 
@@ -149,21 +189,36 @@ impl LiveCapture {
         let host = cpal::default_host();
         let device = input_device(&host, device)?;
         let device_name = device.name().unwrap_or_else(|_| "<unknown>".into());
-        let supported = f32_config(&device, sample_rate, false)?;
+        let supported = nearest_f32_config(&device, sample_rate)?;
         let channels = supported.channels() as usize;
+        let device_rate = supported.sample_rate().0;
         let config: cpal::StreamConfig = supported.config();
 
-        let buffer = Arc::new(Mutex::new(Vec::<f32>::new()));
-        let buf_cb = buffer.clone();
+        let resampler = if device_rate != sample_rate {
+            Some(Resampler::new(device_rate as f64, sample_rate as f64)?)
+        } else {
+            None
+        };
+
+        let (mut producer, buffer) =
+            ring_buffer::<f32>((device_rate * CAPTURE_BUFFER_SECS) as usize, device_rate as f64)?;
+        let mut mono = vec![0.0f32; CAPTURE_SCRATCH_FRAMES];
         let stream = device
             .build_input_stream(
                 &config,
                 move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    if let Ok(mut b) = buf_cb.lock() {
-                        // Down-mix to mono: take the first channel of each frame.
-                        for frame in data.chunks(channels) {
-                            b.push(frame[0]);
+                    if channels == 1 {
+                        producer.push(data);
+                        return;
+                    }
+                    // Down-mix to mono: take the first channel of each frame.
+                    for block in data.chunks(channels * CAPTURE_SCRATCH_FRAMES) {
+                        let mut frames = 0;
+                        for (m, frame) in mono.iter_mut().zip(block.chunks(channels)) {
+                            *m = frame[0];
+                            frames += 1;
                         }
+                        producer.push(&mono[..frames]);
                     }
                 },
                 |e| eprintln!("audio input error: {e}"),
@@ -175,16 +230,51 @@ impl LiveCapture {
         Ok(Self {
             buffer,
             _stream: stream,
+            resampler,
+            next_input: 0,
+            next_output: 0,
             sample_rate,
+            device_rate,
             device_name,
         })
     }
 
-    /// Drain and return all samples captured since the last call.
-    pub fn take(&self) -> Vec<f32> {
-        self.buffer
-            .lock()
-            .map(|mut b| std::mem::take(&mut *b))
-            .unwrap_or_default()
+    /// Drain and return all samples captured since the last call, at
+    /// `sample_rate`. The window's index and timestamp place its first sample
+    /// in the stream.
+    pub fn take(&mut self) -> StreamWindow<f32> {
+        let mut window = self.buffer.take();
+        let Some(resampler) = self.resampler.as_mut() else {
+            return window;
+        };
+        let scale = self.sample_rate as f64 / self.device_rate as f64;
+        if window.start_index != self.next_input {
+            // Samples were dropped: start the resampler afresh after the gap.
+            resampler.reset();
+            self.next_output = (window.start_index as f64 * scale).round() as u64;
+        }
+        self.next_input = window.start_index + window.samples.len() as u64;
+
+        let mut out = Vec::new();
+        if resampler.process(&window.samples, &mut out).is_err() {
+            out.clear();
+        }
+        // The resampler holds back look-ahead, so its first output here lies
+        // before the window's first input; place it by its own index.
+        let lead = window.start_index as f64 / self.device_rate as f64
+            - self.next_output as f64 / self.sample_rate as f64;
+        let lead = std::time::Duration::from_secs_f64(lead.max(0.0));
+        window.timestamp = window.timestamp.checked_sub(lead).unwrap_or(window.timestamp);
+        window.start_index = self.next_output;
+        self.next_output += out.len() as u64;
+        window.samples = out;
+        window.sample_rate = self.sample_rate as f64;
+        window
+    }
+
+    /// Samples dropped because the reader fell more than the buffer length
+    /// behind the device.
+    pub fn dropped(&self) -> u64 {
+        self.buffer.dropped()
     }
 }
@@ -9,16 +9,39 @@
 
 use clap::{Parser, Subcommand, ValueEnum};
 use anyhow::{Result, Context};
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::time::Instant;
 use tracing::{info, debug, warn};
 use serde::{Serialize, Deserialize};
 
-use openham_core::buffer::Complex;
+use openham_core::agc::{Agc, AgcConfig};
+use openham_core::buffer::{ring_buffer, Complex};
+use openham_core::detector::{DetectorConfig, SignalDetector, SignalSegment};
+use openham_core::hilbert::{analytic_signal, HilbertTransformer};
+use openham_core::scenario::ChannelScenario;
 use openham_modem::prelude::*;
 use openham_frame::prelude::*;
 use openham_codecs::prelude::*;
 
+/// Lowest audio frequency the live Hilbert converter passes flat (Hz)
+const LISTEN_HILBERT_EDGE_HZ: f64 = 150.0;
+
+/// Detected signals further than this from the centre frequency (Hz) do not
+/// wake the demodulators
+const LISTEN_MAX_OFFSET_HZ: f64 = 2500.0;
+
+/// Length of each live decode window (s)
+const LISTEN_WINDOW_SECS: f64 = 3.0;
+
+/// Step between overlapping live decode windows (s); a frame shorter than
+/// the window minus the hop lies whole inside at least one window
+const LISTEN_HOP_SECS: f64 = 1.0;
+
+/// Quiet chunks after a transmission before the rest of it is decoded and
+/// the decode queue emptied
+const LISTEN_HANGOVER_CHUNKS: u32 = 2;
+
 /// OpenHam unified digital modes tool
 #[derive(Parser)]
 #[command(name = "openham")]
@@ -61,6 +84,8 @@ enum Commands {
     Analyze(AnalyzeConfig),
     /// Generate test signals and patterns
     Generate(GenerateConfig),
+    /// Replay a channel scenario file (TOML/JSON) over a WAV recording
+    Channel(ChannelConfig),
     /// Show configuration and capabilities
     Info(InfoConfig),
 }
@@ -220,6 +245,10 @@ pub struct ReceiveConfig {
     /// Enable all decoders
     #[arg(long)]
     pub all_modes: bool,
+
+    /// Estimate the carrier offset and retune BPSK to it before decoding
+    #[arg(long)]
+    pub auto_tune: bool,
 }
 
 /// Listening mode configuration
@@ -257,10 +286,23 @@ pub struct ListenConfig {
     #[arg(long)]
     pub auto_detect: bool,
 
-    /// Squelch threshold: skip processing when input RMS is below this (0.0-1.0)
-    #[arg(long, default_value = "0.005")]
+    /// Fixed squelch threshold on input RMS (0.0-1.0); 0 uses the adaptive
+    /// signal detector instead
+    #[arg(long, default_value = "0.0")]
     pub squelch: f64,
 
+    /// Signal detector threshold in dB over the tracked noise floor
+    #[arg(long, default_value = "8.0")]
+    pub detect_threshold: f64,
+
+    /// Apply automatic gain control to the captured audio, gated on the squelch
+    #[arg(long)]
+    pub agc: bool,
+
+    /// Estimate the carrier offset and retune BPSK to it before decoding
+    #[arg(long)]
+    pub auto_tune: bool,
+
     /// List available audio devices and exit
     #[arg(long)]
     pub list_devices: bool,
@@ -330,6 +372,22 @@ pub struct GenerateConfig {
     pub amplitude: f64,
 }
 
+/// Channel simulation configuration
+#[derive(Parser, Clone)]
+pub struct ChannelConfig {
+    /// Channel scenario file (.toml, or .json)
+    #[arg(short, long)]
+    pub scenario: PathBuf,
+    
+    /// Input audio file
+    #[arg(short, long)]
+    pub input: PathBuf,
+    
+    /// Output audio file
+    #[arg(short, long)]
+    pub output: PathBuf,
+}
+
 /// Info configuration
 #[derive(Parser, Clone)]
 pub struct InfoConfig {
@@ -744,7 +802,9 @@ impl TransmissionCoordinator {
 /// Reception coordinator with auto-detection and multiple demodulators
 pub struct ReceptionCoordinator {
     config: ReceiveConfig,
-    demodulators: Vec<(String, Box<dyn Demodulator>)>,
+    /// Each demodulator with its name and the samples one demodulated bit
+    /// spans, which places acquired frames back in the sample stream
+    demodulators: Vec<(String, Box<dyn Demodulator>, f64)>,
 }
 
 impl ReceptionCoordinator {
@@ -758,41 +818,63 @@ impl ReceptionCoordinator {
             config.center_freq,
         )?;
         
-        let mut demodulators: Vec<(String, Box<dyn Demodulator>)> = Vec::new();
+        let mut demodulators: Vec<(String, Box<dyn Demodulator>, f64)> = Vec::new();
+        let samples_per_symbol = mod_config.samples_per_symbol();
         
         if config.modulation == "auto" || config.all_modes {
             // Auto-detect over the modes that actually acquire and decode a live
             // transmission. psk4/ofdm64 are omitted: they are not robust to an
             // arbitrary-offset live stream and add per-cycle cost; decode them by
             // naming the mode explicitly (-m psk4 / -m ofdm64).
-            demodulators.push(("BPSK".to_string(), Box::new(BpskDemodulator::new(mod_config.clone())?)));
-            demodulators.push(("FSK".to_string(), Box::new(FskDemodulator::new(mod_config.clone())?)));
+            demodulators.push((
+                "BPSK".to_string(),
+                Box::new(BpskDemodulator::new(mod_config.clone())?.with_auto_tune(config.auto_tune)),
+                samples_per_symbol,
+            ));
+            demodulators.push((
+                "FSK".to_string(),
+                Box::new(FskDemodulator::new(mod_config.clone())?),
+                samples_per_symbol,
+            ));
             // AFSK (Bell-202: 1200 baud, must match the transmitter)
             let afsk_config = AfskConfig::bell_202();
-            demodulators.push(("AFSK".to_string(), Box::new(AfskDemodulator::new(mod_config.clone(), afsk_config)?)));
+            let afsk_samples_per_bit = config.sample_rate / afsk_config.baud_rate;
+            demodulators.push((
+                "AFSK".to_string(),
+                Box::new(AfskDemodulator::new(mod_config.clone(), afsk_config)?),
+                afsk_samples_per_bit,
+            ));
 
             info!("Auto-detection mode: {} demodulators active", demodulators.len());
         } else {
             // Single demodulator based on specified type
-            let demodulator: Box<dyn Demodulator> = match config.modulation.as_str() {
-                "bpsk" => Box::new(BpskDemodulator::new(mod_config)?),
-                "fsk" => Box::new(FskDemodulator::new(mod_config)?),
+            let (demodulator, samples_per_bit): (Box<dyn Demodulator>, f64) = match config.modulation.as_str() {
+                "bpsk" => (
+                    Box::new(BpskDemodulator::new(mod_config)?.with_auto_tune(config.auto_tune)),
+                    samples_per_symbol,
+                ),
+                "fsk" => (Box::new(FskDemodulator::new(mod_config)?), samples_per_symbol),
                 "afsk" => {
                     // Bell-202: 1200 baud, must match the transmitter.
                     let afsk_config = AfskConfig::bell_202();
-                    Box::new(AfskDemodulator::new(mod_config, afsk_config)?)
+                    let samples_per_bit = config.sample_rate / afsk_config.baud_rate;
+                    (Box::new(AfskDemodulator::new(mod_config, afsk_config)?), samples_per_bit)
                 },
                 "psk4" => {
                     let psk_config = PskConfig::qpsk();
-                    Box::new(PskDemodulator::new(mod_config, psk_config)?)
+                    let samples_per_bit = samples_per_symbol / psk_config.psk_type.bits_per_symbol() as f64;
+                    (Box::new(PskDemodulator::new(mod_config, psk_config)?), samples_per_bit)
                 },
                 "ofdm64" => {
+                    // QPSK on every data carrier of each cyclic-prefixed symbol
                     let ofdm_config = OfdmConfig::amateur_radio_64();
-                    Box::new(OfdmDemodulator::new(mod_config, ofdm_config)?)
+                    let samples_per_bit = (ofdm_config.fft_size + ofdm_config.cp_length) as f64
+                        / (2 * ofdm_config.data_carriers.len()) as f64;
+                    (Box::new(OfdmDemodulator::new(mod_config, ofdm_config)?), samples_per_bit)
                 },
                 _ => anyhow::bail!("Unsupported modulation: {}", config.modulation),
             };
-            demodulators.push((config.modulation.clone(), demodulator));
+            demodulators.push((config.modulation.clone(), demodulator, samples_per_bit));
         }
         
         Ok(Self {
@@ -810,7 +892,7 @@ impl ReceptionCoordinator {
         let encoding = self.config.encoding;
 
         // Try each demodulator
-        for (name, demodulator) in &mut self.demodulators {
+        for (name, demodulator, samples_per_bit) in &mut self.demodulators {
             let mut demod_bytes = Vec::new();
 
             match demodulator.demodulate(samples, &mut demod_bytes) {
@@ -822,10 +904,13 @@ impl ReceptionCoordinator {
                     // started anywhere in the capture is located. (Re-expanding
                     // the bytes to bits preserves the recovered bit sequence.)
                     let bit_stream = bytes_to_bits(&demod_bytes);
-                    let frames = Acquisition::new().find_frames(&bit_stream);
+                    let (frames, stats) = Acquisition::new().locate_frames(&bit_stream);
                     debug!("{} acquired {} frame(s)", name, frames.len());
+                    if stats.payload_errors > 0 {
+                        debug!("{} dropped {} frame(s) with a corrupt payload", name, stats.payload_errors);
+                    }
 
-                    for frame in frames {
+                    for (sync_bit, frame) in frames {
                         let text = Self::decode_payload(
                             &frame.payload,
                             frame.header.frame_type,
@@ -839,6 +924,7 @@ impl ReceptionCoordinator {
                             frame_type: frame.header.frame_type,
                             sequence: frame.header.sequence,
                             signal_quality: quality,
+                            sample_offset: (sync_bit as f64 * *samples_per_bit).round() as usize,
                             timestamp: std::time::SystemTime::now(),
                         });
                     }
@@ -919,6 +1005,10 @@ pub struct DecodedMessage {
     pub frame_type: u8,
     pub sequence: u16,
     pub signal_quality: SignalQuality,
+    /// Offset of the frame's sync word into the samples it was decoded
+    /// from, to within about a symbol (the bit stream is taken to start at
+    /// the first sample)
+    pub sample_offset: usize,
     pub timestamp: std::time::SystemTime,
 }
 
@@ -981,7 +1071,7 @@ fn write_wav_file(samples: &[Complex], path: &PathBuf, sample_rate: f64) -> Resu
     Ok(())
 }
 
-/// Read audio samples from WAV file
+/// Read audio samples from WAV file, as an analytic signal
 fn read_wav_file(path: &PathBuf) -> Result<Vec<Complex>> {
     let mut reader = hound::WavReader::open(path)
         .with_context(|| format!("Failed to open WAV file: {:?}", path))?;
@@ -990,9 +1080,10 @@ fn read_wav_file(path: &PathBuf) -> Result<Vec<Complex>> {
     let samples = samples.with_context(|| "Failed to read audio samples")?;
     
     info!("Read {} samples from {:?}", samples.len(), path);
-    Ok(samples.into_iter()
-        .map(|s| Complex::new(s as f64 / 32767.0, 0.0))
-        .collect())
+    let audio: Vec<f64> = samples.into_iter()
+        .map(|s| s as f64 / 32767.0)
+        .collect();
+    Ok(analytic_signal(&audio))
 }
 
 /// Generate test signals
@@ -1223,6 +1314,9 @@ fn main() -> Result<()> {
                              i + 1, message.modulation, 
                              message.signal_quality.snr_db,
                              message.signal_quality.evm_percent);
+                    if config.auto_tune && message.signal_quality.frequency_offset_hz != 0.0 {
+                        println!("  Carrier offset: {:+.2} Hz", message.signal_quality.frequency_offset_hz);
+                    }
                     println!("  {}", message.text);
                 }
                 
@@ -1255,69 +1349,168 @@ fn main() -> Result<()> {
                 auto_detect: config.auto_detect,
                 threshold: 0.3,
                 all_modes: config.auto_detect || config.modulation == "auto",
+                auto_tune: config.auto_tune,
             };
             let mut coordinator = ReceptionCoordinator::new(rx_config)?;
 
-            let capture =
+            let mut capture =
                 openham_tools::audio::LiveCapture::start(config.sample_rate as u32, Some(config.input.as_str()))?;
             println!(
                 "● Listening on '{}' @ {:.0} Hz, mode={} — press Ctrl-C to stop",
                 capture.device_name, config.sample_rate, config.modulation
             );
+            if capture.device_rate != capture.sample_rate {
+                println!(
+                    "  (device runs at {} Hz; resampling to {:.0} Hz)",
+                    capture.device_rate, config.sample_rate
+                );
+            }
 
-            // Continuous decode. Each captured chunk is gated by signal presence:
-            // accumulate only while a signal is present, decode the window, and
-            // reset it on a successful decode OR after a short silence gap between
-            // transmissions. This keeps the processed window down to ~one
-            // transmission so decoding stays real-time and doesn't fall behind on
-            // a busy band (the previous version grew the window whenever nothing
-            // decoded, which lagged and dropped later transmissions).
-            let max_samples = (config.sample_rate * 3.0) as usize;
-            let mut rolling: Vec<Complex> = Vec::new();
+            // Continuous decode over overlapping windows. Every captured chunk
+            // is levelled, converted to its analytic signal and queued in a
+            // ring buffer that keeps the stream's sample indices. While a
+            // signal is present the decoder reads windows a hop apart; once
+            // the band has been quiet for the hangover, the rest of the
+            // transmission is decoded and the queue emptied, so the next one
+            // decodes fresh and decoding stays real-time on a busy band.
+            let window_len = (config.sample_rate * LISTEN_WINDOW_SECS) as usize;
+            let hop = (config.sample_rate * LISTEN_HOP_SECS) as usize;
+            let (mut queue_in, mut queue) = ring_buffer::<Complex>(2 * window_len, config.sample_rate)?;
             let mut silence = 0u32;
+            let mut dropped = 0u64;
+            // Frames already printed, by mode, text and stream index, so one
+            // seen again in the next overlapping window is not repeated.
+            let mut reported: VecDeque<(String, String, u64)> = VecDeque::new();
+
+            // Signal presence comes from the CFAR detector, which tracks the
+            // band's noise floor, unless a fixed RMS squelch was asked for.
+            // Only signals near the centre frequency count.
+            let mut detector = SignalDetector::new(
+                DetectorConfig::default().with_threshold_db(config.detect_threshold),
+                config.sample_rate,
+            )?;
+            let in_band = |segment: &SignalSegment| {
+                segment.low_frequency() <= config.center_freq + LISTEN_MAX_OFFSET_HZ
+                    && segment.high_frequency() >= config.center_freq - LISTEN_MAX_OFFSET_HZ
+            };
+
+            // Level the input so demodulator thresholds don't depend on the
+            // rig's audio level. The gate freezes the gain between
+            // transmissions; the AGC tracks peaks, so the RMS squelch level
+            // is scaled to the peak of a sine at that RMS.
+            let mut agc = if config.agc {
+                let mut agc_config = AgcConfig::default();
+                if config.squelch > 0.0 {
+                    agc_config = agc_config.with_gate(config.squelch * std::f64::consts::SQRT_2);
+                }
+                Some(Agc::new(agc_config, config.sample_rate)?)
+            } else {
+                None
+            };
+
+            // Captured audio is real; hand the demodulators its analytic
+            // signal. The converter runs on every chunk, quiet ones included,
+            // so its history stays continuous.
+            let mut hilbert = HilbertTransformer::with_band_edge(
+                LISTEN_HILBERT_EDGE_HZ,
+                config.sample_rate,
+            )?;
 
             loop {
                 std::thread::sleep(std::time::Duration::from_millis(250));
-                let new = capture.take();
-                if new.is_empty() {
+                let window = capture.take();
+                if window.samples.is_empty() {
                     continue;
                 }
+                let new = &window.samples;
+                let lost = capture.dropped() + queue.dropped();
+                if lost > dropped {
+                    warn!("decoder fell behind: {} captured samples dropped", lost - dropped);
+                    dropped = lost;
+                }
 
                 let chunk_rms = (new.iter().map(|s| (*s as f64) * (*s as f64)).sum::<f64>()
                     / new.len() as f64)
                     .sqrt();
-                debug!("chunk rms={:.4}, window {} samples", chunk_rms, rolling.len());
-
-                if chunk_rms < config.squelch {
-                    // Quiet chunk — a gap between transmissions. Reset after a
-                    // brief hangover so the next transmission decodes fresh.
+                let mut chunk: Vec<f64> = new.iter().map(|s| *s as f64).collect();
+                // The detector sees the raw level: AGC gain changes would
+                // look like noise-floor changes.
+                let ended = detector.process(&chunk)?;
+                let active = detector.active_segments();
+                for segment in ended.iter().chain(&active) {
+                    debug!(
+                        "signal {:.0} Hz, {:.0} Hz wide, {:.1} dB, samples {}..{}",
+                        segment.center_frequency,
+                        segment.bandwidth,
+                        segment.snr_db,
+                        segment.start,
+                        segment.end
+                    );
+                }
+                let present = if config.squelch > 0.0 {
+                    chunk_rms >= config.squelch
+                } else {
+                    ended.iter().chain(&active).any(in_band)
+                };
+                if let Some(agc) = agc.as_mut() {
+                    // Run quiet chunks through too so the envelope keeps tracking.
+                    agc.process_in_place(&mut chunk);
+                    debug!("agc gain={:.1} dB gated={}", agc.gain_db(), agc.is_gated());
+                }
+                let chunk = hilbert.process(&chunk);
+                // Stamped with its capture time, so samples the capture lost
+                // leave a gap in the queue's timeline instead of pulling the
+                // timestamps of everything after them early.
+                queue_in.push_stamped(&chunk, window.timestamp);
+                debug!("chunk rms={:.4}, {} samples queued", chunk_rms, queue.available());
+
+                let mut windows = Vec::new();
+                if present {
+                    silence = 0;
+                    while let Some(decode) = queue.read_window(window_len, hop)? {
+                        windows.push(decode);
+                    }
+                } else {
                     silence += 1;
-                    if silence >= 2 {
-                        rolling.clear();
+                    if silence == LISTEN_HANGOVER_CHUNKS {
+                        // The transmission has ended: decode what is left of
+                        // it, a stretch at a time if it has gaps.
+                        while queue.available() > 0 {
+                            windows.push(queue.take());
+                        }
+                    } else if silence > LISTEN_HANGOVER_CHUNKS {
+                        queue.skip(queue.available());
                     }
-                    continue;
                 }
-                silence = 0;
 
-                rolling.extend(new.iter().map(|s| Complex::new(*s as f64, 0.0)));
-                if rolling.len() > max_samples {
-                    let drop = rolling.len() - max_samples;
-                    rolling.drain(0..drop);
-                }
-
-                let messages = coordinator.receive(&rolling)?;
-                if !messages.is_empty() {
-                    for m in &messages {
-                        let now = chrono::Local::now().format("%H:%M:%S");
+                for decode in windows {
+                    for mut m in coordinator.receive(&decode.samples)? {
+                        let index = decode.index_at(m.sample_offset);
+                        let repeat = reported.iter().any(|(mode, text, at)| {
+                            *mode == m.modulation && *text == m.text && at.abs_diff(index) < window_len as u64
+                        });
+                        if repeat {
+                            continue;
+                        }
+                        // Stamp decodes with when the frame was heard, not
+                        // when the decoder got to it.
+                        m.timestamp = decode.time_at(m.sample_offset);
+                        let heard = chrono::DateTime::<chrono::Local>::from(m.timestamp).format("%H:%M:%S%.3f");
                         println!(
-                            "[{now}] {:>6} | SNR {:5.1} dB EVM {:4.1}% | {}",
+                            "[{heard}] {:>6} | SNR {:5.1} dB EVM {:4.1}% | {}",
                             m.modulation,
                             m.signal_quality.snr_db,
                             m.signal_quality.evm_percent,
                             m.text
                         );
+                        reported.push_back((m.modulation, m.text, index));
+                    }
+                    while reported
+                        .front()
+                        .is_some_and(|(_, _, at)| at + (window_len as u64) < decode.start_index)
+                    {
+                        reported.pop_front();
                     }
-                    rolling.clear();
                 }
             }
         },
@@ -1338,6 +1531,19 @@ fn main() -> Result<()> {
                      samples.len(), config.output);
         },
         
+        Commands::Channel(config) => {
+            let scenario = ChannelScenario::load(&config.scenario)
+                .with_context(|| format!("Failed to load channel scenario: {:?}", config.scenario))?;
+            if let Some(description) = &scenario.description {
+                info!("Channel scenario: {}", description);
+            }
+            scenario.apply_to_wav(&config.input, &config.output)
+                .with_context(|| format!("Failed to apply channel scenario to {:?}", config.input))?;
+            
+            println!("✓ Applied {} channel stage(s): {:?} -> {:?}",
+                     scenario.stages.len(), config.input, config.output);
+        },
+        
         Commands::Info(config) => {
             show_info(&config);
         },
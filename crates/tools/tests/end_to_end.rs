@@ -7,12 +7,19 @@
 //! regress, and prints a FER-vs-SNR curve for each.
 
 use openham_core::buffer::Complex;
-use openham_core::channel::add_awgn_real_snr;
+use openham_core::channel::{add_awgn_real_snr, Awgn, ChannelChain};
+use openham_core::flowgraph::{Block, ChannelBlock, CollectSink, Flowgraph, FnBlock, VecSource};
 use openham_frame::frame::{frame_flags, frame_types, Frame};
-use openham_frame::framing::{add_preamble_sync, bytes_to_bits, Acquisition};
+use openham_frame::framing::{
+    add_preamble_sync, bytes_to_bits, Acquisition, AcquisitionBlock, PREAMBLE,
+};
+use openham_frame::sweep::{frame_sweep, frame_sweep_to_csv, FrameSweepConfig};
 use openham_modem::afsk::{AfskConfig, AfskDemodulator, AfskModulator};
 use openham_modem::bpsk::{BpskDemodulator, BpskModulator};
-use openham_modem::common::{BitDemodulator, Demodulator, ModulationConfig, Modulator};
+use openham_modem::common::{
+    BitDemodulator, BitDemodulatorBlock, Demodulator, ModulationConfig, Modulator, ModulatorBlock,
+    SoftDemodulatorBlock,
+};
 use openham_modem::fsk::{FskDemodulator, FskModulator};
 use openham_modem::ofdm::{OfdmConfig, OfdmDemodulator, OfdmModulator};
 use openham_modem::psk::{PskConfig, PskDemodulator, PskModulator};
@@ -162,6 +169,42 @@ fn live_modes_acquire_at_multiple_offsets() {
     }
 }
 
+/// The live receiver stamps a decode with the sync word's bit offset scaled
+/// to samples, which must land on the sync word to within a couple of bits.
+#[test]
+fn live_modes_place_sync_in_the_stream() {
+    let payload = b"CQ DE S56SPZ LIVE K";
+    let frame = Frame::new(frame_types::DATA, 1, payload.to_vec(), frame_flags::NONE);
+    let framed = add_preamble_sync(&frame.to_bytes());
+    for m in working_modes() {
+        let samples_per_bit = match m.name {
+            "bpsk" | "fsk" => cfg().samples_per_symbol(),
+            "afsk" => cfg().sample_rate / AfskConfig::bell_202().baud_rate,
+            _ => continue,
+        };
+        for lead_in in [519usize, 5119] {
+            let mut stream = vec![Complex::new(0.0, 0.0); lead_in];
+            stream.extend(modulate(&m, &framed));
+            stream.extend(std::iter::repeat_n(Complex::new(0.0, 0.0), 4096));
+            let mut d = (m.make_demod)();
+            let mut bytes = Vec::new();
+            d.demodulate(&through_wav(&stream), &mut bytes).unwrap();
+
+            let (located, _) = Acquisition::new().locate_frames(&bytes_to_bits(&bytes));
+            assert_eq!(located.len(), 1, "{} at {}", m.name, lead_in);
+            let found = located[0].0 as f64 * samples_per_bit;
+            let expected = lead_in as f64 + (PREAMBLE.len() * 8) as f64 * samples_per_bit;
+            assert!(
+                (found - expected).abs() <= 2.0 * samples_per_bit,
+                "{} placed the sync at {:.0}, expected {:.0}",
+                m.name,
+                found,
+                expected
+            );
+        }
+    }
+}
+
 /// Always-run hard regression: every "working" mode must round-trip a frame
 /// exactly on a clean channel. A short payload keeps this fast.
 #[test]
@@ -289,3 +332,150 @@ fn frame_acquisition_at_arbitrary_offset() {
         );
     }
 }
+
+/// The frame-level sweep harness driven by a real modem: BPSK through an AWGN
+/// `ChannelChain`, with dead air before the burst so acquisition has to find
+/// it. SNR is per sample; at 384 samples/symbol -30 dB is about -4 dB Eb/N0.
+#[test]
+fn bpsk_frame_sweep_through_channel_chain() {
+    let config = FrameSweepConfig {
+        payload_len: 12,
+        min_trials: 10,
+        max_trials: 10,
+        lead_in_samples: 1000,
+        tail_samples: 768,
+        ..FrameSweepConfig::default()
+    };
+    let points = frame_sweep(
+        &config,
+        &[-30.0, 0.0],
+        |bytes| {
+            let mut out = Vec::new();
+            BpskModulator::new(cfg()).unwrap().modulate(bytes, &mut out).unwrap();
+            out
+        },
+        |samples| {
+            let mut bits = Vec::new();
+            let _ = BpskDemodulator::new(cfg()).unwrap().demodulate_bits(samples, &mut bits);
+            bits
+        },
+        |snr_db, seed| ChannelChain::new().then(Awgn::new(snr_db, seed)),
+    );
+    println!("BPSK frame sweep:\n{}", frame_sweep_to_csv(&points));
+
+    assert!(points[0].fer() > 0.5, "{:?}", points[0]);
+    assert_eq!(points[1].frame_errors, 0, "{:?}", points[1]);
+    assert_eq!(points[1].false_syncs, 0, "{:?}", points[1]);
+}
+
+/// The same TX/RX chain as a streaming flowgraph: framed bytes are modulated,
+/// padded with dead air, sent through the mono-WAV channel and AWGN, then
+/// demodulated to bits and acquired, each stage on its own thread. Each
+/// source chunk is one transmission; the receiver demodulates them back to
+/// back as one stream.
+#[test]
+fn bpsk_flowgraph_transmit_receive() {
+    let payloads: [&[u8]; 3] = [b"FLOWGRAPH 1", b"FLOWGRAPH 2", b"FLOWGRAPH 3"];
+    let mut bytes = Vec::new();
+    for (seq, payload) in payloads.iter().enumerate() {
+        let frame = Frame::new(frame_types::DATA, seq as u16, payload.to_vec(), frame_flags::NONE);
+        bytes.extend(add_preamble_sync(&frame.to_bytes()));
+    }
+    let burst_len = bytes.len() / payloads.len();
+
+    let mut graph = Flowgraph::new().with_buffer_depth(1);
+    let tx_bytes = graph.source(VecSource::new(bytes, burst_len));
+    let tx_audio = graph.block(tx_bytes, ModulatorBlock::new(BpskModulator::new(cfg()).unwrap()));
+    let on_air = graph.block(
+        tx_audio,
+        FnBlock::new(|burst: &[Complex], out: &mut Vec<Complex>| {
+            out.extend(std::iter::repeat_n(Complex::new(0.0, 0.0), 531));
+            out.extend(through_wav(burst));
+            out.extend(std::iter::repeat_n(Complex::new(0.0, 0.0), 768));
+            Ok(())
+        }),
+    );
+    let rx_audio = graph.block(on_air, ChannelBlock::new(Awgn::new(25.0, 7)));
+    let rx_bits = graph.block(rx_audio, BitDemodulatorBlock::new(BpskDemodulator::new(cfg()).unwrap()));
+    let frames = graph.block(rx_bits, AcquisitionBlock::new(Acquisition::new()));
+    let sink = CollectSink::new();
+    let received = sink.handle();
+    graph.sink(frames, sink);
+    graph.run().unwrap();
+
+    let received = received.lock().unwrap();
+    let got: Vec<&[u8]> = received.iter().map(|f| f.payload.as_slice()).collect();
+    assert_eq!(got, payloads);
+}
+
+/// One transmission cut into chunks far shorter than a symbol and
+/// demodulated over windows far shorter than the frame: the blocks emit as
+/// the stream goes, and the stitched bits still carry the frame.
+#[test]
+fn bpsk_flowgraph_receives_one_transmission_in_small_chunks() {
+    const WINDOW: usize = 24_576;
+    let frame = Frame::new(frame_types::DATA, 9, b"SMALL CHUNKS".to_vec(), frame_flags::NONE);
+    let mut tx = Vec::new();
+    BpskModulator::new(cfg())
+        .unwrap()
+        .modulate(&add_preamble_sync(&frame.to_bytes()), &mut tx)
+        .unwrap();
+    let mut on_air = vec![Complex::new(0.0, 0.0); 531];
+    on_air.extend(through_wav(&tx));
+    on_air.extend(std::iter::repeat_n(Complex::new(0.0, 0.0), 768));
+    assert!(on_air.len() > 4 * WINDOW);
+
+    let mut graph = Flowgraph::new().with_buffer_depth(1);
+    let samples = graph.source(VecSource::new(on_air.clone(), 100));
+    let rx_audio = graph.block(samples, ChannelBlock::new(Awgn::new(25.0, 7)));
+    let mut branches = graph.split(rx_audio, 4).into_iter();
+    let audio = branches.next().unwrap();
+    let bits = graph.block(
+        branches.next().unwrap(),
+        BitDemodulatorBlock::new(BpskDemodulator::new(cfg()).unwrap()).with_window(WINDOW),
+    );
+    let llrs = graph.block(
+        branches.next().unwrap(),
+        SoftDemodulatorBlock::new(BpskDemodulator::new(cfg()).unwrap()).with_window(WINDOW),
+    );
+    let rx_bits = graph.block(
+        branches.next().unwrap(),
+        BitDemodulatorBlock::new(BpskDemodulator::new(cfg()).unwrap()).with_window(WINDOW),
+    );
+    let frames = graph.block(rx_bits, AcquisitionBlock::new(Acquisition::new()));
+    let (audio_sink, bit_sink, llr_sink, frame_sink) =
+        (CollectSink::new(), CollectSink::new(), CollectSink::new(), CollectSink::new());
+    let (got_audio, got_bits, got_llrs, got_frames) =
+        (audio_sink.handle(), bit_sink.handle(), llr_sink.handle(), frame_sink.handle());
+    graph.sink(audio, audio_sink);
+    graph.sink(bits, bit_sink);
+    graph.sink(llrs, llr_sink);
+    graph.sink(frames, frame_sink);
+    graph.run().unwrap();
+
+    let got_frames = got_frames.lock().unwrap();
+    assert_eq!(got_frames.len(), 1);
+    assert_eq!(got_frames[0].payload, b"SMALL CHUNKS");
+
+    // As many bits as demodulating the whole capture at once, and the soft
+    // decisions agree with the hard ones.
+    let (got_bits, got_llrs) = (got_bits.lock().unwrap(), got_llrs.lock().unwrap());
+    let mut whole = Vec::new();
+    BpskDemodulator::new(cfg())
+        .unwrap()
+        .demodulate_bits(&got_audio.lock().unwrap(), &mut whole)
+        .unwrap();
+    assert!(got_bits.len().abs_diff(whole.len()) <= 2, "{} vs {}", got_bits.len(), whole.len());
+    assert_eq!(got_llrs.len(), got_bits.len());
+    for (&bit, &llr) in got_bits.iter().zip(got_llrs.iter()) {
+        assert_eq!(bit == 1, llr < 0.0);
+    }
+
+    // Bits come out while the stream is still running, not only at its end.
+    let mut block = BitDemodulatorBlock::new(BpskDemodulator::new(cfg()).unwrap()).with_window(WINDOW);
+    let mut streamed = Vec::new();
+    for chunk in on_air.chunks(100) {
+        block.work(chunk, &mut streamed).unwrap();
+    }
+    assert!(streamed.len() > whole.len() / 2);
+}